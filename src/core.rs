@@ -3,57 +3,85 @@
 
 pub mod builtins;
 pub mod data;
+pub mod glob_cache;
+pub mod hash_table;
 pub mod history;
 pub mod jobtable;
 pub mod options;
 
-use self::data::Data;
+use self::data::{Data, ExitStatus};
+use self::builtins::completion::CompSpec;
 use self::options::Options;
 use std::collections::HashMap;
 use std::os::fd::{FromRawFd, OwnedFd};
 use std::{io, env, path, process};
-use nix::{fcntl, unistd};
+use nix::{fcntl, libc, unistd};
+use nix::errno::Errno;
 use nix::sys::{resource, signal, wait};
 use nix::sys::resource::UsageWho;
-use nix::sys::signal::{Signal, SigHandler};
+use nix::sys::signal::{Signal, SigAction, SigHandler};
 use nix::sys::wait::{WaitPidFlag, WaitStatus};
 use nix::sys::time::{TimeSpec, TimeVal};
 use nix::time;
 use nix::time::ClockId;
 use nix::unistd::Pid;
-use crate::error_message;
+use crate::{error_message, utils, Feeder};
+use crate::elements::word::Word;
+use crate::elements::command::function_def::FunctionDefinition;
 use crate::core::jobtable::JobEntry;
+use crate::feeder::input::InputSource;
+use crate::utils::glob::Matcher;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 
 pub struct ShellCore {
     pub data: Data,
-    rewritten_history: HashMap<usize, String>,
     pub history: Vec<String>,
+    pub history_unsaved: usize,
+    pub history_number: usize,
+    pub kill_ring: String,
+    pub command_number: usize,
     pub builtins: HashMap<String, fn(&mut ShellCore, &mut Vec<String>) -> i32>,
     pub sigint: Arc<AtomicBool>,
-    pub read_stdin: bool,
+    pub input_sources: Vec<InputSource>,
     pub word_eval_error: bool,
     pub is_subshell: bool,
     pub source_function_level: i32,
     pub source_level: i32,
     pub eval_level: i32,
+    pub comsub_level: i32,
     pub loop_level: i32,
     pub break_counter: i32,
     pub return_flag: bool,
     pub tty_fd: Option<OwnedFd>,
     pub job_table: Vec<JobEntry>,
     pub job_table_priority: Vec<usize>,
+    pub reaped_statuses: HashMap<Pid, i32>,
     current_dir: Option<path::PathBuf>, // the_current_working_directory
-    pub completion_functions: HashMap<String, String>,
+    pub completion_specs: HashMap<String, CompSpec>,
     pub real_time: TimeSpec, 
     pub user_time: TimeVal, 
     pub sys_time: TimeVal, 
+    pub posix_time: bool,
     pub options: Options,
     pub shopts: Options,
     pub suspend_e_option: bool,
+    /* a failure inside a compound command is not reported again by the compound command */
+    pub error_checked: bool,
     pub script_name: String,
+    pub exit_attempt: Option<usize>,
+    pub dir_stack: Vec<String>,
+    pub traps: HashMap<String, String>,
+    pub saved_sigactions: HashMap<i32, SigAction>,
+    pub hash_table: HashMap<String, (String, usize)>,
+    hash_table_path: String,
+    pub glob_cache: HashMap<(String, bool, bool), Rc<Matcher>>,
+    pub getopts_state: (usize, usize, String),
+    pub extglob_before_posix: Option<bool>,
+    /* the first error on the stdout of a builtin */
+    pub write_error: Option<String>,
 }
 
 fn ignore_signal(sig: Signal) {
@@ -67,73 +95,136 @@ fn restore_signal(sig: Signal) {
 }
 
 impl ShellCore {
-    pub fn new() -> ShellCore {
+    /* the shell becomes interactive when it reads commands from the terminal or -i is given */
+    pub fn new(read_stdin: bool, force_interactive: bool) -> ShellCore {
         let mut core = ShellCore{
             data: Data::new(),
-            rewritten_history: HashMap::new(),
             history: vec![],
+            history_unsaved: 0,
+            history_number: 1,
+            kill_ring: String::new(),
+            command_number: 1,
             builtins: HashMap::new(),
             sigint: Arc::new(AtomicBool::new(false)),
             word_eval_error: false,
-            read_stdin: true,
+            input_sources: vec![],
             is_subshell: false,
+            exit_attempt: None,
+            dir_stack: vec![],
+            traps: HashMap::new(),
+            saved_sigactions: HashMap::new(),
+            hash_table: HashMap::new(),
+            hash_table_path: String::new(),
+            glob_cache: HashMap::new(),
+            getopts_state: (0, 1, String::new()),
+            extglob_before_posix: None,
+            write_error: None,
             source_function_level: 0,
             source_level: 0,
             eval_level: 0,
+            comsub_level: 0,
             loop_level: 0,
             break_counter: 0,
             return_flag: false,
             tty_fd: None,
             job_table: vec![],
             job_table_priority: vec![],
+            reaped_statuses: HashMap::new(),
             current_dir: None,
-            completion_functions: HashMap::new(),
+            completion_specs: HashMap::new(),
             real_time: TimeSpec::new(0, 0),
             user_time: TimeVal::new(0, 0),
             sys_time: TimeVal::new(0, 0),
+            posix_time: false,
             options: Options::new_as_basic_opts(),
             shopts: Options::new_as_shopts(),
             suspend_e_option: false,
+            error_checked: false,
             script_name: "-".to_string(),
         };
 
         core.init_current_directory();
         core.set_initial_parameters();
         core.set_builtins();
+        core.import_functions();
         ignore_signal(Signal::SIGPIPE);
         ignore_signal(Signal::SIGTSTP);
 
         core.data.set_param("PS4", "+ ");
 
-        if unistd::isatty(0) == Ok(true) {
+        let terminal = read_stdin && unistd::isatty(0) == Ok(true);
+        if terminal || force_interactive {
+            core.data.flags += "iH";
+            core.options.set("history", true);
+            core.options.set("emacs", true);
+            core.shopts.set("expand_aliases", true);
+            core.data.set_param("PS1", "🍣 ");
+            core.data.set_param("PS2", "> ");
+        }
+
+        if terminal {
             const V: &'static str = env!("CARGO_PKG_VERSION");
             eprintln!("Rusty Bash (a.k.a. Sushi shell), version {}", V);
 
-            core.data.flags += "i";
-            core.read_stdin = false;
-            core.data.set_param("PS1", "🍣 ");
-            core.data.set_param("PS2", "> ");
+            core.push_source(InputSource::terminal());
             let fd = fcntl::fcntl(2, fcntl::F_DUPFD_CLOEXEC(255))
                 .expect("sush(fatal): Can't allocate fd for tty FD");
             core.tty_fd = Some(unsafe{OwnedFd::from_raw_fd(fd)});
+            core.init_job_control();
+        }else{
+            core.push_source(InputSource::stdin("-"));
         }
 
+        /* the values from the environment are kept */
         let home = core.data.get_param("HOME").to_string();
-        core.data.set_param("HISTFILE", &(home + "/.sush_history"));
-        core.data.set_param("HISTFILESIZE", "2000");
+        let histfile = home + "/.sush_history";
+        for (key, value) in [("HISTFILE", histfile.as_str()), ("HISTFILESIZE", "2000"), ("HISTSIZE", "2000")] {
+            if ! core.data.is_set(key) {
+                core.data.set_param(key, value);
+            }
+        }
 
         core
     }
 
+    /* only the definitions are taken from the environment. anything after them is ignored */
+    fn import_functions(&mut self) {
+        for (name, body) in self.data.take_function_env() {
+            let mut feeder = Feeder::new(&format!("{} {}", name, body.trim_end()));
+            if let Some(f) = FunctionDefinition::parse(&mut feeder, self) {
+                if feeder.len() == 0 {
+                    self.data.functions.insert(name.clone(), f);
+                    self.data.export_function(&name);
+                }
+            }
+        }
+    }
+
     fn set_initial_parameters(&mut self) {
         self.data.set_param("$", &process::id().to_string());
         self.data.set_param("BASHPID", &process::id().to_string());
         self.data.set_param("BASH_SUBSHELL", "0");
         self.data.set_param("BASH_VERSION", &(env!("CARGO_PKG_VERSION").to_string() + "-rusty_bash"));
-        self.data.set_param("?", "0");
+        self.data.set_exit_status(0);
+        self.data.set_param("OPTIND", "1");
+        self.data.set_param("OPTERR", "1");
         self.data.set_param("HOME", &env::var("HOME").unwrap_or("/".to_string()));
     }
 
+    /* the variables that would let a user out of the restricted shell become readonly */
+    pub fn set_restricted(&mut self) {
+        if ! self.data.flags.contains('r') {
+            self.data.flags += "r";
+        }
+        for key in ["PATH", "SHELL", "ENV", "BASH_ENV"] {
+            self.data.set_readonly(key);
+        }
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.data.flags.contains('r')
+    }
+
 /*
     pub fn has_flag(&self, flag: char) -> bool {
         self.data.flags.find(flag) != None 
@@ -145,21 +236,26 @@ impl ShellCore {
             false => Some(WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED)
         };
 
-        let ws = wait::waitpid(child, waitflags);
+        let ws = match self.shopts.query("cmdtimes") {
+            true  => self.wait_with_rusage(child, waitflags),
+            false => wait::waitpid(child, waitflags),
+        };
 
         let exit_status = match ws {
             Ok(WaitStatus::Exited(_pid, status)) => {
                 status
             },
             Ok(WaitStatus::Signaled(pid, signal, coredump)) => {
-                match coredump {
-                    true  => eprintln!("Pid: {:?}, Signal: {:?} (core dumped)", pid, signal),
-                    false => eprintln!("Pid: {:?}, Signal: {:?}", pid, signal),
+                if signal != Signal::SIGINT && signal != Signal::SIGPIPE {
+                    let core_msg = if coredump { " (core dumped)" } else { "" };
+                    let msg = format!("{}: terminated by {}{}", pid, signal.as_str(), core_msg);
+                    error_message::print(&msg, self, true);
                 }
                 128+signal as i32
             },
             Ok(WaitStatus::Stopped(pid, signal)) => {
-                eprintln!("Stopped Pid: {:?}, Signal: {:?}", pid, signal);
+                let msg = format!("{}: stopped by {}", pid, signal.as_str());
+                error_message::print(&msg, self, true);
                 148
             },
             Ok(unsupported) => {
@@ -176,93 +272,179 @@ impl ShellCore {
         if exit_status == 130 {
             self.sigint.store(true, Relaxed);
         }
-        self.data.set_layer_param("?", &exit_status.to_string(), 0); //追加
+        self.data.set_exit_status(exit_status); //追加
         ws.expect("SUSH INTERNAL ERROR: no wait status")
     }
 
-    fn set_foreground(&self) {
+    fn wait_with_rusage(&mut self, child: Pid,
+                        flags: Option<WaitPidFlag>) -> nix::Result<WaitStatus> {
+        let mut status: libc::c_int = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let options = flags.map(|f| f.bits()).unwrap_or(0);
+        let pid = unsafe { libc::wait4(child.as_raw(), &mut status, options, &mut usage) };
+        if pid < 0 {
+            return Err(Errno::last());
+        }
+
+        let ws = WaitStatus::from_raw(Pid::from_raw(pid), status)?;
+        if let WaitStatus::Exited(..) | WaitStatus::Signaled(..) = ws {
+            let usec = (usage.ru_utime.tv_sec + usage.ru_stime.tv_sec) * 1000000
+                     + usage.ru_utime.tv_usec + usage.ru_stime.tv_usec;
+            let cpu = format!("{}.{:06}", usec/1000000, usec%1000000);
+            self.data.set_layer_param("SUSH_LAST_RSS", &usage.ru_maxrss.to_string(), 0);
+            self.data.set_layer_param("SUSH_LAST_CPU", &cpu, 0);
+        }
+        Ok(ws)
+    }
+
+    /* waits until the shell is in the foreground, and then makes its own process group */
+    fn init_job_control(&self) {
         let fd = match self.tty_fd.as_ref() {
             Some(fd) => fd,
             _        => return,
         };
+
+        loop {
+            let pgrp = unistd::getpgrp();
+            if unistd::tcgetpgrp(fd) == Ok(pgrp) {
+                break;
+            }
+            let _ = signal::killpg(pgrp, Signal::SIGTTIN);
+        }
+
+        ignore_signal(Signal::SIGTTIN);
+        ignore_signal(Signal::SIGTTOU);
+
+        let pid = unistd::getpid();
+        if unistd::getpgrp() != pid {
+            let _ = unistd::setpgid(pid, pid);
+        }
+        let _ = unistd::tcsetpgrp(fd, unistd::getpgrp());
+    }
+
+    fn set_foreground(&self) {
         let pgid = unistd::getpgid(Some(Pid::from_raw(0)))
                    .expect("sush(fatal): cannot get pgid");
+        let result = self.give_terminal(pgid);
+        if ! self.is_subshell {
+            result.expect("sush(fatal): cannot get the terminal");
+        }
+    }
+
+    /* the shell itself always ignores SIGTTOU. a child ignores it only while it gets the terminal */
+    fn give_terminal(&self, pgid: Pid) -> nix::Result<()> {
+        let fd = match self.tty_fd.as_ref() {
+            Some(fd) => fd,
+            _        => return Ok(()),
+        };
 
         if unistd::tcgetpgrp(fd) == Ok(pgid) {
-            return;
+            return Ok(());
         }
 
-        ignore_signal(Signal::SIGTTOU); //SIGTTOUを無視
-        unistd::tcsetpgrp(fd, pgid)
-            .expect("sush(fatal): cannot get the terminal");
-        restore_signal(Signal::SIGTTOU); //SIGTTOUを受け付け
+        if ! self.is_subshell {
+            return unistd::tcsetpgrp(fd, pgid);
+        }
+
+        ignore_signal(Signal::SIGTTOU);
+        let result = unistd::tcsetpgrp(fd, pgid);
+        restore_signal(Signal::SIGTTOU);
+        result
     }
 
     fn flip_exit_status(&mut self) {
-        match self.data.get_param("?").as_ref() {
-            "0" => self.data.set_param("?", "1"),
-            _   => self.data.set_param("?", "0"),
-        }
+        let es = self.data.exit_status().flip();
+        self.data.set_exit_status(es.code());
     }
 
-    fn show_time(&self) {
-            let real_end_time = time::clock_gettime(ClockId::CLOCK_MONOTONIC).unwrap();
-
-            let self_usage = resource::getrusage(UsageWho::RUSAGE_SELF).unwrap();
-            let children_usage = resource::getrusage(UsageWho::RUSAGE_CHILDREN).unwrap();
-
-            let real_diff = real_end_time - self.real_time;
-            eprintln!("\nreal\t{}m{}.{:06}s", real_diff.tv_sec()/60,
-                      real_diff.tv_sec()%60, real_diff.tv_nsec()/1000);
-            let user_diff = self_usage.user_time() + children_usage.user_time() - self.user_time;
-            eprintln!("user\t{}m{}.{:06}s", user_diff.tv_sec()/60,
-                      user_diff.tv_sec()%60, user_diff.tv_usec());
-            let sys_diff = self_usage.system_time() + children_usage.system_time() - self.sys_time;
-            eprintln!("sys \t{}m{}.{:06}s", sys_diff.tv_sec()/60,
-                      sys_diff.tv_sec()%60, sys_diff.tv_usec());
+    fn show_time(&mut self) {
+        let real_end_time = time::clock_gettime(ClockId::CLOCK_MONOTONIC).unwrap();
+        let self_usage = resource::getrusage(UsageWho::RUSAGE_SELF).unwrap();
+        let children_usage = resource::getrusage(UsageWho::RUSAGE_CHILDREN).unwrap();
+
+        let usec = |t: TimeVal| t.tv_sec() * 1_000_000 + t.tv_usec();
+        let real_diff = real_end_time - self.real_time;
+        let real = real_diff.tv_sec() * 1_000_000 + real_diff.tv_nsec() / 1000;
+        let user = usec(self_usage.user_time() + children_usage.user_time() - self.user_time);
+        let sys = usec(self_usage.system_time() + children_usage.system_time() - self.sys_time);
+
+        let format = match (self.posix_time, self.data.has_value("TIMEFORMAT", None)) {
+            (true, _)  => "real %2R\nuser %2U\nsys %2S".to_string(),
+            (_, true)  => self.data.get_param("TIMEFORMAT"),
+            (_, false) => "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS".to_string(),
+        };
+        if format.is_empty() {
+            return;
+        }
+
+        match utils::format_times(&format, real, user, sys) {
+            Ok(s)  => eprintln!("{}", s),
+            Err(c) => error_message::print(&format!("TIMEFORMAT: `{}': invalid format character", c), self, true),
+        }
     }
 
-    fn check_e_option(&mut self) {
-        if self.data.get_param("?") != "0" 
-        && self.data.flags.contains("e") 
-        && ! self.suspend_e_option {
-            self.exit();
+    /* the ERR trap and set -e are ignored in the same places */
+    fn check_e_option(&mut self, exclamation: bool) {
+        if self.data.exit_status().is_success() || self.error_checked {
+            return;
         }
+
+        if ! self.suspend_e_option && ! exclamation {
+            crate::signal::run_err_trap(self);
+            if self.data.flags.contains("e") {
+                self.exit();
+            }
+        }
+        self.error_checked = true;
     }
 
-    pub fn wait_pipeline(&mut self, pids: Vec<Option<Pid>>,
+    /* statuses: the exit statuses of the commands that ran without fork */
+    pub fn wait_pipeline(&mut self, pids: Vec<Option<Pid>>, statuses: &[Option<ExitStatus>],
                          exclamation: bool, time: bool) -> Vec<WaitStatus> {
         if pids.len() == 1 && pids[0] == None {
             if time {
                 self.show_time();
             }
+            let es = self.data.exit_status();
+            self.data.set_layer_array("PIPESTATUS", &vec![es.to_string()], 0);
             if exclamation {
                 self.flip_exit_status();
             }
-            self.check_e_option();
+            self.check_e_option(exclamation);
             return vec![];
         }
 
         let mut pipestatus = vec![];
         let mut ans = vec![];
-        for pid in &pids {
-            let ws = self.wait_process(pid.expect("SUSHI INTERNAL ERROR (no pid)"));
-            ans.push(ws);
+        for (i, pid) in pids.iter().enumerate() {
+            match pid {
+                Some(p) => {
+                    ans.push(self.wait_process(*p));
+                    pipestatus.push(self.data.exit_status());
+                },
+                None => {
+                    let es = statuses.get(i).cloned().flatten().unwrap_or_default();
+                    pipestatus.push(es);
+                },
+            }
+        }
 
-            pipestatus.push(self.data.get_param("?"));
+        if let Some(es) = pipestatus.last() {
+            self.data.set_exit_status(es.code());
         }
 
         if time {
             self.show_time();
         }
         self.set_foreground();
-        self.data.set_layer_array("PIPESTATUS", &pipestatus, 0);
+        let strs = pipestatus.iter().map(|es| es.to_string()).collect();
+        self.data.set_layer_array("PIPESTATUS", &strs, 0);
 
         if self.options.query("pipefail") {
-            pipestatus.retain(|e| e != "0");
+            pipestatus.retain(|e| ! e.is_success());
 
-            if pipestatus.len() != 0 {
-                self.data.set_param("?", &pipestatus.last().unwrap());
+            if let Some(es) = pipestatus.last() {
+                self.data.set_exit_status(es.code());
             }
         }
 
@@ -270,7 +452,7 @@ impl ShellCore {
             self.flip_exit_status();
         }
 
-        self.check_e_option();
+        self.check_e_option(exclamation);
 
         ans
     }
@@ -282,9 +464,15 @@ impl ShellCore {
 
         if self.builtins.contains_key(&args[0]) {
             let func = self.builtins[&args[0]];
+            let name = args[0].clone();
             args.append(special_args);
-            let status = func(self, args);
-            self.data.set_layer_param("?", &status.to_string(), 0);
+            self.write_error = None;
+            let mut status = func(self, args);
+            if let Some(e) = self.write_error.take() {
+                error_message::print(&format!("{}: write error: {}", &name, &e), self, true);
+                status = 1;
+            }
+            self.data.set_exit_status(status);
             return true;
         }
 
@@ -292,19 +480,12 @@ impl ShellCore {
     }
 
     pub fn exit(&mut self) -> ! {
+        if let Some(com) = self.traps.remove("EXIT") { //removed first for exit in the trap
+            crate::signal::run_trap_command(self, &com);
+        }
         self.write_history_to_file();
 
-        let es_str = self.data.get_param("?");
-        let exit_status = match es_str.parse::<i32>() {
-            Ok(n)  => n%256,
-            Err(_) => {
-                let msg = format!("exit: {}: numeric argument required", es_str);
-                error_message::print(&msg, self, true);
-                2
-            },
-        };
-    
-        process::exit(exit_status)
+        process::exit(self.data.exit_status().code()%256)
     }
 
     fn set_subshell_parameters(&mut self) {
@@ -314,24 +495,36 @@ impl ShellCore {
             Ok(num) => self.data.set_layer_param("BASH_SUBSHELL", &(num+1).to_string(), 0),
             Err(_) =>  self.data.set_layer_param("BASH_SUBSHELL", "0", 0),
         };
+        self.data.reseed_random();
     }
 
     pub fn set_pgid(&self, pid: Pid, pgid: Pid) {
         let _ = unistd::setpgid(pid, pgid);
-        if pid.as_raw() == 0 && pgid.as_raw() == 0 { //以下3行追加
-            self.set_foreground();
+        if pgid.as_raw() != 0 {
+            return;
+        }
+
+        /* both the parent and the child try it since either can run first */
+        match pid.as_raw() {
+            0 => self.set_foreground(),
+            _ => { let _ = self.give_terminal(pid); },
         }
     }
 
     pub fn initialize_as_subshell(&mut self, pid: Pid, pgid: Pid){
         restore_signal(Signal::SIGINT);
         restore_signal(Signal::SIGTSTP);
+        restore_signal(Signal::SIGTTIN);
+        restore_signal(Signal::SIGTTOU);
         restore_signal(Signal::SIGPIPE);
 
+        crate::signal::reset_traps_for_subshell(self);
+
         self.is_subshell = true;
         self.set_pgid(pid, pgid);
         self.set_subshell_parameters();
         self.job_table.clear();
+        self.reaped_statuses.clear();
     }
 
     pub fn init_current_directory(&mut self) {
@@ -360,13 +553,37 @@ impl ShellCore {
         res
     }
 
-    pub fn get_ps4(&mut self) -> String {
-        let ps4 = self.data.get_param("PS4").trim_end().to_string();
-        let mut multi_ps4 = ps4.to_string();
-        for _ in 0..(self.source_level + self.eval_level) {
-            multi_ps4 += &ps4;
+    /* expanded like a double quoted word; xtrace is off during the expansion */
+    fn expand_ps4(&mut self) -> String {
+        let ps4 = self.data.get_param("PS4");
+        if ! ps4.contains("$") {
+            return ps4;
         }
 
-        multi_ps4
+        let xtrace = self.data.flags.contains('x');
+        self.data.flags.retain(|f| f != 'x');
+        self.push_source(InputSource::text("", "PS4"));
+
+        let mut feeder = Feeder::new(&format!("\"{}\"", ps4.replace("\"", "\\\"")));
+        let ans = match Word::parse(&mut feeder, self, false) {
+            Some(w) => w.eval_for_case_word(self).ok(),
+            None    => None,
+        };
+
+        self.pop_source();
+        if xtrace {
+            self.data.flags.push('x');
+        }
+        ans.unwrap_or(ps4)
+    }
+
+    /* the first character is repeated for each level of eval, source and $( ) */
+    pub fn get_ps4(&mut self) -> String {
+        let ps4 = self.expand_ps4();
+        let level = (self.source_level + self.eval_level + self.comsub_level) as usize;
+        match ps4.chars().next() {
+            Some(c) => c.to_string().repeat(level) + &ps4,
+            None    => ps4,
+        }
     }
 }
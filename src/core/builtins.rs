@@ -2,86 +2,179 @@
 //SPDX-FileCopyrightText: 2023 @caro@mi.shellgei.org
 //SPDX-License-Identifier: BSD-3-Clause
 
+mod alias;
 mod cd;
+pub mod command;
+mod dirs;
+mod echo;
+mod export;
+mod getopts;
 pub mod completion;
 mod history;
 mod job_commands;
 mod local;
 pub mod option_commands;
+mod printf;
 mod pwd;
 mod read;
 mod source;
 mod return_break;
+mod trap;
+mod ulimit;
+mod umask;
 mod unset;
-mod utils;
+pub mod utils;
 
-use crate::{Feeder, Script, ShellCore};
+use crate::{error_message, ShellCore};
+use crate::feeder::input::InputSource;
+use crate::utils::time_string;
+use self::utils::print_line;
+use nix::sys::resource;
+use nix::sys::resource::UsageWho;
+use nix::sys::time::TimeVal;
+
+/* the builtins that are found before functions in posix mode */
+pub fn is_special(name: &str) -> bool {
+    matches!(name, ":" | "." | "break" | "continue" | "eval" | "exec" | "exit" | "export"
+             | "readonly" | "return" | "set" | "shift" | "times" | "trap" | "unset")
+}
 
 impl ShellCore {
     pub fn set_builtins(&mut self) {
         self.builtins.insert(":".to_string(), true_);
-        self.builtins.insert("alias".to_string(), alias);
+        self.builtins.insert("alias".to_string(), alias::alias);
         self.builtins.insert("bg".to_string(), job_commands::bg);
+        self.builtins.insert("builtin".to_string(), builtin);
         self.builtins.insert("break".to_string(), return_break::break_);
+        self.builtins.insert("caller".to_string(), caller);
         self.builtins.insert("cd".to_string(), cd::cd);
         self.builtins.insert("compgen".to_string(), completion::compgen);
         self.builtins.insert("complete".to_string(), completion::complete);
+        self.builtins.insert("declare".to_string(), local::declare);
+        self.builtins.insert("dirs".to_string(), dirs::dirs);
+        self.builtins.insert("echo".to_string(), echo::echo);
         self.builtins.insert("eval".to_string(), eval);
         self.builtins.insert("exit".to_string(), exit);
+        self.builtins.insert("export".to_string(), export::export);
         self.builtins.insert("false".to_string(), false_);
         self.builtins.insert("fg".to_string(), job_commands::fg);
+        self.builtins.insert("getopts".to_string(), getopts::getopts);
+        self.builtins.insert("hash".to_string(), command::hash);
         self.builtins.insert("history".to_string(), history::history);
         self.builtins.insert("jobs".to_string(), job_commands::jobs);
         self.builtins.insert("local".to_string(), local::local);
+        self.builtins.insert("popd".to_string(), dirs::popd);
+        self.builtins.insert("printf".to_string(), printf::printf);
+        self.builtins.insert("pushd".to_string(), dirs::pushd);
         self.builtins.insert("pwd".to_string(), pwd::pwd);
         self.builtins.insert("read".to_string(), read::read);
+        self.builtins.insert("readonly".to_string(), local::readonly);
         self.builtins.insert("return".to_string(), return_break::return_);
         self.builtins.insert("set".to_string(), option_commands::set);
+        self.builtins.insert("shift".to_string(), shift);
         self.builtins.insert("shopt".to_string(), option_commands::shopt);
         self.builtins.insert("unset".to_string(), unset::unset);
         self.builtins.insert("source".to_string(), source::source);
         self.builtins.insert(".".to_string(), source::source);
+        self.builtins.insert("times".to_string(), times);
+        self.builtins.insert("trap".to_string(), trap::trap);
         self.builtins.insert("true".to_string(), true_);
+        self.builtins.insert("type".to_string(), command::type_);
+        self.builtins.insert("ulimit".to_string(), ulimit::ulimit);
+        self.builtins.insert("umask".to_string(), umask::umask);
+        self.builtins.insert("unalias".to_string(), alias::unalias);
         self.builtins.insert("wait".to_string(), job_commands::wait);
     }
 }
 
-pub fn alias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() == 1 {
-        for (k, v) in &core.data.aliases {
-            println!("alias {}='{}'", k, v);
-        }
+/* usually unwrapped by SimpleCommand before reaching here */
+pub fn builtin(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut args = args[1..].to_vec();
+    if args.first().is_some_and(|a| a == "--") {
+        args.remove(0);
+    }
+    if args.is_empty() {
         return 0;
     }
 
-    if args.len() == 2 && args[1].find("=") != None {
-        let kv: Vec<String> = args[1].split("=").map(|t| t.to_string()).collect();
-        core.data.aliases.insert(kv[0].clone(), kv[1..].join("="));
+    if ! core.run_builtin(&mut args, &mut vec![]) {
+        error_message::print(&format!("builtin: {}: not a shell builtin", &args[0]), core, true);
+        return 1;
     }
-
-    0
+    core.data.exit_status().code()
 }
 
-pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let mut feeder = Feeder::new(&args[1..].join(" "));
+/* the line of the call, the function and the file of a frame in the call stack */
+pub fn caller(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let linenos = core.data.get_array_all("BASH_LINENO");
+    let funcs = core.data.get_array_all("FUNCNAME");
+    let sources = core.data.get_array_all("BASH_SOURCE");
 
-    core.eval_level += 1;
-    match Script::parse(&mut feeder, core, false){
-        Some(mut s) => s.exec(core),
-        None        => {},
+    let n = match args.get(1) {
+        None => return match linenos.first() {
+            Some(l) => {
+                print_line(&format!("{} {}", l, sources.get(1).map_or("NULL", |s| s)), core);
+                0
+            },
+            None => 1,
+        },
+        Some(a) if a.starts_with('-') => {
+            error_message::print(&format!("caller: {}: invalid option", a), core, true);
+            error_message::print("caller: usage: caller [expr]", core, true);
+            return 2;
+        },
+        Some(a) => match a.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => {
+                error_message::print(&format!("caller: {}: invalid number", a), core, true);
+                error_message::print("caller: usage: caller [expr]", core, true);
+                return 2;
+            },
+        },
+    };
+
+    match (linenos.get(n), funcs.get(n+1), sources.get(n+1)) {
+        (Some(l), Some(f), Some(s)) => {
+            print_line(&format!("{} {} {}", l, f, s), core);
+            0
+        },
+        _ => 1,
     }
+}
 
+pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    core.eval_level += 1;
+    core.exec_source(InputSource::text(&args[1..].join(" "), "eval"));
     core.eval_level -= 1;
-    match core.data.get_param("?").parse::<i32>() {
-        Ok(es) => es,
-        _      => 1,
-    }
+    core.data.exit_status().code()
 }
 
+/* without an argument, the status of the last command is used */
 pub fn exit(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    eprintln!("exit");
-    if args.len() > 1 {
-        core.data.set_layer_param("?", &args[1], 0);
+    if core.data.flags.contains('i') && ! core.is_subshell {
+        eprintln!("exit");
+    }
+    if args.len() > 2 {
+        error_message::print("exit: too many arguments", core, true);
+        if core.data.flags.contains('i') {
+            return 1;
+        }
+        core.data.set_exit_status(1);
+        core.exit()
+    }
+    if ! core.jobtable_check_before_exit() {
+        return 1;
+    }
+
+    if let Some(arg) = args.get(1) {
+        let es = match arg.parse::<i64>() {
+            Ok(n)  => n.rem_euclid(256),
+            Err(_) => {
+                error_message::print(&format!("exit: {}: numeric argument required", arg), core, true);
+                2
+            },
+        };
+        core.data.set_exit_status(es as i32);
     }
     core.exit()
 }
@@ -90,6 +183,58 @@ pub fn false_(_: &mut ShellCore, _: &mut Vec<String>) -> i32 {
     1
 }
 
+pub fn shift(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let n = match args.len() {
+        1 => 1,
+        _ => match args[1].parse::<i64>() {
+            Ok(n) => n,
+            _ => {
+                error_message::print(&format!("shift: {}: numeric argument required", &args[1]), core, true);
+                return 1;
+            },
+        },
+    };
+
+    if n < 0 {
+        error_message::print(&format!("shift: {}: shift count out of range", n), core, true);
+        return 1;
+    }
+
+    let params = match core.data.position_parameters.last_mut() {
+        Some(p) => p,
+        None    => return 1,
+    };
+
+    let n = n as usize;
+    if n + 1 > params.len() {
+        return 1;
+    }
+    params.drain(1..n+1);
+    0
+}
+
+pub fn times(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if let Some(a) = args.get(1).filter(|a| a.starts_with("-") && a.len() > 1 && *a != "--") {
+        error_message::print(&format!("times: {}: invalid option", a), core, true);
+        error_message::print("times: usage: times", core, true);
+        return 2;
+    }
+
+    for who in [UsageWho::RUSAGE_SELF, UsageWho::RUSAGE_CHILDREN] {
+        let usage = match resource::getrusage(who) {
+            Ok(u) => u,
+            Err(e) => {
+                error_message::print(&format!("times: {}", e.desc()), core, true);
+                return 1;
+            },
+        };
+        let usec = |t: TimeVal| t.tv_sec() * 1_000_000 + t.tv_usec();
+        print_line(&format!("{} {}", time_string(usec(usage.user_time()), 3, true),
+                          time_string(usec(usage.system_time()), 3, true)), core);
+    }
+    0
+}
+
 pub fn true_(_: &mut ShellCore, _: &mut Vec<String>) -> i32 {
     0
 }
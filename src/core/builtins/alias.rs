@@ -0,0 +1,69 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use super::utils::print_line;
+
+fn print(name: &str, value: &str, core: &mut ShellCore) {
+    print_line(&format!("alias {}='{}'", name, value.replace("'", "'\\''")), core);
+}
+
+fn is_alias_name(name: &str) -> bool {
+    ! name.is_empty() && ! name.contains(|c: char| " \t\n;&|()<>'\"\\$`/".contains(c))
+}
+
+pub fn alias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut names = &args[1..];
+    if names.first().is_some_and(|a| a == "-p") {
+        names = &names[1..];
+    }
+
+    if names.is_empty() {
+        let mut list: Vec<(String, String)> = core.data.aliases.clone().into_iter().collect();
+        list.sort();
+        list.iter().for_each(|(k, v)| print(k, v, core));
+        return 0;
+    }
+
+    let mut ans = 0;
+    for a in names {
+        match a.split_once("=") {
+            Some((k, _)) if ! is_alias_name(k) => {
+                error_message::print(&format!("alias: `{}': invalid alias name", k), core, true);
+                ans = 1;
+            },
+            Some((k, v)) => {
+                core.data.aliases.insert(k.to_string(), v.to_string());
+            },
+            None => match core.data.aliases.get(a).cloned() {
+                Some(v) => print(a, &v, core),
+                None    => {
+                    error_message::print(&format!("alias: {}: not found", a), core, true);
+                    ans = 1;
+                },
+            },
+        }
+    }
+    ans
+}
+
+pub fn unalias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 2 {
+        error_message::print("unalias: usage: unalias [-a] name [name ...]", core, true);
+        return 2;
+    }
+
+    if args[1] == "-a" {
+        core.data.aliases.clear();
+        return 0;
+    }
+
+    let mut ans = 0;
+    for name in &args[1..] {
+        if core.data.aliases.remove(name).is_none() {
+            error_message::print(&format!("unalias: {}: not found", name), core, true);
+            ans = 1;
+        }
+    }
+    ans
+}
@@ -2,61 +2,112 @@
 //SPDX-FileCopyrightText: 2023 @caro@mi.shellgei.org
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, ShellCore};
+use crate::utils::file_check;
 use super::utils;
+use std::fs;
+use std::path::PathBuf;
 
 pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() > 2 {
-        eprintln!("sush: cd: too many arguments");
+    if core.is_restricted() {
+        error_message::print("cd: restricted", core, true);
         return 1;
     }
 
-    if args.len() == 1 { //only "cd"
-        return cd_1arg(core, args);
+    let mut physical = false;
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos] != "-" {
+        match args[pos].as_ref() {
+            "--" => {
+                pos += 1;
+                break;
+            },
+            "-L" => physical = false,
+            "-P" => physical = true,
+            opt  => {
+                error_message::print(&format!("cd: {}: invalid option", opt), core, true);
+                error_message::print("cd: usage: cd [-L|-P] [dir]", core, true);
+                return 2;
+            },
+        }
+        pos += 1;
     }
 
-    if args[1] == "-" { // cd -
-        cd_oldpwd(core, args)
-    }else{ // cd /some/dir
-        set_oldpwd(core);
-        change_directory(core, args)
+    if args.len() > pos + 1 {
+        error_message::print("cd: too many arguments", core, true);
+        return 1;
     }
-}
 
-fn cd_1arg(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let var = "~".to_string();
-    args.push(var);
-    set_oldpwd(core);
-    change_directory(core, args)
+    let (dir, print) = match args.get(pos).map(|s| s.as_str()) {
+        None      => match core.data.get_param("HOME") {
+            h if h.is_empty() => {
+                error_message::print("cd: HOME not set", core, true);
+                return 1;
+            },
+            h => (h, false),
+        },
+        Some("-") => match core.data.get_param("OLDPWD") {
+            o if o.is_empty() => {
+                error_message::print("cd: OLDPWD not set", core, true);
+                return 1;
+            },
+            o => (o, true),
+        },
+        Some(d)   => (d.to_string(), false),
+    };
+
+    let (path, found_in_cdpath) = search_cdpath(core, &dir);
+    change_directory(core, "cd", &dir, path, physical, print || found_in_cdpath)
 }
 
-fn cd_oldpwd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let old = core.data.get_param("OLDPWD");
-    if old != "" {
-        println!("{}", &old);
-        args[1] = old.to_string();
-    }else {
-        eprintln!("sush: cd: OLDPWD not set");
-        return 1;
+fn search_cdpath(core: &mut ShellCore, dir: &str) -> (PathBuf, bool) {
+    if dir.starts_with("/") || dir.starts_with("./") || dir.starts_with("../")
+    || dir == "." || dir == ".." {
+        return (utils::make_canonical_path(core, dir), false);
     }
 
-    set_oldpwd(core);
-    change_directory(core, args)
+    for entry in core.data.get_param("CDPATH").split(':') {
+        let candidate = match entry {
+            "" => dir.to_string(),
+            e  => format!("{}/{}", e, dir),
+        };
+
+        let path = utils::make_canonical_path(core, &candidate);
+        if file_check::is_dir(&path.display().to_string()) {
+            return (path, ! entry.is_empty());
+        }
+    }
+
+    (utils::make_canonical_path(core, dir), false)
 }
 
-fn set_oldpwd(core: &mut ShellCore) {
-    if let Some(old) = core.get_current_directory() {
-        core.data.set_layer_param("OLDPWD", &old.display().to_string(), 0);
-    };
+pub fn cd_to(core: &mut ShellCore, dir: &str, com: &str) -> bool {
+    let path = utils::make_canonical_path(core, dir);
+    change_directory(core, com, dir, path, false, false) == 0
 }
 
-fn change_directory(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let path = utils::make_canonical_path(core, &args[1]);
-    if core.set_current_directory(&path).is_ok() {
-        core.data.set_layer_param("PWD", &path.display().to_string(), 0);
-        0
-    }else{
-        eprintln!("sush: cd: {:?}: No such file or directory", &path);
-        1
+fn change_directory(core: &mut ShellCore, com: &str, dir: &str, path: PathBuf,
+                    physical: bool, print: bool) -> i32 {
+    let path = match physical {
+        true  => fs::canonicalize(&path).unwrap_or(path),
+        false => path,
+    };
+
+    let old = core.get_current_directory();
+    if let Err(e) = core.set_current_directory(&path) {
+        let msg = e.to_string();
+        let msg = msg.split(" (os error").next().unwrap_or(&msg);
+        error_message::print(&format!("{}: {}: {}", com, dir, msg), core, true);
+        return 1;
+    }
+
+    if let Some(old) = old {
+        core.data.set_layer_param("OLDPWD", &old.display().to_string(), 0);
+    }
+    core.data.set_layer_param("PWD", &path.display().to_string(), 0);
+
+    if print {
+        utils::print_line(&path.display().to_string(), core);
     }
+    0
 }
@@ -0,0 +1,258 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, utils, ShellCore};
+use crate::utils::file_check;
+use super::utils::print_line;
+
+enum Kind {
+    Alias(String),
+    Keyword,
+    Function,
+    Builtin,
+    Hashed(String),
+    File(String),
+}
+
+fn search_files(core: &mut ShellCore, name: &str, all: bool) -> Vec<Kind> {
+    if name.contains('/') {
+        return match file_check::is_regular_file(name) && file_check::is_executable(name) {
+            true  => vec![Kind::File(name.to_string())],
+            false => vec![],
+        };
+    }
+
+    if ! all {
+        if let Some(p) = core.hashed_path(name) {
+            return vec![Kind::Hashed(p)];
+        }
+    }
+
+    let mut ans = vec![];
+    for dir in core.data.get_param("PATH").split(':') {
+        match core.search_path(name, dir) {
+            Some(p) => ans.push(Kind::File(p)),
+            None    => continue,
+        }
+        if ! all {
+            break;
+        }
+    }
+    ans
+}
+
+/* what the name means as a command, in the order of priority */
+fn kinds(core: &mut ShellCore, name: &str, all: bool) -> Vec<Kind> {
+    let mut ans = vec![];
+    if let Some(a) = core.data.aliases.get(name) {
+        ans.push(Kind::Alias(a.clone()));
+    }
+    if utils::reserved(name) {
+        ans.push(Kind::Keyword);
+    }
+    if core.data.functions.contains_key(name) {
+        ans.push(Kind::Function);
+    }
+    if core.builtins.contains_key(name) {
+        ans.push(Kind::Builtin);
+    }
+
+    if ans.is_empty() || all {
+        ans.append(&mut search_files(core, name, all));
+    }
+    if ! all {
+        ans.truncate(1);
+    }
+    ans
+}
+
+fn describe(name: &str, kind: &Kind) -> String {
+    match kind {
+        Kind::Alias(a)  => format!("{} is aliased to `{}'", name, a),
+        Kind::Keyword   => format!("{} is a shell keyword", name),
+        Kind::Function  => format!("{} is a function", name),
+        Kind::Builtin   => format!("{} is a shell builtin", name),
+        Kind::Hashed(p) => format!("{} is hashed ({})", name, p),
+        Kind::File(p)   => format!("{} is {}", name, p),
+    }
+}
+
+fn type_word(kind: &Kind) -> &str {
+    match kind {
+        Kind::Alias(_)  => "alias",
+        Kind::Keyword   => "keyword",
+        Kind::Function  => "function",
+        Kind::Builtin   => "builtin",
+        Kind::Hashed(_) | Kind::File(_) => "file",
+    }
+}
+
+fn file_path(kind: &Kind) -> Option<&str> {
+    match kind {
+        Kind::Hashed(p) | Kind::File(p) => Some(p),
+        _ => None,
+    }
+}
+
+pub fn type_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut flags = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        pos += 1;
+        if args[pos-1] == "--" {
+            break;
+        }
+        for ch in args[pos-1][1..].chars() {
+            if "afptP".find(ch).is_none() {
+                error_message::print(&format!("type: -{}: invalid option", ch), core, true);
+                error_message::print("type: usage: type [-afptP] name [name ...]", core, true);
+                return 2;
+            }
+            flags.push(ch);
+        }
+    }
+
+    let all = flags.contains('a');
+    let mut ans = 0;
+    for name in &args[pos..] {
+        let list = match flags.contains('P') {
+            true  => search_files(core, name, all),
+            false => kinds(core, name, all),
+        };
+
+        if list.is_empty() {
+            if ! flags.contains('t') && ! flags.contains('p') && ! flags.contains('P') {
+                error_message::print(&format!("type: {}: not found", name), core, true);
+            }
+            ans = 1;
+        }
+
+        for kind in &list {
+            if flags.contains('t') {
+                print_line(type_word(kind), core);
+            }else if flags.contains('p') || flags.contains('P') {
+                if let Some(p) = file_path(kind) {
+                    print_line(p, core);
+                }
+            }else {
+                print_line(&describe(name, kind), core);
+            }
+        }
+    }
+    ans
+}
+
+/* for command -v and -V; the status is 0 if one of the names is found */
+pub fn command_v(core: &mut ShellCore, names: &[String], verbose: bool) -> i32 {
+    let mut ans = 1;
+    for name in names {
+        let kind = match kinds(core, name, false).pop() {
+            Some(k) => k,
+            None => {
+                if verbose {
+                    error_message::print(&format!("command: {}: not found", name), core, true);
+                }
+                continue;
+            },
+        };
+
+        ans = 0;
+        match (verbose, &kind) {
+            (true, _)            => print_line(&describe(name, &kind), core),
+            (false, Kind::Alias(a)) => print_line(&format!("alias {}='{}'", name, a.replace("'", "'\\''")), core),
+            (false, _)           => print_line(file_path(&kind).unwrap_or(name), core),
+        }
+    }
+    ans
+}
+
+fn hash_print(core: &mut ShellCore, reusable: bool) -> i32 {
+    let mut list: Vec<(String, (String, usize))> = core.hash_table.iter()
+                   .map(|(k, v)| (k.clone(), v.clone())).collect();
+    if list.is_empty() {
+        print_line("hash: hash table empty", core);
+        return 0;
+    }
+
+    list.sort();
+    if ! reusable {
+        print_line("hits\tcommand", core);
+    }
+    for (name, (path, hits)) in list {
+        match reusable {
+            true  => print_line(&format!("builtin hash -p {} {}", path, name), core),
+            false => print_line(&format!("{:4}\t{}", hits, path), core),
+        }
+    }
+    0
+}
+
+fn hash_not_found(name: &str, core: &mut ShellCore) -> i32 {
+    error_message::print(&format!("hash: {}: not found", name), core, true);
+    1
+}
+
+pub fn hash(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut flags = String::new();
+    let mut pathname = None;
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        pos += 1;
+        if args[pos-1] == "--" {
+            break;
+        }
+        for ch in args[pos-1][1..].chars() {
+            if ch == 'p' && pos < args.len() {
+                pathname = Some(args[pos].clone());
+                pos += 1;
+            }else if "dlrt".find(ch).is_some() {
+                flags.push(ch);
+            }else {
+                error_message::print(&format!("hash: -{}: invalid option", ch), core, true);
+                error_message::print("hash: usage: hash [-lr] [-p pathname] [-dt] [name ...]", core, true);
+                return 2;
+            }
+        }
+    }
+
+    if let Some(p) = pathname.as_ref().filter(|p| core.is_restricted() && p.contains('/')) {
+        error_message::print(&format!("hash: {}: restricted", p), core, true);
+        return 1;
+    }
+
+    core.check_hash_table_path();
+    if flags.contains('r') {
+        core.hash_table.clear();
+    }
+
+    let names = &args[pos..];
+    if names.is_empty() {
+        return match flags.contains('r') && ! flags.contains('l') {
+            true  => 0,
+            false => hash_print(core, flags.contains('l')),
+        };
+    }
+
+    let mut ans = 0;
+    for name in names {
+        if let Some(p) = &pathname {
+            core.hash_table.insert(name.to_string(), (p.clone(), 0));
+        }else if flags.contains('d') {
+            if core.hash_table.remove(name).is_none() {
+                ans = hash_not_found(name, core);
+            }
+        }else if flags.contains('t') {
+            match (core.hashed_path(name), names.len()) {
+                (Some(p), 1) => print_line(&p, core),
+                (Some(p), _) => print_line(&format!("{}\t{}", name, p), core),
+                (None, _)    => ans = hash_not_found(name, core),
+            }
+        }else if ! name.contains('/') && ! core.builtins.contains_key(name) {
+            core.hash_table.remove(name);
+            if core.hash_search(name).is_none() {
+                ans = hash_not_found(name, core);
+            }
+        }
+    }
+    ans
+}
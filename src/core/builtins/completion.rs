@@ -1,29 +1,43 @@
 //SPDX-FileCopyrightText: 2023 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{file_check, ShellCore, Feeder};
+use crate::{error_message, file_check, ShellCore, Feeder};
 use crate::elements::word::Word;
 use crate::utils;
-use crate::utils::directory;
+use crate::utils::{directory, glob};
+use super::utils::print_line;
 use faccess;
 use faccess::PathExt;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
-use rev_lines::RevLines;
-
-pub fn compgen_f(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
-    let path = match args.len() {
-        2 => "".to_string(),
-        _ => {
-            match args[2].as_str() {
-                "--" => args[3].to_string(),
-                _ => args[2].to_string(),
-            }
-        },
-    }.replace("\\", "");
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+
+/* the part of the shell state used for listing candidates off the main thread */
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: String,
+    pub extglob: bool,
+    pub names: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn new(core: &mut ShellCore) -> Self {
+        let mut names: Vec<String> = core.data.aliases.keys().cloned().collect();
+        names.extend(core.builtins.keys().cloned());
+        names.extend(core.data.functions.keys().cloned());
+
+        Snapshot {
+            path: core.data.get_param("PATH"),
+            extglob: core.shopts.query("extglob"),
+            names,
+        }
+    }
+}
 
+pub fn files(path: &str, extglob: bool, cancel: &AtomicBool) -> Vec<String> {
     let mut split: Vec<String> = path.split("/").map(|s| s.to_string()).collect();
     let key = match split.pop() {
         Some(g) => g, 
@@ -38,44 +52,37 @@ pub fn compgen_f(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
         return files.iter().map(|f| dir.clone() + &f).collect();
     }
 
-    let mut ans = directory::glob(&dir, &(key + "*"), core.shopts.query("extglob"));
-    ans.iter_mut().for_each(|a| { a.pop(); } );
+    let matcher = glob::compile(&key, extglob);
+    let mut files = directory::files(&dir);
+    files.append( &mut vec![".".to_string(), "..".to_string()] );
+
+    let mut ans: Vec<String> = files.iter()
+        .take_while(|_| ! cancel.load(Relaxed))
+        .filter(|f| ! f.starts_with(".") || key.starts_with("."))
+        .filter(|f| ! matcher.prefix_matches(f).is_empty())
+        .map(|f| dir.clone() + f).collect();
     ans.sort();
     ans
 }
 
-fn replace_args(args: &mut Vec<String>) -> bool {
-    if args.len() < 3 || args[1] != "-A" {
-        return true;
-    }
-
-    args.remove(1);
-    let replace = match args[1].as_str() {
-        "command" => "-c",
-        "directory" => "-d",
-        "file" => "-f",
-        "user" => "-u",
-        a => a,
-    };
-
-    args[1] = replace.to_string();
-    true
-}
-
-fn command_list(target: &String, core: &mut ShellCore) -> Vec<String> {
+fn command_list(target: &String, path: &str, cancel: &AtomicBool) -> Vec<String> {
 
     let mut comlist = HashSet::new();
-    for path in core.data.get_param("PATH").to_string().split(":") {
+    for path in path.split(":") {
         if utils::is_wsl() && path.starts_with("/mnt") {
             continue;
         }
 
         for command in directory::files(path).iter() {
-            if ! Path::new(&(path.to_owned() + "/" + command)).executable() {
+            if cancel.load(Relaxed) {
+                return vec![];
+            }
+
+            if ! command.starts_with(target) {
                 continue;
             }
 
-            if command.starts_with(target) {
+            if Path::new(&(path.to_owned() + "/" + command)).executable() {
                 comlist.insert(command.clone());
             }
         }
@@ -85,113 +92,57 @@ fn command_list(target: &String, core: &mut ShellCore) -> Vec<String> {
     ans
 }
 
-pub fn compgen(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 {
-        eprintln!("sush: {}: still unsupported", &args[0]);
-        return 1;
-    }
-
-    replace_args(args);
-
-    let ans = match args[1].as_str() {
-        "-c" => compgen_c(core, args),
-        "-d" => compgen_d(core, args),
-        "-f" => compgen_f(core, args),
-        "-h" => compgen_h(core, args), //history (sush original)
-        "-u" => compgen_u(core, args),
-        "-W" => {
-            if args.len() < 2 {
-                eprintln!("sush: compgen: -W: option requires an argument");
-                return 2;
-            }
-            compgen_large_w(core, args)
-        },
-        _ => {
-            eprintln!("sush: compgen: {}: invalid option", &args[1]);
-            return 2;
-        },
-    };
-
-    ans.iter().for_each(|a| println!("{}", &a));
-    0
-}
-
-fn get_head(args: &mut Vec<String>, pos: usize) -> String {
-    if args.len() > pos && args[pos] != "--" {
-        args[pos].clone()
-    }else if args.len() > pos+1 {
-        args[pos+1].clone()
-    }else{
-        "".to_string()
-    }
-}
-
-fn drop_unmatch(args: &mut Vec<String>, pos: usize, list: &mut Vec<String>) {
-    let head = get_head(args, pos);
-    if head != "" {
-        list.retain(|s| s.starts_with(&head));
-    }
-}
-
-pub fn compgen_c(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
+pub fn commands(head: &str, with_files: bool, snapshot: &Snapshot,
+                cancel: &AtomicBool) -> Vec<String> {
     let mut commands = vec![];
-    if args.len() > 2 {
-        commands.extend(compgen_f(core, args));
+    if with_files {
+        commands.extend(files(head, snapshot.extglob, cancel));
     }
     commands.retain(|p| Path::new(p).executable() || file_check::is_dir(p));
+    commands.extend(snapshot.names.iter().cloned());
 
-    let mut aliases: Vec<String> = core.data.aliases.clone().into_keys().collect();
-    commands.append(&mut aliases);
-    let mut builtins: Vec<String> = core.builtins.clone().into_keys().collect();
-    commands.append(&mut builtins);
-    let mut functions: Vec<String> = core.data.functions.clone().into_keys().collect();
-    commands.append(&mut functions);
-
-    let head = get_head(args, 2);
+    let head = head.to_string();
     if head != "" {
         commands.retain(|a| a.starts_with(&head));
     }
-    let mut command_in_paths = command_list(&head, core);
+    let mut command_in_paths = command_list(&head, &snapshot.path, cancel);
     commands.append(&mut command_in_paths);
     commands
 }
 
-fn compgen_d(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
-    let mut paths = compgen_f(core, args);
-    paths.retain(|p| file_check::is_dir(&p));
-    paths
-}
-
-pub fn compgen_h(core: &mut ShellCore, _: &mut Vec<String>) -> Vec<String> {
+pub fn compgen_h(core: &mut ShellCore) -> Vec<String> {
     let len = core.history.len();
     if len >= 10 {
         return core.history[0..10].to_vec();
     }
 
     let mut ans = core.history.to_vec();
+    while ans.len() < 10 {
+        ans.push("echo Hello World".to_string());
+    }
+    ans
+}
 
-    if let Ok(hist_file) = File::open(core.data.get_param("HISTFILE")){
-        for h in RevLines::new(BufReader::new(hist_file)) {
-            match h {
-                Ok(s) => ans.push(s),
-                _     => {},
-            }
+fn users() -> Vec<String> {
+    let mut ans = vec![];
 
-            if ans.len() >= 10 {
-                return ans;
+    if let Ok(f) = File::open("/etc/passwd") {
+        for line in BufReader::new(f).lines() {
+            match line {
+                Ok(line) => {
+                    let splits: Vec<&str> = line.split(':').collect();
+                    ans.push(splits[0].to_string());
+                },
+                _ => return vec![],
             }
         }
     }
-
-    while ans.len() < 10 {
-        ans.push("echo Hello World".to_string());
-    }
     ans
 }
 
-fn compgen_large_w(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
+fn wordlist(core: &mut ShellCore, words: &str, cur: &str) -> Vec<String> {
     let mut ans: Vec<String> = vec![];
-    let mut feeder = Feeder::new(&args[2]);
+    let mut feeder = Feeder::new(words);
     while feeder.len() != 0 {
         match Word::parse(&mut feeder, core, false) {
             Some(mut w) => {
@@ -205,35 +156,229 @@ fn compgen_large_w(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String>
         }
     }
 
-    drop_unmatch(args, 3, &mut ans);
+    ans.retain(|w| w.starts_with(cur));
     ans
 }
 
-fn compgen_u(_: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
-    let mut ans = vec![];
+/* the options of complete and compgen that are given as letters */
+const ACTIONS: [(char, &str); 9] = [
+    ('a', "alias"), ('b', "builtin"), ('c', "command"), ('d', "directory"), ('e', "export"),
+    ('f', "file"), ('h', "history"), ('u', "user"), ('v', "variable"),
+];
+
+fn action_candidates(core: &mut ShellCore, action: &str, cur: &str) -> Vec<String> {
+    let cancel = AtomicBool::new(false);
+    let mut names: Vec<String> = match action {
+        "alias"     => core.data.aliases.keys().cloned().collect(),
+        "builtin"   => core.builtins.keys().cloned().collect(),
+        "command"   => return commands(cur, ! cur.is_empty(), &Snapshot::new(core), &cancel),
+        "directory" => {
+            let mut paths = files(&cur.replace("\\", ""), core.shopts.query("extglob"), &cancel);
+            paths.retain(|p| file_check::is_dir(p));
+            return paths;
+        },
+        "export"    => core.data.get_exported_keys(),
+        "file"      => return files(&cur.replace("\\", ""), core.shopts.query("extglob"), &cancel),
+        "function"  => core.data.functions.keys().cloned().collect(),
+        "history"   => return compgen_h(core),
+        "user"      => users(),
+        "variable"  => core.data.get_keys(),
+        _ => vec![],
+    };
 
-    if let Ok(f) = File::open("/etc/passwd") {
-        for line in BufReader::new(f).lines() {
-            match line {
-                Ok(line) => {
-                    let splits: Vec<&str> = line.split(':').collect();
-                    ans.push(splits[0].to_string());
+    names.retain(|n| n.starts_with(cur));
+    names.sort();
+    names.dedup();
+    names
+}
+
+/* a completion specification registered by complete or given to compgen */
+#[derive(Debug, Clone, Default)]
+pub struct CompSpec {
+    pub actions: Vec<String>,
+    pub wordlist: Option<String>,
+    pub function: Option<String>,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl CompSpec {
+    /* the function is called with the command name, the current word and the previous word */
+    pub fn generate(&self, core: &mut ShellCore, com: &str, cur: &str, prev: &str) -> Vec<String> {
+        let mut ans = vec![];
+        for action in &self.actions {
+            ans.extend(action_candidates(core, action, cur));
+        }
+        if let Some(words) = &self.wordlist {
+            ans.extend(wordlist(core, words, cur));
+        }
+        if let Some(name) = &self.function {
+            core.data.set_array("COMPREPLY", &vec![]);
+            if let Some(mut f) = core.data.functions.get(name).cloned() {
+                let mut args = vec![name.clone(), com.to_string(), cur.to_string(), prev.to_string()];
+                f.run_as_command(&mut args, core);
+            }
+            ans.extend(core.data.get_array_all("COMPREPLY"));
+        }
+
+        ans.iter().map(|a| format!("{}{}{}", &self.prefix, a, &self.suffix)).collect()
+    }
+
+    fn print(&self, name: &str, core: &mut ShellCore) {
+        let quote = |s: &str| format!("'{}'", s.replace("'", "'\\''"));
+        let mut ans = "complete".to_string();
+        for action in &self.actions {
+            match ACTIONS.iter().find(|(_, a)| a == action) {
+                Some((c, _)) => ans += &format!(" -{}", c),
+                None         => ans += &format!(" -A {}", action),
+            }
+        }
+        if let Some(words) = &self.wordlist {
+            ans += &format!(" -W {}", quote(words));
+        }
+        if ! self.prefix.is_empty() {
+            ans += &format!(" -P {}", quote(&self.prefix));
+        }
+        if ! self.suffix.is_empty() {
+            ans += &format!(" -S {}", quote(&self.suffix));
+        }
+        if let Some(f) = &self.function {
+            ans += &format!(" -F {}", f);
+        }
+        print_line(&format!("{} {}", ans, name), core);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.actions.is_empty() && self.wordlist.is_none() && self.function.is_none()
+    }
+}
+
+fn usage(name: &str, core: &mut ShellCore) -> i32 {
+    match name {
+        "compgen" => error_message::print("compgen: usage: compgen [-abcdefuv] [-A action] [-W wordlist] [-F function] [-P prefix] [-S suffix] [word]", core, true),
+        _ => error_message::print("complete: usage: complete [-abcdefuv] [-pr] [-A action] [-W wordlist] [-F function] [-P prefix] [-S suffix] [name ...]", core, true),
+    }
+    2
+}
+
+/* flags are the letters of the options that are not a part of the specification */
+fn parse_spec(args: &[String], flags: &str, core: &mut ShellCore) -> Result<(CompSpec, String, Vec<String>), i32> {
+    let mut spec = CompSpec::default();
+    let mut given = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        let opts = args[pos].clone();
+        pos += 1;
+        if opts == "--" {
+            break;
+        }
+
+        for (i, ch) in opts[1..].char_indices() {
+            if let Some((_, action)) = ACTIONS.iter().find(|(c, _)| *c == ch) {
+                spec.actions.push(action.to_string());
+                continue;
+            }
+            if flags.contains(ch) {
+                given.push(ch);
+                continue;
+            }
+            if ! "AWFPS".contains(ch) {
+                error_message::print(&format!("{}: -{}: invalid option", &args[0], ch), core, true);
+                return Err(usage(&args[0], core));
+            }
+
+            let arg = match (&opts[i+2..], args.get(pos)) {
+                ("", Some(a)) => {
+                    pos += 1;
+                    a.clone()
                 },
-                _ => return vec![],
+                ("", None) => {
+                    error_message::print(&format!("{}: -{}: option requires an argument", &args[0], ch), core, true);
+                    return Err(usage(&args[0], core));
+                },
+                (a, _) => a.to_string(),
+            };
+
+            match ch {
+                'A' if arg == "function" || ACTIONS.iter().any(|(_, a)| *a == arg) => spec.actions.push(arg),
+                'A' => {
+                    error_message::print(&format!("{}: {}: invalid action name", &args[0], &arg), core, true);
+                    return Err(1);
+                },
+                'W' => spec.wordlist = Some(arg),
+                'F' => spec.function = Some(arg),
+                'P' => spec.prefix = arg,
+                _   => spec.suffix = arg,
             }
+            break;
+        }
+    }
+
+    Ok((spec, given, args[pos..].to_vec()))
+}
+
+pub fn compgen(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (spec, _, words) = match parse_spec(args, "", core) {
+        Ok(ans) => ans,
+        Err(e)  => return e,
+    };
+
+    let cur = words.first().cloned().unwrap_or_default();
+    let ans = spec.generate(core, "compgen", &cur, "");
+    ans.iter().for_each(|a| print_line(a, core));
+    match ans.is_empty() {
+        true  => 1,
+        false => 0,
+    }
+}
+
+fn remove_specs(core: &mut ShellCore, names: &[String]) -> i32 {
+    match names.is_empty() {
+        true  => core.completion_specs.clear(),
+        false => names.iter().for_each(|n| { core.completion_specs.remove(n); }),
+    }
+    0
+}
+
+fn print_specs(core: &mut ShellCore, names: &[String]) -> i32 {
+    if names.is_empty() {
+        let mut names: Vec<String> = core.completion_specs.keys().cloned().collect();
+        names.sort();
+        for n in &names {
+            let spec = core.completion_specs[n].clone();
+            spec.print(n, core);
         }
+        return 0;
     }
 
-    drop_unmatch(args, 2, &mut ans);
+    let mut ans = 0;
+    for name in names {
+        match core.completion_specs.get(name).cloned() {
+            Some(spec) => spec.print(name, core),
+            None => {
+                error_message::print(&format!("complete: {}: no completion specification", name), core, true);
+                ans = 1;
+            },
+        }
+    }
     ans
 }
 
 pub fn complete(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() < 4 || args[1] != "-F" {
-        eprintln!("sush: {}: still unsupported", &args[0]);
-        return 1;
+    let (spec, flags, names) = match parse_spec(args, "pr", core) {
+        Ok(ans) => ans,
+        Err(e)  => return e,
+    };
+
+    if flags.contains('r') {
+        return remove_specs(core, &names);
+    }
+    if flags.contains('p') || spec.is_empty() {
+        return print_specs(core, &names);
     }
 
-    core.completion_functions.insert(args[3].clone(), args[2].clone());
+    for name in names {
+        core.completion_specs.insert(name, spec.clone());
+    }
     0
 }
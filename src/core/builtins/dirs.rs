@@ -0,0 +1,218 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use super::cd;
+use super::utils::print_line;
+
+/* the current directory followed by the saved ones */
+fn full_stack(core: &mut ShellCore) -> Vec<String> {
+    let cwd = match core.get_current_directory() {
+        Some(p) => p.display().to_string(),
+        None    => core.data.get_param("PWD"),
+    };
+
+    let mut ans = vec![cwd];
+    ans.extend(core.dir_stack.iter().cloned());
+    ans
+}
+
+fn with_tilde(core: &mut ShellCore, dir: &str) -> String {
+    let home = core.data.get_param("HOME");
+    if home.is_empty() || home == "/" {
+        return dir.to_string();
+    }
+
+    match dir.strip_prefix(&home) {
+        Some(rest) if rest.is_empty() || rest.starts_with("/") => format!("~{}", rest),
+        _ => dir.to_string(),
+    }
+}
+
+/* +N counts from the left of the list printed by dirs, -N from the right */
+fn parse_index(arg: &str, len: usize) -> Option<Option<usize>> {
+    let from_left = arg.starts_with("+");
+    if ! from_left && ! arg.starts_with("-") {
+        return None;
+    }
+
+    let n = arg[1..].parse::<usize>().ok()?;
+    match (from_left, n < len) {
+        (true, true)   => Some(Some(n)),
+        (false, true)  => Some(Some(len - 1 - n)),
+        (_, false)     => Some(None),
+    }
+}
+
+fn out_of_range(com: &str, arg: &str, core: &mut ShellCore) -> i32 {
+    error_message::print(&format!("{}: {}: directory stack index out of range", com, arg), core, true);
+    1
+}
+
+fn print_stack(core: &mut ShellCore, tilde: bool, vertical: bool, numbered: bool) {
+    let stack = full_stack(core);
+    let dirs: Vec<String> = stack.iter()
+        .map(|d| if tilde { with_tilde(core, d) } else { d.clone() })
+        .collect();
+
+    if numbered {
+        dirs.iter().enumerate().for_each(|(i, d)| print_line(&format!("{:2}  {}", i, d), core));
+    }else if vertical {
+        dirs.iter().for_each(|d| print_line(d, core));
+    }else {
+        print_line(&dirs.join(" "), core);
+    }
+}
+
+pub fn dirs(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut tilde = true;
+    let mut vertical = false;
+    let mut numbered = false;
+
+    for a in &args[1..] {
+        if a.len() > 1 && a[1..].chars().all(|c| c.is_ascii_digit()) {
+            let stack = full_stack(core);
+            return match parse_index(a, stack.len()) {
+                Some(Some(n)) => {
+                    let d = match tilde {
+                        true  => with_tilde(core, &stack[n]),
+                        false => stack[n].clone(),
+                    };
+                    print_line(&d, core);
+                    0
+                },
+                _ => out_of_range("dirs", &a[1..], core),
+            };
+        }
+
+        match a.as_ref() {
+            "-c" => {
+                core.dir_stack.clear();
+                return 0;
+            },
+            "-l" => tilde = false,
+            "-p" => vertical = true,
+            "-v" => numbered = true,
+            _ => {
+                error_message::print(&format!("dirs: {}: invalid option", a), core, true);
+                error_message::print("dirs: usage: dirs [-clpv] [+N] [-N]", core, true);
+                return 2;
+            },
+        }
+    }
+
+    print_stack(core, tilde, vertical, numbered);
+    0
+}
+
+fn rotate(core: &mut ShellCore, arg: &str) -> i32 {
+    if core.dir_stack.is_empty() {
+        error_message::print("pushd: directory stack empty", core, true);
+        return 1;
+    }
+
+    let mut stack = full_stack(core);
+    let n = match parse_index(arg, stack.len()) {
+        Some(Some(n)) => n,
+        _ => return out_of_range("pushd", arg, core),
+    };
+
+    stack.rotate_left(n);
+    if ! cd::cd_to(core, &stack[0], "pushd") {
+        return 1;
+    }
+    core.dir_stack = stack[1..].to_vec();
+    print_stack(core, true, false, false);
+    0
+}
+
+fn swap_top(core: &mut ShellCore) -> i32 {
+    if core.dir_stack.is_empty() {
+        error_message::print("pushd: no other directory", core, true);
+        return 1;
+    }
+
+    let cwd = full_stack(core).remove(0);
+    let dir = core.dir_stack[0].clone();
+    if ! cd::cd_to(core, &dir, "pushd") {
+        return 1;
+    }
+    core.dir_stack[0] = cwd;
+    print_stack(core, true, false, false);
+    0
+}
+
+pub fn pushd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut no_cd = false;
+    let mut pos = 1;
+    if args.get(pos).is_some_and(|a| a == "-n") {
+        no_cd = true;
+        pos += 1;
+    }
+
+    let arg = match args.get(pos) {
+        Some(a) => a.clone(),
+        None    => return swap_top(core),
+    };
+
+    if arg.len() > 1 && arg[1..].chars().all(|c| c.is_ascii_digit())
+    && (arg.starts_with("+") || arg.starts_with("-")) {
+        return rotate(core, &arg);
+    }
+
+    if no_cd {
+        core.dir_stack.insert(0, arg);
+        print_stack(core, true, false, false);
+        return 0;
+    }
+
+    let cwd = full_stack(core).remove(0);
+    if ! cd::cd_to(core, &arg, "pushd") {
+        return 1;
+    }
+    core.dir_stack.insert(0, cwd);
+    print_stack(core, true, false, false);
+    0
+}
+
+pub fn popd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut no_cd = false;
+    let mut index = 0;
+    for a in &args[1..] {
+        if a == "-n" {
+            no_cd = true;
+            continue;
+        }
+
+        let len = core.dir_stack.len() + 1;
+        index = match parse_index(a, len) {
+            Some(Some(n)) => n,
+            Some(None) => return out_of_range("popd", a, core),
+            None => {
+                error_message::print(&format!("popd: {}: invalid argument", a), core, true);
+                error_message::print("popd: usage: popd [-n] [+N | -N]", core, true);
+                return 2;
+            },
+        };
+    }
+
+    if core.dir_stack.is_empty() {
+        error_message::print("popd: directory stack empty", core, true);
+        return 1;
+    }
+
+    if index > 0 {
+        core.dir_stack.remove(index - 1);
+    }else if no_cd {
+        core.dir_stack.remove(0);
+    }else {
+        let dir = core.dir_stack[0].clone();
+        if ! cd::cd_to(core, &dir, "popd") {
+            return 1;
+        }
+        core.dir_stack.remove(0);
+    }
+
+    print_stack(core, true, false, false);
+    0
+}
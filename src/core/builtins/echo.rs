@@ -0,0 +1,55 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use crate::utils::bytes;
+use super::printf;
+use std::io;
+use std::io::Write;
+
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with("-")
+    && arg[1..].chars().all(|c| "neE".contains(c))
+}
+
+pub fn echo(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut newline = true;
+    let mut escape = false;
+    let mut pos = 1;
+    while pos < args.len() && is_option(&args[pos]) {
+        for c in args[pos][1..].chars() {
+            match c {
+                'n' => newline = false,
+                'e' => escape = true,
+                _   => escape = false,
+            }
+        }
+        pos += 1;
+    }
+
+    let mut out = vec![];
+    for (i, a) in args[pos..].iter().enumerate() {
+        if i > 0 {
+            out.push(b' ');
+        }
+
+        match escape {
+            true => if ! printf::interpret_b(a, &mut out) {
+                newline = false;
+                break;
+            },
+            false => out.append(&mut bytes::encode(a)),
+        }
+    }
+
+    if newline {
+        out.push(b'\n');
+    }
+
+    let mut stdout = io::stdout();
+    if let Err(e) = stdout.write_all(&out).and_then(|_| stdout.flush()) {
+        error_message::print(&format!("echo: write error: {}", error_message::io_error(&e)), core, true);
+        return 1;
+    }
+    0
+}
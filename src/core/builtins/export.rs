@@ -0,0 +1,136 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore, Feeder};
+use crate::core::data::Value;
+use crate::elements::command::Command;
+use crate::elements::substitution::Substitution;
+use super::local::declare_line;
+use super::utils::{is_varname, print_line};
+
+fn print_usage(core: &mut ShellCore) {
+    error_message::print("export: usage: export [-fn] [name[=value] ...] or export -p", core, true);
+}
+
+fn print_all(core: &mut ShellCore) -> i32 {
+    for key in core.data.get_exported_keys() {
+        if let Some(line) = declare_line(&key, core) {
+            print_line(&line, core);
+        }
+    }
+    0
+}
+
+fn print_functions(core: &mut ShellCore) -> i32 {
+    for name in core.data.get_exported_functions() {
+        print_line(core.data.functions[&name].get_text().trim_end(), core);
+        print_line(&format!("declare -fx {}", &name), core);
+    }
+    0
+}
+
+fn export_function(name: &str, unexport: bool, core: &mut ShellCore) -> bool {
+    if ! core.data.functions.contains_key(name) {
+        error_message::print(&format!("export: {}: not a function", name), core, true);
+        return false;
+    }
+
+    match unexport {
+        true  => core.data.unexport_function(name),
+        false => core.data.export_function(name),
+    }
+    true
+}
+
+fn set(arg: &str, core: &mut ShellCore) -> bool {
+    if is_varname(arg) {
+        core.data.export(arg);
+        return true;
+    }
+
+    let mut sub = match Substitution::parse(&mut Feeder::new(arg), core) {
+        Some(s) => s,
+        _ => {
+            error_message::print(&format!("export: `{}': not a valid identifier", arg), core, true);
+            return false;
+        },
+    };
+
+    let ok = match sub.eval(core) {
+        Ok(Value::EvaluatedSingle(s)) => core.data.set_param(&sub.key, &s),
+        Ok(Value::EvaluatedArray(a))  => core.data.set_array(&sub.key, &a.values().cloned().collect::<Vec<String>>()),
+        Err(e) => {
+            e.print(core);
+            return false;
+        },
+        _ => error_message::internal("unsupported substitution"),
+    };
+    core.data.export(&sub.key);
+    ok
+}
+
+fn unset(arg: &str, core: &mut ShellCore) -> bool {
+    let name = match arg.split_once('=') {
+        Some((name, _)) => name,
+        None            => arg,
+    };
+
+    if ! is_varname(name) {
+        error_message::print(&format!("export: `{}': not a valid identifier", arg), core, true);
+        return false;
+    }
+
+    if name.len() < arg.len() && ! set(arg, core) {
+        return false;
+    }
+    core.data.unexport(name);
+    true
+}
+
+pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut unexport = false;
+    let mut function = false;
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") {
+        match args[pos].as_ref() {
+            "--" => {
+                pos += 1;
+                break;
+            },
+            "-p" => {},
+            "-n" => unexport = true,
+            "-f" => function = true,
+            "-fn" | "-nf" => {
+                function = true;
+                unexport = true;
+            },
+            opt  => {
+                error_message::print(&format!("export: {}: invalid option", opt), core, true);
+                print_usage(core);
+                return 2;
+            },
+        }
+        pos += 1;
+    }
+
+    if pos >= args.len() {
+        return match function {
+            true  => print_functions(core),
+            false => print_all(core),
+        };
+    }
+
+    let mut ok = true;
+    for a in &args[pos..] {
+        ok &= match (function, unexport) {
+            (true, _)  => export_function(a, unexport, core),
+            (_, true)  => unset(a, core),
+            (_, false) => set(a, core),
+        };
+    }
+
+    match ok {
+        true  => 0,
+        false => 1,
+    }
+}
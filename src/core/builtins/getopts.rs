@@ -0,0 +1,114 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use super::utils::is_varname;
+
+fn set_result(core: &mut ShellCore, name: &str, opt: &str, optarg: Option<&str>) -> i32 {
+    match optarg {
+        Some(a) => core.data.set_param("OPTARG", a),
+        None    => { core.data.unset_var("OPTARG"); true },
+    };
+
+    match core.data.set_param(name, opt) {
+        true  => 0,
+        false => 1,
+    }
+}
+
+fn end(core: &mut ShellCore, name: &str, optind: usize) -> i32 {
+    core.data.set_param("OPTIND", &optind.to_string());
+    core.getopts_state = (optind, 1, String::new());
+    set_result(core, name, "?", None);
+    1
+}
+
+/* the position in a grouped argument like -abc is kept while OPTIND and
+ * the argument are untouched */
+pub fn getopts(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 3 {
+        error_message::print("getopts: usage: getopts optstring name [arg ...]", core, true);
+        return 2;
+    }
+    if ! is_varname(&args[2]) {
+        error_message::print(&format!("getopts: `{}': not a valid identifier", &args[2]), core, true);
+        return 1;
+    }
+
+    let (optstring, name) = (args[1].clone(), args[2].clone());
+    let params = match args.len() {
+        3 => core.data.get_position_params(),
+        _ => args[3..].to_vec(),
+    };
+
+    let mut optind = core.data.get_param("OPTIND").parse::<usize>().unwrap_or(1).max(1);
+    let arg: Vec<char> = match params.get(optind - 1) {
+        Some(a) => a.chars().collect(),
+        None    => return end(core, &name, optind),
+    };
+
+    let (last_optind, last_pos, last_arg) = &core.getopts_state;
+    let mut pos = match *last_optind == optind && *last_arg == params[optind - 1] {
+        true  => *last_pos,
+        false => 1,
+    };
+    if pos >= arg.len() {
+        pos = 1;
+    }
+    if pos == 1 {
+        if arg.len() == 2 && arg[0] == '-' && arg[1] == '-' {
+            return end(core, &name, optind + 1);
+        }
+        if arg.len() < 2 || arg[0] != '-' {
+            return end(core, &name, optind);
+        }
+    }
+
+    let ch = match arg.get(pos) {
+        Some(c) => *c,
+        None    => return end(core, &name, optind),
+    };
+    pos += 1;
+    let rest: String = arg[pos..].iter().collect();
+    if rest.is_empty() {
+        optind += 1;
+        pos = 1;
+    }
+
+    let silent = optstring.starts_with(":");
+    let quiet = silent || core.data.get_param("OPTERR") == "0";
+    let script = core.data.get_param("0");
+    let spec = optstring.trim_start_matches(':');
+    let status = match spec.find(ch) {
+        None => {
+            if ! quiet {
+                eprintln!("{}: illegal option -- {}", script, ch);
+            }
+            let optarg = ch.to_string();
+            set_result(core, &name, "?", silent.then_some(&optarg))
+        },
+        Some(i) if spec[i+ch.len_utf8()..].starts_with(":") => {
+            if ! rest.is_empty() {
+                optind += 1;
+                pos = 1;
+                set_result(core, &name, &ch.to_string(), Some(&rest))
+            }else if let Some(a) = params.get(optind - 1) {
+                optind += 1;
+                set_result(core, &name, &ch.to_string(), Some(a))
+            }else if silent {
+                set_result(core, &name, ":", Some(&ch.to_string()))
+            }else {
+                if ! quiet {
+                    eprintln!("{}: option requires an argument -- {}", script, ch);
+                }
+                set_result(core, &name, "?", None)
+            }
+        },
+        Some(_) => set_result(core, &name, &ch.to_string(), None),
+    };
+
+    core.data.set_param("OPTIND", &optind.to_string());
+    let arg = params.get(optind - 1).cloned().unwrap_or_default();
+    core.getopts_state = (optind, pos, arg);
+    status
+}
@@ -1,33 +1,145 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::{error_message, ShellCore};
+use super::utils::print_line;
 
-pub fn history(core: &mut ShellCore, _: &mut Vec<String>) -> i32 {
-    let mut number = 1;
+fn usage(core: &mut ShellCore) -> i32 {
+    error_message::print("history: usage: history [-c] [-d offset] [n] or history -arw [filename] or history -s arg [arg...]", core, true);
+    2
+}
+
+fn print(core: &mut ShellCore, num: Option<&String>) -> i32 {
+    let (len, next) = (core.history.len(), core.history_number);
+    let num = match num.map(|n| n.parse::<usize>()) {
+        None        => len,
+        Some(Ok(n)) => n.min(len),
+        Some(Err(_)) => {
+            error_message::print(&format!("history: {}: numeric argument required", num.unwrap()), core, true);
+            return 1;
+        },
+    };
 
-    let filename = core.data.get_param("HISTFILE");
-    if filename == "" {
-        return 0;
+    let history = core.history[..num].to_vec();
+    for (i, h) in history.iter().enumerate().rev() {
+        print_line(&format!("{:5}  {}", next - 1 - i, h.replace("↵ \0", "\n")), core);
     }
+    0
+}
 
-    let file = match File::open(&filename) {
-        Ok(f) => f,
-        _     => return 0,
+/* the position is the number printed by history, or counts from the end if negative */
+fn position(core: &mut ShellCore, arg: &str) -> Option<usize> {
+    let next = core.history_number as i64;
+    let base = next - core.history.len() as i64;
+    let n = match arg.parse::<i64>() {
+        Ok(n) if n < 0 => next + n,
+        Ok(n) => n,
+        _ => 0,
     };
 
-    let f = BufReader::new(file);
-    for line in f.lines() {
-        println!("{:5} {}", number, &line.unwrap());
-        number += 1;
+    match base <= n && n < next {
+        true  => Some((next - 1 - n) as usize),
+        false => {
+            error_message::print(&format!("history: {}: history position out of range", arg), core, true);
+            None
+        },
     }
+}
 
-    for h in core.history.iter().rev() {
-        println!("{:5} {}", number, &h);
-        number += 1;
+fn delete(core: &mut ShellCore, arg: &str) -> i32 {
+    let range = match arg.char_indices().skip(1).find(|(_, c)| *c == '-') {
+        Some((i, _)) => (position(core, &arg[..i]), position(core, &arg[i+1..])),
+        None         => {
+            let p = position(core, arg);
+            (p, p)
+        },
+    };
+
+    match range {
+        (Some(from), Some(to)) if to <= from => {
+            core.history.drain(to..=from);
+            core.history_number -= from - to + 1;
+            0
+        },
+        (Some(_), Some(_)) => {
+            error_message::print(&format!("history: {}: history position out of range", arg), core, true);
+            1
+        },
+        _ => 1,
     }
+}
 
-    0
+fn file_operation(core: &mut ShellCore, opt: char, filename: Option<&String>) -> i32 {
+    let filename = match filename {
+        Some(f) => f.clone(),
+        None    => core.data.get_param("HISTFILE"),
+    };
+    if filename.is_empty() {
+        return 1;
+    }
+
+    let result = match opt {
+        'a' => core.write_history_file(&filename, true),
+        'w' => core.write_history_file(&filename, false),
+        _   => core.read_history_file(&filename),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(_) if opt == 'r' => 1,
+        Err(e) => {
+            error_message::print(&format!("history: {}: {}", &filename, e), core, true);
+            1
+        },
+    }
+}
+
+pub fn history(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        let opts = args[pos].clone();
+        pos += 1;
+        if opts == "--" {
+            break;
+        }
+
+        for (i, ch) in opts[1..].char_indices() {
+            match ch {
+                'c' => {
+                    core.history.clear();
+                    core.history_number = 1;
+                },
+                'd' => {
+                    let arg = match (&opts[i+2..], args.get(pos)) {
+                        ("", Some(a)) => a.clone(),
+                        ("", None) => {
+                            error_message::print("history: -d: option requires an argument", core, true);
+                            return usage(core);
+                        },
+                        (a, _) => a.to_string(),
+                    };
+                    return delete(core, &arg);
+                },
+                'a' | 'r' | 'w' => return file_operation(core, ch, args.get(pos)),
+                's' => {
+                    if ! core.history.is_empty() {
+                        core.history.remove(0);
+                        core.history_number -= 1;
+                    }
+                    let line = args[pos..].join(" ");
+                    core.add_history(&line);
+                    return 0;
+                },
+                _ => {
+                    error_message::print(&format!("history: -{}: invalid option", ch), core, true);
+                    return usage(core);
+                },
+            }
+        }
+    }
+
+    match pos {
+        1 => print(core, args.get(pos)),
+        _ => 0,
+    }
 }
@@ -1,12 +1,14 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, signal, ShellCore};
 use crate::core::JobEntry;
-use crate::core::{ignore_signal, restore_signal};
-use nix::sys::signal::Signal;
+use super::utils::print_line;
+use nix::errno::Errno;
+use nix::sys::wait;
 use nix::unistd;
 use nix::unistd::Pid;
+use std::sync::atomic::Ordering::Relaxed;
 
 fn id_to_job(id: usize, jobs: &mut Vec<JobEntry>) -> Option<&mut JobEntry> {
     for job in jobs.iter_mut() {
@@ -19,7 +21,7 @@ fn id_to_job(id: usize, jobs: &mut Vec<JobEntry>) -> Option<&mut JobEntry> {
 }
 
 fn arg_to_id(s: &str, priority: &Vec<usize>) -> usize {
-    if s == "%+" {
+    if s == "%+" || s == "%%" {
         return match priority.len() {
             0 => 0, 
             _ => priority[0],
@@ -87,43 +89,140 @@ pub fn fg(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
         return 1;
     }
 
-    ignore_signal(Signal::SIGTTOU);
-
     let mut exit_status = 1;
     if let Ok(_) =  unistd::tcsetpgrp(fd, pgid) {
         eprintln!("{}", &job.text);
         job.send_cont();
-        exit_status = job.update_status(true);
+        exit_status = job.update_status(true, &core.sigint);
 
         if let Ok(mypgid) = unistd::getpgid(Some(Pid::from_raw(0))) {
             let _ = unistd::tcsetpgrp(fd, mypgid);
         }
     }
 
-    restore_signal(Signal::SIGTTOU);
     exit_status
 }
 
 pub fn jobs(core: &mut ShellCore, _: &mut Vec<String>) -> i32 {
-    for job in core.job_table.iter() {
-        job.print(&core.job_table_priority);
-    }
+    let lines: Vec<String> = core.job_table.iter()
+        .map(|job| job.status_line(&core.job_table_priority)).collect();
+    lines.iter().for_each(|line| print_line(line, core));
     0
 }
 
+fn wait_job(core: &mut ShellCore, arg: &str) -> i32 {
+    let id = arg_to_id(arg, &core.job_table_priority);
+    let pos = match core.job_table.iter().position(|j| j.id == id) {
+        Some(p) => p,
+        None    => {
+            error_message::print(&format!("wait: {}: no such job", arg), core, true);
+            return 127;
+        },
+    };
+
+    core.job_table[pos].update_status(true, &core.sigint);
+    if core.sigint.load(Relaxed) {
+        return 130;
+    }
+    let exit_status = core.job_table[pos].exit_status();
+    core.remove_job(pos);
+    exit_status
+}
+
+fn wait_pid(core: &mut ShellCore, arg: &str) -> i32 {
+    let pid = match arg.parse::<i32>() {
+        Ok(n) if n > 0 => Pid::from_raw(n),
+        _ => {
+            error_message::print(&format!("wait: `{}': not a pid or valid job spec", arg), core, true);
+            return 2;
+        },
+    };
+
+    if let Some(pos) = core.job_table.iter().position(|j| j.has_pid(pid)) {
+        let exit_status = core.job_table[pos].wait_pid(pid, &core.sigint);
+        if core.job_table[pos].is_finished() {
+            core.remove_job(pos);
+        }
+        return exit_status;
+    }
+
+    match core.reaped_statuses.remove(&pid) {
+        Some(es) => es,
+        None     => {
+            error_message::print(&format!("wait: pid {} is not a child of this shell", arg), core, true);
+            127
+        },
+    }
+}
+
+/* wait -n: returns the status of the first job that finishes */
+fn wait_next(core: &mut ShellCore, args: &[String]) -> i32 {
+    let mut ids: Vec<usize> = vec![];
+    for a in args {
+        match a.starts_with("%") {
+            true  => ids.push(arg_to_id(a, &core.job_table_priority)),
+            false => if let Ok(n) = a.parse::<i32>() {
+                let pid = Pid::from_raw(n);
+                ids.extend(core.job_table.iter().filter(|j| j.has_pid(pid)).map(|j| j.id));
+            },
+        }
+    }
+
+    loop {
+        let targets: Vec<usize> = core.job_table.iter().enumerate()
+            .filter(|(_, j)| args.is_empty() || ids.contains(&j.id))
+            .map(|(i, _)| i).collect();
+
+        if targets.is_empty() {
+            return 127;
+        }
+
+        if let Some(pos) = targets.iter().find(|i| core.job_table[**i].is_finished()) {
+            let exit_status = core.job_table[*pos].exit_status();
+            core.remove_job(*pos);
+            return exit_status;
+        }
+
+        if core.sigint.load(Relaxed) {
+            return 130;
+        }
+
+        match signal::interruptible(|| wait::waitpid(Pid::from_raw(-1), None)) {
+            Err(Errno::EINTR) => {},
+            Ok(ws) => if let Some(pid) = ws.pid() {
+                core.job_table.iter_mut()
+                    .for_each(|j| j.set_status(pid, ws));
+            },
+            Err(_) => return 127,
+        }
+    }
+}
+
 pub fn wait(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.get(1).is_some_and(|a| a == "-n") {
+        return wait_next(core, &args[2..]);
+    }
+
     if args.len() <= 1 {
         for job in core.job_table.iter_mut() {
-            job.update_status(true);
+            job.update_status(true, &core.sigint);
+        }
+        if core.sigint.load(Relaxed) {
+            return 130;
         }
+        core.job_table.retain(|j| ! j.is_finished());
+        let ids: Vec<usize> = core.job_table.iter().map(|j| j.id).collect();
+        core.job_table_priority.retain(|id| ids.contains(id));
+        core.reaped_statuses.clear();
         return 0;
     }
 
-    let id = arg_to_id(&args[1], &core.job_table_priority);
-    match id_to_job(id, &mut core.job_table) {
-        Some(job) => {job.update_status(true);},
-        _ => return 1, 
+    let mut exit_status = 0;
+    for a in &args[1..] {
+        exit_status = match a.starts_with("%") {
+            true  => wait_job(core, a),
+            false => wait_pid(core, a),
+        };
     }
-
-    0
+    exit_status
 }
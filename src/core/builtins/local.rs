@@ -4,33 +4,198 @@
 use crate::{error_message, ShellCore, Feeder};
 use crate::core::data::Value;
 use crate::elements::substitution::Substitution;
+use super::option_commands;
+use super::utils::{array_for_declare, is_varname, print_line, quote_for_declare};
+
+/* one line of declare -p, such as declare -ax a=([0]="b") */
+pub fn declare_line(key: &str, core: &mut ShellCore) -> Option<String> {
+    let target = core.data.get_nameref_target(key);
+    let value = match &target {
+        Some(t) if t.is_empty() => None,
+        Some(t) => Some(Value::EvaluatedSingle(t.clone())),
+        None    => core.data.get_value(key),
+    };
+
+    let mut attrs = String::new();
+    if let Some(Value::EvaluatedArray(_)) = value {
+        attrs.push('a');
+    }
+    if target.is_some() {
+        attrs.push('n');
+    }
+    if core.data.is_readonly(key) {
+        attrs.push('r');
+    }
+    if core.data.is_exported(key) {
+        attrs.push('x');
+    }
+    if attrs.is_empty() {
+        attrs.push('-');
+    }
+
+    match value {
+        Some(Value::EvaluatedSingle(s)) => Some(format!("declare -{} {}={}", attrs, key, quote_for_declare(&s))),
+        Some(Value::EvaluatedArray(a))  => Some(format!("declare -{} {}={}", attrs, key, array_for_declare(&a))),
+        _ if attrs != "-" || core.data.is_declared(key) => Some(format!("declare -{} {}", attrs, key)),
+        _ => None,
+    }
+}
+
+fn print(com: &str, names: &[String], core: &mut ShellCore) -> i32 {
+    if names.is_empty() {
+        for key in core.data.get_keys() {
+            if let Some(line) = declare_line(&key, core) {
+                print_line(&line, core);
+            }
+        }
+        return 0;
+    }
+
+    let mut ans = 0;
+    for name in names {
+        match declare_line(name, core) {
+            Some(line) => print_line(&line, core),
+            None => {
+                error_message::print(&format!("{}: {}: not found", com, name), core, true);
+                ans = 1;
+            },
+        }
+    }
+    ans
+}
+
+fn set_nameref(com: &str, arg: &str, core: &mut ShellCore, layer: usize) -> bool {
+    if is_varname(arg) {
+        core.data.set_layer_nameref(arg, "", layer);
+        return true;
+    }
 
-fn set(arg: &str, core: &mut ShellCore, layer: usize) -> bool {
     let mut sub = match Substitution::parse(&mut Feeder::new(arg), core) {
         Some(s) => s,
         _ => {
-            eprintln!("sush: local: `{}': not a valid identifier", arg);
+            error_message::print(&format!("{}: `{}': not a valid identifier", com, arg), core, true);
+            return false;
+        },
+    };
+
+    let target = match sub.eval(core) {
+        Ok(Value::EvaluatedSingle(s)) => s,
+        Err(e) => {
+            e.print(core);
+            return false;
+        },
+        _ => {
+            error_message::print(&format!("{}: {}: reference variable cannot be an array", com, &sub.key), core, true);
+            return false;
+        },
+    };
+
+    if ! is_varname(&target) {
+        error_message::print(&format!("{}: `{}': invalid variable name for name reference", com, &target), core, true);
+        return false;
+    }
+    if target == sub.key {
+        error_message::print(&format!("{}: {}: nameref variable self references not allowed", com, &target), core, true);
+        return false;
+    }
+
+    core.data.set_layer_nameref(&sub.key, &target, layer);
+    true
+}
+
+fn set(com: &str, arg: &str, core: &mut ShellCore, layer: usize) -> bool {
+    if is_varname(arg) {
+        return core.data.set_layer_declared(arg, layer);
+    }
+
+    let mut sub = match Substitution::parse(&mut Feeder::new(arg), core) {
+        Some(s) => s,
+        _ => {
+            error_message::print(&format!("{}: `{}': not a valid identifier", com, arg), core, true);
             return false;
         },
     };
 
     match sub.eval(core) {
-        Value::EvaluatedSingle(s) => core.data.set_layer_param(&sub.key, &s, layer),
-        Value::EvaluatedArray(a)  => core.data.set_layer_array(&sub.key, &a, layer),
+        Ok(Value::EvaluatedSingle(s)) => core.data.set_layer_param(&sub.key, &s, layer),
+        Ok(Value::EvaluatedArray(a))  => core.data.set_layer_array(&sub.key, &a.values().cloned().collect::<Vec<String>>(), layer),
+        Err(e) => {
+            e.print(core);
+            false
+        },
         _ => error_message::internal("unsupported substitution"),
     }
-    true
+}
+
+fn set_all(com: &str, args: &mut Vec<String>, core: &mut ShellCore, layer: usize) -> i32 {
+    let nameref = args.len() > 1 && args[1] == "-n";
+    if nameref {
+        args.remove(1);
+    }else if args.len() > 1 && args[1].starts_with("-") {
+        error_message::print(&format!("{}: {}: invalid option", com, &args[1]), core, true);
+        error_message::print(&format!("{}: usage: {} [-n] [name[=value] ...]", com, com), core, true);
+        return 2;
+    }
+
+    let ok = args[1..].iter().all(|a| match nameref {
+        true  => set_nameref(com, a, core, layer),
+        false => set(com, a, core, layer),
+    });
+
+    match ok {
+        true  => 0,
+        false => 1,
+    }
 }
 
 pub fn local(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let layer = if core.data.get_layer_num() > 2 {
-        core.data.get_layer_num() - 2 //The last element of data.parameters is for local itself.
-    }else{
-        eprintln!("sush: local: can only be used in a function");
-        return 1;
+    let layer = match core.data.get_function_layer() {
+        Some(layer) => layer,
+        None => {
+            error_message::print("local: can only be used in a function", core, true);
+            return 1;
+        },
     };
 
-    match args[1..].iter().all(|a| set(a, core, layer)) {
+    set_all("local", args, core, layer)
+}
+
+pub fn declare(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let layer = core.data.get_function_layer().unwrap_or(0);
+
+    if args.len() == 1 {
+        return option_commands::print(core);
+    }
+    if args[1] == "-p" {
+        return print("declare", &args[2..], core);
+    }
+
+    set_all("declare", args, core, layer)
+}
+
+pub fn readonly(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() == 1 || (args.len() == 2 && args[1] == "-p") {
+        for key in core.data.get_readonly_keys() {
+            if let Some(line) = declare_line(&key, core) {
+                print_line(&line, core);
+            }
+        }
+        return 0;
+    }
+
+    let mut ok = true;
+    for a in &args[1..] {
+        if is_varname(a) {
+            core.data.set_readonly(a);
+        }else if set("readonly", a, core, 0) {
+            let name = a.split('=').next().unwrap_or("").trim_end_matches('+');
+            core.data.set_readonly(name);
+        }else{
+            ok = false;
+        }
+    }
+
+    match ok {
         true  => 0,
         false => 1,
     }
@@ -3,29 +3,23 @@
 
 use crate::{error_message, ShellCore};
 use crate::core::data::Value;
+use crate::core::options::Options;
+use super::utils::{array_for_declare, print_line, quote_for_set};
 
 fn print_data(k: &str, core: &mut ShellCore) {
+    if let Some(target) = core.data.get_nameref_target(k) {
+        print_line(&format!("{}={}", k, quote_for_set(&target)), core);
+        return;
+    }
+
     match core.data.get_value(k) {
-        Some(Value::EvaluatedSingle(s)) => {
-            println!("{}={}", k.to_string(), s.to_string()); 
-        },
-        Some(Value::EvaluatedArray(a)) => {
-            let mut formatted = String::new();
-            formatted += "(";
-            for (i, v) in a.iter().enumerate() {
-                formatted += &format!("[{}]=\"{}\" ", i, v).clone();
-            }
-            if formatted.ends_with(" ") {
-                formatted.pop();
-            }
-            formatted += ")";
-            println!("{}={}", k.to_string(), formatted); 
-        },
+        Some(Value::EvaluatedSingle(s)) => print_line(&format!("{}={}", k, quote_for_set(&s)), core),
+        Some(Value::EvaluatedArray(a))  => print_line(&format!("{}={}", k, array_for_declare(&a)), core),
         _ => {},
     }
 }
 
-fn print(core: &mut ShellCore) -> i32 {
+pub fn print(core: &mut ShellCore) -> i32 {
     core.data.get_keys()
         .into_iter()
         .for_each(|k| print_data(&k, core));
@@ -41,6 +35,21 @@ pub fn set_parameters(core: &mut ShellCore, args: &[String]) -> i32 {
     0
 }
 
+fn replace_parameters(core: &mut ShellCore, args: &[String]) -> i32 {
+    let mut params = match core.data.position_parameters.last() {
+        Some(p) if ! p.is_empty() => vec![p[0].clone()],
+        _ => vec![core.script_name.clone()],
+    };
+    params.extend_from_slice(args);
+    set_parameters(core, &params)
+}
+
+/* options that are kept as letters in $- */
+pub const FLAG_OPTIONS: [(&str, char); 9] = [
+    ("errexit", 'e'), ("errtrace", 'E'), ("functrace", 'T'), ("histexpand", 'H'), ("noclobber", 'C'),
+    ("noglob", 'f'), ("nounset", 'u'), ("verbose", 'v'), ("xtrace", 'x'),
+];
+
 fn set_option(core: &mut ShellCore, opt: char, pm: char) {
     if pm == '+' {
         core.data.flags.retain(|e| e != opt);
@@ -51,19 +60,94 @@ fn set_option(core: &mut ShellCore, opt: char, pm: char) {
     }
 }
 
-fn set_options(core: &mut ShellCore, args: &[String]) -> i32 {
-    for a in args {
-        if a.starts_with("--") {
-            return 0;
+fn set_long_option(core: &mut ShellCore, name: &str, pm: char) -> bool {
+    if let Some((_, ch)) = FLAG_OPTIONS.iter().find(|(n, _)| *n == name) {
+        set_option(core, *ch, pm);
+        return true;
+    }
+
+    if ! core.options.exists(name) {
+        error_message::print(&format!("set: {}: invalid option name", name), core, true);
+        return false;
+    }
+    /* only one of the editing modes is active */
+    match (name, pm) {
+        ("vi", '-')    => { core.options.set("emacs", false); },
+        ("emacs", '-') => { core.options.set("vi", false); },
+        _ => {},
+    }
+    /* extglob is off in the posix mode and gets back its value after the mode */
+    match (name, pm) {
+        ("posix", '-') if ! core.options.query("posix") => {
+            core.extglob_before_posix = Some(core.shopts.query("extglob"));
+            core.shopts.set("extglob", false);
+        },
+        ("posix", '+') => if let Some(onoff) = core.extglob_before_posix.take() {
+            core.shopts.set("extglob", onoff);
+        },
+        _ => {},
+    }
+    core.options.set(name, pm == '-')
+}
+
+fn print_long_options(core: &mut ShellCore, pm: char) {
+    let mut list: Vec<(String, bool)> = FLAG_OPTIONS.iter()
+        .map(|(name, ch)| (name.to_string(), core.data.flags.contains(*ch)))
+        .collect();
+    list.extend(core.options.list());
+    list.sort();
+
+    for (name, onoff) in list {
+        match pm {
+            '-' => print_line(&Options::format(&name, onoff), core),
+            _   => print_line(&Options::format2(&name, onoff), core),
+        }
+    }
+}
+
+fn invalid_option(pm: char, ch: char, core: &mut ShellCore) -> i32 {
+    let letters: String = FLAG_OPTIONS.iter().map(|(_, c)| *c).collect();
+    error_message::print(&format!("set: {}{}: invalid option", pm, ch), core, true);
+    error_message::print(&format!("set: usage: set [-{}] [-o option-name] [--] [-] [arg ...]", letters), core, true);
+    2
+}
+
+pub fn set_options(core: &mut ShellCore, args: &[String]) -> i32 {
+    let mut i = 0;
+    while i < args.len() {
+        let a = &args[i];
+        if a == "--" {
+            return replace_parameters(core, &args[i+1..]);
         }
+        if a == "-" { // also turns off -x and -v
+            set_option(core, 'x', '+');
+            set_option(core, 'v', '+');
+            return match i + 1 < args.len() {
+                true  => replace_parameters(core, &args[i+1..]),
+                false => 0,
+            };
+        }
+        if a.len() < 2 || ! (a.starts_with("-") || a.starts_with("+")) {
+            return replace_parameters(core, &args[i..]);
+        }
+
         let pm = a.chars().nth(0).unwrap();
         for ch in a[1..].chars() {
-            if "xve".find(ch).is_none() {
-                eprintln!("sush: set: {}{}: invalid option", &pm, &ch);
-                return 2;
+            if ch == 'o' {
+                i += 1;
+                match args.get(i) {
+                    Some(name) => if ! set_long_option(core, name, pm) {
+                        return 2;
+                    },
+                    None => print_long_options(core, pm),
+                }
+            }else if FLAG_OPTIONS.iter().any(|(_, c)| *c == ch) {
+                set_option(core, ch, pm);
+            }else{
+                return invalid_option(pm, ch, core);
             }
-            set_option(core, ch, pm);
         }
+        i += 1;
     }
     0
 }
@@ -77,80 +161,120 @@ pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
                 false => set_parameters(core, args),
             }
         },
-        _ => {
-            if args[1].starts_with("--") {
-                args.remove(0);
-                return set_parameters(core, args)
-            }
-
-            if args[1] == "-o" {
-                if args.len() == 2 {
-                    core.options.print_all();
-                    return 0;
-                }else{
-                    match core.options.set(&args[2], true) {
-                        true  => return 0,
-                        false => return 2,
-                    }
-                }
-            }
+        _ => set_options(core, &args[1..]),
+    }
+}
 
-            if args[1] == "+o" {
-                if args.len() == 2 {
-                    core.options.print_all2();
-                    return 0;
-                }else{
-                    match core.options.set(&args[2], false) {
-                        true  => return 0,
-                        false => return 2,
-                    }
-                }
-            }
+fn shopt_query(core: &mut ShellCore, name: &str, set_opts: bool) -> Option<bool> {
+    if ! set_opts {
+        return match core.shopts.exists(name) {
+            true  => Some(core.shopts.query(name)),
+            false => None,
+        };
+    }
 
-            match args[1].starts_with("-") || args[1].starts_with("+") {
-                true  => set_options(core, &args[1..]),
-                false => set_parameters(core, args),
-            }
-        },
+    if let Some((_, ch)) = FLAG_OPTIONS.iter().find(|(n, _)| *n == name) {
+        return Some(core.data.flags.contains(*ch));
+    }
+    match core.options.exists(name) {
+        true  => Some(core.options.query(name)),
+        false => None,
     }
 }
 
-pub fn shopt_print(core: &mut ShellCore, args: &mut Vec<String>, all: bool) -> i32 {
-    if all {
-        core.shopts.print_all();
-        return 0;
-    }
+fn shopt_list(core: &mut ShellCore, set_opts: bool) -> Vec<(String, bool)> {
+    let mut list = match set_opts {
+        true  => FLAG_OPTIONS.iter()
+                 .map(|(name, ch)| (name.to_string(), core.data.flags.contains(*ch)))
+                 .chain(core.options.list())
+                 .collect(),
+        false => core.shopts.list(),
+    };
+    list.sort();
+    list
+}
 
-    let mut res = true;
-    match args[1].as_str() {
-        "-s" => core.shopts.print_if(true),
-        "-u" => core.shopts.print_if(false),
-        opt  => res = core.shopts.print_opt(opt),
+fn shopt_format(name: &str, onoff: bool, reusable: bool, set_opts: bool) -> String {
+    match (reusable, set_opts, onoff) {
+        (false, _, _)        => Options::format(name, onoff),
+        (true, true, _)      => Options::format2(name, onoff),
+        (true, false, true)  => format!("shopt -s {}", name),
+        (true, false, false) => format!("shopt -u {}", name),
     }
+}
 
-    match res {
-        true  => 0,
-        false => 1,
+fn shopt_set(core: &mut ShellCore, names: &[String], onoff: bool, set_opts: bool) -> i32 {
+    let mut ans = 0;
+    for name in names {
+        if shopt_query(core, name, set_opts).is_none() {
+            error_message::print(&format!("shopt: {}: invalid shell option name", name), core, true);
+            ans = 1;
+        }else if set_opts {
+            set_long_option(core, name, if onoff {'-'} else {'+'});
+        }else{
+            core.shopts.set(name, onoff);
+        }
     }
+    ans
 }
 
 pub fn shopt(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() < 3 {
-        return shopt_print(core, args, args.len() < 2);
+    let mut flags = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        pos += 1;
+        if args[pos-1] == "--" {
+            break;
+        }
+        for ch in args[pos-1][1..].chars() {
+            if "opqsu".find(ch).is_none() {
+                error_message::print(&format!("shopt: -{}: invalid option", ch), core, true);
+                error_message::print("shopt: usage: shopt [-pqsu] [-o] [optname ...]", core, true);
+                return 2;
+            }
+            flags.push(ch);
+        }
     }
 
-    let res = match args[1].as_str() {
-        "-s" => core.shopts.set(&args[2], true),
-        "-u" => core.shopts.set(&args[2], false),
-        arg  => {
-            eprintln!("sush: shopt: {}: invalid shell option name", arg);
-            eprintln!("shopt: usage: shopt [-su] [optname ...]");
-            false
+    let set_opts = flags.contains('o');
+    let (on, off) = (flags.contains('s'), flags.contains('u'));
+    if on && off {
+        error_message::print("shopt: cannot set and unset shell options simultaneously", core, true);
+        return 1;
+    }
+
+    let names = &args[pos..];
+    if (on || off) && ! names.is_empty() {
+        return shopt_set(core, names, on, set_opts);
+    }
+
+    let mut ans = 0;
+    let list = match names.is_empty() {
+        true  => shopt_list(core, set_opts).into_iter()
+                 .filter(|(_, onoff)| (! on || *onoff) && (! off || ! *onoff))
+                 .collect(),
+        false => {
+            let mut list = vec![];
+            for name in names {
+                match shopt_query(core, name, set_opts) {
+                    Some(onoff) => list.push((name.clone(), onoff)),
+                    None => {
+                        error_message::print(&format!("shopt: {}: invalid shell option name", name), core, true);
+                        ans = 1;
+                    },
+                }
+            }
+            list
         },
     };
 
-    match res {
-        true  => 0,
-        false => 1,
+    for (name, onoff) in list {
+        if ! flags.contains('q') {
+            print_line(&shopt_format(&name, onoff, flags.contains('p'), set_opts), core);
+        }
+        if ! names.is_empty() && ! onoff {
+            ans = 1;
+        }
     }
+    ans
 }
@@ -0,0 +1,391 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use crate::utils::bytes;
+use super::utils::is_varname;
+use nix::libc;
+use std::ffi::CString;
+use std::io;
+use std::io::Write;
+
+struct Args<'a> {
+    list: &'a [String],
+    pos: usize,
+    errors: Vec<String>,
+}
+
+impl Args<'_> {
+    fn next_str(&mut self) -> String {
+        match self.list.get(self.pos) {
+            Some(s) => {
+                self.pos += 1;
+                s.clone()
+            },
+            None => "".to_string(),
+        }
+    }
+
+    fn invalid(&mut self, s: &str) {
+        self.errors.push(format!("printf: {}: invalid number", s));
+    }
+
+    fn next_int(&mut self) -> i64 {
+        let s = self.next_str();
+        let t = s.trim();
+        if t.starts_with('\'') || t.starts_with('"') {
+            return t.chars().nth(1).map(|c| c as i64).unwrap_or(0);
+        }
+
+        let (minus, digits) = match t.strip_prefix('-') {
+            Some(d) => (true, d),
+            None    => (false, t.strip_prefix('+').unwrap_or(t)),
+        };
+
+        let parsed = if let Some(h) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            u64::from_str_radix(h, 16)
+        }else if digits.len() > 1 && digits.starts_with('0') {
+            u64::from_str_radix(&digits[1..], 8)
+        }else {
+            digits.parse::<u64>()
+        };
+
+        match (parsed, minus) {
+            (Ok(n), false) => n as i64,
+            (Ok(n), true)  => (n as i64).wrapping_neg(),
+            _ if t.is_empty() => 0,
+            _ => {
+                self.invalid(&s);
+                0
+            },
+        }
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let s = self.next_str();
+        let t = s.trim();
+        if t.starts_with('\'') || t.starts_with('"') {
+            return t.chars().nth(1).map(|c| c as u32 as f64).unwrap_or(0.0);
+        }
+
+        match t.parse::<f64>() {
+            Ok(f) => f,
+            _ if t.is_empty() => 0.0,
+            _ => {
+                self.invalid(&s);
+                0.0
+            },
+        }
+    }
+}
+
+struct Spec {
+    flags: String,
+    width: Option<i64>,
+    precision: Option<i64>,
+}
+
+impl Spec {
+    fn to_c_format(&self, conv: &str) -> CString {
+        let mut f = format!("%{}", &self.flags);
+        if let Some(w) = self.width {
+            f += &w.to_string();
+        }
+        if let Some(p) = self.precision {
+            f += &format!(".{}", p);
+        }
+        f += conv;
+        CString::new(f).unwrap()
+    }
+
+    fn pad(&self, s: &[u8]) -> Vec<u8> {
+        let mut s = s.to_vec();
+        if let Some(p) = self.precision {
+            s.truncate(p.max(0) as usize);
+        }
+
+        let width = self.width.unwrap_or(0);
+        let len = bytes::decode(&s).chars().count() as i64;
+        if width.abs() <= len {
+            return s;
+        }
+
+        let fill = vec![b' '; (width.abs() - len) as usize];
+        match width < 0 || self.flags.contains('-') {
+            true  => [s, fill].concat(),
+            false => [fill, s].concat(),
+        }
+    }
+}
+
+fn c_int(spec: &Spec, conv: char, n: i64) -> Vec<u8> {
+    let fmt = spec.to_c_format(&format!("ll{}", conv));
+    let n = n as libc::c_longlong;
+    let len = unsafe { libc::snprintf(std::ptr::null_mut(), 0, fmt.as_ptr(), n) };
+    let mut buf = vec![0u8; len.max(0) as usize + 1];
+    unsafe { libc::snprintf(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), fmt.as_ptr(), n) };
+    buf.pop();
+    buf
+}
+
+fn c_float(spec: &Spec, conv: char, f: f64) -> Vec<u8> {
+    let fmt = spec.to_c_format(&conv.to_string());
+    let len = unsafe { libc::snprintf(std::ptr::null_mut(), 0, fmt.as_ptr(), f) };
+    let mut buf = vec![0u8; len.max(0) as usize + 1];
+    unsafe { libc::snprintf(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), fmt.as_ptr(), f) };
+    buf.pop();
+    buf
+}
+
+fn take_digits(s: &[u8], pos: &mut usize, radix: u32, max: usize) -> Option<u32> {
+    let mut ans = None;
+    for _ in 0..max {
+        match s.get(*pos).and_then(|b| (*b as char).to_digit(radix)) {
+            Some(d) => {
+                ans = Some(ans.unwrap_or(0) * radix + d);
+                *pos += 1;
+            },
+            None => break,
+        }
+    }
+    ans
+}
+
+/* s[*pos] is just after a backslash. returns false on \c in %b */
+fn unescape(s: &[u8], pos: &mut usize, out: &mut Vec<u8>, for_b: bool) -> bool {
+    let c = match s.get(*pos) {
+        Some(c) => *c,
+        None => {
+            out.push(b'\\');
+            return true;
+        },
+    };
+    *pos += 1;
+
+    let simple = match c {
+        b'a' => Some(7),
+        b'b' => Some(8),
+        b'e' | b'E' => Some(27),
+        b'f' => Some(12),
+        b'n' => Some(b'\n'),
+        b'r' => Some(b'\r'),
+        b't' => Some(b'\t'),
+        b'v' => Some(11),
+        b'\\' | b'"' | b'\'' | b'?' => Some(c),
+        _ => None,
+    };
+    if let Some(b) = simple {
+        out.push(b);
+        return true;
+    }
+
+    match c {
+        b'c' if for_b => return false,
+        b'0'..=b'7' => {
+            *pos -= 1;
+            if for_b && c == b'0' {
+                *pos += 1;
+            }
+            let n = take_digits(s, pos, 8, 3).unwrap_or(0);
+            out.push(n as u8);
+        },
+        b'x' => match take_digits(s, pos, 16, 2) {
+            Some(n) => out.push(n as u8),
+            None    => out.extend_from_slice(b"\\x"),
+        },
+        b'u' | b'U' => {
+            let max = if c == b'u' { 4 } else { 8 };
+            match take_digits(s, pos, 16, max).and_then(char::from_u32) {
+                Some(ch) => out.extend_from_slice(ch.to_string().as_bytes()),
+                None     => out.extend_from_slice(&[b'\\', c]),
+            }
+        },
+        _ => out.extend_from_slice(&[b'\\', c]),
+    }
+    true
+}
+
+pub fn interpret_b(arg: &str, out: &mut Vec<u8>) -> bool {
+    let s = bytes::encode(arg);
+    let mut pos = 0;
+    while pos < s.len() {
+        pos += 1;
+        if s[pos-1] != b'\\' {
+            out.push(s[pos-1]);
+        }else if ! unescape(&s, &mut pos, out, true) {
+            return false;
+        }
+    }
+    true
+}
+
+fn quote(arg: &str) -> String {
+    if arg.is_empty() {
+        return "''".to_string();
+    }
+
+    if arg.chars().any(|c| c.is_control() || bytes::raw_byte(c).is_some()) {
+        let mut ans = "$'".to_string();
+        for c in arg.chars() {
+            match (c, bytes::raw_byte(c)) {
+                (_, Some(b)) => ans += &format!("\\{:03o}", b),
+                ('\n', _) => ans += "\\n",
+                ('\t', _) => ans += "\\t",
+                ('\r', _) => ans += "\\r",
+                ('\x1b', _) => ans += "\\E",
+                ('\'', _) | ('\\', _) => ans += &format!("\\{}", c),
+                (c, _) if c.is_control() => ans += &format!("\\{:03o}", c as u32),
+                (c, _) => ans.push(c),
+            }
+        }
+        return ans + "'";
+    }
+
+    let mut ans = String::new();
+    for c in arg.chars() {
+        if ! c.is_alphanumeric() && "_@%+=:,./-".find(c).is_none() {
+            ans.push('\\');
+        }
+        ans.push(c);
+    }
+    ans
+}
+
+fn read_number(fmt: &[u8], pos: &mut usize, args: &mut Args) -> Option<i64> {
+    if fmt.get(*pos) == Some(&b'*') {
+        *pos += 1;
+        return Some(args.next_int());
+    }
+    take_digits(fmt, pos, 10, usize::MAX).map(|n| n as i64)
+}
+
+/* fmt[*pos] is just after '%'. returns Err(true) on \c in %b, Err(false) on errors */
+fn conversion(fmt: &[u8], pos: &mut usize, args: &mut Args,
+              out: &mut Vec<u8>) -> Result<(), bool> {
+    let mut spec = Spec { flags: String::new(), width: None, precision: None };
+    while let Some(c) = fmt.get(*pos).filter(|c| b"-+ #0".contains(c)) {
+        spec.flags.push(*c as char);
+        *pos += 1;
+    }
+
+    spec.width = read_number(fmt, pos, args);
+    if fmt.get(*pos) == Some(&b'.') {
+        *pos += 1;
+        spec.precision = Some(read_number(fmt, pos, args).unwrap_or(0));
+    }
+    while fmt.get(*pos).is_some_and(|c| b"hlLjzt".contains(c)) {
+        *pos += 1;
+    }
+
+    let conv = match fmt.get(*pos) {
+        Some(c) => *c as char,
+        None => {
+            args.errors.push("printf: `%': missing format character".to_string());
+            return Err(false);
+        },
+    };
+    *pos += 1;
+
+    match conv {
+        '%' => out.push(b'%'),
+        's' => out.append(&mut spec.pad(&bytes::encode(&args.next_str()))),
+        'q' => out.append(&mut spec.pad(quote(&args.next_str()).as_bytes())),
+        'c' => {
+            let s = args.next_str();
+            let head: String = s.chars().take(1).collect();
+            spec.precision = None;
+            out.append(&mut spec.pad(&bytes::encode(&head)));
+        },
+        'b' => {
+            let mut tmp = vec![];
+            let cont = interpret_b(&args.next_str(), &mut tmp);
+            out.append(&mut spec.pad(&tmp));
+            if ! cont {
+                return Err(true);
+            }
+        },
+        'd' | 'i' | 'o' | 'u' | 'x' | 'X' => out.append(&mut c_int(&spec, conv, args.next_int())),
+        'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => out.append(&mut c_float(&spec, conv, args.next_float())),
+        _ => {
+            args.errors.push(format!("printf: `{}': invalid format character", conv));
+            return Err(false);
+        },
+    }
+    Ok(())
+}
+
+/* returns Err(true) on \c in %b, Err(false) on errors */
+fn format_once(fmt: &[u8], args: &mut Args, out: &mut Vec<u8>) -> Result<(), bool> {
+    let mut pos = 0;
+    while pos < fmt.len() {
+        pos += 1;
+        match fmt[pos-1] {
+            b'\\' => { unescape(fmt, &mut pos, out, false); },
+            b'%'  => conversion(fmt, &mut pos, args, out)?,
+            b     => out.push(b),
+        }
+    }
+    Ok(())
+}
+
+pub fn printf(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut pos = 1;
+    let mut var = None;
+    if args.len() > 2 && args[1] == "-v" {
+        if ! is_varname(&args[2]) {
+            error_message::print(&format!("printf: `{}': not a valid identifier", &args[2]), core, true);
+            return 2;
+        }
+        var = Some(args[2].clone());
+        pos = 3;
+    }
+    if args.len() > pos && args[pos] == "--" {
+        pos += 1;
+    }
+
+    if args.len() <= pos {
+        error_message::print("printf: usage: printf [-v var] format [arguments]", core, true);
+        return 2;
+    }
+
+    let fmt = bytes::encode(&args[pos]);
+    let mut params = Args { list: &args[pos+1..], pos: 0, errors: vec![] };
+    let mut out = vec![];
+    let mut status = 0;
+    loop {
+        let before = params.pos;
+        match format_once(&fmt, &mut params, &mut out) {
+            Ok(()) => {},
+            Err(true) => break,
+            Err(false) => {
+                status = 1;
+                break;
+            },
+        }
+
+        if params.pos == before || params.pos >= params.list.len() {
+            break;
+        }
+    }
+
+    for msg in &params.errors {
+        error_message::print(msg, core, true);
+        status = 1;
+    }
+
+    match var {
+        Some(v) => {
+            if ! core.data.set_param(&v, &bytes::decode(&out)) {
+                return 1;
+            }
+        },
+        None => {
+            let mut stdout = io::stdout();
+            if let Err(e) = stdout.write_all(&out).and_then(|_| stdout.flush()) {
+                error_message::print(&format!("printf: write error: {}", error_message::io_error(&e)), core, true);
+                return 1;
+            }
+        },
+    }
+    status
+}
@@ -2,7 +2,8 @@
 //SPDX-FileCopyrightText: 2023 @caro@mi.shellgei.org
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, ShellCore};
+use super::utils::print_line;
 
 pub fn pwd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if args.len() == 1 || &args[1][..1] != "-" { // $ pwd, $ pwd aaa
@@ -13,8 +14,8 @@ pub fn pwd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
         "-P" => show_pwd(core, true), // シンボリックリンク名を解決して表示
         "-L" => show_pwd(core, false), // シンボリックリンク名をそのまま表示（bash default）
         _ => {
-            eprintln!("sush: pwd: {}: invalid option", &args[1]);
-            eprintln!("pwd: usage: pwd [-LP]");
+            error_message::print(&format!("pwd: {}: invalid option", &args[1]), core, true);
+            error_message::print("pwd: usage: pwd [-LP]", core, true);
             1
         },
     }
@@ -27,7 +28,7 @@ fn show_pwd(core: &mut ShellCore, physical: bool) -> i32 {
                 path = c;
             }
         }
-        println!("{}", path.display());
+        print_line(&path.display().to_string(), core);
         return 0;
     }
     1
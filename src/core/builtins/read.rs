@@ -1,60 +1,324 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, ShellCore};
+use crate::elements::io::read_byte;
+use crate::utils::bytes;
+use super::utils::is_varname;
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::termios;
+use nix::sys::termios::{LocalFlags, SetArg, Termios};
+use std::io;
+use std::io::{IsTerminal, Write};
+use std::os::fd::AsFd;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::{Duration, Instant};
 
-fn is_varname(s :&String) -> bool {
-    if s.len() == 0 {
-        return false;
+enum End {
+    Delim,
+    Eof,
+    Timeout,
+    Limit,
+    Interrupt,
+}
+
+struct Options {
+    raw: bool,
+    silent: bool,
+    prompt: Option<String>,
+    array: Option<String>,
+    nchars: Option<usize>,
+    timeout: Option<f64>,
+    delim: u8,
+    names: Vec<String>,
+}
+
+fn print_usage(core: &mut ShellCore) {
+    error_message::print("read: usage: read [-rs] [-a array] [-d delim] [-n nchars] [-p prompt] [-t timeout] [name ...]", core, true);
+}
+
+fn parse_options(args: &[String], core: &mut ShellCore) -> Result<Options, i32> {
+    let mut opts = Options {
+        raw: false, silent: false, prompt: None, array: None,
+        nchars: None, timeout: None, delim: b'\n', names: vec![],
+    };
+
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos] != "-" {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+
+        let arg = args[pos].clone();
+        for (i, c) in arg.char_indices().skip(1) {
+            if c == 'r' || c == 's' {
+                match c {
+                    'r' => opts.raw = true,
+                    _   => opts.silent = true,
+                }
+                continue;
+            }
+
+            if "padnt".find(c).is_none() {
+                error_message::print(&format!("read: -{}: invalid option", c), core, true);
+                print_usage(core);
+                return Err(2);
+            }
+
+            let value = match i + 1 < arg.len() {
+                true  => arg[i+1..].to_string(),
+                false => {
+                    pos += 1;
+                    match args.get(pos) {
+                        Some(v) => v.clone(),
+                        None => {
+                            error_message::print(&format!("read: -{}: option requires an argument", c), core, true);
+                            print_usage(core);
+                            return Err(2);
+                        },
+                    }
+                },
+            };
+
+            match c {
+                'p' => opts.prompt = Some(value),
+                'a' => opts.array = Some(value),
+                'd' => opts.delim = bytes::encode(&value).first().copied().unwrap_or(0),
+                'n' => match value.parse::<usize>() {
+                    Ok(n) => opts.nchars = Some(n),
+                    _ => {
+                        error_message::print(&format!("read: {}: invalid number", &value), core, true);
+                        return Err(1);
+                    },
+                },
+                _ => match value.parse::<f64>() {
+                    Ok(t) if t >= 0.0 => opts.timeout = Some(t),
+                    _ => {
+                        error_message::print(&format!("read: {}: invalid timeout specification", &value), core, true);
+                        return Err(1);
+                    },
+                },
+            }
+            break;
+        }
+        pos += 1;
     }
 
-    let first_ch = s.chars().nth(0).unwrap();
+    opts.names = args[pos..].to_vec();
+    Ok(opts)
+}
 
-    if '0' <= first_ch && first_ch <= '9' {
-        return false;
+/* returns None when the input is ready. the wait is cut into slices to notice SIGINT */
+fn wait_input(deadline: Option<Instant>, sigint: &AtomicBool) -> Option<End> {
+    let slice = Duration::from_millis(100);
+    let stdin = io::stdin();
+    loop {
+        if sigint.load(Relaxed) {
+            return Some(End::Interrupt);
+        }
+
+        let rest = match deadline {
+            Some(d) => d.saturating_duration_since(Instant::now()),
+            None    => slice,
+        };
+        let timeout = PollTimeout::try_from(rest.min(slice)).unwrap_or(PollTimeout::MAX);
+        let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, timeout) {
+            Ok(0) if rest <= slice && deadline.is_some() => return Some(End::Timeout),
+            Ok(0) | Err(Errno::EINTR) => continue,
+            _ => return None,
+        }
     }
+}
 
-    let name_c = |c| ('a' <= c && c <= 'z') || ('A' <= c && c <= 'Z')
-                     || ('0' <= c && c <= '9') || '_' == c;
-    s.chars().position(|c| !name_c(c)) == None
+/* the number of continuation bytes following a lead byte of UTF-8 */
+fn continuation_len(byte: u8) -> usize {
+    match byte {
+        0xC0..=0xDF => 1,
+        0xE0..=0xEF => 2,
+        0xF0..=0xF7 => 3,
+        _ => 0,
+    }
 }
 
-pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 {
-        return 0;
+fn read_bytes(opts: &Options, deadline: Option<Instant>, sigint: &AtomicBool) -> (Vec<(u8, bool)>, End) {
+    let mut ans = vec![];
+    let mut count = 0;
+    let mut rest = 0;
+    let mut escaped = false;
+
+    loop {
+        if rest == 0 && opts.nchars.is_some_and(|n| count >= n) {
+            return (ans, End::Limit);
+        }
+        if let Some(end) = wait_input(deadline, sigint) {
+            return (ans, end);
+        }
+
+        let byte = match read_byte(0) {
+            Ok(Some(b)) => b,
+            _ => return (ans, End::Eof),
+        };
+
+        match rest {
+            0 => {
+                count += 1;
+                rest = continuation_len(byte);
+            },
+            _ => rest -= 1,
+        }
+
+        if escaped {
+            escaped = false;
+            if byte != b'\n' {
+                ans.push((byte, true));
+            }
+        }else if byte == opts.delim {
+            return (ans, End::Delim);
+        }else if byte == 0 {
+            continue;
+        }else if ! opts.raw && byte == b'\\' {
+            escaped = true;
+        }else {
+            ans.push((byte, false));
+        }
     }
+}
+
+fn to_string(line: &[(u8, bool)]) -> String {
+    let bs: Vec<u8> = line.iter().map(|e| e.0).collect();
+    bytes::decode(&bs)
+}
+
+fn split_fields(line: &[(u8, bool)], ifs: &[u8], max: usize) -> Vec<String> {
+    let is_ifs = |e: &(u8, bool)| ! e.1 && ifs.contains(&e.0);
+    let is_blank = |e: &(u8, bool)| is_ifs(e) && (e.0 == b' ' || e.0 == b'\t' || e.0 == b'\n');
+    let skip_blank = |pos: &mut usize| {
+        while *pos < line.len() && is_blank(&line[*pos]) {
+            *pos += 1;
+        }
+    };
 
-    for a in &args[1..] {
-        if ! is_varname(&a) {
-            eprintln!("bash: read: `{}': not a valid identifier", &a);
+    let mut ans = vec![];
+    let mut pos = 0;
+    skip_blank(&mut pos);
+
+    while pos < line.len() {
+        if ans.len() + 1 == max {
+            let mut end = line.len();
+            while end > pos && is_blank(&line[end-1]) {
+                end -= 1;
+            }
+            ans.push(to_string(&line[pos..end]));
+            break;
+        }
+
+        let start = pos;
+        while pos < line.len() && ! is_ifs(&line[pos]) {
+            pos += 1;
+        }
+        ans.push(to_string(&line[start..pos]));
+
+        skip_blank(&mut pos);
+        if pos < line.len() && is_ifs(&line[pos]) {
+            pos += 1;
+            skip_blank(&mut pos);
+        }
+    }
+
+    ans
+}
+
+fn set_terminal(opts: &Options) -> Option<Termios> {
+    if ! io::stdin().is_terminal() || (! opts.silent && opts.nchars.is_none()) {
+        return None;
+    }
+
+    let saved = termios::tcgetattr(io::stdin()).ok()?;
+    let mut t = saved.clone();
+    if opts.silent {
+        t.local_flags.remove(LocalFlags::ECHO);
+    }
+    if opts.nchars.is_some() {
+        t.local_flags.remove(LocalFlags::ICANON);
+    }
+    let _ = termios::tcsetattr(io::stdin(), SetArg::TCSADRAIN, &t);
+    Some(saved)
+}
+
+fn assign(core: &mut ShellCore, opts: &Options, line: &[(u8, bool)]) -> bool {
+    let ifs = match core.data.is_set("IFS") {
+        true  => bytes::encode(&core.data.get_param("IFS")),
+        false => b" \t\n".to_vec(),
+    };
+
+    if let Some(a) = &opts.array {
+        let fields = split_fields(line, &ifs, usize::MAX);
+        return core.data.set_array(a, &fields);
+    }
+
+    if opts.names.is_empty() {
+        return core.data.set_param("REPLY", &to_string(line));
+    }
+
+    let fields = split_fields(line, &ifs, opts.names.len());
+    let mut ok = true;
+    for (i, name) in opts.names.iter().enumerate() {
+        let val = fields.get(i).map(|s| s.as_str()).unwrap_or("");
+        ok &= core.data.set_param(name, val);
+    }
+    ok
+}
+
+pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let opts = match parse_options(args, core) {
+        Ok(o)   => o,
+        Err(es) => return es,
+    };
+
+    for name in opts.names.iter().chain(opts.array.iter()) {
+        if ! is_varname(name) {
+            error_message::print(&format!("read: `{}': not a valid identifier", name), core, true);
             return 1;
-        }else{
-            core.data.set_param(&a, "");
         }
     }
 
-    let mut line = String::new();
-    let len = std::io::stdin()
-        .read_line(&mut line)
-        .expect("SUSHI INTERNAL ERROR: Failed to read line");
+    if opts.timeout == Some(0.0) {
+        let now = Some(Instant::now());
+        return match wait_input(now, &core.sigint) {
+            None => 0,
+            _    => 1,
+        };
+    }
 
-    let mut pos = 1;
-    let mut overflow = String::new();
-    for w in line.trim_end().split(' ') {
-        if pos < args.len()-1 {
-            core.data.set_param(&args[pos], &w);
-            pos += 1;
-        }else{
-            if overflow.len() != 0 {
-                overflow += " ";
-            }
-            overflow += &w;
-            core.data.set_param(&args[pos], &overflow);
+    if let Some(p) = &opts.prompt {
+        if io::stdin().is_terminal() {
+            eprint!("{}", p);
+            let _ = io::stderr().flush();
         }
     }
 
-    match len == 0 {
-        true  => 1,
-        false => 0,
+    let deadline = opts.timeout.map(|t| Instant::now() + Duration::from_secs_f64(t));
+    let saved = set_terminal(&opts);
+    let (line, end) = read_bytes(&opts, deadline, &core.sigint);
+    if let Some(t) = saved {
+        let _ = termios::tcsetattr(io::stdin(), SetArg::TCSADRAIN, &t);
+    }
+    if let End::Interrupt = end {
+        return 130;
+    }
+
+    if ! assign(core, &opts, &line) {
+        return 1;
+    }
+
+    match end {
+        End::Delim | End::Limit => 0,
+        End::Eof     => 1,
+        End::Timeout => 142,
+        End::Interrupt => 130,
     }
 }
@@ -1,23 +1,27 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, ShellCore};
 
 pub fn return_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if core.source_function_level <= 0 {
-        eprintln!("sush: return: can only `return' from a function or sourced script");
+        error_message::print("return: can only `return' from a function or sourced script", core, true);
         return 2;
     }
     core.return_flag = true;
 
     if args.len() < 2 {
-        return 0;
+        return core.data.exit_status().code();
+    }
+    if args.len() > 2 {
+        error_message::print("return: too many arguments", core, true);
+        return 2;
     }
 
-    match args[1].parse::<i32>() {
-        Ok(n)  => n%256,
+    match args[1].parse::<i64>() {
+        Ok(n)  => n.rem_euclid(256) as i32,
         Err(_) => {
-            eprintln!("sush: return: {}: numeric argument required", args[1]);
+            error_message::print(&format!("return: {}: numeric argument required", args[1]), core, true);
             2
         },
     }
@@ -25,7 +29,7 @@ pub fn return_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
 
 pub fn break_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if core.loop_level <= 0 {
-        eprintln!("sush: break: only meaningful in a `for', `while', or `until' loop");
+        error_message::print("break: only meaningful in a `for', `while', or `until' loop", core, true);
         return 0;
     }
 
@@ -39,12 +43,12 @@ pub fn break_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
             if n > 0 {
                 core.break_counter += n - 1;
             }else{
-                eprintln!("sush: break: {}: loop count out of range", args[1]);
+                error_message::print(&format!("break: {}: loop count out of range", args[1]), core, true);
                 return 1;
             }
         },
         Err(_) => {
-            eprintln!("sush: break: {}: numeric argument required", args[1]);
+            error_message::print(&format!("break: {}: numeric argument required", args[1]), core, true);
             return 128;
         },
     };
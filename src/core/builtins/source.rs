@@ -1,61 +1,83 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{file_check, Script, ShellCore, Feeder};
-use crate::elements::io;
+use crate::{error_message, file_check, signal, ShellCore};
+use crate::feeder::input::InputSource;
 use std::fs::File;
-use std::os::fd::IntoRawFd;
+
+/* a name without a slash is looked up in PATH first, then in the current directory */
+fn find_file(core: &mut ShellCore, name: &str) -> String {
+    if name.contains('/') {
+        return name.to_string();
+    }
+
+    for dir in core.data.get_param("PATH").split(':') {
+        let full = match dir {
+            "" => name.to_string(),
+            d  => format!("{}/{}", d, name),
+        };
+        if file_check::is_regular_file(&full) && file_check::is_readable(&full) {
+            return full;
+        }
+    }
+    name.to_string()
+}
 
 pub fn source(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() < 2 {
-        eprintln!("sush: source: filename argument required");
-        eprintln!("source: usage: source filename [arguments]");
+    let mut pos = 1;
+    if args.len() > pos && args[pos] == "--" {
+        pos += 1;
+    }
+
+    if args.len() <= pos {
+        error_message::print(&format!("{}: filename argument required", &args[0]), core, true);
+        error_message::print(&format!("{}: usage: {} filename [arguments]", &args[0], &args[0]), core, true);
         return 2;
     }
 
-    if file_check::is_dir(&args[1]) {
-        eprintln!("bash: source: {}: is a directory", &args[1]);
+    if core.is_restricted() && args[pos].contains('/') {
+        error_message::print(&format!("{}: {}: restricted", &args[0], &args[pos]), core, true);
+        return 1;
+    }
+
+    let path = find_file(core, &args[pos]);
+    if file_check::is_dir(&path) {
+        error_message::print(&format!("{}: {}: is a directory", &args[0], &args[pos]), core, true);
         return 1;
     }
 
-    let file = match File::open(&args[1]) {
-        Ok(f)  => f, 
+    let file = match File::open(&path) {
+        Ok(f)  => f,
         Err(e) => {
-            eprintln!("sush: {}: {}", &args[1], &e);
+            let msg = e.to_string();
+            let msg = msg.split(" (os error").next().unwrap_or(&msg);
+            error_message::print(&format!("{}: {}", &args[pos], msg), core, true);
             return 1;
-        }, 
+        },
     };
 
-    let fd = file.into_raw_fd();
-    let backup = io::backup(0);
-    io::replace(fd, 0);
-    let read_stdin_backup = core.read_stdin;
-    core.read_stdin = true;
+    /* positional parameters are replaced only when arguments are given */
+    let replace_params = args.len() > pos + 1;
+    if replace_params {
+        let len = core.data.position_parameters.len();
+        let mut params = args[pos..].to_vec();
+        params[0] = core.data.position_parameters[len-1][0].clone();
+        core.data.position_parameters.push(params);
+    }
+
     core.source_function_level += 1;
     core.source_level += 1;
+    core.data.push_call_frame("source", &args[pos]);
+    core.exec_source(InputSource::file(file, &args[pos]));
+    core.data.pop_call_frame();
+    core.return_flag = false;
+    signal::run_return_trap(core);
+    core.source_function_level -= 1;
+    core.source_level -= 1;
 
-    let mut feeder = Feeder::new("");
-    loop {
-        match feeder.feed_line(core) {
-            Ok(()) => {}, 
-            _ => break,
-        }
-
-        if core.return_flag {
-            feeder.consume(feeder.len());
-        }
-
-        match Script::parse(&mut feeder, core, false){
-            Some(mut s) => s.exec(core),
-            None => {},
-        }
+    if replace_params {
+        core.data.position_parameters.pop();
     }
 
-    io::replace(backup, 0);
-    core.source_function_level -= 1;
-    core.source_level -= 1;
-    core.return_flag = false;
-    core.read_stdin = read_stdin_backup;
-    core.data.get_param("?").parse::<i32>()
-        .expect("SUSH INTERNAL ERROR: BAD EXIT STATUS")
+    core.data.exit_status().code()
 }
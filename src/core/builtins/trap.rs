@@ -0,0 +1,106 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, signal, ShellCore};
+use super::utils::print_line;
+use nix::sys::signal::Signal;
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+fn print_traps(core: &mut ShellCore, names: &[String]) {
+    for name in names {
+        if let Some(com) = core.traps.get(name) {
+            print_line(&format!("trap -- {} {}", quote(com), name), core);
+        }
+    }
+}
+
+fn print_signal_list(core: &mut ShellCore) {
+    let list: Vec<String> = Signal::iterator()
+        .map(|s| format!("{:2}) {}", s as i32, s.as_str()))
+        .collect();
+
+    for line in list.chunks(5) {
+        print_line(&line.join("\t"), core);
+    }
+}
+
+fn invalid_spec(spec: &str, core: &mut ShellCore) -> i32 {
+    error_message::print(&format!("trap: {}: invalid signal specification", spec), core, true);
+    1
+}
+
+fn to_names(specs: &[String], core: &mut ShellCore) -> Result<Vec<String>, i32> {
+    let mut ans = vec![];
+    for spec in specs {
+        match signal::trap_name(spec) {
+            Some(name) => ans.push(name),
+            None       => return Err(invalid_spec(spec, core)),
+        }
+    }
+    Ok(ans)
+}
+
+pub fn trap(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut pos = 1;
+    let mut print = false;
+    while let Some(a) = args.get(pos) {
+        match a.as_ref() {
+            "--" => {
+                pos += 1;
+                break;
+            },
+            "-l" => {
+                print_signal_list(core);
+                return 0;
+            },
+            "-p" => print = true,
+            _ if a.starts_with("-") && a.len() > 1 => {
+                error_message::print(&format!("trap: {}: invalid option", a), core, true);
+                error_message::print("trap: usage: trap [-lp] [[arg] signal_spec ...]", core, true);
+                return 2;
+            },
+            _ => break,
+        }
+        pos += 1;
+    }
+
+    let operands = &args[pos..];
+    if print || operands.is_empty() {
+        let names = match operands.is_empty() {
+            true  => signal::trap_names(),
+            false => match to_names(operands, core) {
+                Ok(names) => names,
+                Err(es)   => return es,
+            },
+        };
+        print_traps(core, &names);
+        return 0;
+    }
+
+    /* "trap SIG" and "trap - SIG..." put the signals back to the defaults */
+    let (command, specs) = match operands.len() {
+        1 => (None, operands),
+        _ if operands[0] == "-" => (None, &operands[1..]),
+        _ => (Some(operands[0].clone()), &operands[1..]),
+    };
+
+    let mut exit_status = 0;
+    for spec in specs {
+        let name = match signal::trap_name(spec) {
+            Some(name) => name,
+            None => {
+                exit_status = invalid_spec(spec, core);
+                continue;
+            },
+        };
+
+        match &command {
+            Some(com) => signal::set_trap(core, &name, com),
+            None      => signal::reset_trap(core, &name),
+        }
+    }
+    exit_status
+}
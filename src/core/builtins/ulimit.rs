@@ -0,0 +1,153 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use super::utils::print_line;
+use nix::sys::resource;
+use nix::libc::rlim_t;
+use nix::sys::resource::{Resource, RLIM_INFINITY};
+
+struct Limit {
+    opt: char,
+    description: &'static str,
+    unit: &'static str,
+    factor: rlim_t,
+    resource: Resource,
+}
+
+const LIMITS: [Limit; 7] = [
+    Limit{ opt: 'c', description: "core file size", unit: "blocks", factor: 1024, resource: Resource::RLIMIT_CORE },
+    Limit{ opt: 'f', description: "file size", unit: "blocks", factor: 1024, resource: Resource::RLIMIT_FSIZE },
+    Limit{ opt: 'n', description: "open files", unit: "", factor: 1, resource: Resource::RLIMIT_NOFILE },
+    Limit{ opt: 's', description: "stack size", unit: "kbytes", factor: 1024, resource: Resource::RLIMIT_STACK },
+    Limit{ opt: 't', description: "cpu time", unit: "seconds", factor: 1, resource: Resource::RLIMIT_CPU },
+    Limit{ opt: 'u', description: "max user processes", unit: "", factor: 1, resource: Resource::RLIMIT_NPROC },
+    Limit{ opt: 'v', description: "virtual memory", unit: "kbytes", factor: 1024, resource: Resource::RLIMIT_AS },
+];
+
+fn find(opt: char) -> Option<&'static Limit> {
+    LIMITS.iter().find(|l| l.opt == opt)
+}
+
+fn label(limit: &Limit) -> String {
+    let unit = match limit.unit {
+        "" => format!("(-{}) ", limit.opt),
+        u  => format!("({}, -{}) ", u, limit.opt),
+    };
+    format!("{:<20} {:>20}", limit.description, unit)
+}
+
+fn get(limit: &Limit, core: &mut ShellCore) -> Option<(rlim_t, rlim_t)> {
+    match resource::getrlimit(limit.resource) {
+        Ok(pair) => Some(pair),
+        Err(e) => {
+            error_message::print(&format!("ulimit: {}: cannot get limit: {}", limit.description, e.desc()), core, true);
+            None
+        },
+    }
+}
+
+fn print(limit: &Limit, hard: bool, with_label: bool, core: &mut ShellCore) -> i32 {
+    let (s, h) = match get(limit, core) {
+        Some(pair) => pair,
+        None       => return 1,
+    };
+
+    let value = if hard { h } else { s };
+    let value = match value == RLIM_INFINITY {
+        true  => "unlimited".to_string(),
+        false => (value / limit.factor).to_string(),
+    };
+
+    match with_label {
+        true  => print_line(&format!("{}{}", label(limit), value), core),
+        false => print_line(&value, core),
+    }
+    0
+}
+
+fn set(limit: &Limit, value: &str, soft: bool, hard: bool, core: &mut ShellCore) -> i32 {
+    let (s, h) = match get(limit, core) {
+        Some(pair) => pair,
+        None       => return 1,
+    };
+
+    let new = match value {
+        "unlimited" => RLIM_INFINITY,
+        "hard"      => h,
+        "soft"      => s,
+        _ => match value.parse::<rlim_t>() {
+            Ok(n) => n.saturating_mul(limit.factor),
+            Err(_) => {
+                error_message::print(&format!("ulimit: {}: invalid number", value), core, true);
+                return 1;
+            },
+        },
+    };
+
+    let (s, h) = match (soft, hard) {
+        (true, false) => (new, h),
+        (false, true) => (s, new),
+        _             => (new, new),
+    };
+
+    match resource::setrlimit(limit.resource, s, h) {
+        Ok(()) => 0,
+        Err(e) => {
+            error_message::print(&format!("ulimit: {}: cannot modify limit: {}", limit.description, e.desc()), core, true);
+            1
+        },
+    }
+}
+
+pub fn ulimit(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (mut soft, mut hard, mut all) = (false, false, false);
+    let mut targets: Vec<(&Limit, Option<String>)> = vec![];
+
+    for arg in &args[1..] {
+        if arg.starts_with("-") && arg.len() > 1 {
+            for ch in arg[1..].chars() {
+                match ch {
+                    'S' => soft = true,
+                    'H' => hard = true,
+                    'a' => all = true,
+                    _ => match find(ch) {
+                        Some(l) => targets.push((l, None)),
+                        None => {
+                            error_message::print(&format!("ulimit: -{}: invalid option", ch), core, true);
+                            error_message::print("ulimit: usage: ulimit [-SHacfnstuv] [limit]", core, true);
+                            return 2;
+                        },
+                    },
+                }
+            }
+            continue;
+        }
+
+        if targets.is_empty() {
+            targets.push((find('f').unwrap(), None));
+        }
+        let last = targets.len() - 1;
+        if targets[last].1.is_none() {
+            targets[last].1 = Some(arg.clone());
+        }
+    }
+
+    if all {
+        return LIMITS.iter().map(|l| print(l, hard && ! soft, true, core)).max().unwrap_or(0);
+    }
+    if targets.is_empty() {
+        targets.push((find('f').unwrap(), None));
+    }
+
+    let with_label = targets.len() > 1;
+    let mut ans = 0;
+    for (limit, value) in targets {
+        let status = match value {
+            Some(v) => set(limit, &v, soft, hard, core),
+            None    => print(limit, hard && ! soft, with_label, core),
+        };
+        ans = ans.max(status);
+    }
+    ans
+}
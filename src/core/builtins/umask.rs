@@ -0,0 +1,130 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use super::utils::print_line;
+use nix::sys::stat;
+use nix::sys::stat::Mode;
+
+fn get_mask() -> u32 {
+    let mask = stat::umask(Mode::empty());
+    stat::umask(mask);
+    mask.bits()
+}
+
+fn symbolic(mask: u32) -> String {
+    let allowed = !mask & 0o777;
+    let perms = |shift: u32| -> String {
+        [(4, 'r'), (2, 'w'), (1, 'x')].iter()
+            .filter(|(bit, _)| allowed >> shift & bit != 0)
+            .map(|(_, c)| *c)
+            .collect()
+    };
+    format!("u={},g={},o={}", perms(6), perms(3), perms(0))
+}
+
+fn bad_symbol(kind: &str, s: &str, core: &mut ShellCore) -> Option<u32> {
+    error_message::print(&format!("umask: `{}': invalid symbolic mode {}", s, kind), core, true);
+    None
+}
+
+/* a mode like u=rwx,g+w,o-r changes the permissions that the mask allows */
+fn parse_symbolic(mode: &str, mask: u32, core: &mut ShellCore) -> Option<u32> {
+    let mut allowed = !mask & 0o777;
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who = 0;
+        while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+            who |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                _   => 0o777,
+            };
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+
+        let op = match chars.next() {
+            Some(c) if "=+-".contains(c) => c,
+            Some(c) => return bad_symbol("operator", &c.to_string(), core),
+            None    => return bad_symbol("operator", "", core),
+        };
+
+        let mut perm = 0;
+        for c in chars {
+            perm |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                _   => return bad_symbol("character", &c.to_string(), core),
+            };
+        }
+
+        match op {
+            '+' => allowed |= perm & who,
+            '-' => allowed &= !(perm & who),
+            _   => allowed = (allowed & !who) | (perm & who),
+        }
+    }
+    Some(!allowed & 0o777)
+}
+
+fn parse_octal(mode: &str, core: &mut ShellCore) -> Option<u32> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(n) if n <= 0o777 => Some(n),
+        _ => {
+            error_message::print(&format!("umask: {}: octal number out of range", mode), core, true);
+            None
+        },
+    }
+}
+
+pub fn umask(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (mut reusable, mut sym) = (false, false);
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        pos += 1;
+        if args[pos-1] == "--" {
+            break;
+        }
+        for ch in args[pos-1][1..].chars() {
+            match ch {
+                'p' => reusable = true,
+                'S' => sym = true,
+                _ => {
+                    error_message::print(&format!("umask: -{}: invalid option", ch), core, true);
+                    error_message::print("umask: usage: umask [-p] [-S] [mode]", core, true);
+                    return 2;
+                },
+            }
+        }
+    }
+
+    if let Some(mode) = args.get(pos) {
+        let mask = match mode.starts_with(|c: char| c.is_ascii_digit()) {
+            true  => parse_octal(mode, core),
+            false => parse_symbolic(mode, get_mask(), core),
+        };
+        match mask {
+            Some(m) => { stat::umask(Mode::from_bits_truncate(m)); },
+            None    => return 1,
+        }
+        if ! sym {
+            return 0;
+        }
+    }
+
+    let mask = get_mask();
+    let value = match sym {
+        true  => symbolic(mask),
+        false => format!("{:04o}", mask),
+    };
+    match (reusable, sym) {
+        (true, true)  => print_line(&format!("umask -S {}", value), core),
+        (true, false) => print_line(&format!("umask {}", value), core),
+        _             => print_line(&value, core),
+    }
+    0
+}
@@ -1,36 +1,123 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{error_message, ShellCore};
+use super::utils::is_varname;
 
-fn unset_all(core: &mut ShellCore, name: &str) -> i32 {
-    core.data.unset(name);
-    0
+fn split_subscript(name: &str) -> Option<(String, String)> {
+    if ! name.ends_with("]") {
+        return None;
+    }
+
+    let (var, sub) = name[..name.len()-1].split_once('[')?;
+    Some((var.to_string(), sub.to_string()))
+}
+
+fn check_readonly(core: &mut ShellCore, name: &str) -> bool {
+    let name = core.data.resolve_nameref(name);
+    if core.data.is_readonly(&name) {
+        error_message::print(&format!("unset: {}: cannot unset: readonly variable", &name), core, true);
+        return false;
+    }
+    true
+}
+
+fn unset_array_elem(core: &mut ShellCore, name: &str, sub: &str) -> bool {
+    if sub == "@" || sub == "*" {
+        core.data.unset_var(name);
+        return true;
+    }
+
+    match sub.parse::<usize>() {
+        Ok(n) => {
+            core.data.unset_array_elem(name, n);
+            true
+        },
+        _ => {
+            error_message::print(&format!("unset: {}: bad array subscript", sub), core, true);
+            false
+        },
+    }
 }
 
-fn unset_var(core: &mut ShellCore, name: &str) -> i32 {
+fn unset_var(core: &mut ShellCore, name: &str) -> bool {
+    if let Some((var, sub)) = split_subscript(name) {
+        if is_varname(&var) {
+            return check_readonly(core, &var) && unset_array_elem(core, &var, &sub);
+        }
+    }
+
+    if ! is_varname(name) {
+        error_message::print(&format!("unset: `{}': not a valid identifier", name), core, true);
+        return false;
+    }
+
+    if ! check_readonly(core, name) {
+        return false;
+    }
     core.data.unset_var(name);
-    0
+    true
+}
+
+fn unset_nameref(core: &mut ShellCore, name: &str) -> bool {
+    if ! is_varname(name) {
+        error_message::print(&format!("unset: `{}': not a valid identifier", name), core, true);
+        return false;
+    }
+
+    if core.data.is_readonly(name) {
+        error_message::print(&format!("unset: {}: cannot unset: readonly variable", name), core, true);
+        return false;
+    }
+    core.data.unset_nameref(name);
+    true
 }
 
-fn unset_function(core: &mut ShellCore, name: &str) -> i32 {
+fn unset_function(core: &mut ShellCore, name: &str) -> bool {
     core.data.unset_function(name);
-    0
+    true
+}
+
+fn unset_var_or_function(core: &mut ShellCore, name: &str) -> bool {
+    if is_varname(name)
+    && ! core.data.is_set(name)
+    && core.data.functions.contains_key(name) {
+        return unset_function(core, name);
+    }
+    unset_var(core, name)
 }
 
 pub fn unset(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    match args[1].as_ref() {
-        "-f" => {
-            if args.len() > 2 {
-                return unset_function(core, &args[2]);
-            }
-        },
-        "-v" => {
-            if args.len() > 2 {
-                return unset_var(core, &args[2]);
-            }
-        },
-        name => return unset_all(core, name),
+    let mut pos = 1;
+    let mut opt = "";
+    while pos < args.len() && args[pos].starts_with("-") {
+        match args[pos].as_ref() {
+            "--" => {
+                pos += 1;
+                break;
+            },
+            "-v" | "-f" | "-n" => opt = &args[pos],
+            o => {
+                error_message::print(&format!("unset: {}: invalid option", o), core, true);
+                error_message::print("unset: usage: unset [-f] [-v] [-n] [name ...]", core, true);
+                return 2;
+            },
+        }
+        pos += 1;
+    }
+
+    let mut ok = true;
+    for name in &args[pos..] {
+        ok &= match opt {
+            "-v" => unset_var(core, name),
+            "-f" => unset_function(core, name),
+            "-n" => unset_nameref(core, name),
+            _    => unset_var_or_function(core, name),
+        };
+    }
+
+    match ok {
+        true  => 0,
+        false => 1,
     }
-    0
 }
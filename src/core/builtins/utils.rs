@@ -3,8 +3,38 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use crate::utils;
+use nix::errno::Errno;
+use nix::unistd;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
 use std::path::{Path, PathBuf, Component};
 
+/* a line of the output of a builtin. println! panics on a write error,
+   which is kept here instead and reported by ShellCore::run_builtin.
+   The line is written to the fd directly so that a failed line doesn't
+   stay in the buffer of stdout after a redirection is restored. */
+pub fn print_line(s: &str, core: &mut ShellCore) {
+    if core.write_error.is_some() {
+        return;
+    }
+
+    let _ = io::stdout().flush();
+    let line = format!("{}\n", s);
+    let mut buf = line.as_bytes();
+    while ! buf.is_empty() {
+        match unistd::write(io::stdout(), buf) {
+            Ok(n)  => buf = &buf[n..],
+            Err(Errno::EINTR) => {},
+            Err(e) => {
+                core.write_error = Some(e.desc().to_string());
+                return;
+            },
+        }
+    }
+}
+
 pub fn make_absolute_path(core: &mut ShellCore, path_str: &str) -> PathBuf {
     let path = Path::new(&path_str);
     let mut absolute = PathBuf::new();
@@ -46,3 +76,68 @@ pub fn make_canonical_path(core: &mut ShellCore, path_str: &str) -> PathBuf {
     }
     canonical
 }
+
+pub fn is_varname(s: &str) -> bool {
+    match s.chars().next() {
+        Some(c) if ! c.is_ascii_digit() => {},
+        _ => return false,
+    }
+
+    s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn to_double_quoted(s: &str) -> String {
+    let escaped: String = s.chars().map(|c| match c {
+        '"' | '\\' | '$' | '`' => format!("\\{}", c),
+        _ => c.to_string(),
+    }).collect();
+    format!("\"{}\"", escaped)
+}
+
+/* $'...' form for values with control characters */
+pub fn to_ansi_c_quoted(s: &str) -> String {
+    let escaped: String = s.chars().map(|c| match c {
+        '\x07' => "\\a".to_string(),
+        '\x08' => "\\b".to_string(),
+        '\x0c' => "\\f".to_string(),
+        '\n'   => "\\n".to_string(),
+        '\r'   => "\\r".to_string(),
+        '\t'   => "\\t".to_string(),
+        '\x0b' => "\\v".to_string(),
+        '\x1b' => "\\E".to_string(),
+        '\'' | '\\' => format!("\\{}", c),
+        c if c.is_ascii_control() => format!("\\{:03o}", c as u32),
+        c => c.to_string(),
+    }).collect();
+    format!("$'{}'", escaped)
+}
+
+fn has_control(s: &str) -> bool {
+    s.chars().any(|c| c.is_ascii_control())
+}
+
+/* values in the output of declare -p */
+pub fn quote_for_declare(s: &str) -> String {
+    match has_control(s) {
+        true  => to_ansi_c_quoted(s),
+        false => to_double_quoted(s),
+    }
+}
+
+/* values in the output of set, which are quoted only if needed */
+pub fn quote_for_set(s: &str) -> String {
+    if s.is_empty() {
+        return String::new();
+    }
+    match has_control(s) {
+        true  => to_ansi_c_quoted(s),
+        false => utils::quote_for_trace(s),
+    }
+}
+
+pub fn array_for_declare(a: &BTreeMap<usize, String>) -> String {
+    let elems: Vec<String> = a.iter()
+        .map(|(i, v)| format!("[{}]={}", i, quote_for_declare(v)))
+        .collect();
+    format!("({})", elems.join(" "))
+}
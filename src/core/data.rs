@@ -1,11 +1,21 @@
 //SPDXFileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDXLicense-Identifier: BSD-3-Clause
 
+mod dynamic;
+mod exit_status;
+
+use self::dynamic::DynamicVars;
+pub use self::exit_status::ExitStatus;
+use crate::error_message;
 use crate::elements::array::Array;
 use crate::elements::word::Word;
 use crate::elements::command::function_def::FunctionDefinition;
+use crate::core::builtins::utils::is_varname;
+use crate::utils::bytes;
 use std::env;
-use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStrExt;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -13,36 +23,106 @@ pub enum Value {
     Single(Word),
     EvaluatedSingle(String),
     Array(Array),
-    EvaluatedArray(Vec<String>),
+    EvaluatedArray(BTreeMap<usize, String>),
+    Nameref(String),
 }
 
 #[derive(Debug)]
 pub struct Data {
     pub flags: String,
     parameters: Vec<HashMap<String, Value>>,
+    function_layers: Vec<usize>,
     pub position_parameters: Vec<Vec<String>>,
     pub aliases: HashMap<String, String>,
     pub functions: HashMap<String, FunctionDefinition>,
     pub alias_memo: Vec<(String, String)>,
+    exported: HashSet<String>,
+    /* exported only for temporary assignments of the running commands */
+    temporary_exports: HashSet<String>,
+    exported_functions: HashSet<String>,
+    readonly: HashSet<String>,
+    hidden_env: Vec<(OsString, OsString)>,
+    dynamic: DynamicVars,
+    exit_status: ExitStatus,
+}
+
+/* an indexed array keeps the index of each element so that it can be sparse */
+pub fn to_array_map(vals: &[String]) -> BTreeMap<usize, String> {
+    vals.iter().cloned().enumerate().collect()
 }
 
 impl Data {
     pub fn new() -> Data {
-        Data {
+        let mut data = Data {
             flags: String::new(),
             parameters: vec![HashMap::new()],
+            function_layers: vec![],
             position_parameters: vec![vec![]],
             aliases: HashMap::new(),
             functions: HashMap::new(),
             alias_memo: vec![],
+            exported: HashSet::new(),
+            temporary_exports: HashSet::new(),
+            exported_functions: HashSet::new(),
+            readonly: HashSet::new(),
+            hidden_env: vec![],
+            dynamic: DynamicVars::new(),
+            exit_status: ExitStatus::default(),
+        };
+
+        data.import_env();
+        data
+    }
+
+    /* entries whose names cannot be variables are passed to children as they are */
+    fn import_env(&mut self) {
+        for (k, v) in env::vars_os() {
+            match k.to_str().filter(|k| is_varname(k)) {
+                Some(key) => {
+                    let val = bytes::decode(v.as_bytes());
+                    self.parameters[0].insert(key.to_string(), Value::EvaluatedSingle(val));
+                    self.exported.insert(key.to_string());
+                },
+                None => self.hidden_env.push((k, v)),
+            }
         }
     }
 
+    pub fn get_hidden_env(&self) -> &Vec<(OsString, OsString)> {
+        &self.hidden_env
+    }
+
+    /* takes the entries of exported functions, BASH_FUNC_name%%=() ..., out of the hidden ones */
+    pub fn take_function_env(&mut self) -> Vec<(String, String)> {
+        let mut ans = vec![];
+        self.hidden_env.retain(|(k, v)| {
+            let name = k.to_str().and_then(|k| k.strip_prefix("BASH_FUNC_"))
+                                 .and_then(|k| k.strip_suffix("%%"));
+            match (name, v.to_str()) {
+                (Some(n), Some(body)) if is_varname(n) && body.starts_with("() ") => {
+                    ans.push((n.to_string(), body.to_string()));
+                    false
+                },
+                _ => true,
+            }
+        });
+        ans
+    }
+
     pub fn get_param(&mut self, key: &str) -> String {
+        let key = &self.resolve_nameref(key);
         if key == "-" {
             return self.flags.clone();
         }
 
+        if key == "#" {
+            return self.get_position_params().len().to_string();
+        }
+
+        if key == "?" {
+            return self.exit_status.to_string();
+        }
+
         if key == "@" || key == "*" {
             return match self.position_parameters.last() {
                 Some(a) => a[1..].join(" "),
@@ -55,35 +135,28 @@ impl Data {
             return self.position_parameters[layer-1][n].to_string();
         }
 
-        match self.get_value(key) {
+        if let Some(v) = self.dynamic.get(key) {
+            return v;
+        }
+
+        match self.get_raw_value(key) {
             Some(Value::EvaluatedSingle(v)) => return v.to_string(),
             Some(Value::EvaluatedArray(a)) => {
-                match a.len() {
-                    0 => return "".to_string(),
-                    _ => return a[0].to_string(),
-                }
+                return a.get(&0).cloned().unwrap_or_default();
             },
             _  => {},
         }
 
-        match env::var(key) {
-            Ok(v) => {
-                self.set_layer_param(key, &v, 0);
-                v
-            },
-            _ => "".to_string()
-        }
+        "".to_string()
     }
 
     pub fn get_array(&mut self, key: &str, pos: &str) -> String {
         match self.get_value(key) {
             Some(Value::EvaluatedArray(a)) => {
                 if pos == "@" {
-                    return a.join(" ");
+                    return a.into_values().collect::<Vec<String>>().join(" ");
                 } else if let Ok(n) = pos.parse::<usize>() {
-                    if n < a.len() {
-                        return a[n].clone();
-                    }
+                    return a.get(&n).cloned().unwrap_or_default();
                 }
             },
             Some(Value::EvaluatedSingle(v)) => {
@@ -99,14 +172,22 @@ impl Data {
     }
 
     pub fn get_value(&mut self, key: &str) -> Option<Value> {
-        let num = self.parameters.len();
-        for layer in (0..num).rev()  {
-            match self.parameters[layer].get(key) {
-                Some(v) => return Some(v.clone()),
-                _ => {},
-            }
+        let key = &self.resolve_nameref(key);
+        if let Some(a) = self.dynamic.get_array(key) {
+            return Some(Value::EvaluatedArray(to_array_map(&a)));
+        }
+        if let Some(v) = self.dynamic.get(key) {
+            return Some(Value::EvaluatedSingle(v));
         }
-        None
+        /* a declared but unset variable hides the outer ones */
+        match self.get_raw_value(key) {
+            Some(Value::None) => None,
+            v => v.cloned(),
+        }
+    }
+
+    fn get_raw_value(&self, key: &str) -> Option<&Value> {
+        self.parameters.iter().rev().find_map(|layer| layer.get(key))
     }
 
     pub fn get_array_len(&mut self, key: &str) -> usize {
@@ -118,7 +199,7 @@ impl Data {
 
     pub fn get_array_all(&mut self, key: &str) -> Vec<String> {
         match self.get_value(key) {
-            Some(Value::EvaluatedArray(a)) => a.clone(),
+            Some(Value::EvaluatedArray(a)) => a.into_values().collect(),
             _ => vec![],
         }
     }
@@ -144,35 +225,144 @@ impl Data {
         }
     }
 
-    pub fn set_layer_param(&mut self, key: &str, val: &str, layer: usize) {
-        match env::var(key) {
-            Ok(_) => env::set_var(key, val),
-            _     => {},
+    pub fn resolve_nameref(&self, key: &str) -> String {
+        let mut name = key.to_string();
+        let mut visited = vec![];
+        while let Some(Value::Nameref(target)) = self.get_raw_value(&name) {
+            if target.is_empty() {
+                break;
+            }
+            if visited.contains(target) || target == key {
+                self.print_error(&format!("warning: {}: circular name reference", key));
+                return key.to_string();
+            }
+            let target = target.clone();
+            visited.push(name);
+            name = target;
+        }
+        name
+    }
+
+    /* true also for a variable that is declared by local or declare without a value */
+    pub fn is_declared(&self, key: &str) -> bool {
+        self.get_raw_value(key).is_some()
+    }
+
+    pub fn is_nameref(&self, key: &str) -> bool {
+        matches!(self.get_raw_value(key), Some(Value::Nameref(_)))
+    }
+
+    pub fn get_nameref_target(&self, key: &str) -> Option<String> {
+        match self.get_raw_value(key) {
+            Some(Value::Nameref(target)) => Some(target.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn set_layer_nameref(&mut self, key: &str, target: &str, layer: usize) {
+        self.parameters[layer].insert(key.to_string(), Value::Nameref(target.to_string()));
+    }
+
+    /* the same form as error_message::print, where a script is not interactive */
+    fn print_error(&self, msg: &str) {
+        let name = match self.position_parameters.last().and_then(|p| p.first()) {
+            Some(name) => name.to_string(),
+            None       => "sush".to_string(),
+        };
+        let lineno = self.dynamic.lineno.to_string();
+        let lang = self.language();
+        match self.flags.contains('i') {
+            true  => error_message::print_located(msg, &name, None, &lang),
+            false => error_message::print_located(msg, &name, Some(&lineno), &lang),
+        }
+    }
+
+    /* the language of messages from the shell variables, which need not be exported */
+    pub fn language(&self) -> String {
+        error_message::LANGUAGE_VARS.iter()
+            .filter_map(|name| match self.get_raw_value(name) {
+                Some(Value::EvaluatedSingle(v)) => Some(v.clone()),
+                _ => None,
+            })
+            .find(|v| ! v.is_empty())
+            .unwrap_or_default()
+    }
+
+    fn check_readonly(&self, key: &str) -> bool {
+        if self.readonly.contains(key) {
+            self.print_error(&format!("{}: readonly variable", key));
+            return false;
+        }
+        true
+    }
+
+    pub fn set_layer_param(&mut self, key: &str, val: &str, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_readonly(key) {
+            return false;
+        }
+        if self.dynamic.set(key, val) {
+            return true;
         }
 
         self.parameters[layer].insert(key.to_string(), Value::EvaluatedSingle(val.to_string()));
+        true
     }
 
-    pub fn set_param(&mut self, key: &str, val: &str) {
-        self.set_layer_param(key, val, 0);
+    /* the innermost layer that has the variable, or the global one */
+    fn get_layer_of(&self, key: &str) -> usize {
+        self.parameters.iter().rposition(|layer| layer.contains_key(key)).unwrap_or(0)
     }
 
-    pub fn set_local_param(&mut self, key: &str, val: &str) {
+    pub fn set_param(&mut self, key: &str, val: &str) -> bool {
+        let key = &self.resolve_nameref(key);
+        let layer = self.get_layer_of(key);
+        self.set_layer_param(key, val, layer)
+    }
+
+    pub fn exit_status(&self) -> ExitStatus {
+        self.exit_status
+    }
+
+    pub fn set_exit_status(&mut self, code: i32) {
+        self.exit_status = ExitStatus::new(code);
+    }
+
+    pub fn set_local_param(&mut self, key: &str, val: &str) -> bool {
         let layer = self.parameters.len();
-        self.set_layer_param(key, val, layer-1);
+        self.set_layer_param(key, val, layer-1)
     }
 
-    pub fn set_layer_array(&mut self, key: &str, vals: &Vec<String>, layer: usize) {
-        self.parameters[layer].insert(key.to_string(), Value::EvaluatedArray(vals.to_vec()));
+    pub fn set_layer_array(&mut self, key: &str, vals: &Vec<String>, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_readonly(key) {
+            return false;
+        }
+
+        self.parameters[layer].insert(key.to_string(), Value::EvaluatedArray(to_array_map(vals)));
+        true
     }
 
-    pub fn set_array(&mut self, key: &str, vals: &Vec<String>) {
-        self.set_layer_array(key, vals, 0);
+    pub fn set_array(&mut self, key: &str, vals: &Vec<String>) -> bool {
+        let key = &self.resolve_nameref(key);
+        let layer = self.get_layer_of(key);
+        self.set_layer_array(key, vals, layer)
     }
 
-    pub fn set_local_array(&mut self, key: &str, vals: &Vec<String>) {
+    /* for local and declare without values; the value in the layer is kept if any */
+    pub fn set_layer_declared(&mut self, key: &str, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_readonly(key) {
+            return false;
+        }
+
+        self.parameters[layer].entry(key.to_string()).or_insert(Value::None);
+        true
+    }
+
+    pub fn set_local_array(&mut self, key: &str, vals: &Vec<String>) -> bool {
         let layer = self.parameters.len();
-        self.set_layer_array(key, vals, layer-1);
+        self.set_layer_array(key, vals, layer-1)
     }
 
     pub fn push_local(&mut self) {
@@ -183,6 +373,21 @@ impl Data {
         self.parameters.pop();
     }
 
+    /* the layer for the local variables of a function, which its callees also see */
+    pub fn push_function_scope(&mut self) {
+        self.parameters.push(HashMap::new());
+        self.function_layers.push(self.parameters.len() - 1);
+    }
+
+    pub fn pop_function_scope(&mut self) {
+        self.function_layers.pop();
+        self.parameters.pop();
+    }
+
+    pub fn get_function_layer(&self) -> Option<usize> {
+        self.function_layers.last().copied()
+    }
+
     pub fn get_layer_num(&mut self) -> usize {
         self.parameters.len()
     }
@@ -210,13 +415,10 @@ impl Data {
         }
     }
 
+    /* an alias at the head of the value is also replaced unless it has appeared */
     fn replace_alias_core(&self, word: &mut String) -> bool {
-        if self.flags.find('i') == None {
-            return false;
-        }
-
         let mut ans = false;
-        let mut prev_head = "".to_string();
+        let mut heads = vec![];
 
         loop {
             let head = match word.replace("\n", " ").split(' ').nth(0) {
@@ -224,30 +426,175 @@ impl Data {
                 _ => return ans,
             };
 
-            if prev_head == head {
+            if heads.contains(&head) {
                 return ans;
             }
-    
-            if let Some(value) = self.aliases.get(&head) {
-                *word = word.replacen(&head, value, 1);
-                ans = true;
+
+            match self.aliases.get(&head) {
+                Some(value) => *word = word.replacen(&head, value, 1),
+                None => return ans,
             }
-            prev_head = head;
+            ans = true;
+            heads.push(head);
+        }
+    }
+
+    pub fn is_set(&mut self, key: &str) -> bool {
+        self.get_value(key).is_some()
+    }
+
+    /* used by set -u; special parameters other than $! always have values */
+    pub fn has_value(&mut self, key: &str, pos: Option<&str>) -> bool {
+        if key.len() == 1 && "-#@*?$0".contains(key) {
+            return true;
+        }
+        if key.len() == 1 && "123456789".contains(key) {
+            return self.get_position_param_pos(key).is_some();
+        }
+
+        match (self.get_value(key), pos) {
+            (None, _) => false,
+            (Some(Value::EvaluatedArray(a)), Some(p)) => match p.parse::<usize>() {
+                Ok(n) => a.contains_key(&n),
+                Err(_) => ! a.is_empty() || p == "@" || p == "*",
+            },
+            (Some(Value::EvaluatedSingle(_)), Some(p)) => p == "0" || p == "@" || p == "*",
+            _ => true,
+        }
+    }
+
+    pub fn is_exported(&self, key: &str) -> bool {
+        self.exported.contains(key)
+    }
+
+    pub fn get_exported_keys(&self) -> Vec<String> {
+        let mut ans: Vec<String> = self.exported.iter().map(|k| k.to_string()).collect();
+        ans.sort();
+        ans
+    }
+
+    pub fn export(&mut self, key: &str) {
+        let key = self.resolve_nameref(key);
+        self.temporary_exports.remove(&key);
+        self.exported.insert(key);
+    }
+
+    pub fn unexport(&mut self, key: &str) {
+        let key = self.resolve_nameref(key);
+        self.temporary_exports.remove(&key);
+        self.exported.remove(&key);
+    }
+
+    pub fn export_temporarily(&mut self, key: &str) {
+        self.exported.insert(key.to_string());
+        self.temporary_exports.insert(key.to_string());
+    }
+
+    /* returns false when the command has exported or unexported the variable by itself */
+    pub fn end_temporary_export(&mut self, key: &str) -> bool {
+        if ! self.temporary_exports.remove(key) {
+            return false;
+        }
+        self.exported.remove(key);
+        true
+    }
+
+    pub fn is_readonly(&self, key: &str) -> bool {
+        self.readonly.contains(key)
+    }
+
+    pub fn get_readonly_keys(&self) -> Vec<String> {
+        let mut ans: Vec<String> = self.readonly.iter().map(|k| k.to_string()).collect();
+        ans.sort();
+        ans
+    }
+
+    pub fn set_readonly(&mut self, key: &str) {
+        self.readonly.insert(key.to_string());
+    }
+
+    fn remove_innermost(&mut self, key: &str) {
+        match self.parameters.iter().rposition(|layer| layer.contains_key(key)) {
+            Some(0) | None => {
+                self.parameters[0].remove(key);
+                self.exported.remove(key);
+            },
+            /* a local variable of the running function stays local */
+            Some(layer) if self.function_layers.last() == Some(&layer) => {
+                self.parameters[layer].insert(key.to_string(), Value::None);
+            },
+            Some(layer) => {
+                self.parameters[layer].remove(key);
+            },
         }
     }
 
+    /* for FUNCNAME, BASH_SOURCE and BASH_LINENO */
+    pub fn push_call_frame(&mut self, name: &str, source: &str) {
+        self.dynamic.push_frame(name, source);
+    }
+
+    pub fn pop_call_frame(&mut self) {
+        self.dynamic.pop_frame();
+    }
+
+    pub fn set_main_source(&mut self, name: &str) {
+        self.dynamic.main_source = name.to_string();
+    }
+
+    /* a subshell must not repeat the random numbers of the parent */
+    pub fn reseed_random(&mut self) {
+        self.dynamic.reseed();
+    }
+
     pub fn unset_var(&mut self, key: &str) {
-        for layer in &mut self.parameters {
-            layer.remove(key);
+        let key = &self.resolve_nameref(key);
+        self.dynamic.unset(key);
+        self.remove_innermost(key);
+    }
+
+    pub fn unset_nameref(&mut self, key: &str) {
+        match self.is_nameref(key) {
+            true  => self.remove_innermost(key),
+            false => self.unset_var(key),
+        }
+    }
+
+    /* arrays are sparse; the indexes of the other elements are kept */
+    pub fn unset_array_elem(&mut self, key: &str, pos: usize) {
+        let key = &self.resolve_nameref(key);
+        let layer = match self.parameters.iter().rposition(|layer| layer.contains_key(key)) {
+            Some(layer) => layer,
+            None        => return,
+        };
+
+        match self.parameters[layer].get_mut(key) {
+            Some(Value::EvaluatedArray(a)) => {
+                a.remove(&pos);
+            },
+            Some(Value::EvaluatedSingle(_)) if pos == 0 => self.remove_innermost(key),
+            _ => {},
         }
     }
 
     pub fn unset_function(&mut self, key: &str) {
         self.functions.remove(key);
+        self.exported_functions.remove(key);
+    }
+
+    pub fn export_function(&mut self, key: &str) {
+        self.exported_functions.insert(key.to_string());
     }
 
-    pub fn unset(&mut self, key: &str) {
-        self.unset_var(key);
-        self.unset_function(key);
+    pub fn unexport_function(&mut self, key: &str) {
+        self.exported_functions.remove(key);
+    }
+
+    pub fn get_exported_functions(&self) -> Vec<String> {
+        let mut ans: Vec<String> = self.exported_functions.iter()
+            .filter(|f| self.functions.contains_key(*f))
+            .map(|f| f.to_string()).collect();
+        ans.sort();
+        ans
     }
 }
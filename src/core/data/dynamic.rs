@@ -0,0 +1,124 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::HashSet;
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/* variables whose values are computed when they are read.
+   they lose the special properties once they are unset */
+#[derive(Debug)]
+pub struct DynamicVars {
+    names: HashSet<&'static str>,
+    pub lineno: usize,
+    command: String,
+    seconds: (Instant, i64),
+    random_state: u32,
+    /* function name or "source", the file of the definition or the sourced file, and the line of the call */
+    call_stack: Vec<(String, String, usize)>,
+    pub main_source: String,
+}
+
+fn epoch() -> (u64, u32) {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs(), d.subsec_micros()),
+        Err(_) => (0, 0),
+    }
+}
+
+fn seed() -> u32 {
+    let (sec, usec) = epoch();
+    (sec as u32) ^ usec ^ process::id()
+}
+
+impl DynamicVars {
+    pub fn new() -> Self {
+        DynamicVars {
+            names: HashSet::from(["LINENO", "SECONDS", "RANDOM", "EPOCHSECONDS", "EPOCHREALTIME",
+                                  "FUNCNAME", "BASH_SOURCE", "BASH_LINENO", "BASH_COMMAND"]),
+            lineno: 0,
+            command: String::new(),
+            seconds: (Instant::now(), 0),
+            random_state: seed(),
+            call_stack: vec![],
+            main_source: "main".to_string(),
+        }
+    }
+
+    pub fn reseed(&mut self) {
+        self.random_state = seed();
+    }
+
+    fn random(&mut self) -> String {
+        self.random_state = self.random_state.wrapping_mul(1103515245).wrapping_add(12345);
+        ((self.random_state >> 16) & 0x7fff).to_string()
+    }
+
+    pub fn push_frame(&mut self, name: &str, source: &str) {
+        self.call_stack.push((name.to_string(), source.to_string(), self.lineno));
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.call_stack.pop();
+    }
+
+    /* the arrays have the innermost frame first. FUNCNAME is empty outside functions */
+    pub fn get_array(&self, name: &str) -> Option<Vec<String>> {
+        if ! self.names.contains(name) {
+            return None;
+        }
+
+        let frames = self.call_stack.iter().rev();
+        let ans = match name {
+            "FUNCNAME" if self.call_stack.iter().all(|f| f.0 == "source") => vec![],
+            "FUNCNAME" => frames.map(|f| f.0.clone()).chain(["main".to_string()]).collect(),
+            "BASH_SOURCE" => frames.map(|f| f.1.clone()).chain([self.main_source.clone()]).collect(),
+            "BASH_LINENO" => frames.map(|f| f.2.to_string()).chain(["0".to_string()]).collect(),
+            _ => return None,
+        };
+        Some(ans)
+    }
+
+    pub fn get(&mut self, name: &str) -> Option<String> {
+        if ! self.names.contains(name) {
+            return None;
+        }
+        if let Some(a) = self.get_array(name) {
+            return Some(a.first().cloned().unwrap_or_default());
+        }
+
+        let ans = match name {
+            "LINENO" => self.lineno.to_string(),
+            "BASH_COMMAND" => self.command.clone(),
+            "SECONDS" => (self.seconds.1 + self.seconds.0.elapsed().as_secs() as i64).to_string(),
+            "RANDOM" => self.random(),
+            "EPOCHSECONDS" => epoch().0.to_string(),
+            _ => {
+                let (sec, usec) = epoch();
+                format!("{}.{:06}", sec, usec)
+            },
+        };
+        Some(ans)
+    }
+
+    /* returns false if the variable is not dynamic */
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        if ! self.names.contains(name) {
+            return false;
+        }
+
+        let n = value.trim().parse::<i64>().unwrap_or(0);
+        match name {
+            "BASH_COMMAND" => self.command = value.to_string(),
+            "LINENO" => self.lineno = n.max(0) as usize,
+            "SECONDS" => self.seconds = (Instant::now(), n),
+            "RANDOM" => self.random_state = n as u32,
+            _ => {},
+        }
+        true
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.names.remove(name);
+    }
+}
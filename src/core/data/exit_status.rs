@@ -0,0 +1,36 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::fmt;
+
+/* the exit status of the last command. it becomes a string only when $? is expanded */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExitStatus(i32);
+
+impl ExitStatus {
+    pub fn new(code: i32) -> Self {
+        ExitStatus(code)
+    }
+
+    pub fn code(&self) -> i32 {
+        self.0
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.0 == 0
+    }
+
+    /* the status of a pipeline with ! */
+    pub fn flip(&self) -> Self {
+        match self.is_success() {
+            true  => ExitStatus(1),
+            false => ExitStatus(0),
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
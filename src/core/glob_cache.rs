@@ -0,0 +1,35 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::utils::glob;
+use crate::utils::glob::Matcher;
+use std::rc::Rc;
+
+/* the number of patterns kept; the table is simply cleared when it is full */
+const GLOB_CACHE_SIZE: usize = 256;
+
+impl ShellCore {
+    /* a pattern is compiled only once while it comes again with the same options,
+       e.g. in case or [[ == ]] repeated in a loop */
+    pub fn glob_matcher(&mut self, pattern: &str, nocase: bool) -> Rc<Matcher> {
+        let key = (pattern.to_string(), self.shopts.query("extglob"), nocase);
+        if let Some(m) = self.glob_cache.get(&key) {
+            return m.clone();
+        }
+
+        if self.glob_cache.len() >= GLOB_CACHE_SIZE {
+            self.glob_cache.clear();
+        }
+        let m = Rc::new(glob::compile(&key.0, key.1).ignore_case(nocase));
+        self.glob_cache.insert(key, m.clone());
+        m
+    }
+
+    /* for GLOBIGNORE, HISTIGNORE and EXECIGNORE, which are colon-separated lists of patterns */
+    pub fn ignored_by(&mut self, var: &str, word: &str) -> bool {
+        let patterns = self.data.get_param(var);
+        patterns.split(':').filter(|p| ! p.is_empty())
+                .any(|p| self.glob_matcher(p, false).matches(word))
+    }
+}
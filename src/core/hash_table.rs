@@ -0,0 +1,50 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::utils::file_check;
+
+impl ShellCore {
+    /* the table is cleared when PATH has been changed after the last lookup */
+    pub fn check_hash_table_path(&mut self) -> String {
+        let path = self.data.get_param("PATH");
+        if path != self.hash_table_path {
+            self.hash_table.clear();
+            self.hash_table_path = path.clone();
+        }
+        path
+    }
+
+    pub fn hashed_path(&mut self, name: &str) -> Option<String> {
+        self.check_hash_table_path();
+        self.hash_table.get(name).map(|e| e.0.clone())
+    }
+
+    /* searches PATH when the name is not in the table. a file in the table
+       is checked only with checkhash so as not to access the file system */
+    pub fn hash_search(&mut self, name: &str) -> Option<String> {
+        let path = self.check_hash_table_path();
+        let checkhash = self.shopts.query("checkhash");
+        if let Some((p, _)) = self.hash_table.get(name) {
+            if ! checkhash || file_check::is_executable(p) {
+                return Some(p.clone());
+            }
+        }
+
+        let found = self.search_path(name, &path)?;
+        self.hash_table.insert(name.to_string(), (found.clone(), 0));
+        Some(found)
+    }
+
+    /* files matching a pattern in EXECIGNORE are not regarded as commands */
+    pub fn search_path(&mut self, name: &str, path: &str) -> Option<String> {
+        path.split(':').filter_map(|dir| file_check::search_path(name, dir))
+            .find(|p| ! self.ignored_by("EXECIGNORE", p))
+    }
+
+    pub fn hash_hit(&mut self, name: &str) {
+        if let Some(e) = self.hash_table.get_mut(name) {
+            e.1 += 1;
+        }
+    }
+}
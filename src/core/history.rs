@@ -2,47 +2,137 @@
 //SPDXLicense-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
-use rev_lines::RevLines;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::fs::OpenOptions;
+use crate::error_message;
+use crate::utils::glob;
 
+/* the history list is kept from the newest entry to the oldest one,
+   and history_number is the number of the entry to be added next */
 impl ShellCore {
     pub fn fetch_history(&mut self, pos: usize, prev: usize, prev_str: String) -> String {
         if prev < self.history.len() {
             self.history[prev] = prev_str;
-        }else{
-            self.rewritten_history.insert(prev + 1 - self.history.len(), prev_str);
         }
 
-        if pos < self.history.len() {
-            self.history[pos].clone()
-        }else{
-            self.fetch_history_file(pos + 1 - self.history.len())
+        match self.history.get(pos) {
+            Some(h) => h.clone(),
+            None    => String::new(),
         }
     }
 
-    pub fn fetch_history_file(&mut self, pos: usize) -> String {
-        if let Some(s) = self.rewritten_history.get(&pos) {
-            return s.to_string();
+    fn history_limit(&mut self, name: &str) -> Option<usize> {
+        match self.data.get_param(name).parse::<i64>() {
+            Ok(n) if n >= 0 => Some(n as usize),
+            _ => None,
         }
-        if pos == 0 {
-            return String::new();
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        let control = self.data.get_param("HISTCONTROL");
+        let control: Vec<&str> = control.split(':').collect();
+        let ignore_space = control.contains(&"ignorespace") || control.contains(&"ignoreboth");
+        let ignore_dups = control.contains(&"ignoredups") || control.contains(&"ignoreboth");
+
+        if line.is_empty()
+        || (ignore_space && line.starts_with(' '))
+        || (ignore_dups && self.history.first().is_some_and(|h| h == line))
+        || self.history_ignored(line) {
+            return;
+        }
+        if control.contains(&"erasedups") {
+            self.history.retain(|h| h != line);
         }
 
-        let mut file_line = pos - 1;
-        if let Ok(n) = self.data.get_param("HISTFILESIZE").parse::<usize>() {
-            file_line %= n;
+        self.history.insert(0, line.to_string());
+        self.history_unsaved += 1;
+        self.history_number += 1;
+        if let Some(n) = self.history_limit("HISTSIZE") {
+            self.history.truncate(n);
         }
+    }
 
-        if let Ok(hist_file) = File::open(self.data.get_param("HISTFILE")){
-            let mut rev_lines = RevLines::new(BufReader::new(hist_file));
-            if let Some(Ok(s)) = rev_lines.nth(file_line) {
-                return s;
+    /* an & in HISTIGNORE stands for the previous history line */
+    fn history_ignored(&mut self, line: &str) -> bool {
+        let prev = self.history.first().cloned().unwrap_or_default();
+        let patterns = self.data.get_param("HISTIGNORE").split(':')
+                           .filter(|p| ! p.is_empty())
+                           .map(|p| replace_ampersand(p, &prev))
+                           .collect::<Vec<String>>();
+        patterns.iter().any(|p| self.glob_matcher(p, false).matches(line))
+    }
+
+    pub fn load_history(&mut self) {
+        if ! self.data.flags.contains('i') {
+            return;
+        }
+        let filename = self.data.get_param("HISTFILE");
+        if ! filename.is_empty() {
+            let _ = self.read_history_file(&filename);
+        }
+        self.history_unsaved = 0;
+    }
+
+    /* the lines in the file are added as the newest entries */
+    pub fn read_history_file(&mut self, filename: &str) -> Result<(), String> {
+        let file = File::open(filename).map_err(|e| error_message::io_error(&e))?;
+        for line in BufReader::new(file).lines() {
+            match line {
+                Ok(ln) if ! ln.is_empty() => {
+                    self.history.insert(0, ln);
+                    self.history_number += 1;
+                },
+                _ => {},
             }
         }
 
-        String::new()
+        if let Some(n) = self.history_limit("HISTSIZE") {
+            self.history.truncate(n);
+        }
+        Ok(())
+    }
+
+    /* only the entries added after the last reading or writing if new_only */
+    pub fn write_history_file(&mut self, filename: &str, new_only: bool) -> Result<(), String> {
+        let file = OpenOptions::new().create(true).write(true)
+                   .append(new_only).truncate(! new_only)
+                   .open(filename).map_err(|e| error_message::io_error(&e))?;
+
+        let num = match new_only {
+            true  => self.history_unsaved.min(self.history.len()),
+            false => self.history.len(),
+        };
+
+        let mut f = BufWriter::new(file);
+        for h in self.history[..num].iter().rev().filter(|h| ! h.is_empty()) {
+            writeln!(f, "{}", h).map_err(|e| error_message::io_error(&e))?;
+        }
+        f.flush().map_err(|e| error_message::io_error(&e))?;
+
+        self.history_unsaved = 0;
+        Ok(())
+    }
+
+    fn truncate_history_file(&mut self, filename: &str) {
+        let limit = match self.history_limit("HISTFILESIZE") {
+            Some(n) => n,
+            None    => return,
+        };
+
+        let lines: Vec<String> = match File::open(filename) {
+            Ok(f) => BufReader::new(f).lines().map_while(Result::ok).collect(),
+            _     => return,
+        };
+        if lines.len() <= limit {
+            return;
+        }
+
+        if let Ok(mut f) = File::create(filename) {
+            for ln in &lines[lines.len() - limit..] {
+                let _ = writeln!(f, "{}", ln);
+            }
+        }
     }
 
     pub fn write_history_to_file(&mut self) {
@@ -50,28 +140,31 @@ impl ShellCore {
             return;
         }
         let filename = self.data.get_param("HISTFILE");
-        if filename == "" {
-            eprintln!("sush: HISTFILE is not set");
+        if filename.is_empty() {
             return;
         }
-    
-        let file = match OpenOptions::new().create(true)
-                .write(true).append(true).open(&filename) {
-            Ok(f) => f,
-            _     => {
-                eprintln!("sush: invalid history file");
-                return;
+
+        if let Err(e) = self.write_history_file(&filename, false) {
+            error_message::print(&format!("{}: {}", &filename, e), self, true);
+            return;
+        }
+        self.truncate_history_file(&filename);
+    }
+}
+
+/* an escaped & is left for the matcher as a plain char */
+fn replace_ampersand(pattern: &str, prev: &str) -> String {
+    let mut ans = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                ans.push(c);
+                ans.extend(chars.next());
             },
-        };
-    
-        let mut f = BufWriter::new(file);
-        for h in self.history.iter().rev() {
-            if h == "" {
-                continue;
-            }
-            let _ = f.write(h.as_bytes());
-            let _ = f.write(&vec![0x0A]);
+            '&' => ans += &glob::escape(prev),
+            _ => ans.push(c),
         }
-        let _ = f.flush();
     }
+    ans
 }
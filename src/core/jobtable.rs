@@ -2,10 +2,16 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use crate::core::builtins::utils::print_line;
+use nix::errno::Errno;
 use nix::unistd;
 use nix::unistd::Pid;
 use nix::sys::signal;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use std::io;
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
 
 #[derive(Debug)]
 pub struct JobEntry {
@@ -34,19 +40,30 @@ fn wait_nonblock(pid: &Pid, status: &mut WaitStatus) {
     }
 }
 
-fn wait_block(pid: &Pid, status: &mut WaitStatus) -> i32 {
-    match waitpid(*pid, Some(WaitPidFlag::WUNTRACED)) {
-        Ok(s) => {
-            *status = s;
+/* SIGINT to the shell breaks the wait so that it stops waiting but not the process */
+fn wait_block(pid: &Pid, status: &mut WaitStatus, sigint: &AtomicBool) -> i32 {
+    loop {
+        if sigint.load(Relaxed) {
+            return 130;
+        }
 
-            return match status {
-                WaitStatus::Exited(_, es) => *es,
-                WaitStatus::Stopped(_, _) => 148,
-                WaitStatus::Signaled(_, sig, _) => *sig as i32 + 128,
-                _ => 1,
-            };
-        },
-        _  => panic!("SUSHI INTERNAL ERROR (wrong pid wait)"),
+        match crate::signal::interruptible(|| waitpid(*pid, Some(WaitPidFlag::WUNTRACED))) {
+            Ok(s) => {
+                *status = s;
+                return exit_status(status);
+            },
+            Err(Errno::EINTR) => {},
+            _  => panic!("SUSHI INTERNAL ERROR (wrong pid wait)"),
+        }
+    }
+}
+
+fn exit_status(status: &WaitStatus) -> i32 {
+    match status {
+        WaitStatus::Exited(_, es) => *es,
+        WaitStatus::Stopped(_, _) => 148,
+        WaitStatus::Signaled(_, sig, _) => *sig as i32 + 128,
+        _ => 1,
     }
 }
 
@@ -72,13 +89,13 @@ impl JobEntry {
         }
     }
 
-    pub fn update_status(&mut self, wait: bool) -> i32 {
+    pub fn update_status(&mut self, wait: bool, sigint: &AtomicBool) -> i32 {
         let mut exit_status = 0;
         let before = self.proc_statuses[0];
         for (status, pid) in self.proc_statuses.iter_mut().zip(&self.pids) {
             if still(status) {
                 match wait {
-                    true  => exit_status = wait_block(pid, status),
+                    true  => exit_status = wait_block(pid, status, sigint),
                     false => wait_nonblock(pid, status),
                 }
             }
@@ -106,14 +123,57 @@ impl JobEntry {
         exit_status
     }
 
-    pub fn print(&self, priority: &Vec<usize>) {
-        if priority[0] == self.id {
-            println!("[{}]+  {}     {}", self.id, &self.display_status, &self.text);
+    pub fn has_pid(&self, pid: Pid) -> bool {
+        self.pids.contains(&pid)
+    }
+
+    pub fn last_pid(&self) -> Option<Pid> {
+        self.pids.last().copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        ! self.proc_statuses.iter().any(still)
+    }
+
+    /* the status of the last process in the pipeline */
+    pub fn exit_status(&self) -> i32 {
+        match self.proc_statuses.last() {
+            Some(s) => exit_status(s),
+            None    => 0,
+        }
+    }
+
+    pub fn set_status(&mut self, pid: Pid, ws: WaitStatus) {
+        if let Some(i) = self.pids.iter().position(|p| *p == pid) {
+            self.proc_statuses[i] = ws;
+            self.change = true;
+        }
+    }
+
+    pub fn wait_pid(&mut self, pid: Pid, sigint: &AtomicBool) -> i32 {
+        let i = match self.pids.iter().position(|p| *p == pid) {
+            Some(i) => i,
+            None    => return 127,
+        };
+
+        if still(&self.proc_statuses[i]) {
+            let es = wait_block(&pid, &mut self.proc_statuses[i], sigint);
+            if still(&self.proc_statuses[i]) {
+                return es;
+            }
+        }
+        exit_status(&self.proc_statuses[i])
+    }
+
+    pub fn status_line(&self, priority: &[usize]) -> String {
+        let mark = if priority[0] == self.id {
+            '+'
         }else if priority.len() > 1 && priority[1] == self.id {
-            println!("[{}]-  {}     {}", self.id, &self.display_status, &self.text);
+            '-'
         }else {
-            println!("[{}]   {}     {}", self.id, &self.display_status, &self.text);
-        }
+            ' '
+        };
+        format!("[{}]{}  {}     {}", self.id, mark, &self.display_status, &self.text)
     }
 
     fn display_status_on_signal(signal: &signal::Signal, coredump: bool) -> String {
@@ -181,25 +241,80 @@ impl JobEntry {
 
 impl ShellCore {
     pub fn jobtable_check_status(&mut self) {
+        if ! crate::signal::child_changed() {
+            return;
+        }
+
         for e in self.job_table.iter_mut() {
-            e.update_status(false);
+            e.update_status(false, &self.sigint);
         }
     }
 
+    /* the notices go to stderr and only in the interactive mode as in bash */
     pub fn jobtable_print_status_change(&mut self) {
+        let interactive = self.data.flags.contains('i');
         for e in self.job_table.iter_mut() {
             if e.change {
-                e.print(&self.job_table_priority);
+                if interactive {
+                    let _ = writeln!(io::stderr(), "{}", e.status_line(&self.job_table_priority));
+                }
                 e.change = false;
             }
         }
 
+        for e in self.job_table.iter().filter(|e| e.is_finished()) {
+            for (pid, status) in e.pids.iter().zip(e.proc_statuses.iter()) {
+                self.reaped_statuses.insert(*pid, exit_status(status));
+            }
+        }
+
         self.job_table.retain(|e| still(&e.proc_statuses[0]) || e.display_status == "Stopped");
 
         let ids = self.job_table.iter().map(|j| j.id).collect::<Vec<usize>>();
         self.job_table_priority.retain(|id| ids.contains(id) );
     }
 
+    /* returns false when exit should be canceled due to remaining jobs */
+    pub fn jobtable_check_before_exit(&mut self) -> bool {
+        if ! self.data.flags.contains('i') || self.is_subshell {
+            return true;
+        }
+
+        if let Some(n) = self.exit_attempt {
+            if n == self.command_number || n + 1 == self.command_number {
+                return true;
+            }
+        }
+
+        self.jobtable_check_status();
+        let checkjobs = self.shopts.query("checkjobs");
+        let stopped = self.job_table.iter().any(|j| j.display_status == "Stopped");
+        let running = checkjobs && self.job_table.iter()
+                          .any(|j| still(&j.proc_statuses[0]) && j.display_status != "Stopped");
+
+        if ! stopped && ! running {
+            return true;
+        }
+
+        if checkjobs {
+            let lines: Vec<String> = self.job_table.iter()
+                .map(|e| e.status_line(&self.job_table_priority)).collect();
+            lines.iter().for_each(|line| print_line(line, self));
+        }
+
+        match stopped {
+            true  => eprintln!("There are stopped jobs."),
+            false => eprintln!("There are running jobs."),
+        }
+        self.exit_attempt = Some(self.command_number);
+        false
+    }
+
+    pub fn remove_job(&mut self, pos: usize) {
+        let id = self.job_table.remove(pos).id;
+        self.job_table_priority.retain(|i| *i != id);
+    }
+
     pub fn generate_new_job_id(&self) -> usize {
         match self.job_table.last() {
             None      => 1,
@@ -1,9 +1,10 @@
 //SPDXFileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDXLicense-Identifier: BSD-3-Clause
 
+use crate::error_message;
 use std::collections::HashMap;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Options {
     opts: HashMap<String, bool>,
 }
@@ -15,6 +16,10 @@ impl Options {
         };
 
         options.opts.insert("pipefail".to_string(), false);
+        options.opts.insert("history".to_string(), false);
+        options.opts.insert("emacs".to_string(), false);
+        options.opts.insert("vi".to_string(), false);
+        options.opts.insert("posix".to_string(), false);
 
         options
     }
@@ -42,6 +47,22 @@ impl Options {
         }*/
 
         options.opts.insert("extglob".to_string(), true);
+        options.opts.insert("checkhash".to_string(), false);
+        options.opts.insert("checkjobs".to_string(), false);
+        options.opts.insert("cmdtimes".to_string(), false);
+        options.opts.insert("execfail".to_string(), false);
+        options.opts.insert("extquote".to_string(), true);
+        options.opts.insert("dotglob".to_string(), false);
+        options.opts.insert("expand_aliases".to_string(), false);
+        options.opts.insert("extdebug".to_string(), false);
+        options.opts.insert("failglob".to_string(), false);
+        options.opts.insert("globstar".to_string(), false);
+        options.opts.insert("lastpipe".to_string(), false);
+        options.opts.insert("legacy_arith_warn".to_string(), false);
+        options.opts.insert("login_shell".to_string(), false);
+        options.opts.insert("nocaseglob".to_string(), false);
+        options.opts.insert("nocasematch".to_string(), false);
+        options.opts.insert("nullglob".to_string(), false);
 
         options
     }
@@ -67,45 +88,12 @@ impl Options {
         format!("set {}o {}", onoff_str, opt)
     }
 
-    pub fn print_opt(&self, opt: &str) -> bool {
-        match self.opts.get_key_value(opt) {
-            None     => {
-                eprintln!("sush: shopt: {}: invalid shell option name", opt);
-                false
-            },
-            Some(kv) => {
-                println!("{}", Self::format(kv.0, *kv.1));
-                true
-            },
-        }
-    }
-
-    pub fn print_all(&self) {
-        let mut list = self.opts.iter()
-                       .map(|opt| Self::format(opt.0, *opt.1))
-                       .collect::<Vec<String>>();
-
-        list.sort();
-        list.iter().for_each(|e| println!("{}", e));
+    pub fn list(&self) -> Vec<(String, bool)> {
+        self.opts.iter().map(|(k, v)| (k.clone(), *v)).collect()
     }
 
-    pub fn print_all2(&self) {
-        let mut list = self.opts.iter()
-                       .map(|opt| Self::format2(opt.0, *opt.1))
-                       .collect::<Vec<String>>();
-
-        list.sort();
-        list.iter().for_each(|e| println!("{}", e));
-    }
-
-    pub fn print_if(&self, onoff: bool) {
-        let mut list = self.opts.iter()
-                       .filter(|opt| *opt.1 == onoff)
-                       .map(|opt| Self::format(opt.0, *opt.1))
-                       .collect::<Vec<String>>();
-
-        list.sort();
-        list.iter().for_each(|e| println!("{}", e));
+    pub fn exists(&self, opt: &str) -> bool {
+        self.opts.contains_key(opt)
     }
 
     pub fn query(&self, opt: &str) -> bool {
@@ -114,7 +102,7 @@ impl Options {
 
     pub fn set(&mut self, opt: &str, onoff: bool) -> bool {
         if ! self.opts.contains_key(opt) {
-            eprintln!("sush: shopt: {}: invalid shell option name", opt);
+            error_message::print_without_core(&format!("shopt: {}: invalid shell option name", opt));
             return false;
         }
 
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
 use crate::elements::command;
 use super::word::Word;
 
@@ -12,17 +13,14 @@ pub struct Array {
 }
 
 impl Array {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<Vec<String>, ExecError> {
         let mut ans = vec![];
 
         for w in &mut self.words {
-            match w.eval(core) {
-                None     => return None,
-                Some(ws) => ans.extend(ws),
-            }
+            ans.extend(w.eval(core)?);
         }
 
-        Some(ans)
+        Ok(ans)
     }
 
     pub fn new() -> Array {
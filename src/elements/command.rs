@@ -3,6 +3,7 @@
 
 pub mod arithmetic;
 pub mod case;
+pub mod coproc;
 pub mod simple;
 pub mod paren;
 pub mod brace;
@@ -16,6 +17,7 @@ pub mod r#if;
 use crate::{ShellCore, Feeder, Script};
 use self::arithmetic::ArithmeticCommand;
 use self::case::CaseCommand;
+use self::coproc::CoprocCommand;
 use self::simple::SimpleCommand;
 use self::paren::ParenCommand;
 use self::brace::BraceCommand;
@@ -28,6 +30,7 @@ use self::test::TestCommand;
 use std::fmt;
 use std::fmt::Debug;
 use super::{io, Pipe};
+use super::io::RedirectGuard;
 use super::io::redirect::Redirect;
 use nix::unistd;
 use nix::unistd::{ForkResult, Pid};
@@ -72,12 +75,12 @@ pub trait Command {
     }
 
     fn nofork_exec(&mut self, core: &mut ShellCore) {
-        if self.get_redirects().iter_mut().all(|r| r.connect(true, core)){
+        let mut guard = RedirectGuard::new();
+        if self.get_redirects().iter_mut().all(|r| r.connect(Some(&mut guard), core)){
             self.run(core, false);
         }else{
-            core.data.set_param("?", "1");
+            core.data.set_exit_status(1);
         }
-        self.get_redirects().iter_mut().rev().for_each(|r| r.restore());
     }
 
     fn run(&mut self, _: &mut ShellCore, fork: bool);
@@ -95,7 +98,7 @@ pub fn eat_inner_script(feeder: &mut Feeder, core: &mut ShellCore,
     }
     feeder.nest.push( (left.to_string(), right.iter().map(|e| e.to_string()).collect()) );
     feeder.consume(left.len());
-    *ans = Script::parse(feeder, core, permit_empty);
+    *ans = Script::parse(feeder, core, permit_empty).ok();
     feeder.nest.pop();
     ! ans.is_none()
 }
@@ -112,10 +115,29 @@ pub fn eat_blank_with_comment(feeder: &mut Feeder, core: &mut ShellCore, ans_tex
     true
 }
 
+/* blanks, newlines and comments between the parts of a compound command;
+   more lines are read while the input runs out */
+pub fn eat_blank_lines(feeder: &mut Feeder, core: &mut ShellCore, ans_text: &mut String) -> bool {
+    loop {
+        let blank_len = feeder.scanner_multiline_blank(core);
+        *ans_text += &feeder.consume(blank_len);
+        let comment_len = feeder.scanner_comment();
+        *ans_text += &feeder.consume(comment_len);
+
+        if feeder.len() == 0 {
+            if ! feeder.feed_additional_line(core) {
+                return false;
+            }
+        }else if blank_len + comment_len == 0 {
+            return true;
+        }
+    }
+}
+
 fn eat_redirect(feeder: &mut Feeder, core: &mut ShellCore,
                      ans: &mut Vec<Redirect>, ans_text: &mut String) -> bool {
     if let Some(r) = Redirect::parse(feeder, core) {
-        *ans_text += &r.text.clone();
+        *ans_text += &r.text;
         ans.push(r);
         true
     }else{
@@ -134,7 +156,10 @@ pub fn eat_redirects(feeder: &mut Feeder, core: &mut ShellCore,
 }
 
 pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Command>> {
+    feeder.replace_alias(core);
+
     if let Some(a) = FunctionDefinition::parse(feeder, core) { Some(Box::new(a)) }
+    else if let Some(a) = CoprocCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = SimpleCommand::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = IfCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = ArithmeticCommand::parse(feeder, core) { Some(Box::new(a)) }
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
 use super::{Command, Redirect};
 use crate::elements::expr::arithmetic::ArithmeticExpr;
 
@@ -16,11 +17,12 @@ pub struct ArithmeticCommand {
 impl Command for ArithmeticCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
         let exit_status = match self.eval(core).as_deref() {
-            Some("0") => "1",
-            Some(_) => "0",
-            None => "1",
+            Ok("0") => 1,
+            Ok(_) => 0,
+            Err(ExecError::InvalidArithmetic) => 1,
+            Err(e) => return e.abort(core),
         };
-        core.data.set_param("?", exit_status );
+        core.data.set_exit_status(exit_status);
     }
 
     fn get_text(&self) -> String { self.text.clone() }
@@ -40,15 +42,12 @@ impl ArithmeticCommand {
         }
     }
 
-    pub fn eval(&mut self, core: &mut ShellCore) -> Option<String> {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<String, ExecError> {
         let mut ans = String::new();
         for a in &mut self.expressions {
-            match a.eval(core) {
-                Some(s) => ans = s,
-                None    => return None,
-            }
+            ans = a.eval(core)?;
         }
-        Some(ans)
+        Ok(ans)
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
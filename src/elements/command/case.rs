@@ -4,7 +4,6 @@
 use crate::{error_message, ShellCore, Feeder, Script};
 use crate::elements::command;
 use crate::elements::word::Word;
-use crate::utils::glob;
 use super::{Command, Redirect};
 
 #[derive(Debug, Clone)]
@@ -18,38 +17,37 @@ pub struct CaseCommand {
 
 impl Command for CaseCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
-        let mut next = false;
         let word = self.word.clone()
                    .expect(&error_message::internal_str("no case condition"));
 
         if core.data.flags.contains('x') {
             let ps4 = core.get_ps4();
-            eprintln!("{} case {} in", ps4, word.text);
+            eprintln!("{}case {} in", ps4, word.text);
         }
 
+        /* the subject is expanded once without splitting and globbing */
         let w = match word.eval_for_case_word(core) {
-            Some(w) => w, 
-            _       => "".to_string(),
+            Ok(w)  => w, 
+            Err(e) => return e.abort(core),
         };
 
-        let extglob = core.shopts.query("extglob");
-
+        let mut next = false;
+        let mut executed = false;
         for e in &mut self.patterns_script_end {
-            for pattern in &mut e.0 {
-                let p = match pattern.eval_for_case_pattern(core) {
-                    Some(p) => p, 
-                    _       => continue,
-                };
-
-                if glob::compare(&w, &p, extglob) || next {
-                    e.1.exec(core);
+            if ! next && ! Self::match_patterns(&w, &mut e.0, core) {
+                continue;
+            }
 
-                    if e.2 == ";;" {
-                        return;
-                    }
-                    next = e.2 == ";&";
-                }
+            e.1.exec(core);
+            executed = true;
+            if e.2 == ";;" {
+                return;
             }
+            next = e.2 == ";&";
+        }
+
+        if ! executed {
+            core.data.set_exit_status(0);
         }
     }
 
@@ -61,6 +59,23 @@ impl Command for CaseCommand {
 }
 
 impl CaseCommand {
+    /* patterns are expanded one by one only until one of them matches */
+    fn match_patterns(w: &str, patterns: &mut Vec<Word>, core: &mut ShellCore) -> bool {
+        let nocase = core.shopts.query("nocasematch");
+        for pattern in patterns {
+            match pattern.eval_for_case_pattern(core) {
+                Ok(p) => if core.glob_matcher(&p, nocase).matches(w) {
+                    return true;
+                },
+                Err(e) => {
+                    e.abort(core);
+                    return false;
+                },
+            }
+        }
+        false
+    }
+
     fn new() -> Self {
         CaseCommand {
             text: String::new(),
@@ -118,15 +133,15 @@ impl CaseCommand {
         ans.text = feeder.consume(4);
 
         if ! Self::eat_word(feeder, &mut ans, core) 
+        || ! command::eat_blank_lines(feeder, core, &mut ans.text)
         || ! feeder.starts_with("in") {
             return None;
         }
         ans.text += &feeder.consume(2);
 
         loop {
-            command::eat_blank_with_comment(feeder, core, &mut ans.text);
-            if feeder.starts_with("\n") {
-                ans.text += &feeder.consume(1);
+            if ! command::eat_blank_lines(feeder, core, &mut ans.text) {
+                return None;
             }
 
             if feeder.starts_with("esac") {
@@ -134,12 +149,6 @@ impl CaseCommand {
                 break;
             }
 
-            if feeder.len() == 0 {
-                match feeder.feed_additional_line(core) {
-                    true  => continue,
-                    false => return None,
-                }
-            }
             let mut patterns = vec![];
             if ! Self::eat_patterns(feeder, &mut patterns, &mut ans.text, core) {
                 return None;
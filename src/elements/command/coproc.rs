@@ -0,0 +1,120 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore, Feeder};
+use crate::core::jobtable::JobEntry;
+use crate::elements::{command, io, Pipe};
+use super::{Command, Redirect};
+use nix::sys::wait::WaitStatus;
+use nix::unistd;
+use nix::unistd::{ForkResult, Pid};
+use std::os::fd::{AsRawFd, IntoRawFd};
+
+#[derive(Debug, Clone)]
+pub struct CoprocCommand {
+    text: String,
+    name: String,
+    command: Option<Box<dyn Command>>,
+    redirects: Vec<Redirect>,
+}
+
+impl Command for CoprocCommand {
+    /* NAME[0] reads the output of the command, and NAME[1] writes to its input */
+    fn run(&mut self, core: &mut ShellCore, _: bool) {
+        let pgid = match core.is_subshell {
+            true  => unistd::getpgrp(),
+            false => Pid::from_raw(0),
+        };
+        let pipes = (unistd::pipe(), unistd::pipe());
+        let ((coproc_in, parent_out), (parent_in, coproc_out)) = match pipes {
+            (Ok(p1), Ok(p2)) => (p1, p2),
+            _ => {
+                error_message::print("coproc: cannot make pipes", core, true);
+                core.data.set_exit_status(1);
+                return;
+            },
+        };
+
+        let pid = match unsafe{unistd::fork()} {
+            Ok(ForkResult::Child) => {
+                drop(parent_out);
+                drop(parent_in);
+                core.initialize_as_subshell(Pid::from_raw(0), pgid);
+                io::replace(coproc_in.into_raw_fd(), 0);
+                io::replace(coproc_out.into_raw_fd(), 1);
+                let pid = self.command.as_mut().unwrap().exec(core, &mut Pipe::end(-1, pgid));
+                let status = pid.map(|_| None).unwrap_or_else(|| Some(core.data.exit_status()));
+                core.wait_pipeline(vec![pid], &[status], false, false);
+                core.exit()
+            },
+            Ok(ForkResult::Parent { child } ) => child,
+            Err(err) => panic!("sush(fatal): Failed to fork. {}", err),
+        };
+        core.set_pgid(pid, pgid);
+
+        let fds = vec![io::backup(parent_in.as_raw_fd()).to_string(),
+                       io::backup(parent_out.as_raw_fd()).to_string()];
+        core.data.set_array(&self.name, &fds);
+        core.data.set_param(&(self.name.clone() + "_PID"), &pid.to_string());
+        core.data.set_param("!", &pid.to_string());
+
+        let text = format!("coproc {} {}", self.name, self.command.as_ref().unwrap().get_text().trim());
+        let new_job_id = core.generate_new_job_id();
+        core.job_table_priority.insert(0, new_job_id);
+        core.job_table.push(JobEntry::new(vec![Some(pid)], &vec![WaitStatus::StillAlive],
+                                          &text, "Running", new_job_id));
+        core.data.set_exit_status(0);
+    }
+
+    fn get_text(&self) -> String { self.text.clone() }
+    fn get_redirects(&mut self) -> &mut Vec<Redirect> { &mut self.redirects }
+    fn set_force_fork(&mut self) { }
+    fn boxed_clone(&self) -> Box<dyn Command> {Box::new(self.clone())}
+    fn force_fork(&self) -> bool { false }
+}
+
+impl CoprocCommand {
+    fn new() -> CoprocCommand {
+        CoprocCommand {
+            text: String::new(),
+            name: "COPROC".to_string(),
+            command: None,
+            redirects: vec![],
+        }
+    }
+
+    /* a name is given only before a compound command */
+    fn eat_name(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) {
+        let len = feeder.scanner_name(core);
+        if len == 0 {
+            return;
+        }
+
+        feeder.set_backup();
+        let name = feeder.consume(len);
+        let mut blank = String::new();
+        if ! command::eat_blank_with_comment(feeder, core, &mut blank)
+        || ! ["{", "(", "[[", "while", "until", "for", "if", "case"].iter()
+             .any(|w| feeder.starts_with(w)) {
+            feeder.rewind();
+            return;
+        }
+        feeder.pop_backup();
+        ans.name = name.clone();
+        ans.text += &(name + &blank);
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<CoprocCommand> {
+        if ! feeder.starts_with("coproc ") && ! feeder.starts_with("coproc\t") {
+            return None;
+        }
+        let mut ans = Self::new();
+        ans.text = feeder.consume(6);
+        command::eat_blank_with_comment(feeder, core, &mut ans.text);
+        Self::eat_name(feeder, &mut ans, core);
+
+        ans.command = command::parse(feeder, core);
+        ans.text += &ans.command.as_ref()?.get_text();
+        Some(ans)
+    }
+}
@@ -1,7 +1,8 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{error_message, ShellCore, Feeder, Script};
+use crate::{error_message, utils, ShellCore, Feeder, Script};
+use crate::error_message::ExecError;
 use super::{Command, Redirect};
 use crate::elements::command;
 use crate::elements::word::Word;
@@ -30,8 +31,8 @@ impl Command for ForCommand {
             false => self.run_with_values(core),
         };
 
-        if ! ok && core.data.get_param("?") == "0" {
-            core.data.set_param("?", "1");
+        if ! ok && core.data.exit_status().is_success() {
+            core.data.set_exit_status(1);
         }
 
         core.loop_level -= 1;
@@ -48,33 +49,40 @@ impl Command for ForCommand {
 }
 
 impl ForCommand {
-    fn eval_values(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+    fn eval_values(&mut self, core: &mut ShellCore) -> Result<Vec<String>, ExecError> {
         let mut ans = vec![];
         for w in &mut self.values {
-            match w.eval(core) {
-                Some(mut ws) => ans.append(&mut ws),
-                None     => return None,
-            }
+            ans.append(&mut w.eval(core)?);
         }
 
-        Some(ans)
+        Ok(ans)
     }
 
     fn run_with_values(&mut self, core: &mut ShellCore) -> bool {
         let values = match self.has_in {
             true  => match self.eval_values(core) {
-                Some(vs) => vs,
-                None     => return false,
+                Ok(vs) => vs,
+                Err(e) => {
+                    e.abort(core);
+                    return false;
+                },
             },
             false => core.data.get_position_params(),
         };
 
-        for p in values {
+        core.data.set_exit_status(0);
+        for p in values.iter() {
             if core.sigint.load(Relaxed) {
                 return false;
             }
 
-            core.data.set_param(&self.name, &p);
+            if core.data.flags.contains('x') {
+                let ps4 = core.get_ps4();
+                let vs: Vec<String> = values.iter().map(|v| utils::quote_for_trace(v)).collect();
+                eprintln!("{}for {} in {}", ps4, &self.name, vs.join(" "));
+            }
+
+            core.data.set_param(&self.name, p);
 
             self.do_script.as_mut()
                 .expect(&error_message::internal_str("no script)"))
@@ -94,9 +102,11 @@ impl ForCommand {
         }
 
         match a.clone().unwrap().eval(core) {
-            Some(n) => return (true, n),
-            None    => return (false, "0".to_string()), 
+            Ok(n) => return (true, n),
+            Err(ExecError::InvalidArithmetic) => {},
+            Err(e) => e.abort(core),
         }
+        (false, "0".to_string())
     }
 
     fn run_with_arithmetic(&mut self, core: &mut ShellCore) -> bool {
@@ -155,7 +165,7 @@ impl ForCommand {
         }
 
         ans.name = feeder.consume(len);
-        ans.text += &ans.name.clone();
+        ans.text += &ans.name;
         command::eat_blank_with_comment(feeder, core, &mut ans.text);
         true
     }
@@ -177,8 +187,8 @@ impl ForCommand {
             }
 
             let a = ArithmeticExpr::parse(feeder, core, true);
-            if a.is_some() {
-                ans.text += &a.as_ref().unwrap().text.clone();
+            if let Some(a) = &a {
+                ans.text += &a.text;
             }
             ans.arithmetics.push(a);
 
@@ -212,7 +222,7 @@ impl ForCommand {
             command::eat_blank_with_comment(feeder, core, &mut ans.text);
             match Word::parse(feeder, core, false) {
                 Some(w) => {
-                    ans.text += &w.text.clone();
+                    ans.text += &w.text;
                     ans.values.push(w);
                 },
                 None    => return,
@@ -224,8 +234,7 @@ impl ForCommand {
         command::eat_blank_with_comment(feeder, core, &mut ans.text);
         if feeder.starts_with(";") || feeder.starts_with("\n") {
             ans.text += &feeder.consume(1);
-            command::eat_blank_with_comment(feeder, core, &mut ans.text);
-            true
+            command::eat_blank_lines(feeder, core, &mut ans.text)
         }else{
             false
         }
@@ -248,10 +257,6 @@ impl ForCommand {
             return None;
         }
 
-        if feeder.len() == 0 && ! feeder.feed_additional_line(core) {
-            return None;
-        }
-
         if command::eat_inner_script(feeder, core, "do", vec!["done"],  &mut ans.do_script, false) {
             ans.text.push_str("do");
             ans.text.push_str(&ans.do_script.as_mut().unwrap().get_text());
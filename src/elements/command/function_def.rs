@@ -1,7 +1,7 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{error_message, ShellCore, Feeder};
+use crate::{error_message, signal, ShellCore, Feeder};
 use super::{Command, Pipe, Redirect};
 use crate::elements::command;
 use crate::elements::command::{BraceCommand, IfCommand, ParenCommand, WhileCommand, UntilCommand};
@@ -21,6 +21,7 @@ pub struct FunctionDefinition {
     command: Option<Box<dyn Command>>,
     redirects: Vec<Redirect>,
     force_fork: bool,
+    source: String,
 }
 
 impl Command for FunctionDefinition {
@@ -30,6 +31,7 @@ impl Command for FunctionDefinition {
         }
 
         core.data.functions.insert(self.name.to_string(), self.clone());
+        core.data.set_exit_status(0);
         None
     }
 
@@ -49,6 +51,15 @@ impl FunctionDefinition {
             command: None,
             redirects: vec![],
             force_fork: false,
+            source: String::new(),
+        }
+    }
+
+    /* the definition without the name, which is passed to children in the environment */
+    pub fn body_text(&self) -> String {
+        match &self.command {
+            Some(c) => format!("() {}", c.get_text().trim()),
+            None    => "() { :; }".to_string(),
         }
     }
 
@@ -61,11 +72,29 @@ impl FunctionDefinition {
 
         let mut dummy = Pipe::new("|".to_string());
 
+        /* traps that are not inherited are put back unless the function sets them */
+        let inherited = signal::inherited_traps(core);
+        let traps: Vec<(&str, String)> = ["DEBUG", "RETURN", "ERR"].into_iter()
+            .filter(|name| ! inherited.contains(name))
+            .filter_map(|name| core.traps.remove(name).map(|com| (name, com)))
+            .collect();
         core.source_function_level += 1;
+        core.data.push_call_frame(&self.name, &self.source);
+        core.data.push_function_scope();
+        if inherited.contains(&"DEBUG") { // the trap also fires on the entry as in bash
+            signal::run_debug_trap(core);
+        }
         let pid = self.command.clone()
                         .expect(&error_message::internal_str("empty function"))
                         .exec(core, &mut dummy);
         core.return_flag = false;
+        core.error_checked = false;
+        signal::run_return_trap(core);
+        for (name, com) in traps {
+            core.traps.entry(name.to_string()).or_insert(com);
+        }
+        core.data.pop_function_scope();
+        core.data.pop_call_frame();
         core.source_function_level -= 1;
 
         core.data.position_parameters.pop();
@@ -104,20 +133,31 @@ impl FunctionDefinition {
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
         let mut ans = Self::new();
+        ans.source = core.source_file_name();
         feeder.set_backup();
 
-        if feeder.starts_with("function") {
+        let keyword = feeder.starts_with_word("function") && ! core.options.query("posix");
+        if keyword {
             ans.text += &feeder.consume(8);
             command::eat_blank_with_comment(feeder, core, &mut ans.text);
         }
         
-        if ! Self::eat_name(feeder, &mut ans, core) 
-        || ! feeder.starts_with("()") {
+        if ! Self::eat_name(feeder, &mut ans, core) {
+            feeder.rewind();
+            return None;
+        }
+
+        if feeder.starts_with("()") {
+            ans.text += &feeder.consume(2);
+        }else if ! keyword {
+            feeder.rewind();
+            return None;
+        }
+
+        if ! command::eat_blank_lines(feeder, core, &mut ans.text) {
             feeder.rewind();
             return None;
         }
-        ans.text += &feeder.consume(2);
-        command::eat_blank_with_comment(feeder, core, &mut ans.text);
 
         Self::eat_compound_command(feeder, &mut ans, core);
         command::eat_blank_with_comment(feeder, core, &mut ans.text);
@@ -17,9 +17,12 @@ pub struct IfCommand {
 
 impl Command for IfCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
+        let suspend_e_option = core.suspend_e_option;
         for i in 0..self.if_elif_scripts.len() {
+            core.suspend_e_option = true;
             self.if_elif_scripts[i].exec(core);
-            if core.data.get_param("?") == "0" {
+            core.suspend_e_option = suspend_e_option;
+            if core.data.exit_status().is_success() {
                 self.then_scripts[i].exec(core);
                 return;
             }
@@ -3,14 +3,18 @@
 
 pub mod parser;
 
-use crate::{error_message, ShellCore};
+use crate::{error_message, signal, utils, ShellCore};
 use super::{Command, Pipe, Redirect};
+use crate::core::builtins;
 use crate::core::data::Value;
 use crate::elements::substitution::Substitution;
 use crate::elements::word::Word;
+use crate::utils::{bytes, file_check};
 use nix::unistd;
+use std::env;
 use std::ffi::CString;
-use std::{env, process};
+use std::os::unix::ffi::OsStrExt;
+use std::process;
 use std::sync::atomic::Ordering::Relaxed;
 
 use nix::unistd::Pid;
@@ -27,6 +31,12 @@ pub struct SimpleCommand {
     force_fork: bool, 
     substitutions_as_args: Vec<Substitution>,
     permit_substitution_arg: bool,
+    bypass_function: bool,
+    command_path: Option<String>,
+    replace_shell: bool,
+    clear_env: bool,
+    argv0: Option<String>,
+    lineno: usize,
 }
 
 
@@ -35,16 +45,26 @@ impl Command for SimpleCommand {
         if core.return_flag || core.break_counter > 0 {
             return None;
         }
+        core.data.set_param("LINENO", &self.lineno.to_string());
+        if ! core.running_trap() {
+            core.data.set_param("BASH_COMMAND", self.text.trim_end());
+        }
+        if ! signal::run_debug_trap(core) {
+            return None;
+        }
 
         if ! self.eval_substitutions(core){
-            core.data.set_param("?", "1");
             return None;
         }
 
         self.args.clear();
+        self.bypass_function = false;
+        self.command_path = None;
+        self.replace_shell = false;
+        self.clear_env = false;
+        self.argv0 = None;
         let mut words = self.words.to_vec();
         if ! words.iter_mut().all(|w| self.set_arg(w, core)){
-            core.word_eval_error = true;
             return None;
         }
 
@@ -52,7 +72,8 @@ impl Command for SimpleCommand {
             core.data.set_param("_", "");
             self.option_x_output(core);
             self.exec_set_params(core)
-        }else if Self::check_sigint(core) {
+        }else if Self::check_sigint(core) || ! self.unwrap(core)
+              || ! self.check_restriction(core) {
             None
         }else{
             core.data.set_param("_", &self.args.last().unwrap());
@@ -63,9 +84,14 @@ impl Command for SimpleCommand {
 
     fn run(&mut self, core: &mut ShellCore, fork: bool) {
         core.data.push_local();
-        self.set_local_params(core);
+        let exported = match self.is_posix_special(core) {
+            true  => { self.exec_set_params(core); vec![] },
+            false => self.set_local_params(core),
+        };
 
-        if core.data.functions.contains_key(&self.args[0]) {
+        if self.replace_shell {
+            self.exec_external_command(core);
+        }else if self.is_function(core) {
             let mut f = core.data.functions[&self.args[0]].clone();
             f.run_as_command(&mut self.args, core);
         } else if core.builtins.contains_key(&self.args[0]) {
@@ -75,7 +101,16 @@ impl Command for SimpleCommand {
             self.exec_external_command(core);
         }
 
+        /* a variable exported by the command itself keeps the value and the attribute */
+        let mut kept = vec![];
+        for key in exported {
+            if ! core.data.end_temporary_export(&key) && core.data.is_exported(&key) {
+                let value = core.data.get_param(&key);
+                kept.push((key, value));
+            }
+        }
         core.data.pop_local();
+        kept.iter().for_each(|(key, value)| { core.data.set_param(key, value); });
 
         if fork {
             core.exit();
@@ -90,37 +125,102 @@ impl Command for SimpleCommand {
 }
 
 impl SimpleCommand {
-    fn exec_external_command(&mut self, core: &mut ShellCore) -> ! {
-        self.set_environment_variables();
-        let cargs = Self::to_cargs(&self.args);
+    fn execute(&mut self, core: &mut ShellCore) -> Result<std::convert::Infallible, Errno> {
+        let envs = match self.clear_env {
+            true  => vec![],
+            false => self.make_environment(core),
+        };
 
-        match unistd::execvp(&cargs[0], &cargs) {
-            Err(Errno::E2BIG) => {
-                eprintln!("sush: {}: Arg list too long", &self.args[0]);
-                process::exit(126)
-            },
-            Err(Errno::EACCES) => {
-                eprintln!("sush: {}: Permission denied", &self.args[0]);
-                process::exit(126)
+        let mut cargs = Self::to_cargs(&self.args);
+        if let Some(name) = &self.argv0 {
+            cargs[0] = bytes::to_cstring(name);
+        }
+
+        let path = match &self.command_path {
+            Some(p) => p.clone(),
+            None    => self.search_command(core).ok_or(Errno::ENOENT)?,
+        };
+        self.command_path = Some(path.clone());
+
+        match unistd::execve(&bytes::to_cstring(&path), &cargs, &envs) {
+            Err(Errno::ENOEXEC) if ! file_check::is_binary(&path) => {
+                Self::execute_as_script(&path, cargs, &envs)
             },
-            Err(Errno::ENOENT) => {
-                let msg = format!("{}: command not found", &self.args[0]);
+            ans => ans,
+        }
+    }
+
+    /* a file without any magic number is given to a new shell like bash does.
+       the system shell is used if the binary of this shell is not found */
+    fn execute_as_script(path: &str, mut cargs: Vec<CString>,
+                         envs: &[CString]) -> Result<std::convert::Infallible, Errno> {
+        let shell = match env::current_exe() {
+            Ok(p)  => p.to_string_lossy().to_string(),
+            Err(_) => "/bin/sh".to_string(),
+        };
+        cargs[0] = bytes::to_cstring(path);
+        cargs.insert(0, bytes::to_cstring(&shell));
+        unistd::execve(&cargs[0], &cargs, envs)
+    }
+
+    /* the PATH in the temporary assignment is searched without the hash table */
+    fn search_command(&self, core: &mut ShellCore) -> Option<String> {
+        if self.args[0].contains('/') {
+            return Some(self.args[0].clone());
+        }
+
+        let path = self.evaluated_subs.iter().rev()
+            .find_map(|s| match (s.0.as_str(), &s.1) {
+                ("PATH", Value::EvaluatedSingle(v)) => Some(v.clone()),
+                _ => None,
+            });
+
+        if let Some(p) = path {
+            return core.search_path(&self.args[0], &p);
+        }
+
+        let ans = core.hash_search(&self.args[0]);
+        core.hash_hit(&self.args[0]);
+        ans
+    }
+
+    fn exec_external_command(&mut self, core: &mut ShellCore) -> ! {
+        let err = match self.execute(core) {
+            Err(e) => e,
+            _ => error_message::internal("never come here"),
+        };
+
+        let name = &self.args[0];
+        let (msg, status) = match (err, &self.command_path) {
+            (Errno::ENOENT, None) => {
+                let msg = format!("{}: command not found", name);
                 error_message::print(&msg, core, false);
                 process::exit(127)
             },
-            Err(err) => {
-                eprintln!("Failed to execute. {:?}", err);
-                process::exit(127)
-            }
-            _ => error_message::internal("never come here")
-        }
+            (Errno::ENOENT, Some(p)) if file_check::exists(p) => {
+                (format!("{}: cannot execute: required file not found", name), 127)
+            },
+            (Errno::ENOENT, Some(p)) => (format!("{}: {}", p, err.desc()), 127),
+            (Errno::ENOEXEC, _) => (format!("{}: cannot execute binary file: {}", name, err.desc()), 126),
+            (Errno::EACCES, Some(p)) if file_check::is_dir(p) => (format!("{}: Is a directory", name), 126),
+            _ => (format!("{}: {}", name, err.desc()), 126),
+        };
+        error_message::print(&msg, core, true);
+        process::exit(status)
     }
 
     fn exec_command(&mut self, core: &mut ShellCore, pipe: &mut Pipe) -> Option<Pid> {
-        if self.force_fork 
-        || pipe.is_connected() 
-        || ( ! core.builtins.contains_key(&self.args[0]) 
-           && ! core.data.functions.contains_key(&self.args[0]) ) {
+        if self.replace_shell && ! self.force_fork && ! pipe.is_connected() {
+            self.exec_in_place(core);
+            return None;
+        }
+
+        let external = ! core.builtins.contains_key(&self.args[0]) && ! self.is_function(core);
+        if external && self.command_path.is_none() { // searched here to record it in the hash table
+            self.command_path = self.search_command(core);
+        }
+
+        if self.force_fork || pipe.is_connected() || external {
             self.fork_exec(core, pipe)
         }else{
             self.nofork_exec(core);
@@ -128,9 +228,192 @@ impl SimpleCommand {
         }
     }
 
+    fn is_function(&self, core: &mut ShellCore) -> bool {
+        ! self.bypass_function && core.data.functions.contains_key(&self.args[0])
+        && ! self.is_posix_special(core)
+    }
+
+    /* the assignments before a special builtin remain in posix mode */
+    fn is_posix_special(&self, core: &mut ShellCore) -> bool {
+        core.options.query("posix") && ! self.replace_shell
+        && builtins::is_special(&self.args[0])
+    }
+
+    /* builtin, command, and exec change how the rest of the args are executed */
+    fn unwrap(&mut self, core: &mut ShellCore) -> bool {
+        loop {
+            let ok = match self.args[0].as_ref() {
+                "builtin" => self.unwrap_builtin(core),
+                "command" => self.unwrap_command(core),
+                "exec"    => return self.unwrap_exec(core),
+                _         => return true,
+            };
+
+            if ! ok {
+                return false;
+            }
+        }
+    }
+
+    fn unwrap_builtin(&mut self, core: &mut ShellCore) -> bool {
+        let pos = match self.args.get(1).is_some_and(|a| a == "--") {
+            true  => 2,
+            false => 1,
+        };
+        self.args.drain(..pos);
+        if self.args.is_empty() {
+            core.data.set_exit_status(0);
+            return false;
+        }
+
+        if ! core.builtins.contains_key(&self.args[0])
+        && ! ["command", "exec"].contains(&self.args[0].as_str()) {
+            error_message::print(&format!("builtin: {}: not a shell builtin", &self.args[0]), core, true);
+            core.data.set_exit_status(1);
+            return false;
+        }
+
+        self.bypass_function = true;
+        true
+    }
+
+    fn unwrap_command(&mut self, core: &mut ShellCore) -> bool {
+        let mut default_path = false;
+        let mut pos = 1;
+        while pos < self.args.len() && self.args[pos].starts_with("-") {
+            match self.args[pos].as_ref() {
+                "--" => {
+                    pos += 1;
+                    break;
+                },
+                "-p" if core.is_restricted() => {
+                    error_message::print("command: -p: restricted", core, true);
+                    core.data.set_exit_status(1);
+                    return false;
+                },
+                "-p" => default_path = true,
+                "-v" | "-V" => {
+                    let verbose = self.args[pos] == "-V";
+                    let es = builtins::command::command_v(core, &self.args[pos+1..], verbose);
+                    core.data.set_exit_status(es);
+                    return false;
+                },
+                opt  => {
+                    error_message::print(&format!("command: {}: invalid option", opt), core, true);
+                    eprintln!("command: usage: command [-pVv] command [arg ...]");
+                    core.data.set_exit_status(2);
+                    return false;
+                },
+            }
+            pos += 1;
+        }
+
+        self.args.drain(..pos);
+        if self.args.is_empty() {
+            core.data.set_exit_status(0);
+            return false;
+        }
+
+        self.bypass_function = true;
+        if default_path
+        && ! core.builtins.contains_key(&self.args[0])
+        && ! self.args[0].contains('/') {
+            let path = file_check::search_path(&self.args[0], &utils::default_path());
+            self.command_path = Some(path.unwrap_or(self.args[0].clone()));
+        }
+        true
+    }
+
+    fn unwrap_exec(&mut self, core: &mut ShellCore) -> bool {
+        let mut pos = 1;
+        while pos < self.args.len() && self.args[pos].starts_with("-") {
+            match self.args[pos].as_ref() {
+                "--" => {
+                    pos += 1;
+                    break;
+                },
+                "-c" => self.clear_env = true,
+                "-a" if pos + 1 < self.args.len() => {
+                    pos += 1;
+                    self.argv0 = Some(self.args[pos].clone());
+                },
+                opt  => {
+                    error_message::print(&format!("exec: {}: invalid option", opt), core, true);
+                    eprintln!("exec: usage: exec [-c] [-a name] [command [argument ...]] [redirection ...]");
+                    core.data.set_exit_status(2);
+                    return false;
+                },
+            }
+            pos += 1;
+        }
+
+        self.args.drain(..pos);
+        if self.args.is_empty() {
+            /* redirections are applied to the shell itself */
+            let ok = self.redirects.iter_mut().all(|r| r.connect(None, core));
+            core.data.set_exit_status(if ok { 0 } else { 1 });
+            return false;
+        }
+
+        if core.is_restricted() {
+            error_message::print("exec: restricted", core, true);
+            core.data.set_exit_status(1);
+            return false;
+        }
+
+        match self.search_command(core) {
+            Some(p) => self.command_path = Some(p),
+            None => {
+                error_message::print(&format!("exec: {}: not found", &self.args[0]), core, true);
+                Self::exec_failure(core, 127);
+                return false;
+            },
+        }
+
+        self.replace_shell = true;
+        true
+    }
+
+    /* only an interactive shell or a shell with execfail survives */
+    fn exec_failure(core: &mut ShellCore, status: i32) {
+        core.data.set_exit_status(status);
+        if ! core.data.flags.contains("i") && ! core.shopts.query("execfail") {
+            core.exit();
+        }
+    }
+
+    fn exec_in_place(&mut self, core: &mut ShellCore) {
+        if ! self.redirects.iter_mut().all(|r| r.connect(None, core)) {
+            core.data.set_exit_status(1);
+            return;
+        }
+
+        let err = match self.execute(core) {
+            Err(e) => e,
+            _ => error_message::internal("never come here"),
+        };
+
+        let status = match err {
+            Errno::ENOENT => 127,
+            _ => 126,
+        };
+        error_message::print(&format!("exec: {}: {}", &self.args[0], err.desc()), core, true);
+        Self::exec_failure(core, status);
+    }
+
+    fn check_restriction(&mut self, core: &mut ShellCore) -> bool {
+        if ! core.is_restricted() || ! self.args[0].contains('/') {
+            return true;
+        }
+
+        error_message::print(&format!("{}: restricted: cannot specify `/' in command names", &self.args[0]), core, true);
+        core.data.set_exit_status(1);
+        false
+    }
+
     fn check_sigint(core: &mut ShellCore) -> bool {
         if core.sigint.load(Relaxed) {
-            core.data.set_param("?", "130");
+            core.data.set_exit_status(130);
             return true;
         }
         false
@@ -138,37 +421,80 @@ impl SimpleCommand {
 
     fn exec_set_params(&mut self, core: &mut ShellCore) -> Option<Pid> {
         for s in &self.evaluated_subs {
-            match &s.1 {
+            let ok = match &s.1 {
                 Value::EvaluatedSingle(v) => core.data.set_param(&s.0, &v),
-                Value::EvaluatedArray(a) => core.data.set_array(&s.0, &a),
-                _ => {},
+                Value::EvaluatedArray(a) => core.data.set_array(&s.0, &a.values().cloned().collect::<Vec<String>>()),
+                _ => true,
+            };
+
+            if ! ok {
+                core.data.set_exit_status(1);
             }
         }
         None
     }
 
-    fn set_local_params(&mut self, core: &mut ShellCore) {
+    /* temporary assignments are exported only while the command runs
+       so that the children of builtins and functions also see them */
+    fn set_local_params(&mut self, core: &mut ShellCore) -> Vec<String> {
+        let mut exported = vec![];
         for s in &self.evaluated_subs {
-            match &s.1 {
+            let ok = match &s.1 {
                 Value::EvaluatedSingle(v) => core.data.set_local_param(&s.0, &v),
-                Value::EvaluatedArray(a) => core.data.set_local_array(&s.0, &a),
-                _ => {},
+                Value::EvaluatedArray(a) => core.data.set_local_array(&s.0, &a.values().cloned().collect::<Vec<String>>()),
+                _ => false,
+            };
+
+            let key = core.data.resolve_nameref(&s.0);
+            if ok && ! core.data.is_exported(&key) {
+                core.data.export_temporarily(&key);
+                exported.push(key);
             }
         }
+        exported
     }
 
-    fn set_environment_variables(&mut self) {
+    /* hidden entries, exported scalars and functions, and then temporary assignments.
+       arrays and namerefs are not passed to children */
+    fn make_environment(&self, core: &mut ShellCore) -> Vec<CString> {
+        let mut envs: Vec<(Vec<u8>, Vec<u8>)> = core.data.get_hidden_env().iter()
+            .map(|(k, v)| (k.as_bytes().to_vec(), v.as_bytes().to_vec()))
+            .collect();
+
+        let mut set = |key: &str, val: &str| {
+            let key = bytes::encode(key);
+            envs.retain(|e| e.0 != key);
+            envs.push((key, bytes::encode(val)));
+        };
+
+        for key in core.data.get_exported_keys() {
+            if core.data.is_nameref(&key) {
+                continue;
+            }
+            if let Some(Value::EvaluatedSingle(v)) = core.data.get_value(&key) {
+                set(&key, &v);
+            }
+        }
+
+        for name in core.data.get_exported_functions() {
+            let body = core.data.functions[&name].body_text();
+            set(&format!("BASH_FUNC_{}%%", name), &body);
+        }
+
         for s in &self.evaluated_subs {
-            match &s.1 {
-                Value::EvaluatedSingle(v) => env::set_var(&s.0, &v),
-                _ => {},
+            if let Value::EvaluatedSingle(v) = &s.1 {
+                set(&s.0, v);
             }
         }
+
+        envs.into_iter()
+            .filter_map(|(k, v)| CString::new([k, b"=".to_vec(), v].concat()).ok())
+            .collect()
     }
 
     fn to_cargs(args: &Vec<String>) -> Vec<CString> {
         args.iter()
-            .map(|a| CString::new(a.to_string()).unwrap())
+            .map(|a| bytes::to_cstring(a))
             .collect()
     }
 
@@ -176,8 +502,11 @@ impl SimpleCommand {
         self.evaluated_subs.clear();
         for s in &mut self.substitutions {
             match s.eval(core) {
-                Value::None => return false,
-                a           => self.evaluated_subs.push( (s.key.clone(), a) ),
+                Ok(a)  => self.evaluated_subs.push( (s.key.clone(), a) ),
+                Err(e) => {
+                    e.abort(core);
+                    return false;
+                },
             }
         }
         true
@@ -185,14 +514,12 @@ impl SimpleCommand {
 
     fn set_arg(&mut self, word: &mut Word, core: &mut ShellCore) -> bool {
         match word.eval(core) {
-            Some(ws) => {
+            Ok(ws) => {
                 self.args.extend(ws);
                 true
             },
-            None => {
-                if ! core.sigint.load(Relaxed) {
-                    core.data.set_param("?", "1");
-                }
+            Err(e) => {
+                e.abort(core);
                 false
             },
         }
@@ -204,21 +531,18 @@ impl SimpleCommand {
         }
 
         let ps4 = core.get_ps4();
-        for s in &self.substitutions {
-            eprintln!("{} {}", &ps4, &s.text);
+        for (s, (key, value)) in self.substitutions.iter().zip(self.evaluated_subs.iter()) {
+            match value {
+                Value::EvaluatedSingle(v) => eprintln!("{}{}={}", &ps4, key, utils::quote_for_trace(v)),
+                _ => eprintln!("{}{}", &ps4, &s.text),
+            }
         }
 
         if self.args.len() == 0 {
             return;
         }
 
-        eprint!("{}", &ps4);
-        for a in &self.args {
-            match a.contains(" "){
-                false => eprint!(" {}", &a),
-                true  => eprint!(" '{}'", &a),
-            }
-        }
-        eprintln!("");
+        let args: Vec<String> = self.args.iter().map(|a| utils::quote_for_trace(a)).collect();
+        eprintln!("{}{}", &ps4, args.join(" "));
     }
 }
@@ -19,6 +19,12 @@ impl SimpleCommand {
             force_fork: false,
             substitutions_as_args: vec![],
             permit_substitution_arg: false,
+            bypass_function: false,
+            command_path: None,
+            replace_shell: false,
+            clear_env: false,
+            argv0: None,
+            lineno: 0,
         }
     }
 
@@ -44,51 +50,22 @@ impl SimpleCommand {
         };
 
         if ans.words.len() == 0 {
-            if utils::reserved(&w.text) {
+            if utils::reserved(&w.text)
+            && ! (w.text == "[[" && core.options.query("posix")) {
                 return false;
-            }else if w.text == "local" {
+            }else if ["local", "declare", "export", "readonly"].contains(&w.text.as_str()) {
                 ans.permit_substitution_arg = true;
             }
         }
-        if Self::set_alias(&w, &mut ans.words, &mut ans.text, core, feeder) {
-            return true;
-        }
-
         ans.text += &w.text;
         ans.words.push(w);
 
         true
     }
 
-    fn set_alias(word: &Word, words: &mut Vec<Word>, text: &mut String,
-                 core: &mut ShellCore, feeder: &mut Feeder) -> bool {
-        let mut w = word.text.clone();
-        if ! core.data.replace_alias(&mut w) {
-            return false;
-        }
-
-        let mut feeder_local = Feeder::new(&mut w);
-        loop {
-            match Word::parse(&mut feeder_local, core, false) {
-                Some(w) => {
-                    text.push_str(&w.text);
-                    words.push(w);
-                },
-                None    => break,
-            }
-            command::eat_blank_with_comment(&mut feeder_local, core, text);
-        }
-
-        if words.len() == 0 {
-            panic!("sush: alias: fatal alias");
-        }
-
-        feeder.replace(0, &feeder_local.consume(feeder_local.len()));
-        true
-    }
-
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<SimpleCommand> {
         let mut ans = Self::new();
+        ans.lineno = core.lineno(); // for error messages in compound commands and functions
         feeder.set_backup();
 
         while Self::eat_substitution(feeder, &mut ans, core) {
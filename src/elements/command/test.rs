@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{error_message, ShellCore, Feeder};
+use crate::error_message::ExecError;
 use super::{Command, Redirect};
 use crate::elements::command;
 use crate::elements::expr::conditional::{ConditionalExpr, CondElem};
@@ -17,15 +18,16 @@ pub struct TestCommand {
 impl Command for TestCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
         match self.cond.clone().unwrap().eval(core) {
-            Ok(CondElem::Ans(true))  => core.data.set_param("?", "0"),
-            Ok(CondElem::Ans(false)) => core.data.set_param("?", "1"),
-            Err(err_msg)  => {
+            Ok(CondElem::Ans(true))  => { core.data.set_exit_status(0); },
+            Ok(CondElem::Ans(false)) => { core.data.set_exit_status(1); },
+            Err(ExecError::Other(err_msg))  => {
                 error_message::print(&err_msg, core, true);
-                core.data.set_param("?", "2");
+                core.data.set_exit_status(2);
             },
+            Err(e) => e.abort(core),
             _  => {
                 error_message::print("unknown error", core, true);
-                core.data.set_param("?", "2");
+                core.data.set_exit_status(2);
             },
         } 
     }
@@ -48,7 +50,7 @@ impl TestCommand {
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
-        if ! feeder.starts_with("[[") {
+        if ! feeder.starts_with("[[") || core.options.query("posix") {
             return None;
         }
 
@@ -57,7 +59,7 @@ impl TestCommand {
 
         match ConditionalExpr::parse(feeder, core) {
             Some(e) => {
-                ans.text += &e.text.clone();
+                ans.text += &e.text;
                 ans.cond = Some(e);
             },
             None => return None,
@@ -4,6 +4,7 @@
 use crate::{error_message, ShellCore, Feeder, Script};
 use super::{Command, Redirect};
 use crate::elements::command;
+use std::sync::atomic::Ordering::Relaxed;
 
 #[derive(Debug, Clone)]
 pub struct UntilCommand {
@@ -17,26 +18,33 @@ pub struct UntilCommand {
 impl Command for UntilCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
         core.loop_level += 1;
+        let mut exit_status = 0;
         loop {
+            if core.sigint.load(Relaxed) {
+                core.data.set_exit_status(130);
+                break;
+            }
+
             core.suspend_e_option = true;
             self.until_script.as_mut()
                 .expect(&error_message::internal_str("no script"))
                 .exec(core);
 
             core.suspend_e_option = false;
+            if core.data.exit_status().is_success() {
+                core.data.set_exit_status(exit_status);
+                break;
+            }
 
             self.do_script.as_mut()
                 .expect(&error_message::internal_str("no script"))
                 .exec(core);
+            exit_status = core.data.exit_status().code();
 
             if core.break_counter > 0 {
                 core.break_counter -= 1;
                 break;
             }
-            if core.data.get_param("?") != "0" {
-                core.data.set_param("?", "0");
-                break;
-            }
         }
         core.loop_level -= 1;
         if core.loop_level == 0 {
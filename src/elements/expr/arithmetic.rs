@@ -10,6 +10,7 @@ pub mod word;
 mod int;
 mod float;
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore};
 use self::calculator::calculate;
 use self::elem::ArithElem;
@@ -24,21 +25,22 @@ pub struct ArithmeticExpr {
 }
 
 impl ArithmeticExpr {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Option<String> {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<String, ExecError> {
         match self.eval_elems(core, true) {
-            Ok(ArithElem::Integer(n)) => self.ans_to_string(n),
-            Ok(ArithElem::Float(f))   => Some(f.to_string()),
-            Err(msg) => {
-                eprintln!("sush: {}: {}", &self.text, msg);
-                None
+            Ok(ArithElem::Integer(n)) => self.ans_to_string(n, core),
+            Ok(ArithElem::Float(f))   => Ok(f.to_string()),
+            Err(ExecError::Other(msg)) => {
+                error_message::print(&format!("{}: {}", &self.text, msg), core, true);
+                Err(ExecError::InvalidArithmetic)
             },
+            Err(e) => Err(e),
             _ => error_message::internal("invalid calculation result"),
         }
     }
 
-    pub fn eval_elems(&mut self, core: &mut ShellCore, permit_empty: bool) -> Result<ArithElem, String> {
+    pub fn eval_elems(&mut self, core: &mut ShellCore, permit_empty: bool) -> Result<ArithElem, ExecError> {
         if self.elements.len() == 0 && ! permit_empty {
-            return Err("operand expexted (error token: \")\")".to_string());
+            return Err(ExecError::Other("operand expexted (error token: \")\")".to_string()));
         }
         let es = match self.decompose_increments() {
             Ok(data)     => data, 
@@ -48,24 +50,24 @@ impl ArithmeticExpr {
         calculate(&es, core)
     }
 
-    fn ans_to_string(&self, n: i64) -> Option<String> {
+    fn ans_to_string(&self, n: i64, core: &mut ShellCore) -> Result<String, ExecError> {
         let base_str = self.output_base.clone();
 
         if base_str == "10" {
-            return Some(n.to_string());
+            return Ok(n.to_string());
         }
 
         let base = match base_str.parse::<i64>() {
             Ok(b) => b,
             _     => {
-                eprintln!("sush: {0}: invalid arithmetic base (error_message token is \"{0}\")", base_str);
-                return None;
+                error_message::print(&format!("{0}: invalid arithmetic base (error_message token is \"{0}\")", base_str), core, true);
+                return Err(ExecError::InvalidArithmetic);
             },
         };
 
         if base <= 1 || base > 64 {
-            eprintln!("sush: {0}: invalid arithmetic base (error_message token is \"{0}\")", base_str);
-            return None;
+            error_message::print(&format!("{0}: invalid arithmetic base (error_message token is \"{0}\")", base_str), core, true);
+            return Err(ExecError::InvalidArithmetic);
         }
 
         let mut tmp = n.abs();
@@ -84,7 +86,7 @@ impl ArithmeticExpr {
             ans.insert(0, '-');
         }
 
-        Some(ans)
+        Ok(ans)
     }
 
     fn dec_to_str(nums: &Vec<u8>, base: i64) -> String {
@@ -105,7 +107,7 @@ impl ArithmeticExpr {
         std::str::from_utf8(&ascii).unwrap().to_string()
     }
 
-    fn eval_in_cond(&mut self, core: &mut ShellCore) -> Result<ArithElem, String> {
+    fn eval_in_cond(&mut self, core: &mut ShellCore) -> Result<ArithElem, ExecError> {
         let es = match self.decompose_increments() {
             Ok(data)     => data, 
             Err(err_msg) => return Err(err_msg),
@@ -135,7 +137,7 @@ impl ArithmeticExpr {
         0
     }
 
-    fn decompose_increments(&mut self) -> Result<Vec<ArithElem>, String> {
+    fn decompose_increments(&mut self) -> Result<Vec<ArithElem>, ExecError> {
         let mut ans = vec![];
         let mut pre_increment = 0;
 
@@ -159,8 +161,8 @@ impl ArithmeticExpr {
         }
 
         match pre_increment {
-            1  => Err(error_message::syntax("++")),
-            -1 => Err(error_message::syntax("--")),
+            1  => Err(ExecError::Other(error_message::syntax("++"))),
+            -1 => Err(ExecError::Other(error_message::syntax("--"))),
             _  => Ok(ans),
         }
     }
@@ -1,20 +1,21 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore};
 use super::elem::ArithElem;
 use super::{elem, float, int, rev_polish, trenary, word};
 
-pub fn pop_operand(stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<ArithElem, String> {
+pub fn pop_operand(stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<ArithElem, ExecError> {
     match stack.pop() {
         Some(ArithElem::Word(w, inc)) => word::to_operand(&w, 0, inc, core),
         Some(ArithElem::InParen(mut a)) => a.eval_elems(core, false),
         Some(elem) => Ok(elem),
-        None       => Err("no operand".to_string()),
+        None       => Err(ExecError::Other("no operand".to_string())),
     }
 }
 
-fn bin_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), String> {
+fn bin_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), ExecError> {
     match op {
     "=" | "*=" | "/=" | "%=" | "+=" | "-=" | "<<=" | ">>=" | "&=" | "^=" | "|=" 
           => word::substitution(op, stack, core),
@@ -22,7 +23,7 @@ fn bin_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) ->
     }
 }
 
-fn bin_calc_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), String> {
+fn bin_calc_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), ExecError> {
     let right = match pop_operand(stack, core) {
         Ok(v)  => v,
         Err(e) => return Err(e),
@@ -47,7 +48,7 @@ fn bin_calc_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore
     };
 }
 
-fn unary_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), String> {
+fn unary_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), ExecError> {
     let operand = match pop_operand(stack, core) {
         Ok(v)  => v,
         Err(e) => return Err(e),
@@ -60,14 +61,14 @@ fn unary_operation(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -
     }
 }
 
-pub fn calculate(elements: &Vec<ArithElem>, core: &mut ShellCore) -> Result<ArithElem, String> {
+pub fn calculate(elements: &[ArithElem], core: &mut ShellCore) -> Result<ArithElem, ExecError> {
     if elements.len() == 0 {
         return Ok(ArithElem::Integer(0));
     }
 
     let rev_pol = match rev_polish::rearrange(elements) {
         Ok(ans) => ans,
-        Err(e)  => return Err( error_message::syntax(&elem::to_string(&e)) ),
+        Err(e)  => return Err(ExecError::Other(error_message::syntax(&elem::to_string(&e)))),
     };
 
     let mut stack = vec![];
@@ -107,15 +108,15 @@ pub fn calculate(elements: &Vec<ArithElem>, core: &mut ShellCore) -> Result<Arit
     }
 
     if stack.len() != 1 {
-        return Err( format!("unknown syntax error_message (stack inconsistency)",) );
+        return Err(ExecError::Other("unknown syntax error_message (stack inconsistency)".to_string()));
     }
     pop_operand(&mut stack, core)
 }
 
-fn check_skip(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<String, String> {
+fn check_skip(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<String, ExecError> {
     let last = pop_operand(stack, core);
     let last_result = match &last {
-        Err(e) => return Err(e.to_string()),
+        Err(e) => return Err(e.clone()),
         Ok(ArithElem::Integer(0)) => 0,
         Ok(_) => 1,
     };
@@ -132,7 +133,7 @@ fn check_skip(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Res
     Ok("".to_string())
 }
 
-fn inc(inc: i64, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), String> {
+fn inc(inc: i64, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(), ExecError> {
     match stack.pop() {
         Some(ArithElem::Word(w, inc_post)) => {
             match word::to_operand(&w, inc, inc_post, core) {
@@ -143,6 +144,6 @@ fn inc(inc: i64, stack: &mut Vec<ArithElem>, core: &mut ShellCore) -> Result<(),
                 Err(e) => Err(e),
             }
         },
-        _ => Err("invalid increment".to_string()),
+        _ => Err(ExecError::Other("invalid increment".to_string())),
     }
 }
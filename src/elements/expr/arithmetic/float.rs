@@ -1,20 +1,21 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore};
 use super::{ArithElem, word};
 
-pub fn unary_calc(op: &str, num: f64, stack: &mut Vec<ArithElem>) -> Result<(), String> {
+pub fn unary_calc(op: &str, num: f64, stack: &mut Vec<ArithElem>) -> Result<(), ExecError> {
     match op {
         "+"  => stack.push( ArithElem::Float(num) ),
         "-"  => stack.push( ArithElem::Float(-num) ),
-        _ => return Err("not supported operator for float number".to_string()),
+        _ => return Err(ExecError::Other("not supported operator for float number".to_string())),
     }
     Ok(())
 }
 
 pub fn bin_calc(op: &str, left: f64, right: f64,
-                stack: &mut Vec<ArithElem>) -> Result<(), String> {
+                stack: &mut Vec<ArithElem>) -> Result<(), ExecError> {
     let bool_to_01 = |b| { if b { ArithElem::Integer(1) } else { ArithElem::Integer(0) } };
 
     match op {
@@ -29,7 +30,7 @@ pub fn bin_calc(op: &str, left: f64, right: f64,
         "!="  => stack.push(bool_to_01( left != right )),
         "/" => {
             if right == 0.0 {
-                return Err("divided by 0".to_string());
+                return Err(ExecError::Other("divided by 0".to_string()));
             }
             stack.push(ArithElem::Float(left / right));
         },
@@ -38,28 +39,28 @@ pub fn bin_calc(op: &str, left: f64, right: f64,
                 let r = right.try_into().unwrap();
                 stack.push(ArithElem::Float(left.powf(r)));
             }else{
-                return Err( error_message::exponent(&right.to_string()) );
+                return Err(ExecError::Other(error_message::exponent(&right.to_string())));
             }
         },
-        _    => return Err("not supported operator for float numbers".to_string()),
+        _    => return Err(ExecError::Other("not supported operator for float numbers".to_string())),
     }
 
     Ok(())
 }
 
 pub fn substitute(op: &str, name: &String, cur: f64, right: f64, core: &mut ShellCore)
-                                      -> Result<ArithElem, String> {
+                                      -> Result<ArithElem, ExecError> {
     let new_value = match op {
         "+=" => cur + right,
         "-=" => cur - right,
         "*=" => cur * right,
         "/=" => {
             match right == 0.0 {
-                true  => return Err("divided by 0".to_string()),
+                true  => return Err(ExecError::Other("divided by 0".to_string())),
                 false => cur / right,
             }
         },
-        _   => return Err("Not supprted operation for float numbers".to_string()),
+        _   => return Err(ExecError::Other("Not supprted operation for float numbers".to_string())),
     };
 
     core.data.set_param(&name, &new_value.to_string());
@@ -1,10 +1,11 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore};
 use super::{ArithElem, word};
 
-pub fn unary_calc(op: &str, num: i64, stack: &mut Vec<ArithElem>) -> Result<(), String> {
+pub fn unary_calc(op: &str, num: i64, stack: &mut Vec<ArithElem>) -> Result<(), ExecError> {
     match op {
         "+"  => stack.push( ArithElem::Integer(num) ),
         "-"  => stack.push( ArithElem::Integer(-num) ),
@@ -15,7 +16,7 @@ pub fn unary_calc(op: &str, num: i64, stack: &mut Vec<ArithElem>) -> Result<(),
     Ok(())
 }
 
-pub fn bin_calc(op: &str, left: i64, right: i64, stack: &mut Vec<ArithElem>) -> Result<(), String> {
+pub fn bin_calc(op: &str, left: i64, right: i64, stack: &mut Vec<ArithElem>) -> Result<(), ExecError> {
     let bool_to_01 = |b| { if b { 1 } else { 0 } };
 
     let ans = match op {
@@ -37,7 +38,7 @@ pub fn bin_calc(op: &str, left: i64, right: i64, stack: &mut Vec<ArithElem>) ->
         "!="  => bool_to_01( left != right ),
         "%" | "/" => {
             if right == 0 {
-                return Err("divided by 0".to_string());
+                return Err(ExecError::Other("divided by 0".to_string()));
             }
             match op {
                 "%" => left % right,
@@ -49,7 +50,7 @@ pub fn bin_calc(op: &str, left: i64, right: i64, stack: &mut Vec<ArithElem>) ->
                 let r = right.try_into().unwrap();
                 left.pow(r)
             }else{
-                return Err( error_message::exponent(&right.to_string()) );
+                return Err(ExecError::Other(error_message::exponent(&right.to_string())));
             }
         },
         _    => error_message::internal("unknown binary operator"),
@@ -60,7 +61,7 @@ pub fn bin_calc(op: &str, left: i64, right: i64, stack: &mut Vec<ArithElem>) ->
 }
 
 pub fn substitute(op: &str, name: &String, cur: i64, right: i64, core: &mut ShellCore)
-                                      -> Result<ArithElem, String> {
+                                      -> Result<ArithElem, ExecError> {
     let new_value = match op {
         "+=" => cur + right,
         "-=" => cur - right,
@@ -72,14 +73,14 @@ pub fn substitute(op: &str, name: &String, cur: i64, right: i64, core: &mut Shel
         ">>="  => if right < 0 {0} else {cur >> right},
         "/=" | "%=" => {
             if right == 0 {
-                return Err("divided by 0".to_string());
+                return Err(ExecError::Other("divided by 0".to_string()));
             }
             match op == "%=" {
                 true  => cur % right,
                 false => cur / right,
             }
         },
-        _   => return Err("Not supprted operation for integer numbers".to_string()),
+        _   => return Err(ExecError::Other("Not supprted operation for integer numbers".to_string())),
     };
 
     core.data.set_param(&name, &new_value.to_string());
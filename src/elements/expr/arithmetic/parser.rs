@@ -76,7 +76,7 @@ impl ArithmeticExpr {
             return true;
         }
 
-        ans.text += &word.text.clone();
+        ans.text += &word.text;
 
         if let Some(w) = word.make_unquoted_word() {
             if word.text.find('\'').is_none() {
@@ -103,7 +103,7 @@ impl ArithmeticExpr {
         }
 
         let mut s = feeder.consume(len);
-        ans.text += &s.clone();
+        ans.text += &s;
         ans.hide_base = s.find("##").is_some();
         s.retain(|c| '0' <= c && c <= '9');
         ans.output_base = s;
@@ -124,7 +124,7 @@ impl ArithmeticExpr {
             len => feeder.consume(len),
         };
 
-        ans.text += &s.clone();
+        ans.text += &s;
         ans.elements.push( ArithElem::UnaryOp(s) );
         true
     }
@@ -155,7 +155,7 @@ impl ArithmeticExpr {
         }
 
         let s = feeder.consume(len);
-        ans.text += &s.clone();
+        ans.text += &s;
         ans.elements.push( ArithElem::BinaryOp(s) );
         true
     }
@@ -1,20 +1,18 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore, Feeder};
 use super::{ArithElem, float, int, Word};
 
 pub fn to_operand(w: &Word, pre_increment: i64, post_increment: i64,
-                   core: &mut ShellCore) -> Result<ArithElem, String> {
+                   core: &mut ShellCore) -> Result<ArithElem, ExecError> {
     if pre_increment != 0 && post_increment != 0 
     || w.text.find('\'').is_some() {
-        return Err(error_message::syntax(&w.text));
+        return Err(ExecError::Other(error_message::syntax(&w.text)));
     }
 
-    let name = match w.eval_as_value(core) {
-        Some(v) => v, 
-        None => return Err(format!("{}: wrong substitution", &w.text)),
-    };
+    let name = w.eval_as_value(core)?;
 
     let res = match pre_increment {
         0 => change_variable(&name, core, post_increment, false),
@@ -27,15 +25,12 @@ pub fn to_operand(w: &Word, pre_increment: i64, post_increment: i64,
     }
 }
 
-fn to_num(w: &Word, core: &mut ShellCore) -> Result<ArithElem, String> {
+fn to_num(w: &Word, core: &mut ShellCore) -> Result<ArithElem, ExecError> {
     if w.text.find('\'').is_some() {
-        return Err(error_message::syntax(&w.text));
+        return Err(ExecError::Other(error_message::syntax(&w.text)));
     }
 
-    let name = match w.eval_as_value(core) {
-        Some(v) => v, 
-        None => return Err(format!("{}: wrong substitution", &w.text)),
-    };
+    let name = w.eval_as_value(core)?;
 
     str_to_num(&name, core)
 }
@@ -45,19 +40,22 @@ fn is_name(s: &str, core: &mut ShellCore) -> bool {
     s.len() > 0 && f.scanner_name(core) == s.len()
 }
 
-pub fn str_to_num(name: &str, core: &mut ShellCore) -> Result<ArithElem, String> {
+pub fn str_to_num(name: &str, core: &mut ShellCore) -> Result<ArithElem, ExecError> {
     let mut name = name.to_string();
 
     const RESOLVE_LIMIT: i32 = 10000;
 
     for i in 0..RESOLVE_LIMIT {
-        match is_name(&name, core) {
-            true  => name = core.data.get_param(&name),
-            false => break,
+        if ! is_name(&name, core) {
+            break;
         }
+        if core.data.flags.contains('u') && ! core.data.has_value(&name, None) {
+            return Err(ExecError::UnboundVariable(name));
+        }
+        name = core.data.get_param(&name);
 
         if i == RESOLVE_LIMIT - 1 {
-            return Err(error_message::recursion(&name));
+            return Err(ExecError::Other(error_message::recursion(&name)));
         }
     }
 
@@ -68,14 +66,14 @@ pub fn str_to_num(name: &str, core: &mut ShellCore) -> Result<ArithElem, String>
     }else if let Some(f) = float::parse(&name) {
         Ok( ArithElem::Float(f) )
     }else{
-        Err(error_message::syntax(&name))
+        Err(ExecError::Other(error_message::syntax(&name)))
     }
 }
 
-fn change_variable(name: &str, core: &mut ShellCore, inc: i64, pre: bool) -> Result<ArithElem, String> {
+fn change_variable(name: &str, core: &mut ShellCore, inc: i64, pre: bool) -> Result<ArithElem, ExecError> {
     if ! is_name(name, core) {
         return match inc != 0 && ! pre {
-            true  => Err(error_message::syntax(name)),
+            true  => Err(ExecError::Other(error_message::syntax(name))),
             false => str_to_num(&name, core),
         }
     }
@@ -112,16 +110,16 @@ pub fn get_sign(s: &mut String) -> String {
     }
 }
 
-pub fn substitution(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore)-> Result<(), String> {
+pub fn substitution(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore)-> Result<(), ExecError> {
     let right = match stack.pop() {
         Some(e) => e,
-        _       => return Err( error_message::syntax(op) ),
+        _       => return Err(ExecError::Other(error_message::syntax(op))),
     };
 
     let left = match stack.pop() {
         Some(ArithElem::Word(w, 0)) => w,
-        Some(ArithElem::Word(_, _)) => return Err( error_message::assignment(op) ),
-        _ => return Err( error_message::assignment(op) ),
+        Some(ArithElem::Word(_, _)) => return Err(ExecError::Other(error_message::assignment(op))),
+        _ => return Err(ExecError::Other(error_message::assignment(op))),
     };
 
     match subs(op, &left, &right, core) {
@@ -132,15 +130,12 @@ pub fn substitution(op: &str, stack: &mut Vec<ArithElem>, core: &mut ShellCore)-
 }
 
 fn subs(op: &str, w: &Word, right_value: &ArithElem, core: &mut ShellCore)
-                                      -> Result<ArithElem, String> {
+                                      -> Result<ArithElem, ExecError> {
     if w.text.find('\'').is_some() {
-        return Err(error_message::syntax(&w.text));
+        return Err(ExecError::Other(error_message::syntax(&w.text)));
     }
 
-    let name = match w.eval_as_value(core) {
-        Some(v) => v, 
-        None => return Err(format!("{}: wrong substitution", &w.text)),
-    };
+    let name = w.eval_as_value(core)?;
 
     let right_str = match right_value {
         ArithElem::Integer(n) => n.to_string(),
@@ -166,7 +161,7 @@ fn subs(op: &str, w: &Word, right_value: &ArithElem, core: &mut ShellCore)
         (ArithElem::Float(cur), ArithElem::Integer(right)) => float::substitute(op, &name, cur, *right as f64, core),
         (ArithElem::Float(cur), ArithElem::Float(right)) => float::substitute(op, &name, cur, *right, core),
         (ArithElem::Integer(cur), ArithElem::Float(right)) => float::substitute(op, &name, cur as f64, *right, core),
-        _ => Err("support not yet".to_string()),
+        _ => Err(ExecError::Other("support not yet".to_string())),
     }
 
 }
@@ -1,12 +1,13 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error_message::ExecError;
 use crate::{error_message, ShellCore, Feeder};
 use crate::utils::file_check;
 use crate::elements::word::Word;
+use regex::RegexBuilder;
 use super::arithmetic::word;
 use super::arithmetic::elem::ArithElem;
-use std::env;
 
 #[derive(Debug, Clone)]
 pub enum CondElem {
@@ -46,19 +47,16 @@ pub fn to_string(op: &CondElem) -> String {
     }
 }
 
-fn to_operand(w: &Word, core: &mut ShellCore) -> Result<CondElem, String> {
-    match w.eval_as_value(core) {
-        Some(v) => Ok(CondElem::Operand(v)),
-        None => return Err(format!("{}: wrong substitution", &w.text)),
-    }
+fn to_operand(w: &Word, core: &mut ShellCore) -> Result<CondElem, ExecError> {
+    Ok(CondElem::Operand(w.eval_as_value(core)?))
 }
 
-fn pop_operand(stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<CondElem, String> {
+fn pop_operand(stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<CondElem, ExecError> {
     match stack.pop() {
         Some(CondElem::InParen(mut expr)) => expr.eval(core),
         Some(CondElem::Word(w)) => to_operand(&w, core),
         Some(elem) => Ok(elem),
-        None => return Err("no operand".to_string()),
+        None => Err(ExecError::Other("no operand".to_string())),
     }
 }
 
@@ -69,7 +67,7 @@ pub struct ConditionalExpr {
 }
 
 impl ConditionalExpr {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Result<CondElem, String> {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<CondElem, ExecError> {
         let mut from = 0;
         let mut next = true;
         let mut last = CondElem::Ans(true);
@@ -97,7 +95,7 @@ impl ConditionalExpr {
         Ok(last)
     }
 
-    fn calculate(elems: &[CondElem], core: &mut ShellCore) -> Result<CondElem, String> {
+    fn calculate(elems: &[CondElem], core: &mut ShellCore) -> Result<CondElem, ExecError> {
         let rev_pol = match Self::rev_polish(elems) {
             Ok(ans) => ans,
             Err(e) => return Err(e),
@@ -113,7 +111,7 @@ impl ConditionalExpr {
         }
     }
 
-    fn rev_polish(elems: &[CondElem]) -> Result<Vec<CondElem>, String> {
+    fn rev_polish(elems: &[CondElem]) -> Result<Vec<CondElem>, ExecError> {
         let mut ans = vec![];
         let mut stack = vec![];
     
@@ -125,7 +123,7 @@ impl ConditionalExpr {
     
             if !ok {
                 let msg = "syntax error near ".to_owned() + &to_string(e);
-                return Err(msg);
+                return Err(ExecError::Other(msg));
             }
         }
     
@@ -136,7 +134,7 @@ impl ConditionalExpr {
         Ok(ans)
     }
 
-    fn reduce(rev_pol: &[CondElem], core: &mut ShellCore) -> Result<Vec<CondElem>, String> {
+    fn reduce(rev_pol: &[CondElem], core: &mut ShellCore) -> Result<Vec<CondElem>, ExecError> {
         let mut stack = vec![];
 
         for e in rev_pol {
@@ -157,13 +155,13 @@ impl ConditionalExpr {
                         stack.push(CondElem::Ans(!res));
                         Ok(())
                     },
-                    _ => Err("no operand to negate".to_string()),
+                    _ => Err(ExecError::Other("no operand to negate".to_string())),
                 },
-                _ => Err( error_message::syntax("TODO")),
+                _ => Err(ExecError::Other(error_message::syntax("TODO"))),
             };
     
             if let Err(err_msg) = result {
-                core.data.set_param("?", "2");
+                core.data.set_exit_status(2);
                 return Err(err_msg);
             }
         }
@@ -175,23 +173,69 @@ impl ConditionalExpr {
                 error_message::print(&err, core, true);
                 err = format!("syntax error near `{}'", to_string(&stack[0]));
             }
-            return Err(err);
+            return Err(ExecError::Other(err));
         }   
 
         Ok(stack)
     }
 
-    fn unary_operation(op: &str, stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<(), String> {
+    fn pattern_match(op: &str, pattern: &Word, stack: &mut Vec<CondElem>,
+                     core: &mut ShellCore) -> Result<(), ExecError> {
+        let pattern = pattern.eval_for_case_pattern(core)?;
+
+        let left = match pop_operand(stack, core) {
+            Ok(CondElem::Operand(name)) => name,
+            Ok(_)  => return Err(ExecError::Other("Invalid operand".to_string())),
+            Err(e) => return Err(e),
+        };
+
+        let nocase = core.shopts.query("nocasematch");
+        let ans = core.glob_matcher(&pattern, nocase).matches(&left);
+        stack.push( CondElem::Ans(ans ^ (op == "!=")) );
+        Ok(())
+    }
+
+    fn regex_match(pattern: &Word, stack: &mut Vec<CondElem>,
+                   core: &mut ShellCore) -> Result<(), ExecError> {
+        let pattern = pattern.eval_for_regex(core)?;
+
+        let left = match pop_operand(stack, core) {
+            Ok(CondElem::Operand(name)) => name,
+            Ok(_)  => return Err(ExecError::Other("Invalid operand".to_string())),
+            Err(e) => return Err(e),
+        };
+
+        let nocase = core.shopts.query("nocasematch");
+        let re = match RegexBuilder::new(&pattern).case_insensitive(nocase).build() {
+            Ok(re) => re,
+            Err(_) => return Err(ExecError::Other(format!("{}: invalid regular expression", &pattern))),
+        };
+
+        /* unmatched groups are empty strings */
+        let groups: Vec<String> = match re.captures(&left) {
+            Some(caps) => caps.iter()
+                          .map(|m| m.map(|m| m.as_str().to_string()).unwrap_or_default())
+                          .collect(),
+            None => vec![],
+        };
+
+        stack.push( CondElem::Ans(! groups.is_empty()) );
+        core.data.set_array("BASH_REMATCH", &groups);
+        Ok(())
+    }
+
+    fn unary_operation(op: &str, stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<(), ExecError> {
         let operand = match pop_operand(stack, core) {
             Ok(CondElem::Operand(v))  => v,
-            Ok(_)  => return Err("unknown operand".to_string()), 
-            Err(e) => return Err(e + " to conditional unary operator"),
+            Ok(_)  => return Err(ExecError::Other("unknown operand".to_string())), 
+            Err(ExecError::Other(msg)) => return Err(ExecError::Other(msg + " to conditional unary operator")),
+            Err(e) => return Err(e),
         };
 
         if op == "-o" || op == "-v" || op == "-z" || op == "-n" {
             let ans = match op {
                 "-o" => core.options.query(&operand),
-                "-v" => core.data.get_value(&operand).is_some() || env::var(&operand).is_ok(),
+                "-v" => core.data.is_set(&operand),
                 "-z" => operand.len() == 0,
                 "-n" => operand.len() > 0,
                 _    => false,
@@ -204,16 +248,32 @@ impl ConditionalExpr {
         Self::unary_file_check(op, &operand, stack)
     }
 
-    fn bin_operation(op: &str, stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<(), String> {
+    fn bin_operation(op: &str, stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<(), ExecError> {
+        if op == "==" || op == "=" || op == "!=" {
+            if let Some(CondElem::Word(w)) = stack.last() {
+                let w = w.clone();
+                stack.pop();
+                return Self::pattern_match(op, &w, stack, core);
+            }
+        }
+
+        if op == "=~" {
+            if let Some(CondElem::Word(w)) = stack.last() {
+                let w = w.clone();
+                stack.pop();
+                return Self::regex_match(&w, stack, core);
+            }
+        }
+
         let right = match pop_operand(stack, core) {
             Ok(CondElem::Operand(name)) => name,
-            Ok(_)  => return Err("Invalid operand".to_string()),
+            Ok(_)  => return Err(ExecError::Other("Invalid operand".to_string())),
             Err(e) => return Err(e),
         };
     
         let left = match pop_operand(stack, core) {
             Ok(CondElem::Operand(name)) => name,
-            Ok(_)  => return Err("Invalid operand".to_string()),
+            Ok(_)  => return Err(ExecError::Other("Invalid operand".to_string())),
             Err(e) => return Err(e),
         };
 
@@ -233,12 +293,12 @@ impl ConditionalExpr {
         if op == "-eq" || op == "-ne" || op == "-lt" || op == "-le" || op == "-gt" || op == "-ge" {
             let lnum = match word::str_to_num(&left, core) {
                 Ok(ArithElem::Integer(n)) => n,
-                Ok(_) => return Err("non integer number is not supported".to_string()),
+                Ok(_) => return Err(ExecError::Other("non integer number is not supported".to_string())),
                 Err(msg) => return Err(msg),
             };
             let rnum = match word::str_to_num(&right, core) {
                 Ok(ArithElem::Integer(n)) => n,
-                Ok(_) => return Err("non integer number is not supported".to_string()),
+                Ok(_) => return Err(ExecError::Other("non integer number is not supported".to_string())),
                 Err(msg) => return Err(msg),
             };
 
@@ -261,7 +321,7 @@ impl ConditionalExpr {
         Ok(())
     }
 
-    fn unary_file_check(op: &str, s: &String, stack: &mut Vec<CondElem>) -> Result<(), String> {
+    fn unary_file_check(op: &str, s: &str, stack: &mut Vec<CondElem>) -> Result<(), ExecError> {
         let result = match op {
             "-a" | "-e"  => file_check::exists(s),
             "-d"  => file_check::is_dir(s),
@@ -273,7 +333,7 @@ impl ConditionalExpr {
             "-x"  => file_check::is_executable(s),
             "-b" | "-c" | "-g" | "-k" | "-p" | "-s" | "-u" | "-G" | "-N" | "-O" | "-S"
                   => file_check::metadata_check(s, op),
-            _  => return Err("unsupported option".to_string()),
+            _  => return Err(ExecError::Other("unsupported option".to_string())),
         };
 
         stack.push( CondElem::Ans(result) );
@@ -318,7 +378,7 @@ impl ConditionalExpr {
 
         match Word::parse(feeder, core, false) {
             Some(w) => {
-                ans.text += &w.text.clone();
+                ans.text += &w.text;
                 ans.elements.push(CondElem::Word(w));
 
                 true
@@ -327,6 +387,22 @@ impl ConditionalExpr {
         }
     }
 
+    fn eat_regex(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        match ans.elements.last() {
+            Some(CondElem::BinaryOp(op)) if op == "=~" => {},
+            _ => return false,
+        }
+
+        match Word::parse_regex(feeder, core) {
+            Some(w) => {
+                ans.text += &w.text;
+                ans.elements.push(CondElem::Word(w));
+                true
+            },
+            _ => false
+        }
+    }
+
     fn eat_compare_op(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         let len = feeder.scanner_test_compare_op(core);
         if len == 0 {
@@ -334,7 +410,7 @@ impl ConditionalExpr {
         }
 
         let opt = feeder.consume(len);
-        ans.text += &opt.clone();
+        ans.text += &opt;
         ans.elements.push(CondElem::BinaryOp(opt));
 
         true
@@ -347,7 +423,7 @@ impl ConditionalExpr {
         }
 
         let opt = feeder.consume(len);
-        ans.text += &opt.clone();
+        ans.text += &opt;
         ans.elements.push(CondElem::UnaryOp(opt));
 
         true
@@ -398,19 +474,27 @@ impl ConditionalExpr {
             return false;
         }
 
-        ans.text += &expr.text.clone();
+        ans.text += &expr.text;
         ans.elements.push( CondElem::InParen(expr) );
         ans.text += &feeder.consume(1);
         true
     }
 
     fn eat_blank(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        match feeder.scanner_blank(core) {
-            0 => false,
-            n => {
-                ans.text += &feeder.consume(n);
-                true
-            },
+        let mut eaten = false;
+        loop {
+            match feeder.scanner_multiline_blank(core) {
+                0 => {},
+                n => {
+                    ans.text += &feeder.consume(n);
+                    eaten = true;
+                },
+            }
+
+            /* the expression continues on the next line */
+            if feeder.len() != 0 || ! feeder.feed_additional_line(core) {
+                return eaten;
+            }
         }
     }
 
@@ -429,6 +513,16 @@ impl ConditionalExpr {
                 return Some(ans);
             }
 
+            if Self::eat_regex(feeder, &mut ans, core) {
+                continue;
+            }
+
+            /* the right side of a binary operator is a word even if it is !(...) */
+            let after_op = matches!(ans.elements.last(), Some(CondElem::BinaryOp(_)));
+            if after_op && Self::eat_word(feeder, &mut ans, core) {
+                continue;
+            }
+
             if Self::eat_paren(feeder, &mut ans, core) 
             || Self::eat_compare_op(feeder, &mut ans, core)
             || Self::eat_file_check_option(feeder, &mut ans, core)
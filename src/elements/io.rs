@@ -4,9 +4,10 @@
 pub mod pipe;
 pub mod redirect;
 
+use std::io::Write;
 use std::os::unix::prelude::RawFd;
 use nix::{fcntl, unistd};
-use crate::{process, ShellCore};
+use crate::{error_message, process, ShellCore};
 use nix::errno::Errno;
 use crate::elements::Pipe;
 use crate::elements::io::redirect::Redirect;
@@ -22,17 +23,21 @@ pub fn replace(from: RawFd, to: RawFd) -> bool {
         return false;
     }
 
+    if from == to { // dup2 keeps FD_CLOEXEC in this case
+        return fcntl::fcntl(to, fcntl::F_SETFD(fcntl::FdFlag::empty())).is_ok();
+    }
+
     match unistd::dup2(from, to) {
         Ok(_) => {
             close(from, &format!("sush(fatal): {}: cannot be closed", from));
             true
         },
         Err(Errno::EBADF) => {
-            eprintln!("sush: {}: Bad file descriptor", to);
+            error_message::print_without_core(&format!("{}: Bad file descriptor", to));
             false
         },
         Err(_) => {
-            eprintln!("sush: dup2 Unknown error");
+            error_message::print_without_core("dup2 Unknown error");
             false
         },
     }
@@ -46,24 +51,72 @@ fn share(from: RawFd, to: RawFd) -> bool {
     match unistd::dup2(from, to) {
         Ok(_) => true,
         Err(Errno::EBADF) => {
-            eprintln!("sush: {}: Bad file descriptor", to);
+            error_message::print_without_core(&format!("{}: Bad file descriptor", to));
             false
         },
         Err(_) => {
-            eprintln!("sush: dup2 Unknown error");
+            error_message::print_without_core("dup2 Unknown error");
             false
         },
     }
 }
 
+/* records the fds changed in this process and puts them back when it is dropped,
+   even if the command between fails on the way */
+#[derive(Debug, Default)]
+pub struct RedirectGuard {
+    backups: Vec<(RawFd, RawFd)>, // (changed fd, its backup or -1 if it was not open)
+}
+
+impl RedirectGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn save(&mut self, fd: RawFd) {
+        if fd >= 0 && ! self.backups.iter().any(|b| b.0 == fd) {
+            self.backups.push((fd, backup(fd)));
+        }
+    }
+}
+
+impl Drop for RedirectGuard {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().flush(); // the output of builtins left in the buffer
+        while let Some((fd, backup)) = self.backups.pop() {
+            match backup {
+                -1 => { let _ = unistd::close(fd); },
+                b  => { replace(b, fd); },
+            }
+        }
+    }
+}
+
+/* returns -1 when the fd is not open */
 pub fn backup(from: RawFd) -> RawFd {
-    fcntl::fcntl(from, fcntl::F_DUPFD_CLOEXEC(10))
-           .expect("Can't allocate fd for backup")
+    match fcntl::fcntl(from, fcntl::F_DUPFD_CLOEXEC(10)) {
+        Ok(fd) => fd,
+        Err(Errno::EBADF) => -1,
+        Err(e) => panic!("Can't allocate fd for backup: {:?}", e),
+    }
+}
+
+/* one byte at a time so that the rest of the input is left to other readers of the fd */
+pub fn read_byte(fd: RawFd) -> Result<Option<u8>, Errno> {
+    let mut buf = [0u8; 1];
+    loop {
+        match unistd::read(fd, &mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buf[0])),
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 pub fn connect(pipe: &mut Pipe, rs: &mut Vec<Redirect>, core: &mut ShellCore) {
     pipe.connect();
-    if ! rs.iter_mut().all(|r| r.connect(false, core)){
+    if ! rs.iter_mut().all(|r| r.connect(None, core)){
         process::exit(1);
     }
 }
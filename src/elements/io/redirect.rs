@@ -2,9 +2,13 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use std::fs::{File, OpenOptions};
-use std::os::fd::{IntoRawFd, RawFd};
-use std::io::Error;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::os::fd::{IntoRawFd, OwnedFd, RawFd};
+use std::io::{Error, ErrorKind};
+use nix::errno::Errno;
+use nix::{fcntl, unistd};
 use crate::elements::io;
+use crate::elements::io::RedirectGuard;
 use crate::elements::word::Word;
 use crate::{error_message, Feeder, ShellCore};
 
@@ -15,30 +19,79 @@ pub struct Redirect {
     pub right: Word,
     pub left: String,
     left_fd: RawFd,
-    left_backup: RawFd,
-    extra_left_backup: RawFd, // &>, &>>用
+}
+
+fn truncate() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    options
+}
+
+fn open_socket(dest: &str, tcp: bool) -> Result<File, Error> {
+    let (host, port) = match dest.split_once('/') {
+        Some((h, p)) if ! h.is_empty() && ! p.contains('/') => (h, p),
+        _ => return Err(Error::from_raw_os_error(Errno::ENOENT as i32)),
+    };
+    let addr = match port.parse::<u16>().ok()
+                     .and_then(|p| (host, p).to_socket_addrs().ok()?.next()) {
+        Some(a) => a,
+        None    => return Err(Error::from_raw_os_error(Errno::EINVAL as i32)),
+    };
+
+    let fd: OwnedFd = match tcp {
+        true  => TcpStream::connect(addr)?.into(),
+        false => {
+            let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+            let socket = UdpSocket::bind(local)?;
+            socket.connect(addr)?;
+            socket.into()
+        },
+    };
+    Ok(File::from(fd))
 }
 
 impl Redirect {
-    pub fn connect(&mut self, restore: bool, core: &mut ShellCore) -> bool {
+    /* the fds changed here are recorded in the guard when they are restored later */
+    pub fn connect(&mut self, guard: Option<&mut RedirectGuard>, core: &mut ShellCore) -> bool {
         let args = match self.right.eval(core) {
-            Some(v) => v,
-            None => return false,
+            Ok(v)  => v,
+            Err(e) => {
+                e.print(core);
+                return false;
+            },
         };
 
         if args.len() != 1 {
-            eprintln!("sush: {}: ambiguous redirect", self.right.text);
+            error_message::print(&format!("{}: ambiguous redirect", self.right.text), core, true);
             return false;
         }else{
             self.right.text = args[0].clone();
         }
 
+        if core.is_restricted()
+        && [">", ">|", ">>", "&>", "<>"].contains(&self.symbol.as_str()) {
+            error_message::print(&format!("{}: restricted: cannot redirect output", &self.right.text), core, true);
+            return false;
+        }
+
+        self.set_left_fd(if self.symbol.starts_with('<') { 0 } else { 1 });
+        if let Some(g) = guard {
+            g.save(self.left_fd);
+            if self.symbol == "&>" {
+                g.save(2);
+            }
+        }
+
         match self.symbol.as_str() {
-            "<" => self.redirect_simple_input(restore),
-            ">" => self.redirect_simple_output(restore),
-            ">&" => self.redirect_output_fd(restore),
-            ">>" => self.redirect_append(restore),
-            "&>" => self.redirect_both_output(restore),
+            "<" => self.connect_to_file(OpenOptions::new().read(true), core),
+            "<>" => self.connect_to_file(OpenOptions::new().read(true)
+                                         .write(true).create(true), core),
+            ">" => self.connect_to_output_file(core),
+            ">|" => self.connect_to_file(&truncate(), core),
+            ">&" | "<&" => self.redirect_fd(core),
+            ">>" => self.connect_to_file(OpenOptions::new().create(true)
+                                         .append(true), core),
+            "&>" => self.redirect_both_output(core),
             _ => error_message::internal(" (Unknown redirect symbol)"),
         }
     }
@@ -52,12 +105,25 @@ impl Redirect {
         };
     }
 
-    fn connect_to_file(&mut self, file_open_result: Result<File,Error>, restore: bool) -> bool {
-        if restore {
-            self.left_backup = io::backup(self.left_fd);
+    /* /dev/tcp/host/port and /dev/udp/host/port are connections to the host */
+    fn open(&self, options: &OpenOptions) -> Result<File, Error> {
+        let path = &self.right.text;
+        if let Some(dest) = path.strip_prefix("/dev/tcp/") {
+            return open_socket(dest, true);
         }
+        if let Some(dest) = path.strip_prefix("/dev/udp/") {
+            return open_socket(dest, false);
+        }
+        options.open(path)
+    }
 
-        match file_open_result {
+    fn connect_to_file(&mut self, options: &OpenOptions, core: &mut ShellCore) -> bool {
+        let file = self.open(options);
+        self.connect_opened_file(file, core)
+    }
+
+    fn connect_opened_file(&mut self, file: Result<File, Error>, core: &mut ShellCore) -> bool {
+        match file {
             Ok(file) => {
                 let fd = file.into_raw_fd();
                 let result = io::replace(fd, self.left_fd);
@@ -67,61 +133,64 @@ impl Redirect {
                 }
                 result
             },
-            _  => {
-                eprintln!("sush: {}: {}", &self.right.text, Error::last_os_error().kind());
+            Err(e) => {
+                let msg = format!("{}: {}", &self.right.text, error_message::io_error(&e));
+                error_message::print(&msg, core, true);
                 false
             },
         }
     }
 
-    fn redirect_simple_input(&mut self, restore: bool) -> bool {
-        self.set_left_fd(0);
-        self.connect_to_file(File::open(&self.right.text), restore)
-    }
+    /* with noclobber, > and &> don't overwrite an existing regular file. A new file
+       is created exclusively, and an existing one is checked after it is opened
+       without truncation so that it cannot be replaced in between */
+    fn connect_to_output_file(&mut self, core: &mut ShellCore) -> bool {
+        if ! core.data.flags.contains('C') {
+            return self.connect_to_file(&truncate(), core);
+        }
 
-    fn redirect_simple_output(&mut self, restore: bool) -> bool {
-        self.set_left_fd(1);
-        self.connect_to_file(File::create(&self.right.text), restore)
+        let file = match self.open(OpenOptions::new().write(true).create_new(true)) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => self.open(OpenOptions::new().write(true)),
+            file => return self.connect_opened_file(file, core),
+        };
+
+        if file.as_ref().is_ok_and(|f| f.metadata().is_ok_and(|m| m.is_file())) {
+            let msg = format!("{}: cannot overwrite existing file", &self.right.text);
+            error_message::print(&msg, core, true);
+            return false;
+        }
+        self.connect_opened_file(file, core)
     }
 
-    fn redirect_output_fd(&mut self, _: bool) -> bool {
+    /* n>&m and n<&m duplicate m, and n>&- closes n */
+    fn redirect_fd(&mut self, core: &mut ShellCore) -> bool {
+        if self.right.text == "-" {
+            let _ = unistd::close(self.left_fd);
+            return true;
+        }
+
         let fd = match self.right.text.parse::<RawFd>() {
             Ok(n) => n,
             _     => return false,
         };
 
-        self.set_left_fd(1);
+        if fcntl::fcntl(fd, fcntl::F_GETFD).is_err() {
+            let msg = format!("{}: Bad file descriptor", fd);
+            error_message::print(&msg, core, true);
+            return false;
+        }
         io::share(fd, self.left_fd)
     }
 
-    fn redirect_append(&mut self, restore: bool) -> bool {
-        self.set_left_fd(1);
-        self.connect_to_file(OpenOptions::new().create(true)
-                .write(true).append(true).open(&self.right.text), restore)
-    }
-
-    fn redirect_both_output(&mut self, restore: bool) -> bool {
+    fn redirect_both_output(&mut self, core: &mut ShellCore) -> bool {
         self.left_fd = 1;
-        if ! self.connect_to_file(File::create(&self.right.text), restore){
+        if ! self.connect_to_output_file(core) {
             return false;
         }
-
-        if restore {
-            self.extra_left_backup = io::backup(2);
-        }
         io::share(1, 2);
         true
     }
 
-    pub fn restore(&mut self) {
-        if self.left_backup >= 0 && self.left_fd >= 0 {
-            io::replace(self.left_backup, self.left_fd);
-        }
-        if self.extra_left_backup >= 0 {
-            io::replace(self.extra_left_backup, 2);
-        }
-    }
-
     pub fn new() -> Redirect {
         Redirect {
             text: String::new(),
@@ -129,8 +198,6 @@ impl Redirect {
             right: Word::new(),
             left: String::new(),
             left_fd: -1,
-            left_backup: -1,
-            extra_left_backup: -1,
         }
     }
 
@@ -139,7 +206,7 @@ impl Redirect {
             0 => false,
             n => {
                 ans.symbol = feeder.consume(n);
-                ans.text += &ans.symbol.clone();
+                ans.text += &ans.symbol;
                 true
             },
         }
@@ -154,7 +221,7 @@ impl Redirect {
             _       => return false,
         };
 
-        ans.text += &w.text.clone();
+        ans.text += &w.text;
         ans.right = w;
         true
     }
@@ -166,7 +233,7 @@ impl Redirect {
         }
 
         ans.left = feeder.consume(len);
-        ans.text += &ans.left.clone();
+        ans.text += &ans.left;
 
         match ans.left.parse::<RawFd>() {
             Ok(_) => true,
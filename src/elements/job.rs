@@ -1,8 +1,9 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use super::command;
 use super::pipeline::Pipeline;
-use crate::{Feeder, ShellCore};
+use crate::{signal, Feeder, ShellCore};
 use crate::core::jobtable::JobEntry;
 use nix::sys::wait::WaitStatus;
 use nix::unistd;
@@ -26,6 +27,7 @@ impl Job {
             true  => self.exec_bg(core, pgid),
             false => self.exec_fg(core, pgid),
         }
+        signal::run_traps(core);
     }
 
     fn exec_fg(&mut self, core: &mut ShellCore, pgid: Pid) {
@@ -40,12 +42,14 @@ impl Job {
 
             if do_next {
                 core.jobtable_check_status();
+                core.error_checked = false;
                 let (pids, exclamation, time) = pipeline.exec(core, pgid);
-                let waitstatuses = core.wait_pipeline(pids.clone(), exclamation, time);
+                let waitstatuses = core.wait_pipeline(pids.clone(), &pipeline.statuses,
+                                                      exclamation, time);
 
                 Self::check_stop(core, &pipeline.text, &pids, &waitstatuses);
             }
-            do_next = (core.data.get_param("?") == "0") == (end == "&&");
+            do_next = core.data.exit_status().is_success() == (end == "&&");
         }
     }
 
@@ -82,6 +86,9 @@ impl Job {
             vec![self.exec_fork_bg(core, pgid)]
         };
         eprintln!("{}", &pids[0].unwrap().as_raw());
+        if let Some(Some(pid)) = pids.last() {
+            core.data.set_param("!", &pid.to_string());
+        }
         let len = pids.len();
         let new_job_id = core.generate_new_job_id();
         core.job_table_priority.insert(0, new_job_id);
@@ -130,7 +137,7 @@ impl Job {
     fn eat_pipeline(feeder: &mut Feeder, ans: &mut Job, core: &mut ShellCore) -> bool {
         match Pipeline::parse(feeder, core){
             Some(pipeline) => {
-                ans.text += &pipeline.text.clone();
+                ans.text += &pipeline.text;
                 ans.pipelines.push(pipeline);
                 true
             },
@@ -154,14 +161,9 @@ impl Job {
         }
 
         while Self::eat_and_or(feeder, &mut ans, core) { 
-            loop {
-                while Self::eat_blank_line(feeder, &mut ans, core) {} 
-                if Self::eat_pipeline(feeder, &mut ans, core) {
-                    break;  
-                }
-                if feeder.len() != 0 || ! feeder.feed_additional_line(core) {
-                    return None;
-                }
+            if ! command::eat_blank_lines(feeder, core, &mut ans.text)
+            || ! Self::eat_pipeline(feeder, &mut ans, core) {
+                return None;
             }
         }
 
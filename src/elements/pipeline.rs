@@ -1,14 +1,17 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{error_message, Feeder, ShellCore};
+use crate::{Feeder, ShellCore};
+use crate::core::data::ExitStatus;
 use super::command;
 use super::command::Command;
-use super::Pipe;
+use super::{io, Pipe};
+use super::io::RedirectGuard;
 use nix::time;
 use nix::sys::resource;
 use nix::time::ClockId;
 use nix::unistd::Pid;
+use std::os::unix::prelude::RawFd;
 use std::sync::atomic::Ordering::Relaxed;
 
 #[derive(Debug, Clone)]
@@ -16,15 +19,17 @@ pub struct Pipeline {
     pub commands: Vec<Box<dyn Command>>,
     pub pipes: Vec<Pipe>,
     pub text: String,
+    pub statuses: Vec<Option<ExitStatus>>,
     exclamation: bool,
     pub time: bool,
+    posix_time: bool,
 }
 
 impl Pipeline {
     pub fn exec(&mut self, core: &mut ShellCore, pgid: Pid)
            -> (Vec<Option<Pid>>, bool, bool) {
         if core.sigint.load(Relaxed) { //以下4行追加
-            core.data.set_param("?", "130");
+            core.data.set_exit_status(130);
             return (vec![], false, false);
         }
 
@@ -39,23 +44,50 @@ impl Pipeline {
 
         self.set_time(core);
 
+        self.statuses.clear();
         for (i, p) in self.pipes.iter_mut().enumerate() {
             p.set(prev, pgid);
             pids.push(self.commands[i].exec(core, p));
-            if i == 0 && pgid.as_raw() == 0 { // 最初のexecが終わったら、pgidにコマンドのPIDを記録
-                pgid = pids[0].expect(&error_message::internal_str("unforked in pipeline"));
+            if pgid.as_raw() == 0 { // 最初のexecが終わったら、pgidにコマンドのPIDを記録
+                pgid = pids[i].unwrap_or(pgid);
             }
+            self.statuses.push(Self::status_of(core, pids[i]));
             prev = p.recv;
             core.word_eval_error = false;
         }
 
-        pids.push(
-            self.commands[self.pipes.len()].exec(core, &mut Pipe::end(prev, pgid))
-        );
+        let last = &mut self.commands[self.pipes.len()];
+        let pid = match prev >= 0 && Self::lastpipe(core) {
+            true  => Self::exec_lastpipe(last, core, prev, pgid),
+            false => last.exec(core, &mut Pipe::end(prev, pgid)),
+        };
+        pids.push(pid);
+        self.statuses.push(Self::status_of(core, pid));
 
         (pids, self.exclamation, self.time)
     }
 
+    fn lastpipe(core: &mut ShellCore) -> bool {
+        core.shopts.query("lastpipe") && ! core.data.flags.contains('i')
+    }
+
+    /* the last command runs in this process with the pipe connected to its stdin */
+    fn exec_lastpipe(command: &mut Box<dyn Command>, core: &mut ShellCore,
+                     prev: RawFd, pgid: Pid) -> Option<Pid> {
+        let mut guard = RedirectGuard::new();
+        guard.save(0);
+        io::replace(prev, 0);
+        command.exec(core, &mut Pipe::end(-1, pgid))
+    }
+
+    /* a command that ran in this process has already set $? */
+    fn status_of(core: &mut ShellCore, pid: Option<Pid>) -> Option<ExitStatus> {
+        match pid {
+            Some(_) => None,
+            None    => Some(core.data.exit_status()),
+        }
+    }
+
     fn set_time(&mut self, core: &mut ShellCore) {
         if ! self.time {
             return;
@@ -67,15 +99,18 @@ impl Pipeline {
         core.user_time = self_usage.user_time() + children_usage.user_time();
         core.sys_time = self_usage.system_time() + children_usage.system_time();
         core.real_time = time::clock_gettime(ClockId::CLOCK_MONOTONIC).unwrap();
+        core.posix_time = self.posix_time;
     }
 
     pub fn new() -> Pipeline {
         Pipeline {
             text: String::new(),
+            statuses: vec![],
             commands: vec![],
             pipes: vec![],
             exclamation: false,
             time: false,
+            posix_time: false,
         }
     }
 
@@ -92,14 +127,21 @@ impl Pipeline {
     }
 
     fn eat_time(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        match feeder.starts_with("time") {
-            true  => ans.text += &feeder.consume(4),
-            false => return false,
+        if ! feeder.starts_with_word("time") {
+            return false;
         }
 
+        ans.text += &feeder.consume(4);
         ans.time = true;
         let blank_len = feeder.scanner_blank(core);
         ans.text += &feeder.consume(blank_len);
+
+        if feeder.starts_with_word("-p") {
+            ans.text += &feeder.consume(2);
+            ans.posix_time = true;
+            let blank_len = feeder.scanner_blank(core);
+            ans.text += &feeder.consume(blank_len);
+        }
         true
     }
 
@@ -118,7 +160,7 @@ impl Pipeline {
 
     fn eat_pipe(feeder: &mut Feeder, ans: &mut Pipeline, core: &mut ShellCore) -> bool {
         if let Some(p) = Pipe::parse(feeder, core) {
-            ans.text += &p.text.clone();
+            ans.text += &p.text;
             ans.pipes.push(p);
             true
         }else{
@@ -126,18 +168,6 @@ impl Pipeline {
         }
     }
 
-    fn eat_blank_and_comment(feeder: &mut Feeder, ans: &mut Pipeline, core: &mut ShellCore) {
-        loop {
-            let blank_len = feeder.scanner_multiline_blank(core);
-            ans.text += &feeder.consume(blank_len);             //空白、空行を削除
-            let comment_len = feeder.scanner_comment();
-            ans.text += &feeder.consume(comment_len);             //コメントを削除
-            if blank_len + comment_len == 0 { //空白、空行、コメントがなければ出る
-                break;
-            }
-        }
-    }
-
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Pipeline> {
         let mut ans = Pipeline::new();
 
@@ -152,14 +182,9 @@ impl Pipeline {
         }
 
         while Self::eat_pipe(feeder, &mut ans, core){
-            loop {
-                Self::eat_blank_and_comment(feeder, &mut ans, core);
-                if Self::eat_command(feeder, &mut ans, core) {
-                    break;
-                }
-                if feeder.len() != 0 || ! feeder.feed_additional_line(core) {
-                    return None;
-                }
+            if ! command::eat_blank_lines(feeder, core, &mut ans.text)
+            || ! Self::eat_command(feeder, &mut ans, core) {
+                return None;
             }
         }
 
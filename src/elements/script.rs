@@ -3,6 +3,8 @@
 
 use super::job::Job;
 use crate::{error_message, Feeder, ShellCore};
+use crate::feeder::ParseError;
+use std::sync::atomic::Ordering::Relaxed;
 
 enum Status{
     UnexpectedSymbol(String),
@@ -39,7 +41,7 @@ impl Script {
 
     fn eat_job(feeder: &mut Feeder, core: &mut ShellCore, ans: &mut Script) -> bool {
         if let Some(job) = Job::parse(feeder, core){
-            ans.text += &job.text.clone();
+            ans.text += &job.text;
             ans.jobs.push(job);
             true
         }else{
@@ -74,9 +76,12 @@ impl Script {
         }
 
         if feeder.len() > 0 {
-            let remaining = feeder.consume(feeder.len());
-            let first_token = remaining.split(" ").nth(0).unwrap().to_string();
-            return Status::UnexpectedSymbol(first_token);
+            let len = feeder.scanner_token();
+            let token = match feeder.consume(len).as_str() {
+                "\n" => "newline".to_string(),
+                t => t.to_string(),
+            };
+            return Status::UnexpectedSymbol(token);
         }
 
         Status::NeedMoreLine
@@ -90,8 +95,18 @@ impl Script {
         core.data.alias_memo.clear();
     }
 
+    /* an inner script leaves the error in the feeder for the outer ones */
+    fn error(feeder: &mut Feeder, core: &mut ShellCore, e: ParseError) -> Result<Script, ParseError> {
+        feeder.consume(feeder.len());
+        core.data.alias_memo.clear();
+        if feeder.nest.len() > 1 {
+            feeder.error = Some(e.clone());
+        }
+        Err(e)
+    }
+
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore,
-                 permit_empty: bool) -> Option<Script> {
+                 permit_empty: bool) -> Result<Script, ParseError> {
         let mut ans = Self::new();
         
         if permit_empty {
@@ -103,26 +118,30 @@ impl Script {
             while Self::eat_job(feeder, core, &mut ans) 
                && Self::eat_job_end(feeder, &mut ans) {}
 
+            if let Some(e) = feeder.error.take() {
+                return Self::error(feeder, core, e);
+            }
+            if core.sigint.load(Relaxed) {
+                return Self::error(feeder, core, ParseError::Interrupt);
+            }
+
             match ans.check_nest(feeder){
                 Status::NormalEnd => {
                     ans.unalias(core);
-                    return Some(ans)
+                    return Ok(ans)
                 },
                 Status::UnexpectedSymbol(s) => {
-                    eprintln!("Unexpected token: {}", s);
-                    core.data.set_param("?", "2");
-                    break;
+                    core.data.set_exit_status(2);
+                    let e = ParseError::UnexpectedSymbol(s, feeder.lineno());
+                    return Self::error(feeder, core, e);
                 },
                 Status::NeedMoreLine => {
                     if ! feeder.feed_additional_line(core) {
-                        break;
+                        let e = feeder.error.take().unwrap_or(ParseError::Interrupt);
+                        return Self::error(feeder, core, e);
                     }
                 },
             }
         }
-
-        feeder.consume(feeder.len());
-        core.data.alias_memo.clear();
-        return None;
     }
 }
@@ -39,6 +39,9 @@ impl Subscript {
 
         while ! feeder.starts_with("]") {
             let len = feeder.scanner_inner_subscript(core);
+            if len == 0 && ! feeder.feed_additional_line(core) {
+                return None;
+            }
             ans.text += &feeder.consume(len);
         }
 
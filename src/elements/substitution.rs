@@ -2,6 +2,8 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
+use crate::core::data;
 use crate::core::data::Value;
 use super::array::Array;
 use super::word::Word;
@@ -14,26 +16,12 @@ pub struct Substitution {
 }
 
 impl Substitution {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Value {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<Value, ExecError> {
         match &self.value {
-            Value::None      => Value::EvaluatedSingle("".to_string()),
-            Value::Single(v) => Self::eval_as_value(&v, core),
-            Value::Array(a)  => Self::eval_as_array(&mut a.clone(), core),
-            _                => Value::None,
-        }
-    }
-
-    fn eval_as_value(w: &Word, core: &mut ShellCore) -> Value {
-        match w.eval_as_value(core) {
-            Some(s) => Value::EvaluatedSingle(s),
-            None    => Value::None,
-        }
-    }
-
-    fn eval_as_array(a: &mut Array, core: &mut ShellCore) -> Value {
-        match a.eval(core) {
-            Some(values) => Value::EvaluatedArray(values),
-            None         => Value::None,
+            Value::None      => Ok(Value::EvaluatedSingle("".to_string())),
+            Value::Single(v) => Ok(Value::EvaluatedSingle(v.eval_as_value(core)?)),
+            Value::Array(a)  => Ok(Value::EvaluatedArray(data::to_array_map(&a.clone().eval(core)?))),
+            _                => Ok(Value::None),
         }
     }
 
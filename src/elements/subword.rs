@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 pub mod simple;
+mod ansi_c_quoted;
 pub mod single_quoted;
 mod braced_param;
 mod command;
@@ -13,6 +14,8 @@ mod varname;
 mod arithmetic;
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
+use self::ansi_c_quoted::AnsiCQuoted;
 use self::arithmetic::Arithmetic;
 use self::simple::SimpleSubword;
 use self::braced_param::BracedParam;
@@ -38,19 +41,14 @@ impl Clone for Box::<dyn Subword> {
     }
 }
 
+/* backslashes are not escapes here since only expansion results are split */
 fn split_str(s: &str) -> Vec<&str> {
-    let mut esc = false;
     let mut from = 0;
     let mut pos = 0;
     let mut ans = vec![];
 
     for c in s.chars() {
         pos += c.len_utf8();
-        if esc || c == '\\' {
-            esc = ! esc;
-            continue;
-        }
-
         if c == ' ' || c == '\t' || c == '\n' {
             ans.push(&s[from..pos-1]);
             from = pos;
@@ -65,7 +63,7 @@ pub trait Subword {
     fn get_text(&self) -> &str;
     fn set_text(&mut self, _: &str) {}
     fn boxed_clone(&self) -> Box<dyn Subword>;
-    fn substitute(&mut self, _: &mut ShellCore) -> bool {true}
+    fn substitute(&mut self, _: &mut ShellCore) -> Result<(), ExecError> {Ok(())}
     fn substitute_replace(&self) -> Vec<Box<dyn Subword>> {vec![]}
 
     fn split(&self, _core: &mut ShellCore) -> Vec<Box<dyn Subword>>{
@@ -75,6 +73,7 @@ pub trait Subword {
     }
 
     fn make_glob_string(&mut self) -> String {self.get_text().to_string()}
+    fn make_regex_string(&mut self) -> String {self.get_text().to_string()}
 
     fn make_unquoted_string(&mut self) -> Option<String> {
         match self.get_text() {
@@ -88,40 +87,13 @@ pub trait Subword {
     fn get_child_subwords(&self) -> Option<&Vec<Box<dyn Subword>>> { None }
 }
 
-fn replace_history_expansion(feeder: &mut Feeder, core: &mut ShellCore) -> bool {
-    let len = feeder.scanner_history_expansion(core);
-    if len == 0 {
-        return false;
-    }
-
-    let history_len = core.history.len();
-    if history_len < 2 {
-        feeder.replace(len, "");
-        return true;
-    }
-
-    let mut his = String::new();
-    for h in &core.history[1..] {
-        let last = h.split(" ").last().unwrap();
-
-        if ! last.starts_with("!$") {
-            his = last.to_string();
-            break;
-        }
-    }
-
-    feeder.replace(len, &his);
-    true
-}
-
 pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Subword>> {
-    if replace_history_expansion(feeder, core) {
-        return parse(feeder, core);
-    }
-
     if let Some(a) = BracedParam::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = Arithmetic::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = Arithmetic::parse_legacy(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = CommandSubstitution::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = CommandSubstitution::parse_backquoted(feeder, core, false){ Some(Box::new(a)) }
+    else if let Some(a) = AnsiCQuoted::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = SingleQuoted::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = DoubleQuoted::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = ExtGlob::parse(feeder, core){ Some(Box::new(a)) }
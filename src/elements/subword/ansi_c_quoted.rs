@@ -0,0 +1,123 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::utils::bytes;
+use super::Subword;
+
+#[derive(Debug, Clone)]
+pub struct AnsiCQuoted {
+    pub text: String,
+    value: String,
+}
+
+impl Subword for AnsiCQuoted {
+    fn get_text(&self) -> &str {&self.text}
+    fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
+
+    fn make_unquoted_string(&mut self) -> Option<String> {
+        Some( self.value.clone() )
+    }
+
+    fn make_glob_string(&mut self) -> String {
+        self.value.replace("\\", "\\\\")
+            .replace("*", "\\*")
+            .replace("?", "\\?")
+            .replace("[", "\\[")
+            .replace("]", "\\]")
+            .replace("(", "\\(")
+    }
+
+    fn make_regex_string(&mut self) -> String {
+        regex::escape(&self.make_unquoted_string().unwrap_or_default())
+    }
+
+    fn no_split(&self) -> bool {true}
+}
+
+/* reads up to max digits of the radix and returns the number and the digit count */
+fn read_number(chars: &[char], radix: u32, max: usize) -> (u32, usize) {
+    let mut num = 0;
+    let mut len = 0;
+    while len < max && len < chars.len() {
+        match chars[len].to_digit(radix) {
+            Some(d) => num = num * radix + d,
+            None    => break,
+        }
+        len += 1;
+    }
+    (num, len)
+}
+
+fn push_char(ans: &mut Vec<u8>, c: char) {
+    match bytes::raw_byte(c) {
+        Some(b) => ans.push(b),
+        None    => ans.extend_from_slice(c.to_string().as_bytes()),
+    }
+}
+
+/* \xHH and \nnn give bytes, so the result is decoded after all escapes are replaced */
+fn decode(body: &str) -> String {
+    let chars: Vec<char> = body.chars().collect();
+    let mut ans = vec![];
+    let mut pos = 0;
+    while pos < chars.len() {
+        if chars[pos] != '\\' || pos + 1 == chars.len() {
+            push_char(&mut ans, chars[pos]);
+            pos += 1;
+            continue;
+        }
+
+        let rest = &chars[pos+2..];
+        let (byte, ch, len) = match chars[pos+1] {
+            'a' => (Some(0x07), None, 0),
+            'b' => (Some(0x08), None, 0),
+            'e' | 'E' => (Some(0x1b), None, 0),
+            'f' => (Some(0x0c), None, 0),
+            'n' => (Some(b'\n'), None, 0),
+            'r' => (Some(b'\r'), None, 0),
+            't' => (Some(b'\t'), None, 0),
+            'v' => (Some(0x0b), None, 0),
+            c @ ('\\' | '\'' | '"' | '?') => (Some(c as u8), None, 0),
+            '0'..='7' => {
+                let (n, len) = read_number(&chars[pos+1..], 8, 3);
+                (Some((n % 256) as u8), None, len - 1)
+            },
+            'x' => match read_number(rest, 16, 2) {
+                (_, 0)   => (None, None, 0),
+                (n, len) => (Some(n as u8), None, len),
+            },
+            c @ ('u' | 'U') => match read_number(rest, 16, if c == 'u' {4} else {8}) {
+                (_, 0)   => (None, None, 0),
+                (n, len) => (None, char::from_u32(n), len),
+            },
+            'c' if ! rest.is_empty() => (Some(rest[0].to_ascii_uppercase() as u8 ^ 0x40), None, 1),
+            _ => (None, None, 0),
+        };
+
+        match (byte, ch) {
+            (Some(0), _) => break, //the string ends at a null byte as in bash
+            (Some(b), _) => ans.push(b),
+            (None, Some(c)) => ans.extend_from_slice(c.to_string().as_bytes()),
+            (None, None) => {
+                push_char(&mut ans, '\\');
+                push_char(&mut ans, chars[pos+1]);
+            },
+        }
+        pos += 2 + len;
+    }
+    bytes::decode(&ans)
+}
+
+impl AnsiCQuoted {
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        match feeder.scanner_ansi_c_quoted_subword(core) {
+            0 => None,
+            n => {
+                let text = feeder.consume(n);
+                let value = decode(&text[2..text.len()-1]);
+                Some(AnsiCQuoted{ text, value })
+            },
+        }
+    }
+}
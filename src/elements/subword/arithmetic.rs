@@ -1,7 +1,8 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{ShellCore, Feeder};
+use crate::{error_message, ShellCore, Feeder};
+use crate::error_message::ExecError;
 use crate::elements::command::arithmetic::ArithmeticCommand;
 use crate::elements::subword::Subword;
 
@@ -9,18 +10,20 @@ use crate::elements::subword::Subword;
 pub struct Arithmetic {
     pub text: String,
     com: ArithmeticCommand,
+    legacy: bool,
 }
 
 impl Subword for Arithmetic {
     fn get_text(&self) -> &str { &self.text.as_ref() }
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
-    fn substitute(&mut self, core: &mut ShellCore) -> bool {
-        if let Some(s) = self.com.eval(core) {
-            self.text = s;
-            return true;
+    fn substitute(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
+        if self.legacy && core.shopts.query("legacy_arith_warn") {
+            error_message::print(&format!("{}: deprecated arithmetic expansion, use $((...))", &self.text), core, true);
         }
-        false
+
+        self.text = self.com.eval(core)?;
+        Ok(())
     }
 }
 
@@ -34,9 +37,54 @@ impl Arithmetic {
 
         if let Some(a) = ArithmeticCommand::parse(feeder, core) {
             feeder.pop_backup();
-            return Some(Arithmetic{ text: dl + &a.text.clone(), com: a});
+            return Some(Arithmetic{ text: dl + &a.text.clone(), com: a, legacy: false});
         }
         feeder.rewind();
         None
     }
+
+    /* $[...]: the old form of $((...)), which is parsed as ((...)) */
+    pub fn parse_legacy(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        if ! feeder.starts_with("$[") {
+            return None;
+        }
+        feeder.set_backup();
+        let mut text = feeder.consume(2);
+        let mut inner = String::new();
+        let mut nest = 0;
+
+        loop {
+            let len = feeder.scanner_inner_legacy_arithmetic(core);
+            inner += &feeder.consume(len);
+
+            if feeder.starts_with("[") {
+                nest += 1;
+            }else if feeder.starts_with("]") {
+                if nest == 0 {
+                    break;
+                }
+                nest -= 1;
+            }else if ! feeder.feed_additional_line(core) {
+                feeder.rewind();
+                return None;
+            }else {
+                continue;
+            }
+            inner += &feeder.consume(1);
+        }
+
+        let mut f = Feeder::new(&format!("(({}))", &inner));
+        match ArithmeticCommand::parse(&mut f, core) {
+            Some(a) if f.len() == 0 => {
+                feeder.pop_backup();
+                text += &inner;
+                text += &feeder.consume(1);
+                Some(Arithmetic{ text, com: a, legacy: true })
+            },
+            _ => {
+                feeder.rewind();
+                None
+            },
+        }
+    }
 }
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
 use crate::elements::subword;
 use crate::elements::subword::Subword;
 use crate::elements::subscript::Subscript;
@@ -43,16 +44,18 @@ impl Subword for BracedParam {
     fn get_text(&self) -> &str { &self.text.as_ref() }
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
-    fn substitute(&mut self, core: &mut ShellCore) -> bool {
+    fn substitute(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
         if self.name.len() == 0 || ! is_param(&self.name) {
-            eprintln!("sush: {}: bad substitution", &self.text);
-            return false;
+            return Err(ExecError::BadSubstitution(self.text.clone()));
         }
         if self.unknown.len() > 0 
         && ! self.unknown.starts_with("-")
         && ! self.unknown.starts_with(",") {
-            eprintln!("sush: {}: bad substitution", &self.text);
-            return false;
+            return Err(ExecError::BadSubstitution(self.text.clone()));
+        }
+
+        if core.data.flags.contains('u') && self.default_symbol.is_none() {
+            self.check_set(core)?;
         }
 
         if let Some(sub) = self.subscript.as_mut() {
@@ -71,7 +74,7 @@ impl Subword for BracedParam {
             _ => {},
         }
 
-        true
+        Ok(())
     }
 
     fn set_text(&mut self, text: &str) { self.text = text.to_string(); }
@@ -96,45 +99,73 @@ impl BracedParam {
         }
     }
 
-    fn replace_to_default(&mut self, core: &mut ShellCore) -> bool {
+    fn check_set(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
+        let pos = self.subscript.as_mut().and_then(|s| s.eval());
+        if core.data.has_value(&self.name, pos.as_deref()) {
+            return Ok(());
+        }
+
+        match pos {
+            Some(p) => Err(ExecError::UnboundVariable(format!("{}[{}]", &self.name, p))),
+            None    => Err(ExecError::UnboundVariable(self.name.clone())),
+        }
+    }
+
+    fn replace_to_default(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
         let symbol = match self.default_symbol.as_ref() {
             Some(s) => s,
-            None    => return true,
+            None    => return Ok(()),
         };
 
         let word = match self.default_value.as_ref() {
-            Some(w) => match w.tilde_and_dollar_expansion(core) {
-                            Some(w2) => w2,
-                            None     => return false,
-                        },
-            None    => return false,
+            Some(w) => w.tilde_and_dollar_expansion(core)?,
+            None    => return Err(ExecError::BadSubstitution(self.text.clone())),
         };
 
         let value: String = word.subwords.iter().map(|s| s.get_text()).collect();
 
         if symbol == ":-" {
             self.default_value = Some(word);
-            return true;
+            return Ok(());
         }
         if symbol == ":=" {
             core.data.set_param(&self.name, &value);
             self.default_value = None;
             self.text = value;
-            return true;
+            return Ok(());
         }
         if symbol == ":?" {
-            eprintln!("sush: {}: {}", &self.name, &value);
-            return false;
+            return Err(ExecError::Other(format!("{}: {}", &self.name, &value)));
         }
         if symbol == ":+" {
             self.default_value = match self.text.as_str() {
                 "" => None,
                 _  => Some(word),
             };
-            return true;
+            return Ok(());
         }
 
-        return false;
+        Err(ExecError::BadSubstitution(self.text.clone()))
+    }
+
+    /* in double quotes, quotes in the default value are literal except $'...' under extquote */
+    pub fn literalize_quotes(&mut self, core: &mut ShellCore) {
+        let extquote = core.shopts.query("extquote");
+        let word = match self.default_value.as_mut() {
+            Some(w) => w,
+            None    => return,
+        };
+
+        for sw in word.subwords.iter_mut() {
+            let text = sw.get_text();
+            let literal = text.starts_with("'")
+                || (text.starts_with("$'") && ! extquote)
+                || (text.starts_with("\\") && ! ["\\$", "\\\\", "\\\"", "\\`"].contains(&text));
+
+            if literal {
+                *sw = Box::new(SimpleSubword{ text: text.to_string() });
+            }
+        }
     }
 
     fn eat_subscript(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
@@ -151,7 +182,7 @@ impl BracedParam {
         let blank = feeder.consume(len);
         let sw = Box::new(SimpleSubword{ text: blank.clone() });
         word.subwords.push(sw);
-        ans.text += &blank.clone();
+        ans.text += &blank;
     }
 
     fn eat_default_value(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
@@ -176,7 +207,9 @@ impl BracedParam {
 
             if feeder.starts_with("\n") {
                 Self::push_default_subword(1, feeder, ans, &mut word);
-                feeder.feed_additional_line(core);
+                if ! feeder.feed_additional_line(core) {
+                    return false;
+                }
             }
 
             let num = feeder.scanner_blank(core);
@@ -208,9 +241,9 @@ impl BracedParam {
         feeder.starts_with("}")
     }
 
-    fn eat_unknown(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) {
-        if feeder.len() == 0 {
-            feeder.feed_additional_line(core);
+    fn eat_unknown(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        if feeder.len() == 0 && ! feeder.feed_additional_line(core) {
+            return false;
         }
 
         let unknown = match feeder.starts_with("\\}") {
@@ -218,9 +251,9 @@ impl BracedParam {
             false => feeder.consume(1),
         };
 
-        ans.unknown += &unknown.clone();
+        ans.unknown += &unknown;
         ans.text += &unknown;
-        return;
+        true
         /*
 
         let len = feeder.scanner_unknown_in_param_brace();
@@ -229,7 +262,7 @@ impl BracedParam {
         }
 
         let unknown = feeder.consume(len);
-        ans.unknown += &unknown.clone();
+        ans.unknown += &unknown;
         ans.text += &unknown;
         true
         */
@@ -248,7 +281,9 @@ impl BracedParam {
         }
 
         while ! feeder.starts_with("}") {
-            Self::eat_unknown(feeder, &mut ans, core);
+            if ! Self::eat_unknown(feeder, &mut ans, core) {
+                return None;
+            }
         }
 
         ans.text += &feeder.consume(1);
@@ -1,7 +1,9 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{ShellCore, Feeder};
+use crate::{error_message, ShellCore, Feeder};
+use crate::error_message::ExecError;
+use crate::utils::bytes;
 use crate::elements::Pipe;
 use crate::elements::command::Command;
 use crate::elements::command::paren::ParenCommand;
@@ -23,28 +25,36 @@ impl Subword for CommandSubstitution {
     fn get_text(&self) -> &str {&self.text.as_ref()}
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
-    fn substitute(&mut self, core: &mut ShellCore) -> bool {
+    fn substitute(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
         let mut pipe = Pipe::new("|".to_string());
         pipe.set(-1, unistd::getpgrp());
+        core.comsub_level += 1;
         let pid = self.command.exec(core, &mut pipe);
+        core.comsub_level -= 1;
         let result = self.read(pipe.recv, core);
-        core.wait_pipeline(vec![pid], false, false);
+
+        /* the status is checked by the command that has the substitution */
+        let (suspend_e_option, error_checked) = (core.suspend_e_option, core.error_checked);
+        core.suspend_e_option = true;
+        core.wait_pipeline(vec![pid], &[], false, false);
+        (core.suspend_e_option, core.error_checked) = (suspend_e_option, error_checked);
         result
     }
 }
 
 impl CommandSubstitution {
-    fn set_line(&mut self, line: Result<String, Error>) -> bool {
+    fn set_line(&mut self, line: Result<Vec<u8>, Error>, core: &mut ShellCore) -> Result<(), ExecError> {
         match line {
-            Ok(ln) => {
-                self.text.push_str(&ln);
+            Ok(mut ln) => {
+                if ln.contains(&0) {
+                    error_message::print("warning: command substitution: ignored null byte in input", core, true);
+                    ln.retain(|b| *b != 0);
+                }
+                self.text.push_str(&bytes::decode(&ln));
                 self.text.push('\n');
-                true
-            },
-            Err(e) => {
-                eprintln!("sush: {}", &e);
-                false
+                Ok(())
             },
+            Err(e) => Err(ExecError::Other(e.to_string())),
         }
     }
 
@@ -55,20 +65,18 @@ impl CommandSubstitution {
         core.sigint.load(Relaxed) 
     }
 
-    fn read(&mut self, fd: RawFd, core: &mut ShellCore) -> bool {
+    fn read(&mut self, fd: RawFd, core: &mut ShellCore) -> Result<(), ExecError> {
         let f = unsafe { File::from_raw_fd(fd) };
         let reader = BufReader::new(f);
         self.text.clear();
-        for (i, line) in reader.lines().enumerate() {
+        for (i, line) in reader.split(b'\n').enumerate() {
             if self.interrupted(i, core) {
-                break;
-            }
-            if ! self.set_line(line) {
-                return false;
+                return Err(ExecError::Interrupted);
             }
+            self.set_line(line, core)?;
         }
         self.text.pop();
-        true
+        Ok(())
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
@@ -84,4 +92,41 @@ impl CommandSubstitution {
             None
         }
     }
+
+    /* `...`: the backslashes before $, ` and \ (and " in double quotes)
+     * are removed before the inner text is parsed as $(...) */
+    pub fn parse_backquoted(feeder: &mut Feeder, core: &mut ShellCore,
+                            in_double_quotes: bool) -> Option<Self> {
+        let len = feeder.scanner_backquoted_subword(core);
+        if len == 0 {
+            return None;
+        }
+        feeder.set_backup();
+        let text = feeder.consume(len);
+
+        let mut inner = String::new();
+        let escapable = |n: &char| "$`\\".contains(*n) || (in_double_quotes && *n == '"');
+        let mut chars = text[1..len-1].chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                if let Some(n) = chars.next_if(escapable) {
+                    inner.push(n);
+                    continue;
+                }
+            }
+            inner.push(c);
+        }
+
+        let mut f = Feeder::new(&format!("({}\n)", inner));
+        match ParenCommand::parse(&mut f, core, true) {
+            Some(pc) if f.len() == 0 => {
+                feeder.pop_backup();
+                Some(CommandSubstitution {text, command: pc} )
+            },
+            _ => {
+                feeder.rewind();
+                None
+            },
+        }
+    }
 }
@@ -2,6 +2,8 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{error_message, ShellCore, Feeder};
+use crate::core::data::Value;
+use crate::error_message::ExecError;
 use crate::elements::word::{Word, substitution};
 use crate::elements::subword::CommandSubstitution;
 use super::{BracedParam, EscapedChar, SimpleSubword, Parameter, Subword, VarName};
@@ -11,21 +13,20 @@ pub struct DoubleQuoted {
     text: String,
     subwords: Vec<Box<dyn Subword>>,
     split_points: Vec<usize>,
+    no_position_params: bool,
 }
 
 impl Subword for DoubleQuoted {
     fn get_text(&self) -> &str {&self.text.as_ref()}
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
-    fn substitute(&mut self, core: &mut ShellCore) -> bool {
+    fn substitute(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
         let mut word = Word::new();
         word.subwords = self.replace_position_params(core);
-        if ! substitution::eval(&mut word, core) {
-            return false;
-        }
+        substitution::eval(&mut word, core)?;
         self.subwords = word.subwords;
         self.text = self.subwords.iter().map(|s| s.get_text()).collect();
-        true
+        Ok(())
     }
 
     fn make_glob_string(&mut self) -> String {
@@ -33,10 +34,19 @@ impl Subword for DoubleQuoted {
                         .replace("*", "\\*")
                         .replace("?", "\\?")
                         .replace("[", "\\[")
-                        .replace("]", "\\]");
+                        .replace("]", "\\]")
+                        .replace("(", "\\(");
+    }
+
+    fn make_regex_string(&mut self) -> String {
+        regex::escape(&self.make_unquoted_string().unwrap_or_default())
     }
 
     fn make_unquoted_string(&mut self) -> Option<String> {
+        if self.no_position_params && self.text.is_empty() {
+            return None;
+        }
+
         Some(self.subwords.iter_mut()
             .map(|s| s.make_unquoted_string())
             .filter(|s| *s != None)
@@ -49,7 +59,7 @@ impl Subword for DoubleQuoted {
         let mut ans = vec![];
 
         let mut points = self.split_points.clone();
-        points[self.split_points.len()-1] = self.split_points.len();
+        points.push(self.subwords.len());
 
         let mut last = 0;
         for p in points {
@@ -62,7 +72,7 @@ impl Subword for DoubleQuoted {
         ans
     }
 
-    fn no_split(&self) -> bool {self.split_points.len() < 2}
+    fn no_split(&self) -> bool {self.split_points.is_empty()}
 }
 
 impl DoubleQuoted {
@@ -71,18 +81,40 @@ impl DoubleQuoted {
             text: String::new(),
             subwords: vec![],
             split_points: vec![],
+            no_position_params: false,
         }
     }
 
+    fn join_by_ifs(params: &[String], core: &mut ShellCore) -> String {
+        let sep = match core.data.is_set("IFS") {
+            true  => core.data.get_param("IFS").chars().take(1).collect(),
+            false => " ".to_string(),
+        };
+        params.join(&sep)
+    }
+
     fn replace_position_params(&mut self, core: &mut ShellCore) -> Vec<Box<dyn Subword>> {
         let mut ans = vec![];
 
         for sw in &self.subwords {
-            if sw.get_text() == "$@" {
-                for pp in core.data.get_position_params() {
+            let text = sw.get_text();
+            let elems = match text == "$@" || text == "${@}" {
+                true  => Some(core.data.get_position_params()),
+                false => Self::array_elements(text, core),
+            };
+
+            if let Some(params) = elems {
+                self.no_position_params |= params.is_empty();
+                for (i, pp) in params.into_iter().enumerate() {
+                    if i > 0 { // a word boundary between elements
+                        self.split_points.push(ans.len());
+                    }
                     ans.push(Box::new( SimpleSubword {text: pp}) as Box<dyn Subword>);
-                    self.split_points.push(ans.len());
                 }
+            }else if text == "$*" || text == "${*}" {
+                let params = core.data.get_position_params();
+                let text = Self::join_by_ifs(&params, core);
+                ans.push(Box::new( SimpleSubword {text}) as Box<dyn Subword>);
             }else{
                 ans.push(sw.boxed_clone());
             }
@@ -90,6 +122,21 @@ impl DoubleQuoted {
         ans
     }
 
+    /* the elements of ${name[@]} become separate words like $@ */
+    fn array_elements(text: &str, core: &mut ShellCore) -> Option<Vec<String>> {
+        let name = text.strip_prefix("${")?.strip_suffix("[@]}")?;
+        if name.is_empty() || ! name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+
+        match core.data.get_value(name) {
+            Some(Value::EvaluatedArray(a)) => Some(a.into_values().collect()),
+            Some(Value::EvaluatedSingle(v)) => Some(vec![v]),
+            None if ! core.data.flags.contains('u') => Some(vec![]),
+            _ => None,
+        }
+    }
+
     fn set_simple_subword(feeder: &mut Feeder, ans: &mut Self, len: usize) -> bool {
         if len == 0 {
             return false;
@@ -102,7 +149,8 @@ impl DoubleQuoted {
     }
 
     fn eat_braced_param(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        if let Some(a) = BracedParam::parse(feeder, core){
+        if let Some(mut a) = BracedParam::parse(feeder, core){
+            a.literalize_quotes(core);
             ans.text += a.get_text();
             ans.subwords.push(Box::new(a));
             true
@@ -112,7 +160,12 @@ impl DoubleQuoted {
     }
 
     fn eat_command_substitution(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        if let Some(a) = CommandSubstitution::parse(feeder, core){
+        let sub = match CommandSubstitution::parse(feeder, core) {
+            None => CommandSubstitution::parse_backquoted(feeder, core, true),
+            a    => a,
+        };
+
+        if let Some(a) = sub {
             ans.text += a.get_text();
             ans.subwords.push(Box::new(a));
             true
@@ -139,7 +192,7 @@ impl DoubleQuoted {
     }
 
     fn eat_escaped_char(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        if feeder.starts_with("\\$") || feeder.starts_with("\\\\") {
+        if ["\\$", "\\\\", "\\\"", "\\`"].iter().any(|e| feeder.starts_with(e)) {
             let txt = feeder.consume(2);
             ans.text += &txt;
             ans.subwords.push(Box::new(EscapedChar{ text: txt }));
@@ -162,16 +215,21 @@ impl DoubleQuoted {
     }
 
     fn eat_other(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        let len = feeder.scanner_double_quoted_subword(core);
+        let len = match feeder.scanner_double_quoted_subword(core) {
+            0 if feeder.starts_with("`") => 1, //unparsable backquote
+            n => n,
+        };
         Self::set_simple_subword(feeder, ans, len)
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<DoubleQuoted> {
-        if ! feeder.starts_with("\"") {
+        let mut ans = Self::new();
+        if feeder.starts_with("$\"") { //locale translation is not supported
+            ans.text = feeder.consume(1);
+        }else if ! feeder.starts_with("\"") {
             return None;
         }
-        let mut ans = Self::new();
-        ans.text = feeder.consume(1);
+        ans.text += &feeder.consume(1);
 
         loop {
             while Self::eat_braced_param(feeder, &mut ans, core)
@@ -20,6 +20,12 @@ impl Subword for EscapedChar {
             _ => Some(self.text[1..].to_string()),
         }
     }
+
+    fn make_regex_string(&mut self) -> String {
+        regex::escape(&self.make_unquoted_string().unwrap_or_default())
+    }
+
+    fn no_split(&self) -> bool {true}
 }
 
 impl EscapedChar {
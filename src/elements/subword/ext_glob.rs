@@ -3,7 +3,8 @@
 
 use crate::{error_message, ShellCore, Feeder};
 use crate::elements::subword::CommandSubstitution;
-use super::{BracedParam, EscapedChar, SimpleSubword, Parameter, Subword, VarName};
+use super::{BracedParam, DoubleQuoted, EscapedChar, SimpleSubword, SingleQuoted,
+            Parameter, Subword, VarName};
 
 #[derive(Debug, Clone)]
 pub struct ExtGlob {
@@ -47,7 +48,12 @@ impl ExtGlob {
     }
 
     fn eat_command_substitution(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        if let Some(a) = CommandSubstitution::parse(feeder, core){
+        let sub = match CommandSubstitution::parse(feeder, core) {
+            None => CommandSubstitution::parse_backquoted(feeder, core, false),
+            a    => a,
+        };
+
+        if let Some(a) = sub {
             ans.text += a.get_text();
             ans.subwords.push(Box::new(a));
             true
@@ -66,6 +72,16 @@ impl ExtGlob {
         }
     }
 
+    fn eat_quoted(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        let sw: Box<dyn Subword> = if let Some(a) = SingleQuoted::parse(feeder, core) { Box::new(a) }
+        else if let Some(a) = DoubleQuoted::parse(feeder, core) { Box::new(a) }
+        else { return false };
+
+        ans.text += sw.get_text();
+        ans.subwords.push(sw);
+        true
+    }
+
     fn eat_extglob(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         if let Some(a) = Self::parse(feeder, core){
             ans.text += a.get_text();
@@ -112,7 +128,10 @@ impl ExtGlob {
     }
 
     fn eat_other(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        let len = feeder.scanner_extglob_subword(core);
+        let len = match feeder.scanner_extglob_subword(core) {
+            0 if feeder.starts_with("`") => 1, //unparsable backquote
+            n => n,
+        };
         Self::set_simple_subword(feeder, ans, len)
     }
 
@@ -129,6 +148,7 @@ impl ExtGlob {
         loop {
             while Self::eat_braced_param(feeder, &mut ans, core)
                || Self::eat_command_substitution(feeder, &mut ans, core)
+               || Self::eat_quoted(feeder, &mut ans, core)
                || Self::eat_extglob(feeder, &mut ans, core)
                || Self::eat_special_or_positional_param(feeder, &mut ans, core)
                || Self::eat_doller(feeder, &mut ans)
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
 use super::Subword;
 
 #[derive(Debug, Clone)]
@@ -13,10 +14,18 @@ impl Subword for Parameter {
     fn get_text(&self) -> &str {&self.text.as_ref()}
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
-    fn substitute(&mut self, core: &mut ShellCore) -> bool {
+    fn substitute(&mut self, core: &mut ShellCore) -> Result<(), ExecError> {
+        let name = &self.text[1..];
+        if core.data.flags.contains('u') && ! core.data.has_value(name, None) {
+            return match name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                true  => Err(ExecError::UnboundVariable(name.to_string())),
+                false => Err(ExecError::UnboundVariable(self.text.clone())),
+            };
+        }
+
         let value = core.data.get_param(&self.text[1..]);
         self.text = value.to_string();
-        true
+        Ok(())
     }
 }
 
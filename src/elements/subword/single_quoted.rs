@@ -23,6 +23,11 @@ impl Subword for SingleQuoted {
             .replace("?", "\\?")
             .replace("[", "\\[")
             .replace("]", "\\]")
+            .replace("(", "\\(")
+    }
+
+    fn make_regex_string(&mut self) -> String {
+        regex::escape(&self.make_unquoted_string().unwrap_or_default())
     }
 
     fn no_split(&self) -> bool {true}
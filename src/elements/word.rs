@@ -8,8 +8,10 @@ mod path_expansion;
 mod split;
 
 use crate::{ShellCore, Feeder};
+use crate::error_message::ExecError;
 use crate::elements::subword;
 use super::subword::Subword;
+use super::subword::simple::SimpleSubword;
 
 #[derive(Debug, Clone)]
 pub struct Word {
@@ -18,57 +20,57 @@ pub struct Word {
 }
 
 impl Word {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+    pub fn eval(&mut self, core: &mut ShellCore) -> Result<Vec<String>, ExecError> {
         let mut ws = vec![];
         for w in brace_expansion::eval(&mut self.clone()) {
-            match w.tilde_and_dollar_expansion(core) {
-                Some(w) => ws.append( &mut w.split_and_path_expansion(core) ),
-                None    => return None,
-            };
+            let w = w.tilde_and_dollar_expansion(core)?;
+            ws.append( &mut w.split_and_path_expansion(core)? );
         }
 
-        Some( Self::make_args(&mut ws) )
+        Ok( Self::make_args(&mut ws) )
     }
 
-    pub fn eval_as_value(&self, core: &mut ShellCore) -> Option<String> {
-        let mut ws = match self.tilde_and_dollar_expansion(core) {
-            Some(w) => w.split_and_path_expansion(core),
-            None    => return None,
-        };
+    pub fn eval_as_value(&self, core: &mut ShellCore) -> Result<String, ExecError> {
+        let w = self.tilde_and_dollar_expansion(core)?;
+        let mut ws = w.split_and_path_expansion(core)?;
+        Ok( Self::make_args(&mut ws).join(" ") )
+    }
 
-        Some( Self::make_args(&mut ws).join(" ") )
+    pub fn eval_for_case_word(&self, core: &mut ShellCore) -> Result<String, ExecError> {
+        let mut w = self.tilde_and_dollar_expansion(core)?;
+        Ok(w.make_unquoted_word().unwrap_or_default())
     }
 
-    pub fn eval_for_case_word(&self, core: &mut ShellCore) -> Option<String> {
-        match self.tilde_and_dollar_expansion(core) {
-            Some(mut w) => w.make_unquoted_word(),
-            None    => return None,
-        }
+    pub fn eval_for_case_pattern(&self, core: &mut ShellCore) -> Result<String, ExecError> {
+        let mut w = self.tilde_and_dollar_expansion(core)?;
+        Ok(w.make_glob_string())
     }
 
-    pub fn eval_for_case_pattern(&self, core: &mut ShellCore) -> Option<String> {
-        match self.tilde_and_dollar_expansion(core) {
-            Some(mut w) => Some(w.make_glob_string()),
-            None    => return None,
-        }
+    pub fn eval_for_regex(&self, core: &mut ShellCore) -> Result<String, ExecError> {
+        let mut w = self.tilde_and_dollar_expansion(core)?;
+        Ok(w.subwords.iter_mut()
+            .map(|s| s.make_regex_string())
+            .collect::<Vec<String>>()
+            .concat())
     }
 
-    pub fn tilde_and_dollar_expansion(&self, core: &mut ShellCore) -> Option<Word> {
+    pub fn tilde_and_dollar_expansion(&self, core: &mut ShellCore) -> Result<Word, ExecError> {
         let mut w = self.clone();
         tilde_expansion::eval(&mut w, core);
-        match substitution::eval(&mut w, core) {
-            true  => Some(w),
-            false => None,
-        }
+        substitution::eval(&mut w, core)?;
+        Ok(w)
     }
 
-    pub fn split_and_path_expansion(&self, core: &mut ShellCore) -> Vec<Word> {
+    pub fn split_and_path_expansion(&self, core: &mut ShellCore) -> Result<Vec<Word>, ExecError> {
         let mut ans = vec![];
-        let extglob = core.shopts.query("extglob");
+        let noglob = core.data.flags.contains('f');
         for mut w in split::eval(self, core) {
-            ans.append(&mut path_expansion::eval(&mut w, extglob) );
+            match noglob {
+                true  => ans.push(w),
+                false => ans.append(&mut path_expansion::eval(&mut w, core)? ),
+            }
         }
-        ans
+        Ok(ans)
     }
 
     fn make_args(words: &mut Vec<Word>) -> Vec<String> {
@@ -133,6 +135,35 @@ impl Word {
             }
         }
 
+        Self::flatten(ans)
+    }
+
+    /* the right side of =~ in [[ ]], where parens and bars are parts of the word */
+    pub fn parse_regex(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Word> {
+        let mut ans = Word::new();
+        let mut nest = 0;
+
+        loop {
+            if feeder.starts_with("(") {
+                nest += 1;
+            }else if feeder.starts_with(")") && nest > 0 {
+                nest -= 1;
+            }else if ! feeder.starts_with("|") {
+                match subword::parse(feeder, core) {
+                    Some(sw) => ans.push(&sw),
+                    None     => break,
+                }
+                continue;
+            }
+
+            let sw: Box<dyn Subword> = Box::new(SimpleSubword{ text: feeder.consume(1) });
+            ans.push(&sw);
+        }
+
+        Self::flatten(ans)
+    }
+
+    fn flatten(ans: Word) -> Option<Word> {
         match ans.subwords.len() {
             0 => None,
             _ => {
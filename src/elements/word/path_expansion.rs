@@ -1,24 +1,49 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::ShellCore;
+use crate::core::options::Options;
+use crate::error_message::ExecError;
 use crate::elements::word::Word;
-use crate::utils::directory;
+use crate::utils::{directory, glob};
 use super::subword::simple::SimpleSubword;
 
-pub fn eval(word: &mut Word, extglob: bool) -> Vec<Word> {
-    let paths = expand(&word.make_glob_string(), extglob);
+pub fn eval(word: &mut Word, core: &mut ShellCore) -> Result<Vec<Word>, ExecError> {
+    let globstr = word.make_glob_string();
+    let paths = expand_with_ignore(&globstr, core);
+    let opts = &core.shopts;
 
     if paths.len() > 0 {
         let mut tmp = word.clone();
-        paths.iter()
-             .map(|p| rewrite(&mut tmp, &p))
-             .collect()
+        return Ok( paths.iter().map(|p| rewrite(&mut tmp, p)).collect() );
+    }
+
+    let has_wildcard = || glob::compile(&globstr, opts.query("extglob")).has_wildcard();
+    if opts.query("failglob") && has_wildcard() {
+        Err(ExecError::NoMatch(word.text.clone()))
+    }else if opts.query("nullglob") && has_wildcard() {
+        Ok(vec![])
     }else{
-        vec![word.clone()]
+        Ok(vec![word.clone()])
+    }
+}
+
+/* a non-null GLOBIGNORE also makes dotfiles matched as dotglob does,
+   except . and .. */
+fn expand_with_ignore(globstr: &str, core: &mut ShellCore) -> Vec<String> {
+    if core.data.get_param("GLOBIGNORE").is_empty() {
+        return expand(globstr, &core.shopts);
     }
+
+    let mut opts = core.shopts.clone();
+    opts.set("dotglob", true);
+    let mut paths = expand(globstr, &opts);
+    paths.retain(|p| ! p.ends_with("/.") && ! p.ends_with("/..") && p != "." && p != "..");
+    paths.retain(|p| ! core.ignored_by("GLOBIGNORE", p));
+    paths
 }
 
-fn expand(globstr: &str, extglob: bool) -> Vec<String> {
+fn expand(globstr: &str, opts: &Options) -> Vec<String> {
     if globstr.find("*") == None 
     && globstr.find("?") == None
     && globstr.find("@") == None
@@ -33,13 +58,17 @@ fn expand(globstr: &str, extglob: bool) -> Vec<String> {
 
     for glob_elem in globstr.split("/") {
         for cand in ans_cands {
-            tmp_ans_cands.extend( directory::glob(&cand, &glob_elem, extglob) );
+            match glob_elem == "**" && opts.query("globstar") {
+                true  => tmp_ans_cands.extend( directory::glob_star(&cand, opts.query("dotglob")) ),
+                false => tmp_ans_cands.extend( directory::glob(&cand, glob_elem, opts) ),
+            }
         }
         ans_cands = tmp_ans_cands.clone();
         tmp_ans_cands.clear();
     }
 
     ans_cands.iter_mut().for_each(|e| {e.pop();} );
+    ans_cands.retain(|e| ! e.is_empty());
     ans_cands.sort();
     ans_cands
 }
@@ -2,19 +2,20 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use crate::error_message::ExecError;
 use crate::elements::word::Word;
 use crate::elements::subword::Subword;
 use crate::elements::subword::parameter::Parameter;
 
-pub fn eval(word: &mut Word, core: &mut ShellCore) -> bool {
+pub fn eval(word: &mut Word, core: &mut ShellCore) -> Result<(), ExecError> {
     for i in word.scan_pos("$") {
         connect_names(&mut word.subwords[i..]);
     }
-    if ! word.subwords.iter_mut().all(|w| w.substitute(core)) {
-        return false;
+    for sw in word.subwords.iter_mut() {
+        sw.substitute(core)?;
     }
     substitute_replace(word);
-    true
+    Ok(())
 }
 
 fn substitute_replace(word: &mut Word) {
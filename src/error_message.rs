@@ -2,16 +2,92 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use nix::errno::Errno;
+use std::env;
+use std::io;
+use std::io::Write;
 
+/* the last phrases of messages and their translations */
+const CATALOG_JA: [(&str, &str); 20] = [
+    ("command not found", "コマンドが見つかりません"),
+    ("No such file or directory", "そのようなファイルやディレクトリはありません"),
+    ("Permission denied", "許可がありません"),
+    ("Is a directory", "ディレクトリです"),
+    ("not a valid identifier", "有効な識別子ではありません"),
+    ("cannot unset: readonly variable", "消去できません: 読み取り専用の変数です"),
+    ("readonly variable", "読み取り専用の変数です"),
+    ("unbound variable", "未割り当ての変数です"),
+    ("bad substitution", "誤った置換です"),
+    ("ambiguous redirect", "曖昧なリダイレクトです"),
+    ("invalid option", "無効なオプションです"),
+    ("option requires an argument", "オプションには引数が必要です"),
+    ("numeric argument required", "数字の引数が必要です"),
+    ("invalid number", "無効な数字です"),
+    ("too many arguments", "引数が多すぎます"),
+    ("can only be used in a function", "関数の中でのみ使用できます"),
+    ("directory stack empty", "ディレクトリスタックが空です"),
+    ("HOME not set", "HOME が設定されていません"),
+    ("OLDPWD not set", "OLDPWD が設定されていません"),
+    ("not found", "見つかりません"),
+];
+
+pub const LANGUAGE_VARS: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+/* only the last phrase is translated since the rest contains names and values */
+pub fn translate(s: &str, lang: &str) -> String {
+    if ! lang.starts_with("ja") {
+        return s.to_string();
+    }
+
+    for (en, ja) in CATALOG_JA {
+        if let Some(head) = s.strip_suffix(en) {
+            return format!("{}{}", head, ja);
+        }
+    }
+    s.to_string()
+}
+
+/* name: line N: message in a script, and name: message on a terminal */
 pub fn print(s: &str, core: &mut ShellCore, show_sush: bool) {
     let name = core.data.get_param("0");
-    match (core.read_stdin, show_sush) {
+    let lang = core.data.language();
+    match (! core.reading_terminal(), show_sush) {
         (true, _) => {
             let lineno = core.data.get_param("LINENO");
-            eprintln!("{}: line {}: {}", &name, &lineno, s)
+            print_located(s, &name, Some(&lineno), &lang);
         },
-        (false, true)  => eprintln!("{}: {}", &name, &s),
-        (false, false) => eprintln!("{}", &s),
+        (false, true)  => print_located(s, &name, None, &lang),
+        (false, false) => write_stderr(&translate(s, &lang)),
+    }
+}
+
+/* eprintln! panics when stderr is closed, which must not stop the shell */
+fn write_stderr(s: &str) {
+    let _ = writeln!(io::stderr(), "{}", s);
+}
+
+pub fn print_located(s: &str, name: &str, lineno: Option<&str>, lang: &str) {
+    match lineno {
+        Some(n) => write_stderr(&format!("{}: line {}: {}", name, n, translate(s, lang))),
+        None    => write_stderr(&format!("{}: {}", name, translate(s, lang))),
+    }
+}
+
+/* for the places where the core is not available; the language is taken
+ * from the environment since the shell variables are not either */
+pub fn print_without_core(s: &str) {
+    let lang = LANGUAGE_VARS.iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|v| ! v.is_empty())
+        .unwrap_or_default();
+    write_stderr(&format!("sush: {}", translate(s, &lang)));
+}
+
+/* the description of errno without "(os error N)" */
+pub fn io_error(e: &std::io::Error) -> String {
+    match e.raw_os_error() {
+        Some(n) => Errno::from_raw(n).desc().to_string(),
+        None    => e.to_string(),
     }
 }
 
@@ -42,3 +118,42 @@ pub fn syntax(token: &str) -> String {
 pub fn syntax_in_cond_expr(token: &str) -> String {
     format!("syntax error in conditional expression: unexpected token `{}'", token)
 }
+
+/* reasons why a word can't be evaluated */
+#[derive(Debug, Clone)]
+pub enum ExecError {
+    UnboundVariable(String),
+    BadSubstitution(String),
+    NoMatch(String),
+    Interrupted,
+    /* the arithmetic evaluator reports its own errors */
+    InvalidArithmetic,
+    Other(String),
+}
+
+impl ExecError {
+    pub fn print(&self, core: &mut ShellCore) {
+        let msg = match self {
+            ExecError::UnboundVariable(name) => format!("{}: unbound variable", name),
+            ExecError::BadSubstitution(text) => format!("{}: bad substitution", text),
+            ExecError::NoMatch(glob)         => format!("no match: {}", glob),
+            ExecError::Other(msg)            => msg.to_string(),
+            _ => return,
+        };
+        print(&msg, core, true);
+    }
+
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            ExecError::Interrupted => 130,
+            _                      => 1,
+        }
+    }
+
+    /* the command with the word fails and the rest of the line is skipped */
+    pub fn abort(&self, core: &mut ShellCore) {
+        self.print(core);
+        core.data.set_exit_status(self.exit_status());
+        core.word_eval_error = true;
+    }
+}
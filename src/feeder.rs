@@ -1,11 +1,14 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+pub mod input;
+mod alias;
 mod terminal;
 mod scanner;
+pub mod quoting;
 
-use std::{io, process};
-use crate::ShellCore;
+use crate::{utils, Script, ShellCore};
+use std::collections::VecDeque;
 use std::sync::atomic::Ordering::Relaxed;
 
 pub enum InputError {
@@ -13,86 +16,224 @@ pub enum InputError {
     Eof,
 }
 
+/* the line number is of the line where the parser stopped */
+#[derive(Clone, Debug)]
+pub enum ParseError {
+    UnexpectedSymbol(String, usize),
+    UnexpectedEof(usize),
+    Interrupt,
+}
+
+impl ParseError {
+    pub fn print(&self, core: &mut ShellCore) {
+        let (msg, lineno) = match self {
+            ParseError::UnexpectedSymbol(token, n) => (format!("syntax error near unexpected token `{}'", token), n),
+            ParseError::UnexpectedEof(n) => ("syntax error: unexpected end of file".to_string(), n),
+            ParseError::Interrupt => return,
+        };
+
+        let name = core.data.get_param("0");
+        match core.reading_terminal() {
+            true  => eprintln!("{}: {}", &name, &msg),
+            false => eprintln!("{}: line {}: {}", &name, lineno, &msg),
+        }
+    }
+}
+
+/* a position to go back to. when the buffer is rewritten after the backup
+   is made, the backup refers to the text before the rewrite in the snapshots */
+#[derive(Clone, Debug)]
+struct Backup {
+    pos: usize,
+    snapshot: Option<usize>,
+    expanding: Vec<(String, usize)>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Feeder {
-    remaining: String,
-    backup: Vec<String>,
+    /* the input given so far. the parser reads it from pos */
+    buffer: String,
+    pos: usize,
+    backup: Vec<Backup>,
+    /* one text is shared by all the backups made before the same rewrite */
+    snapshots: Vec<String>,
     pub nest: Vec<(String, Vec<String>)>,
+    /* lines already read but not given to the parser yet */
+    pending: VecDeque<String>,
+    check_only: bool,
+    need_more: bool,
+    /* aliases under expansion and the bytes of their values not parsed yet */
+    expanding: Vec<(String, usize)>,
     lineno: usize,
+    /* an error in an inner script, which makes the outer ones fail */
+    pub error: Option<ParseError>,
 }
 
 impl Feeder {
     pub fn new(s: &str) -> Feeder {
         Feeder {
-            remaining: s.to_string(),
+            buffer: s.to_string(),
+            pos: 0,
             backup: vec![],
+            snapshots: vec![],
             nest: vec![("".to_string(), vec![])],
+            pending: VecDeque::new(),
+            check_only: false,
+            need_more: false,
+            expanding: vec![],
             lineno: 0,
+            error: None,
         }
     }
 
+    /* parses the text line by line without executing it to know if
+       more lines are required. input with a syntax error is regarded as complete */
+    pub fn is_complete(text: &str, core: &mut ShellCore) -> bool {
+        let mut feeder = Feeder::new("");
+        feeder.pending = text.split_inclusive('\n').map(|l| l.to_string()).collect();
+        feeder.check_only = true;
+
+        let status = core.data.exit_status();
+        while let Some(line) = feeder.pending.pop_front() {
+            feeder.buffer += &line;
+            let _ = Script::parse(&mut feeder, core, false);
+            if feeder.need_more {
+                break;
+            }
+        }
+        core.data.set_exit_status(status.code());
+
+        ! feeder.need_more
+    }
+
+    fn remaining(&self) -> &str {
+        &self.buffer[self.pos..]
+    }
+
+    /* the length is in bytes. a cut in a multibyte char takes the whole char */
     pub fn consume(&mut self, cutpos: usize) -> String {
-        let cut = self.remaining[0..cutpos].to_string();
-        self.remaining = self.remaining[cutpos..].to_string();
+        let cutpos = utils::char_boundary(self.remaining(), cutpos);
+        let cut = self.remaining()[0..cutpos].to_string();
+        self.pos += cutpos;
+
+        self.expanding.iter_mut().for_each(|e| e.1 = e.1.saturating_sub(cutpos));
+        self.expanding.retain(|e| e.1 > 0);
 
         cut
     }
 
     pub fn refer(&mut self, cutpos: usize) -> &str {
-        &self.remaining[0..cutpos]
+        let cutpos = utils::char_boundary(self.remaining(), cutpos);
+        &self.remaining()[0..cutpos]
     }
 
     pub fn set_backup(&mut self) {
-        self.backup.push(self.remaining.clone());
+        let b = Backup { pos: self.pos, snapshot: None, expanding: self.expanding.clone() };
+        self.backup.push(b);
     }
 
     pub fn pop_backup(&mut self) {
-        self.backup.pop().expect("SUSHI INTERNAL ERROR (backup error)");
+        let b = self.backup.pop().expect("SUSHI INTERNAL ERROR (backup error)");
+        if self.snapshot_released(&b) {
+            self.snapshots.pop();
+        }
+    }
+
+    /* backups without a snapshot are always on the top of the stack. so the snapshot
+       of a popped backup is the last one, which is unused if the new top doesn't share it */
+    fn snapshot_released(&self, b: &Backup) -> bool {
+        b.snapshot.is_some() && self.backup.last().and_then(|t| t.snapshot) != b.snapshot
     }
 
+    /* the snapshots get the line with the buffer */
     pub fn add_backup(&mut self, line: &str) {
-        for b in self.backup.iter_mut() {
-            if b.ends_with("\\\n") {
-                b.pop();
-                b.pop();
+        for text in self.snapshots.iter_mut() {
+            if text.ends_with("\\\n") {
+                text.pop();
+                text.pop();
             }
-            *b += &line;
+            *text += line;
         }
     }
 
+    /* the backups keep the text before the rewrite */
+    fn rewrite(&mut self, start: usize, len: usize, to: &str) {
+        if self.backup.last().is_some_and(|b| b.snapshot.is_none()) {
+            let i = self.snapshots.len();
+            self.snapshots.push(self.buffer.clone());
+            for b in self.backup.iter_mut().rev().take_while(|b| b.snapshot.is_none()) {
+                b.snapshot = Some(i);
+            }
+        }
+        let start = self.pos + start;
+        self.buffer.replace_range(start..start+len, to);
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.pos = 0;
+        self.backup.clear();
+        self.snapshots.clear();
+        self.expanding.clear();
+        self.pending.clear();
+        self.nest = vec![("".to_string(), vec![])];
+        self.error = None;
+    }
+
     pub fn rewind(&mut self) {
-        self.remaining = self.backup.pop().expect("SUSHI INTERNAL ERROR (backup error)");
+        let b = self.backup.pop().expect("SUSHI INTERNAL ERROR (backup error)");
+        if let Some(i) = b.snapshot {
+            self.buffer = match self.snapshot_released(&b) {
+                true  => self.snapshots.pop().unwrap_or_default(),
+                false => self.snapshots[i].clone(),
+            };
+        }
+        self.pos = b.pos;
+        self.expanding = b.expanding;
     }   
 
-    fn read_line_stdin(core: &mut ShellCore) -> Result<String, InputError> {
-        let mut line = String::new();
+    /* a multi-line input from the terminal is given to the parser line by line */
+    fn read_line(&mut self, core: &mut ShellCore, prompt: &str) -> Result<String, InputError> {
+        if let Some(ln) = self.pending.pop_front() {
+            return Ok(ln);
+        }
 
-        let len = match io::stdin().read_line(&mut line) {
-            Ok(len)  => len,
-            Err(why) => {
-                eprintln!("sush: {}: {}", &core.script_name, why);
-                process::exit(1)
-            },
-        };
+        if core.reading_terminal() {
+            let text = terminal::read_line(core, prompt)?;
+            self.pending = text.split_inclusive('\n').map(|l| l.to_string()).collect();
+            return Ok(self.pending.pop_front().unwrap_or_default());
+        }
+
+        if core.data.flags.contains('i') && core.input_sources.last().is_some_and(|s| s.is_stdin()) {
+            return terminal::read_line_without_terminal(core, prompt);
+        }
 
-        if len == 0 {
-            Err(InputError::Eof)
-        }else{
-            Ok(line)
+        match core.input_sources.last_mut() {
+            Some(s) => s.read_line(),
+            None    => Err(InputError::Eof),
         }
     }
 
     fn feed_additional_line_core(&mut self, core: &mut ShellCore) -> Result<(), InputError> {
+        if self.check_only {
+            return match self.pending.pop_front() {
+                Some(ln) => {
+                    self.buffer += &ln;
+                    self.add_backup(&ln);
+                    Ok(())
+                },
+                None => {
+                    self.need_more = true;
+                    Err(InputError::Eof)
+                },
+            };
+        }
+
         if core.sigint.load(Relaxed) {
             return Err(InputError::Interrupt);
         }
 
-        let line = match ! core.read_stdin {
-            true  => terminal::read_line(core, "PS2"),
-            false => Self::read_line_stdin(core),
-        };
-
-        match line { 
+        match self.read_line(core, "PS2") {
             Ok(ln) => {
                 self.add_line(ln.clone(), core);
                 self.add_backup(&ln);
@@ -106,28 +247,19 @@ impl Feeder {
         match self.feed_additional_line_core(core) {
             Ok(()) => true,
             Err(InputError::Eof) => {
-                eprintln!("sush: syntax error: unexpected end of file");
-                core.data.set_param("?", "2");
-
-                match core.data.flags.contains('S') { //S: on source command
-                    true  => return false,
-                    false => core.exit(),
-                }
+                self.error = Some(ParseError::UnexpectedEof(self.lineno + 1));
+                false
             },
             Err(InputError::Interrupt) => {
-                core.data.set_param("?", "130");
+                core.data.set_exit_status(130);
+                self.error = Some(ParseError::Interrupt);
                 false
             },
         }
     }
 
     pub fn feed_line(&mut self, core: &mut ShellCore) -> Result<(), InputError> {
-        let line = match ! core.read_stdin {
-            true  => terminal::read_line(core, "PS1"),
-            false => Self::read_line_stdin(core),
-        };
-
-        match line {
+        match self.read_line(core, "PS1") {
             Ok(ln) => {
                 self.add_line(ln, core);
                 Ok(())
@@ -138,27 +270,46 @@ impl Feeder {
 
     pub fn add_line(&mut self, line: String, core: &mut ShellCore) {
         if core.data.flags.contains('v') {
-            eprint!("{}", &line);
+            match line.ends_with('\n') {
+                true  => eprint!("{}", &line),
+                false => eprintln!("{}", &line),
+            }
         }
 
-        self.lineno += 1;
+        self.lineno = core.count_line();
         core.data.set_param("LINENO", &self.lineno.to_string());
-        match self.remaining.len() {
-            0 => self.remaining = line,
-            _ => self.remaining += &line,
+        /* the parsed part is dropped when nothing refers to it */
+        if self.backup.is_empty() {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+        }
+        match self.buffer.len() {
+            0 => self.buffer = line,
+            _ => self.buffer += &line,
         };
     }
 
     pub fn replace(&mut self, num: usize, to: &str) {
         self.consume(num);
-        self.remaining = to.to_string() + &self.remaining;
+        self.rewrite(0, 0, to);
     }
 
     pub fn starts_with(&self, s: &str) -> bool {
-        self.remaining.starts_with(s)
+        self.remaining().starts_with(s)
+    }
+
+    pub fn starts_with_word(&self, w: &str) -> bool {
+        match self.remaining().strip_prefix(w) {
+            Some(rest) => rest.is_empty() || rest.starts_with(|c| " \t\n;&|()<>".contains(c)),
+            None       => false,
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.remaining.len()
+        self.remaining().len()
+    }
+
+    pub fn lineno(&self) -> usize {
+        self.lineno
     }
 }
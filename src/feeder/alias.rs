@@ -0,0 +1,51 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use super::Feeder;
+use crate::{utils, ShellCore};
+
+impl Feeder {
+    /* the length of a word that can be an alias name at pos */
+    fn scanner_alias_name(&self, pos: usize) -> usize {
+        let delimiter = |c: char| " \t\n;&|()<>".contains(c);
+        let remaining = &self.remaining()[pos..];
+        let len = remaining.find(delimiter).unwrap_or(remaining.len());
+
+        match remaining[..len].contains(|c: char| "'\"\\$`=/".contains(c)) {
+            true  => 0,
+            false => len,
+        }
+    }
+
+    /* the next word is also checked when the value ends with a blank */
+    pub fn replace_alias(&mut self, core: &mut ShellCore) {
+        if ! core.shopts.query("expand_aliases") {
+            return;
+        }
+
+        let mut pos = 0;
+        loop {
+            let len = self.scanner_alias_name(pos);
+            let name = self.remaining()[pos..pos+len].to_string();
+            if len == 0 || utils::reserved(&name)
+            || self.expanding.iter().any(|e| e.0 == name) {
+                return;
+            }
+
+            let mut value = name.clone();
+            if ! core.data.replace_alias(&mut value) {
+                return;
+            }
+
+            self.rewrite(pos, len, &value);
+            pos += value.len();
+            self.expanding.push((name, pos));
+
+            if ! value.ends_with(" ") && ! value.ends_with("\t") {
+                return;
+            }
+            let blank = &self.remaining()[pos..];
+            pos += blank.len() - blank.trim_start_matches([' ', '\t']).len();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use super::{Feeder, InputError, ParseError};
+use crate::{error_message, Script, ShellCore};
+use crate::elements::io;
+use crate::utils::bytes;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+
+#[derive(Debug)]
+enum Kind {
+    Terminal,
+    Stdin,
+    File(BufReader<File>),
+    Text(VecDeque<String>),
+}
+
+#[derive(Debug)]
+pub struct InputSource {
+    kind: Kind,
+    pub name: String,
+    pub lineno: usize,
+}
+
+impl InputSource {
+    fn new(kind: Kind, name: &str) -> InputSource {
+        InputSource {
+            kind,
+            name: name.to_string(),
+            lineno: 0,
+        }
+    }
+
+    pub fn terminal() -> InputSource {
+        Self::new(Kind::Terminal, "")
+    }
+
+    pub fn stdin(name: &str) -> InputSource {
+        Self::new(Kind::Stdin, name)
+    }
+
+    pub fn file(file: File, name: &str) -> InputSource {
+        Self::new(Kind::File(BufReader::new(file)), name)
+    }
+
+    pub fn text(s: &str, name: &str) -> InputSource {
+        let lines = s.split_inclusive('\n').map(|l| l.to_string()).collect();
+        Self::new(Kind::Text(lines), name)
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self.kind, Kind::File(_))
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        matches!(self.kind, Kind::Stdin)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.kind, Kind::Terminal)
+    }
+
+    fn read_line_from(reader: &mut dyn BufRead, name: &str) -> Result<String, InputError> {
+        let mut line = vec![];
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0)    => Err(InputError::Eof),
+            Ok(_)    => Ok(bytes::decode(&line)),
+            Err(why) => {
+                error_message::print_without_core(&format!("{}: {}", name, why));
+                process::exit(1)
+            },
+        }
+    }
+
+    fn read_line_from_stdin(name: &str) -> Result<String, InputError> {
+        let mut line = vec![];
+        loop {
+            match io::read_byte(0) {
+                Ok(Some(b)) => {
+                    line.push(b);
+                    if b == b'\n' {
+                        break;
+                    }
+                },
+                Ok(None)  => break,
+                Err(why)  => {
+                    error_message::print_without_core(&format!("{}: {}", name, why.desc()));
+                    process::exit(1)
+                },
+            }
+        }
+
+        match line.is_empty() {
+            true  => Err(InputError::Eof),
+            false => Ok(bytes::decode(&line)),
+        }
+    }
+
+    /* the terminal is read by feeder::terminal since it needs the core */
+    pub fn read_line(&mut self) -> Result<String, InputError> {
+        match &mut self.kind {
+            Kind::Terminal => Err(InputError::Eof),
+            Kind::Stdin    => Self::read_line_from_stdin(&self.name),
+            Kind::File(f)  => Self::read_line_from(f, &self.name),
+            Kind::Text(ls) => ls.pop_front().ok_or(InputError::Eof),
+        }
+    }
+}
+
+impl ShellCore {
+    pub fn push_source(&mut self, source: InputSource) {
+        self.input_sources.push(source);
+    }
+
+    pub fn pop_source(&mut self) {
+        self.input_sources.pop();
+        if let Some(s) = self.input_sources.last() {
+            let lineno = s.lineno.to_string();
+            self.data.set_param("LINENO", &lineno);
+        }
+    }
+
+    pub fn exec_source(&mut self, source: InputSource) {
+        self.push_source(source);
+
+        let mut feeder = Feeder::new("");
+        let mut syntax_error = false;
+        while feeder.feed_line(self).is_ok() {
+            match Script::parse(&mut feeder, self, false) {
+                Ok(mut s) => s.exec(self),
+                Err(ParseError::Interrupt) => {},
+                Err(e) => {
+                    e.print(self);
+                    self.data.set_exit_status(2);
+                    syntax_error = true;
+                    break;
+                },
+            }
+
+            if self.return_flag {
+                break;
+            }
+        }
+
+        self.pop_source();
+
+        /* a syntax error aborts the shell unless it is in a sourced file or an eval */
+        if syntax_error && self.input_sources.is_empty() && ! self.data.flags.contains('i') {
+            self.exit();
+        }
+    }
+
+    pub fn reading_terminal(&self) -> bool {
+        match self.input_sources.last() {
+            Some(s) => s.is_terminal(),
+            None    => false,
+        }
+    }
+
+    pub fn nested_source(&self) -> bool {
+        self.input_sources.len() > 1
+    }
+
+    /* the innermost file being read, which is used in BASH_SOURCE */
+    pub fn source_file_name(&self) -> String {
+        match self.input_sources.iter().rev().find(|s| s.is_file()) {
+            Some(s) => s.name.clone(),
+            None    => "main".to_string(),
+        }
+    }
+
+    pub fn running_trap(&self) -> bool {
+        self.input_sources.iter().any(|s| s.name == "trap")
+    }
+
+    pub fn lineno(&self) -> usize {
+        self.input_sources.last().map_or(0, |s| s.lineno)
+    }
+
+    pub fn count_line(&mut self) -> usize {
+        match self.input_sources.last_mut() {
+            Some(s) => {
+                s.lineno += 1;
+                s.lineno
+            },
+            None => 0,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+#[derive(Clone, Copy, PartialEq)]
+enum Region {
+    Single,     // '...'
+    AnsiC,      // $'...'
+    Double,     // "..."
+    Backquote,  // `...`
+    Paren,      // $(...) and the parens in it
+    Brace,      // ${...} and the braces in it
+}
+
+impl Region {
+    fn closer(&self) -> char {
+        match self {
+            Region::Single | Region::AnsiC => '\'',
+            Region::Double    => '"',
+            Region::Backquote => '`',
+            Region::Paren     => ')',
+            Region::Brace     => '}',
+        }
+    }
+}
+
+/* reads a text char by char with the state of quotes, escapes and
+ * nested substitutions so that a scanner never splits a text in them.
+ * Each item is (position, char, bare), where bare is true only for
+ * a char outside of all the regions. */
+pub struct Quoting<'a> {
+    text: &'a str,
+    pos: usize,
+    regions: Vec<Region>,
+    escaped: bool,
+    opening: usize,
+}
+
+impl<'a> Quoting<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Quoting { text, pos: 0, regions: vec![], escaped: false, opening: 0 }
+    }
+
+    /* true when the text read so far leaves a quote or a nest open */
+    pub fn is_open(&self) -> bool {
+        ! self.regions.is_empty() || self.escaped || self.opening > 0
+    }
+
+    fn opener(&self, rest: &str) -> Option<(usize, Region)> {
+        let top = self.regions.last().copied();
+        let starts = |s: &str| rest.starts_with(s);
+
+        match top {
+            Some(Region::Single) | Some(Region::AnsiC) | Some(Region::Backquote) => None,
+            Some(Region::Double) => {
+                if starts("${") { Some((2, Region::Brace)) }
+                else if starts("$(") { Some((2, Region::Paren)) }
+                else if starts("`") { Some((1, Region::Backquote)) }
+                else { None }
+            },
+            _ => {
+                if starts("$'") { Some((2, Region::AnsiC)) }
+                else if starts("${") { Some((2, Region::Brace)) }
+                else if starts("$(") { Some((2, Region::Paren)) }
+                else if starts("'") { Some((1, Region::Single)) }
+                else if starts("\"") { Some((1, Region::Double)) }
+                else if starts("`") { Some((1, Region::Backquote)) }
+                else if starts("(") && top == Some(Region::Paren) { Some((1, Region::Paren)) }
+                else if starts("{") && top == Some(Region::Brace) { Some((1, Region::Brace)) }
+                else { None }
+            },
+        }
+    }
+}
+
+impl Iterator for Quoting<'_> {
+    type Item = (usize, char, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.pos..];
+        let c = rest.chars().next()?;
+        let pos = self.pos;
+        self.pos += c.len_utf8();
+
+        if self.opening > 0 {
+            self.opening -= 1;
+            return Some((pos, c, false));
+        }
+        if self.escaped {
+            self.escaped = false;
+            return Some((pos, c, false));
+        }
+
+        let top = self.regions.last().copied();
+        if c == '\\' && top != Some(Region::Single) {
+            self.escaped = true;
+            return Some((pos, c, false));
+        }
+        if top.is_some_and(|r| r.closer() == c) {
+            self.regions.pop();
+            return Some((pos, c, false));
+        }
+        if let Some((len, region)) = self.opener(rest) {
+            self.regions.push(region);
+            self.opening = len - 1;
+            return Some((pos, c, false));
+        }
+
+        Some((pos, c, self.regions.is_empty()))
+    }
+}
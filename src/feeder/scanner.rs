@@ -2,17 +2,19 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use super::Feeder;
+use super::quoting::Quoting;
 use crate::ShellCore;
 
 impl Feeder {
     fn feed_and_connect(&mut self, core: &mut ShellCore) {
-        self.remaining.pop();
-        self.remaining.pop();
+        self.buffer.pop();
+        self.buffer.pop();
         let _ = self.feed_additional_line_core(core);
     }
 
-    fn backslash_check_and_feed(&mut self, starts: Vec<&str>, core: &mut ShellCore) {
-        let check = |s: &str| self.remaining.starts_with(&(s.to_owned() + "\\\n"));
+    fn backslash_check_and_feed(&mut self, starts: &[&str], core: &mut ShellCore) {
+        let check = |s: &str| self.remaining().strip_prefix(s)
+                                  .is_some_and(|rest| rest.starts_with("\\\n"));
         if starts.iter().any(|s| check(s)) {
             self.feed_and_connect(core);
         }
@@ -22,20 +24,52 @@ impl Feeder {
                      core: &mut ShellCore, skip_bytes: usize) -> usize {
         loop {
             let mut ans = 0;
-            for ch in self.remaining[skip_bytes..].chars() {
+            for ch in self.remaining()[skip_bytes..].chars() {
                 match judge(ch) {
                     true  => ans += ch.len_utf8(),
                     false => break,
                 }
             }
 
-            match &self.remaining[skip_bytes+ans..] == "\\\n" {
+            match &self.remaining()[skip_bytes+ans..] == "\\\n" {
                 true  => self.feed_and_connect(core),
                 false => return ans,
             }
         }
     }
 
+    /* the length before the first char that is out of quotes and nests
+     * and judged true; lines are added while a quote or a nest is open */
+    fn scanner_until_bare(&mut self, judge: fn(char) -> bool, core: &mut ShellCore) -> usize {
+        loop {
+            let mut quoting = Quoting::new(self.remaining());
+            if let Some((i, _, _)) = quoting.find(|(_, c, bare)| *bare && judge(*c)) {
+                return i;
+            }
+
+            if ! quoting.is_open() || ! self.feed_additional_line(core) {
+                return self.len();
+            }
+        }
+    }
+
+    /* the length of the quoted or nested region at the head, such as
+     * '...', $'...' and `...`; lines are added while it is open */
+    fn scanner_region(&mut self, core: &mut ShellCore) -> usize {
+        loop {
+            let mut quoting = Quoting::new(self.remaining());
+            while let Some((i, c, _)) = quoting.next() {
+                if ! quoting.is_open() {
+                    return i + c.len_utf8();
+                }
+            }
+
+            if ! self.feed_additional_line(core) {
+                return 0;
+            }
+        }
+    }
+
     fn scanner_one_of(&self, cands: &[&str]) -> usize {
         for c in cands {
             if self.starts_with(c) {
@@ -51,13 +85,13 @@ impl Feeder {
     }
 
     pub fn scanner_math_symbol(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec![""], core);
+        self.backslash_check_and_feed(&[""], core);
         self.scanner_one_of(&["/", "*", "?", ":", "+", "-", "=", "^", "%", ","])
     }
 
     pub fn scanner_unary_operator(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["+", "-", "!", "~"], core);
-        if let Some('=') = self.remaining.chars().nth(1) {
+        self.backslash_check_and_feed(&["+", "-", "!", "~"], core);
+        if let Some('=') = self.remaining().chars().nth(1) {
             return 0;
         }
 
@@ -65,14 +99,14 @@ impl Feeder {
     }
 
     pub fn scanner_math_output_format(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["[#", "["], core);
+        self.backslash_check_and_feed(&["[#", "["], core);
         if ! self.starts_with("[#") {
             return 0;
         }
 
         let mut ans = 2;
         let mut ok = false;
-        for (i, ch) in self.remaining[2..].chars().enumerate() {
+        for (i, ch) in self.remaining()[2..].chars().enumerate() {
             if i == 0 && ch == '#' {
                 ans += 1;
                 continue;
@@ -105,33 +139,26 @@ impl Feeder {
             return 0;
         }
 
-        match self.remaining.chars().nth(1) {
+        match self.remaining().chars().nth(1) {
             Some(ch) => 1 + ch.len_utf8(),
             None =>     1,
         }
     }
 
-    pub fn scanner_history_expansion(&mut self, _: &mut ShellCore) -> usize {
-        match self.starts_with("!$") {
-            true  => 2,
-            false => 0,
-        }
-    }
-
     pub fn scanner_dollar_special_and_positional_param(&mut self, core: &mut ShellCore) -> usize {
         if ! self.starts_with("$") {
             return 0;
         }
-        self.backslash_check_and_feed(vec!["$"], core);
+        self.backslash_check_and_feed(&["$"], core);
 
-        match self.remaining.chars().nth(1) {
+        match self.remaining().chars().nth(1) {
             Some(c) => if "$?*@#-!_0123456789".find(c) != None { 2 }else{ 0 },
             None    => 0,
         }
     }
 
     pub fn scanner_special_and_positional_param(&mut self) -> usize {
-        match self.remaining.chars().nth(0) {
+        match self.remaining().chars().nth(0) {
             Some(c) => if "$?*@#-!_0123456789".find(c) != None { 1 }else{ 0 },
             None    => 0,
         }
@@ -139,8 +166,8 @@ impl Feeder {
 
     pub fn scanner_subword(&mut self) -> usize {
         let mut ans = 0;
-        for ch in self.remaining.chars() {
-            if " \t\n;&|()<>{},\\'$/~\"*+-?@!.:=^".find(ch) != None {
+        for ch in self.remaining().chars() {
+            if " \t\n;&|()<>{},\\'$/~\"`*+-?@!.:=^".contains(ch) {
                 break;
             }
             ans += ch.len_utf8();
@@ -149,12 +176,12 @@ impl Feeder {
     }
 
     pub fn scanner_double_quoted_subword(&mut self, core: &mut ShellCore) -> usize {
-        let judge = |ch| "\"\\$".find(ch) == None;
+        let judge = |ch| ! "\"\\$`".contains(ch);
         self.scanner_chars(judge, core, 0)
     }
 
     pub fn scanner_extglob_subword(&mut self, core: &mut ShellCore) -> usize {
-        let judge = |ch| ")|,}".find(ch) == None;
+        let judge = |ch| ! ")|,}'\"`$\\".contains(ch);
         self.scanner_chars(judge, core, 0)
     }
 
@@ -162,27 +189,33 @@ impl Feeder {
         if ! self.starts_with("'") {
             return 0;
         }
-        if self.starts_with("''") {
-            return 2;
+        self.scanner_region(core)
+    }
+
+    pub fn scanner_backquoted_subword(&mut self, core: &mut ShellCore) -> usize {
+        if ! self.starts_with("`") {
+            return 0;
         }
+        self.scanner_region(core)
+    }
 
-        loop {
-            if let Some(n) = self.remaining[1..].find("'") {
-                return n + 2;
-            }else if ! self.feed_additional_line(core) {
-                break;
-            }
+    pub fn scanner_ansi_c_quoted_subword(&mut self, core: &mut ShellCore) -> usize {
+        if ! self.starts_with("$'") {
+            return 0;
         }
-        0
+        self.scanner_region(core)
     }
 
     pub fn scanner_inner_subscript(&mut self, core: &mut ShellCore) -> usize {
-        let judge = |ch| "]".find(ch) == None;
-        self.scanner_chars(judge, core, 0)
+        self.scanner_until_bare(|ch| ch == ']', core)
+    }
+
+    pub fn scanner_inner_legacy_arithmetic(&mut self, core: &mut ShellCore) -> usize {
+        self.scanner_until_bare(|ch| "[]".contains(ch), core)
     }
 
     pub fn scanner_unknown_in_param_brace(&mut self) -> usize {
-        match self.remaining.chars().nth(0) {
+        match self.remaining().chars().nth(0) {
             Some(c) => if "'$".find(c) == None { c.len_utf8() }else{ 0 },
             None    => 0,
         }
@@ -199,7 +232,7 @@ impl Feeder {
     }
 
     pub fn scanner_binary_operator(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["<<", ">>", "+", "-", "/", "*", "%", "<",
+        self.backslash_check_and_feed(&["<<", ">>", "+", "-", "/", "*", "%", "<",
                                            ">", "=", "&", "|", "^", "/", "%"], core);
         self.scanner_one_of(&["<<=", ">>=",
             "&&", "||", "**", "==", "!=", "*=", "/=", "%=", "+=", "-=", "&=", "^=", "|=",
@@ -212,7 +245,7 @@ impl Feeder {
     }
 
     pub fn scanner_name(&mut self, core: &mut ShellCore) -> usize {
-        let c = self.remaining.chars().nth(0).unwrap_or('0');
+        let c = self.remaining().chars().nth(0).unwrap_or('0');
         if '0' <= c && c <= '9' {
             return 0;
         }
@@ -229,7 +262,7 @@ impl Feeder {
             return 0;
         }
 
-        if self.remaining.chars().nth(name_len).unwrap_or('x') == '=' {
+        if self.remaining().chars().nth(name_len).unwrap_or('x') == '=' {
             name_len + 1
         }else{
             0
@@ -240,13 +273,26 @@ impl Feeder {
         self.scanner_one_of(&[";", "&", "\n"])
     }
 
+    /* the operator or the word reported in a syntax error */
+    pub fn scanner_token(&self) -> usize {
+        let len = self.scanner_one_of(&[";;&", ";;", ";&", "&&", "||", "|&", "&>", ">&", ">>",
+                                        "<<", ";", "&", "|", "(", ")", "<", ">", "\n"]);
+        if len > 0 {
+            return len;
+        }
+        Quoting::new(self.remaining())
+            .find(|(_, c, bare)| *bare && " \t\n;&|()<>".contains(*c))
+            .map(|(i, _, _)| i)
+            .unwrap_or(self.remaining().len())
+    }
+
     pub fn scanner_and_or(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["|", "&"], core);
+        self.backslash_check_and_feed(&["|", "&"], core);
         self.scanner_one_of(&["||", "&&"])
     }
 
     pub fn scanner_pipe(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["|"], core);
+        self.backslash_check_and_feed(&["|"], core);
         if self.starts_with("||") {
             return 0;
         }
@@ -254,12 +300,12 @@ impl Feeder {
     }
 
     pub fn scanner_comment(&self) -> usize {
-        if ! self.remaining.starts_with("#") {
+        if ! self.remaining().starts_with("#") {
             return 0;
         }
 
         let mut ans = 0;
-        for ch in self.remaining.chars() {
+        for ch in self.remaining().chars() {
             if "\n".find(ch) != None {
                 break;
             }
@@ -269,8 +315,8 @@ impl Feeder {
     }
 
     pub fn scanner_redirect_symbol(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec![">", "&"], core);
-        self.scanner_one_of(&["&>", ">&", ">>", "<", ">"])
+        self.backslash_check_and_feed(&[">", "&"], core);
+        self.scanner_one_of(&["&>", ">&", ">>", ">|", "<>", "<&", "<", ">"])
     }
 
     pub fn scanner_parameter_default_symbol(&mut self) -> usize {
@@ -278,13 +324,13 @@ impl Feeder {
     }
 
     pub fn scanner_test_check_option(&mut self, core: &mut ShellCore) -> usize {
-        match self.remaining.chars().nth(0) {
+        match self.remaining().chars().nth(0) {
             Some('-') => {},
             _ => return 0,
         }
-        self.backslash_check_and_feed(vec!["-"], core);
+        self.backslash_check_and_feed(&["-"], core);
 
-        if let Some(c) = self.remaining.chars().nth(1) {
+        if let Some(c) = self.remaining().chars().nth(1) {
             match "abcdefghknoprstuvwxzGLNOS".contains(c) {
                 true  => return 2,
                 false => return 0,
@@ -294,8 +340,8 @@ impl Feeder {
     }
 
     pub fn scanner_test_compare_op(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec!["-", "-e", "-n", "-o", "=", "!"], core);
-        self.scanner_one_of(&["-ef", "-nt", "-ot", "==", "=", "!=", "<", ">",
+        self.backslash_check_and_feed(&["-", "-e", "-n", "-o", "=", "!"], core);
+        self.scanner_one_of(&["-ef", "-nt", "-ot", "==", "=~", "=", "!=", "<", ">",
                               "-eq", "-ne", "-lt", "-le", "-gt", "-ge"])
     }
 }
@@ -2,19 +2,22 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 mod completion;
-
-use crate::{file_check, InputError, ShellCore};
+mod edit;
+mod history_expansion;
+mod prompt;
+mod search;
+mod vi;
+
+use crate::{error_message, Feeder, InputError, ShellCore};
+use self::vi::ViResult;
 use std::io;
-use std::fs::File;
-use std::io::{Write, Stdout};
+use std::io::{Write, Stdin, Stdout};
 use std::sync::atomic::Ordering::Relaxed;
-use std::path::Path;
-use nix::unistd;
-use nix::unistd::User;
 use termion::cursor::DetectCursorPos;
 use termion::event;
+use termion::event::Event;
 use termion::raw::{IntoRawMode, RawTerminal};
-use termion::input::TermRead;
+use termion::input::{Events, TermRead};
 use unicode_width::UnicodeWidthChar;
 
 struct Terminal {
@@ -30,62 +33,20 @@ struct Terminal {
     tab_row: i32,
     tab_col: i32,
     escape_at_completion: bool,
-}
-
-fn oct_string(s: &str) -> bool {
-    if s.chars().nth(0) != Some('\\') {
-        return false;
-    }
-
-    for i in 1..4 {
-        match s.chars().nth(i) {
-            Some(c) => {
-                if c < '0' || '9' < c {
-                    return false;
-                }
-            },
-            _ => return false,
-        }
-    }
-
-    true
-}
-
-fn oct_to_hex_in_str(from: &str) -> String {
-    let mut i = 0;
-    let mut pos = vec![];
-
-    for ch in from.chars() {
-        if oct_string(&from[i..]) {
-            pos.push(i);
-        }
-        i += ch.len_utf8();
-    }
-
-    let mut prev = 0;
-    let mut ans = String::new();
-    for p in pos {
-        ans += &from[prev..p];
-        if let Ok(n) = u32::from_str_radix(&from[p+1..p+4], 8) {
-            ans += &char::from_u32(n).unwrap().to_string();
-        }
-        prev = p+4;
-    }
-    ans += &from[prev..];
-    ans
+    /* for vi mode */
+    vi_normal: bool,
+    vi_last_find: Option<(char, char)>,
 }
 
 impl Terminal {
     pub fn new(core: &mut ShellCore, ps: &str) -> Self {
-        let raw_prompt = core.data.get_param(ps);
-        let ansi_on_prompt = oct_to_hex_in_str(&raw_prompt);
-
-        let replaced_prompt = Self::make_prompt_string(&ansi_on_prompt);
+        let replaced_prompt = prompt::make_prompt_string(ps, core);
         let prompt = replaced_prompt.replace("\\[", "").replace("\\]", "").to_string();
         print!("{}", prompt);
         io::stdout().flush().unwrap();
 
         let mut sout = io::stdout().into_raw_mode().unwrap();
+        write!(sout, "\x1b[?2004h").unwrap(); // bracketed paste mode
         let row = sout.cursor_pos().unwrap_or((1,1)).1;
 
         Terminal {
@@ -100,63 +61,11 @@ impl Terminal {
             tab_row: -1,
             tab_col: -1,
             escape_at_completion: true,
+            vi_normal: false,
+            vi_last_find: None,
         }
     }
 
-    fn get_branch(cwd: &String) -> String {
-        let mut dirs: Vec<String> = cwd.split("/").map(|s| s.to_string()).collect();
-        while dirs.len() > 0 {
-            let path = dirs.join("/") + "/.git/HEAD";
-            dirs.pop();
-
-            if ! file_check::is_regular_file(&path) {
-                continue;
-            }
-
-            if let Ok(mut f) = File::open(Path::new(&path)){
-                return match f.read_line() {
-                    Ok(Some(s)) => s.replace("ref: refs/heads/","") + "🌵",
-                    _ => "".to_string(),
-                };
-            }
-        }
-
-        "".to_string()
-    }
-
-    fn make_prompt_string(raw: &str) -> String {
-        let uid = unistd::getuid();
-        let user = match User::from_uid(uid) {
-            Ok(Some(u)) => u.name,
-            _ => "".to_string(),
-        };
-        let hostname = match unistd::gethostname() {
-            Ok(h) => h.to_string_lossy().to_string(),
-            _ => "".to_string(),
-        };
-
-        let homedir = match User::from_uid(uid) {
-            Ok(Some(u)) => u.dir.to_string_lossy().to_string(),
-            _ => "".to_string(),
-        };
-        let mut cwd = match unistd::getcwd() {
-            Ok(p) => p.to_string_lossy()
-                      .to_string(),
-            _ => "".to_string(),
-        };
-        let branch = Self::get_branch(&cwd);
-
-        if cwd.starts_with(&homedir) {
-            cwd = cwd.replacen(&homedir, "~", 1);
-        }
-
-        raw.replace("\\u", &user)
-           .replace("\\h", &hostname)
-           .replace("\\w", &cwd)
-           .replace("\\b", &branch)
-           .to_string()
-    }
-
     fn make_width_map(prompt: &str) -> Vec<usize> {
         let tmp = prompt.replace("\\[", "\x01").replace("\\]", "\x02").to_string();
         let mut in_escape = false;
@@ -184,6 +93,10 @@ impl Terminal {
         self.stdout.flush().unwrap();
     }
 
+    fn origin(&self) -> usize {
+        self.prompt.chars().count()
+    }
+
     fn char_width(&self, c: &char, pos: usize) -> usize {
         if pos < self.prompt.chars().count() {
             return self.prompt_width_map[pos];
@@ -299,6 +212,33 @@ impl Terminal {
         self.flush();
     }
 
+    fn line_head(&self, pos: usize) -> usize {
+        let origin = self.origin();
+        match (origin..pos).rev().find(|i| self.chars[*i] == '\n') {
+            Some(i) => i + 1,
+            None    => origin,
+        }
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        (pos..self.chars.len()).find(|i| self.chars[*i] == '\n').unwrap_or(self.chars.len())
+    }
+
+    /* moves the cursor in a multi-line command. false if there is no line to move to */
+    pub fn move_line(&mut self, up: bool) -> bool {
+        let head = self.line_head(self.head);
+        let target = match up {
+            true if head > self.origin() => self.line_head(head - 1),
+            false if self.line_end(self.head) < self.chars.len() => self.line_end(self.head) + 1,
+            _ => return false,
+        };
+
+        self.head = (target + self.head - head).min(self.line_end(target));
+        self.goto(self.head);
+        self.flush();
+        true
+    }
+
     pub fn check_scroll(&mut self) {
         let extra_lines = self.head_to_cursor_pos(self.chars.len(), 0).1;
         let row = Terminal::size().1;
@@ -321,10 +261,18 @@ impl Terminal {
         self.prompt_row = std::cmp::max(ans, 1) as usize;
     }
 
+    /* another history entry is called at the first or the last line */
+    pub fn move_line_or_history(&mut self, up: bool, core: &mut ShellCore) {
+        if ! self.move_line(up) {
+            self.call_history(if up { 1 } else { -1 }, core);
+        }
+    }
+
     pub fn call_history(&mut self, inc: i32, core: &mut ShellCore){
         let prev = self.hist_ptr;
         let prev_str = self.get_string(self.prompt.chars().count());
-        Self::shift_in_range(&mut self.hist_ptr, inc, 0, std::isize::MAX as usize);
+        let max = core.history.len().saturating_sub(1);
+        Self::shift_in_range(&mut self.hist_ptr, inc, 0, max);
 
         self.chars = self.prompt.chars().collect();
         self.chars.extend(core.fetch_history(self.hist_ptr, prev, prev_str).replace("↵ \0", "\n").chars());
@@ -332,17 +280,48 @@ impl Terminal {
         self.rewrite(true);
     }
 
+    pub fn is_complete(&self, core: &mut ShellCore) -> bool {
+        let text = self.get_string(self.prompt.chars().count()) + "\n";
+        Feeder::is_complete(&text, core)
+    }
+
     pub fn set_double_tab_completion(&mut self) {
         let s = self.completion_candidate.clone() + " ";
         self.replace_input(&s);
     }
 
+    pub fn accept(&mut self) {
+        self.goto(self.chars.len());
+        self.write("\r\n");
+        self.chars.push('\n');
+    }
+
     pub fn cloop(&mut self) {
         print!("\x07");
         self.flush();
     }
 }
 
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.write("\x1b[?2004l");
+        self.flush();
+    }
+}
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/* events other than keys are skipped */
+fn next_key(events: &mut Events<Stdin>) -> Option<event::Key> {
+    for e in events.by_ref() {
+        if let Ok(Event::Key(k)) = e {
+            return Some(k);
+        }
+    }
+    None
+}
+
 fn is_completion_key(key: event::Key) -> bool {
     match key {
         event::Key::Char('\t') 
@@ -364,8 +343,8 @@ fn on_arrow_key(term: &mut Terminal, core: &mut ShellCore, key: &event::Key, tab
         term.completion(core, tab_num);
     }else{
         match key {
-            event::Key::Down  => term.call_history(-1, core),
-            event::Key::Up    => term.call_history(1, core),
+            event::Key::Down  => term.move_line_or_history(false, core),
+            event::Key::Up    => term.move_line_or_history(true, core),
             event::Key::Right => term.shift_cursor(1),
             event::Key::Left  => term.shift_cursor(-1),
             _ => {},
@@ -374,16 +353,81 @@ fn on_arrow_key(term: &mut Terminal, core: &mut ShellCore, key: &event::Key, tab
 }
 
 pub fn read_line(core: &mut ShellCore, prompt: &str) -> Result<String, InputError>{
+    if prompt == "PS1" {
+        prompt::run_prompt_command(core);
+    }
+    let line = edit_line(core, prompt)?;
+    Ok(expand_history(line, core))
+}
+
+/* an interactive shell reading stdin without a terminal shows the prompt on stderr as bash */
+pub fn read_line_without_terminal(core: &mut ShellCore, prompt: &str) -> Result<String, InputError>{
+    if prompt == "PS1" {
+        prompt::run_prompt_command(core);
+    }
+    let ps = prompt::make_prompt_string(prompt, core);
+    eprint!("{}", ps.replace("\\[", "").replace("\\]", ""));
+
+    let line = match core.input_sources.last_mut() {
+        Some(s) => s.read_line()?,
+        None    => return Err(InputError::Eof),
+    };
+    Ok(expand_history(line, core))
+}
+
+fn expand_history(line: String, core: &mut ShellCore) -> String {
+    if ! core.data.flags.contains('H') {
+        return line;
+    }
+
+    match history_expansion::expand(&line, core) {
+        Ok(Some(ln)) => {
+            eprint!("{}", &ln);
+            ln
+        },
+        Ok(None) => line,
+        Err(e) => {
+            error_message::print(&e.to_string(), core, true);
+            "\n".to_string()
+        },
+    }
+}
+
+fn edit_line(core: &mut ShellCore, prompt: &str) -> Result<String, InputError>{
     let mut term = Terminal::new(core, prompt);
     let mut term_size = Terminal::size();
     core.history.insert(0, String::new());
     let mut prev_key = event::Key::Char('a');
     let mut tab_num = 0;
 
-    for c in io::stdin().keys() {
+    let mut events = io::stdin().events();
+    while let Some(e) = events.next() {
+        let c = match e {
+            Ok(Event::Key(k)) => k,
+            Ok(Event::Unsupported(s)) if s == PASTE_START => {
+                term.paste(&mut events);
+                term.check_scroll();
+                continue;
+            },
+            _ => continue,
+        };
         term.check_size_change(&mut term_size);
 
-        match c.as_ref().unwrap() {
+        if core.options.query("vi") {
+            match term.vi_key(c, core, &mut events) {
+                ViResult::Pass => {},
+                ViResult::Done => {
+                    term.check_scroll();
+                    continue;
+                },
+                ViResult::Accept => {
+                    term.accept();
+                    break;
+                },
+            }
+        }
+
+        match c {
             event::Key::Ctrl('a') => term.goto_origin(),
             event::Key::Ctrl('b') => term.shift_cursor(-1),
             event::Key::Ctrl('c') => {
@@ -402,19 +446,38 @@ pub fn read_line(core: &mut ShellCore, prompt: &str) -> Result<String, InputErro
             },
             event::Key::Ctrl('e') => term.goto_end(),
             event::Key::Ctrl('f') => term.shift_cursor(1),
+            event::Key::Ctrl('h') => term.backspace(),
+            event::Key::Ctrl('k') => term.kill_line(core),
+            event::Key::Ctrl('l') => term.clear_screen(),
+            event::Key::Ctrl('r') => {
+                let found = term.reverse_search(core, &mut events);
+                if found {
+                    term.accept();
+                    break;
+                }
+            },
+            event::Key::Ctrl('u') => term.kill_line_backward(core),
+            event::Key::Ctrl('w') => term.kill_word_backward(core),
+            event::Key::Ctrl('y') => term.yank(core),
+            event::Key::Alt('b') => term.backward_word(),
+            event::Key::Alt('d') => term.kill_word(core),
+            event::Key::Alt('f') => term.forward_word(),
+            event::Key::Home => term.goto_origin(),
+            event::Key::End => term.goto_end(),
             event::Key::Down |
             event::Key::Left |
             event::Key::Right |
-            event::Key::Up => on_arrow_key(&mut term, core, c.as_ref().unwrap(), tab_num),
+            event::Key::Up => on_arrow_key(&mut term, core, &c, tab_num),
             event::Key::Backspace => term.backspace(),
             event::Key::Delete => term.delete(),
             event::Key::Char('\n') => {
                 if term.completion_candidate.len() > 0 {
                     term.set_double_tab_completion();
+                }else if prompt == "PS1" && ! term.is_complete(core) {
+                    term.goto_end();
+                    term.insert('\n');
                 }else{
-                    term.goto(term.chars.len());
-                    term.write("\r\n");
-                    term.chars.push('\n');
+                    term.accept();
                     break;
                 }
             },
@@ -431,12 +494,12 @@ pub fn read_line(core: &mut ShellCore, prompt: &str) -> Result<String, InputErro
                 term.completion(core, tab_num);
             },
             event::Key::Char(c) => {
-                term.insert(*c);
+                term.insert(c);
             },
             _  => {},
         }
         term.check_scroll();
-        prev_key = c.as_ref().unwrap().clone();
+        prev_key = c;
         if ! is_completion_key(prev_key) {
             tab_num = 0;
             term.completion_candidate = String::new();
@@ -3,13 +3,30 @@
 
 use crate::{error_message, file_check, Feeder, ShellCore, utils};
 use crate::core::builtins::completion;
+use crate::core::builtins::completion::Snapshot;
 use crate::elements::command::simple::SimpleCommand;
-use crate::elements::command::Command;
-use crate::elements::io::pipe::Pipe;
 use crate::feeder::terminal::Terminal;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use std::io;
+use std::os::fd::AsFd;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
 use termion::cursor::DetectCursorPos;
 use unicode_width::UnicodeWidthStr;
 
+const SPINNER: &str = "|/-\\";
+const SPINNER_DELAY: Duration = Duration::from_millis(300);
+
+fn key_pending() -> bool {
+    let stdin = io::stdin();
+    let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+    matches!(poll(&mut fds, PollTimeout::ZERO), Ok(n) if n > 0)
+}
+
 fn str_width(s: &str) -> usize {
     UnicodeWidthStr::width(s)
 }
@@ -53,9 +70,11 @@ impl Terminal {
         core.data.set_array("COMPREPLY", &vec![]);
         self.set_completion_info(core);
 
-        if ! Self::set_custom_compreply(core)
+        if ! self.set_custom_compreply(core)
         && ! self.set_default_compreply(core) {
-            self.cloop();
+            if ! key_pending() {
+                self.cloop();
+            }
             return;
         }
 
@@ -65,32 +84,28 @@ impl Terminal {
         }
     }
 
-    fn set_custom_compreply(core: &mut ShellCore) -> bool {
+    fn set_custom_compreply(&mut self, core: &mut ShellCore) -> bool {
         let cur_pos = Self::get_cur_pos(core);
-        let prev_pos = cur_pos - 1;
         let word_num = core.data.get_array_len("COMP_WORDS") as i32;
-
-        if prev_pos < 0 || prev_pos >= word_num {
+        if cur_pos < 1 || cur_pos > word_num {
             return false;
         }
 
         let org_word = core.data.get_array("COMP_WORDS", "0");
-        let prev_word = core.data.get_array("COMP_WORDS", &prev_pos.to_string());
+        let spec = match core.completion_specs.get(&org_word) {
+            Some(s) => s.clone(),
+            None    => return false,
+        };
+        let prev_word = core.data.get_array("COMP_WORDS", &(cur_pos - 1).to_string());
         let cur_word = core.data.get_array("COMP_WORDS", &cur_pos.to_string());
 
-        match core.completion_functions.get(&org_word) {
-            Some(value) => {
-                let command = format!("prev={} cur={} {}", &prev_word, &cur_word, &value);//TODO: cur should be set
-                let mut feeder = Feeder::new(&command);                                        // by bash-completion 
+        let prompt = self.prompt.chars().count();
+        core.data.set_param("COMP_LINE", &self.get_string(prompt));
+        core.data.set_param("COMP_POINT", &(self.head - prompt).to_string());
 
-                if let Some(mut a) = SimpleCommand::parse(&mut feeder, core) {
-                    let mut dummy = Pipe::new("".to_string());
-                    a.exec(core, &mut dummy);
-                }
-                core.data.get_array_len("COMPREPLY") != 0
-            },
-            _ => false
-        }
+        let list = spec.generate(core, &org_word, &cur_word, &prev_word);
+        core.data.set_array("COMPREPLY", &list);
+        ! list.is_empty()
     }
 
     fn get_cur_pos(core: &mut ShellCore) -> i32 {
@@ -107,17 +122,18 @@ impl Terminal {
 
         let (tilde_prefix, tilde_path, last_tilde_expanded) = Self::set_tilde_transform(&last, core);
 
-        let mut args = vec!["".to_string(), "".to_string(), last_tilde_expanded.to_string()];
+        let snapshot = Snapshot::new(core);
+        let target = last_tilde_expanded.replace("\\", "");
         let list = match pos == "0" {
             true  => {
                 if core.data.get_array_len("COMP_WORDS") == 0 {
                     self.escape_at_completion = false;
-                    completion::compgen_h(core, &mut args).to_vec().into_iter().filter(|h| h.len() > 0).collect()
+                    completion::compgen_h(core).into_iter().filter(|h| ! h.is_empty()).collect()
                 }else{
-                    completion::compgen_c(core, &mut args)
+                    self.generate(move |c| completion::commands(&target, true, &snapshot, c))
                 }
             },
-            false => completion::compgen_f(core, &mut args),
+            false => self.generate(move |c| completion::files(&target, snapshot.extglob, c)),
         };
 
         if list.len() == 0 {
@@ -129,6 +145,47 @@ impl Terminal {
         true
     }
 
+    /* candidates are listed on a worker thread so that slow directories
+       do not freeze the editor. a key input cancels the listing */
+    fn generate<F>(&mut self, job: F) -> Vec<String>
+    where F: FnOnce(&AtomicBool) -> Vec<String> + Send + 'static {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let flag = cancel.clone();
+        thread::spawn(move || { let _ = tx.send(job(&flag)); });
+
+        let start = Instant::now();
+        let mut spinning = false;
+        for frame in SPINNER.chars().cycle() {
+            match rx.recv_timeout(Duration::from_millis(50)) {
+                Ok(list) => {
+                    if spinning {
+                        self.rewrite(true);
+                    }
+                    return list;
+                },
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {},
+            }
+
+            if key_pending() {
+                cancel.store(true, Relaxed);
+                break;
+            }
+
+            if start.elapsed() >= SPINNER_DELAY {
+                self.write(&format!("{}\x08", frame));
+                self.flush();
+                spinning = true;
+            }
+        }
+
+        if spinning {
+            self.rewrite(true);
+        }
+        vec![]
+    }
+
     pub fn try_completion(&mut self, core: &mut ShellCore) {
         let pos = core.data.get_param("COMP_CWORD").to_string();
         let target = core.data.get_array("COMP_WORDS", &pos);
@@ -0,0 +1,118 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::terminal::{Terminal, PASTE_END};
+use std::io::Stdin;
+use termion::event::{Event, Key};
+use termion::input::Events;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Terminal {
+    fn backward_word_pos(&self, is_word: fn(char) -> bool) -> usize {
+        let mut pos = self.head;
+        while pos > self.origin() && ! is_word(self.chars[pos-1]) {
+            pos -= 1;
+        }
+        while pos > self.origin() && is_word(self.chars[pos-1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    fn forward_word_pos(&self) -> usize {
+        let mut pos = self.head;
+        while pos < self.chars.len() && ! is_word_char(self.chars[pos]) {
+            pos += 1;
+        }
+        while pos < self.chars.len() && is_word_char(self.chars[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn move_to(&mut self, pos: usize) {
+        if pos == self.head {
+            self.cloop();
+            return;
+        }
+        self.head = pos;
+        self.goto(self.head);
+        self.flush();
+    }
+
+    pub fn backward_word(&mut self) {
+        self.move_to(self.backward_word_pos(is_word_char));
+    }
+
+    pub fn forward_word(&mut self) {
+        self.move_to(self.forward_word_pos());
+    }
+
+    /* the removed text is kept in the core for yank */
+    fn kill(&mut self, from: usize, to: usize, core: &mut ShellCore) {
+        if from >= to {
+            self.cloop();
+            return;
+        }
+        core.kill_ring = self.chars.drain(from..to).collect();
+        self.head = from;
+        self.rewrite(true);
+    }
+
+    pub fn kill_line(&mut self, core: &mut ShellCore) {
+        self.kill(self.head, self.chars.len(), core);
+    }
+
+    pub fn kill_line_backward(&mut self, core: &mut ShellCore) {
+        self.kill(self.origin(), self.head, core);
+    }
+
+    pub fn kill_word(&mut self, core: &mut ShellCore) {
+        self.kill(self.head, self.forward_word_pos(), core);
+    }
+
+    /* Ctrl-W uses white spaces as the boundaries of words */
+    pub fn kill_word_backward(&mut self, core: &mut ShellCore) {
+        let pos = self.backward_word_pos(|c| ! c.is_whitespace());
+        self.kill(pos, self.head, core);
+    }
+
+    pub fn yank(&mut self, core: &mut ShellCore) {
+        if core.kill_ring.is_empty() {
+            self.cloop();
+            return;
+        }
+        let text: Vec<char> = core.kill_ring.chars().collect();
+        let len = text.len();
+        self.chars.splice(self.head..self.head, text);
+        self.head += len;
+        self.rewrite(true);
+    }
+
+    /* pasted text is inserted as it is, and newlines in it don't accept the line */
+    pub fn paste(&mut self, events: &mut Events<Stdin>) {
+        let mut text = vec![];
+        for e in events.by_ref() {
+            match e {
+                Ok(Event::Key(Key::Char(c))) => text.push(c),
+                Ok(Event::Unsupported(s)) if s == PASTE_END => break,
+                _ => {},
+            }
+        }
+
+        let len = text.len();
+        self.chars.splice(self.head..self.head, text);
+        self.head += len;
+        self.rewrite(true);
+    }
+
+    pub fn clear_screen(&mut self) {
+        self.write(termion::clear::All.as_ref());
+        self.prompt_row = 1;
+        self.rewrite(true);
+    }
+}
@@ -0,0 +1,267 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::quoting::Quoting;
+
+fn is_meta(c: char) -> bool {
+    ";&|<>()".contains(c)
+}
+
+fn push_word(words: &mut Vec<String>, word: &mut String) {
+    if ! word.is_empty() {
+        words.push(std::mem::take(word));
+    }
+}
+
+/* quotes are kept in the words, and operators are separated from the others */
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoting = Quoting::new(line);
+
+    loop {
+        let outside = ! quoting.is_open();
+        let c = match quoting.next() {
+            Some((_, c, _)) => c,
+            None => break,
+        };
+
+        if outside && (c == ' ' || c == '\t' || c == '\n') {
+            push_word(&mut words, &mut word);
+            continue;
+        }
+        if outside && word.ends_with(is_meta) != is_meta(c) {
+            push_word(&mut words, &mut word);
+        }
+        word.push(c);
+    }
+    push_word(&mut words, &mut word);
+    words
+}
+
+fn scan_while(chars: &[char], pos: &mut usize, f: impl Fn(char) -> bool) -> String {
+    let start = *pos;
+    while *pos < chars.len() && f(chars[*pos]) {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+fn scan_number(chars: &[char], pos: &mut usize) -> Option<usize> {
+    scan_while(chars, pos, |c| c.is_ascii_digit()).parse::<usize>().ok()
+}
+
+/* history has the previous lines from the newest one, and next is the number of the current line */
+fn find_event(chars: &[char], pos: &mut usize, history: &[String], next: usize) -> Option<String> {
+    let found = match chars.get(*pos) {
+        Some('!') => {
+            *pos += 1;
+            history.first()
+        },
+        Some('$') | Some('^') | Some('*') | Some(':') => history.first(),
+        Some('-') => {
+            *pos += 1;
+            let n = scan_number(chars, pos)?;
+            history.get(n.checked_sub(1)?)
+        },
+        Some(c) if c.is_ascii_digit() => {
+            let n = scan_number(chars, pos)?;
+            history.get((next - 1).checked_sub(n)?)
+        },
+        Some('?') => {
+            *pos += 1;
+            let s = scan_while(chars, pos, |c| c != '?' && c != '\n');
+            if chars.get(*pos) == Some(&'?') {
+                *pos += 1;
+            }
+            match s.is_empty() {
+                true  => None,
+                false => history.iter().find(|h| h.contains(&s)),
+            }
+        },
+        _ => {
+            let s = scan_while(chars, pos, |c| ! c.is_whitespace() && ! ":;&|<>()\"'`".contains(c));
+            match s.is_empty() {
+                true  => None,
+                false => history.iter().find(|h| h.starts_with(&s)),
+            }
+        },
+    };
+    found.cloned()
+}
+
+/* word designators: n, ^, $, *, x-y, x-, x* */
+fn select_words(line: &str, chars: &[char], pos: &mut usize) -> Option<String> {
+    let designator = |c: Option<&char>| c.is_some_and(|c| c.is_ascii_digit() || "^$*-".contains(*c));
+    if chars.get(*pos) == Some(&':') && designator(chars.get(*pos + 1)) {
+        *pos += 1;
+    }else if ! chars.get(*pos).is_some_and(|c| "^$*".contains(*c)) {
+        return Some(line.to_string());
+    }
+
+    let words = split_words(line);
+    let last = words.len().checked_sub(1)?;
+    let (from, to) = match chars[*pos] {
+        '^' => { *pos += 1; (1, 1) },
+        '$' => { *pos += 1; (last, last) },
+        '*' => { *pos += 1; (1, last) },
+        _ => {
+            let from = match chars[*pos] {
+                '-' => 0,
+                _   => scan_number(chars, pos)?,
+            };
+            match chars.get(*pos) {
+                Some('*') => { *pos += 1; (from, last) },
+                Some('-') => {
+                    *pos += 1;
+                    match chars.get(*pos) {
+                        Some('$') => { *pos += 1; (from, last) },
+                        Some(c) if c.is_ascii_digit() => (from, scan_number(chars, pos)?),
+                        _ => (from, last.checked_sub(1)?),
+                    }
+                },
+                _ => (from, from),
+            }
+        },
+    };
+
+    if from > to && chars[*pos - 1] == '*' {
+        return Some(String::new());
+    }
+    match from <= to && to <= last {
+        true  => Some(words[from..=to].join(" ")),
+        false => None,
+    }
+}
+
+fn substitute(text: &str, old: &str, new: &str) -> Option<String> {
+    match ! old.is_empty() && text.contains(old) {
+        true  => Some(text.replacen(old, &new.replace('&', old), 1)),
+        false => None,
+    }
+}
+
+/* modifiers: h, t, r, e, s/old/new/ */
+fn modify(mut text: String, chars: &[char], pos: &mut usize) -> Option<String> {
+    while chars.get(*pos) == Some(&':') {
+        text = match chars.get(*pos + 1) {
+            Some('h') => match text.rfind('/') {
+                Some(i) => text[..i].to_string(),
+                None    => text,
+            },
+            Some('t') => text.rsplit('/').next().unwrap_or("").to_string(),
+            Some('r') => match text.rfind('.') {
+                Some(i) if ! text[i..].contains('/') => text[..i].to_string(),
+                _ => text,
+            },
+            Some('e') => match text.rfind('.') {
+                Some(i) if ! text[i..].contains('/') => text[i..].to_string(),
+                _ => String::new(),
+            },
+            Some('s') if *pos + 2 < chars.len() => {
+                let delim = chars[*pos + 2];
+                *pos += 3;
+                let old = scan_while(chars, pos, |c| c != delim && c != '\n');
+                if chars.get(*pos) == Some(&delim) {
+                    *pos += 1;
+                }
+                let new = scan_while(chars, pos, |c| c != delim && c != '\n');
+                if chars.get(*pos) == Some(&delim) {
+                    *pos += 1;
+                }
+                text = substitute(&text, &old, &new)?;
+                continue;
+            },
+            _ => break,
+        };
+        *pos += 2;
+    }
+    Some(text)
+}
+
+fn expand_bang(chars: &[char], pos: &mut usize, history: &[String], next: usize) -> Result<String, String> {
+    let start = *pos - 1;
+    let spec = |pos: usize| chars[start..pos.min(chars.len())].iter().collect::<String>();
+
+    let line = find_event(chars, pos, history, next)
+               .ok_or_else(|| format!("{}: event not found", spec(*pos)))?;
+    let words = select_words(&line, chars, pos)
+                .ok_or_else(|| format!("{}: bad word specifier", spec(*pos)))?;
+    modify(words, chars, pos).ok_or_else(|| format!("{}: substitution failed", spec(*pos)))
+}
+
+/* ^old^new^ at the head of a line is the same as !!:s^old^new^ */
+fn quick_substitution(chars: &[char], pos: &mut usize, history: &[String]) -> Result<String, String> {
+    *pos = 1;
+    let old = scan_while(chars, pos, |c| c != '^' && c != '\n');
+    if chars.get(*pos) == Some(&'^') {
+        *pos += 1;
+    }
+    let new = scan_while(chars, pos, |c| c != '^' && c != '\n');
+    if chars.get(*pos) == Some(&'^') {
+        *pos += 1;
+    }
+
+    let spec: String = chars[..(*pos).min(chars.len())].iter().collect();
+    let line = history.first().ok_or_else(|| format!("{}: event not found", spec))?;
+    substitute(line, &old, &new).ok_or_else(|| format!("{}: substitution failed", spec))
+}
+
+fn is_literal_bang(next: Option<&char>, quote: Option<char>) -> bool {
+    match next {
+        None => true,
+        Some(c) => c.is_whitespace() || *c == '=' || *c == '(' || (*c == '"' && quote == Some('"')),
+    }
+}
+
+/* returns None if the line has no history expansion */
+pub fn expand(line: &str, core: &mut ShellCore) -> Result<Option<String>, String> {
+    if ! line.contains('!') && ! line.starts_with('^') {
+        return Ok(None);
+    }
+
+    /* the line being edited on the terminal is in the history as the first one */
+    let skip = core.reading_terminal() as usize;
+    let history: Vec<String> = core.history.iter().skip(skip)
+                               .map(|h| h.replace("↵ \0", "\n")).collect();
+    let next = core.history_number;
+    let chars: Vec<char> = line.chars().collect();
+    let mut ans = String::new();
+    let mut pos = 0;
+    let mut quote = None;
+    let mut expanded = false;
+
+    if chars.first() == Some(&'^') {
+        ans = quick_substitution(&chars, &mut pos, &history)?;
+        expanded = true;
+    }
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some('\''), _) => {},
+            (_, '\\') if pos < chars.len() => {
+                ans.push(c);
+                ans.push(chars[pos]);
+                pos += 1;
+                continue;
+            },
+            (_, '!') if ! is_literal_bang(chars.get(pos), quote) => {
+                ans += &expand_bang(&chars, &mut pos, &history, next)?;
+                expanded = true;
+                continue;
+            },
+            _ => {},
+        }
+        ans.push(c);
+    }
+
+    match expanded {
+        true  => Ok(Some(ans)),
+        false => Ok(None),
+    }
+}
@@ -0,0 +1,235 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{file_check, Feeder, ShellCore};
+use crate::core::data::Value;
+use crate::elements::word::Word;
+use crate::feeder::input::InputSource;
+use std::ffi::CString;
+use std::fs::File;
+use std::path::Path;
+use nix::{libc, unistd};
+use nix::unistd::User;
+use termion::input::TermRead;
+
+fn oct_string(s: &str) -> bool {
+    if ! s.starts_with('\\') {
+        return false;
+    }
+
+    for i in 1..4 {
+        match s.chars().nth(i) {
+            Some(c) => {
+                if ! c.is_ascii_digit() {
+                    return false;
+                }
+            },
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+fn oct_to_hex_in_str(from: &str) -> String {
+    let mut i = 0;
+    let mut pos = vec![];
+
+    for ch in from.chars() {
+        if oct_string(&from[i..]) {
+            pos.push(i);
+        }
+        i += ch.len_utf8();
+    }
+
+    let mut prev = 0;
+    let mut ans = String::new();
+    for p in pos {
+        ans += &from[prev..p];
+        if let Ok(n) = u32::from_str_radix(&from[p+1..p+4], 8) {
+            ans += &char::from_u32(n).unwrap().to_string();
+        }
+        prev = p+4;
+    }
+    ans += &from[prev..];
+    ans
+}
+
+fn get_branch(cwd: &str) -> String {
+    let mut dirs: Vec<String> = cwd.split("/").map(|s| s.to_string()).collect();
+    while ! dirs.is_empty() {
+        let path = dirs.join("/") + "/.git/HEAD";
+        dirs.pop();
+
+        if ! file_check::is_regular_file(&path) {
+            continue;
+        }
+
+        if let Ok(mut f) = File::open(Path::new(&path)){
+            return match f.read_line() {
+                Ok(Some(s)) => s.replace("ref: refs/heads/","") + "🌵",
+                _ => "".to_string(),
+            };
+        }
+    }
+
+    "".to_string()
+}
+
+fn strftime(format: &str) -> String {
+    let format = match CString::new(format) {
+        Ok(f) => f,
+        _     => return String::new(),
+    };
+
+    let mut buf = [0u8; 256];
+    let len = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), format.as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
+
+fn current_directory(core: &mut ShellCore) -> String {
+    match core.get_current_directory() {
+        Some(p) => p.display().to_string(),
+        None    => core.data.get_param("PWD"),
+    }
+}
+
+fn working_directory(core: &mut ShellCore, basename: bool) -> String {
+    let cwd = current_directory(core);
+    let home = core.data.get_param("HOME");
+    if ! home.is_empty() && cwd == home {
+        return "~".to_string();
+    }
+    if basename {
+        return match cwd.rsplit_once('/') {
+            Some((_, "")) | None => cwd,
+            Some((_, name))      => name.to_string(),
+        };
+    }
+
+    match cwd.strip_prefix(&home) {
+        Some(rest) if ! home.is_empty() && rest.starts_with("/") => format!("~{}", rest),
+        _ => cwd,
+    }
+}
+
+fn escape(c: char, chars: &mut std::iter::Peekable<std::str::Chars>, core: &mut ShellCore) -> Option<String> {
+    let hostname = || match unistd::gethostname() {
+        Ok(h) => h.to_string_lossy().to_string(),
+        _ => "".to_string(),
+    };
+
+    let ans = match c {
+        'u' => match User::from_uid(unistd::getuid()) {
+            Ok(Some(u)) => u.name,
+            _ => "".to_string(),
+        },
+        'h' => hostname().split('.').next().unwrap_or("").to_string(),
+        'H' => hostname(),
+        'w' => working_directory(core, false),
+        'W' => working_directory(core, true),
+        'b' => get_branch(&current_directory(core)),
+        '$' => if unistd::geteuid().is_root() { "#" } else { "$" }.to_string(),
+        'd' => strftime("%a %b %d"),
+        't' => strftime("%H:%M:%S"),
+        'T' => strftime("%I:%M:%S"),
+        '@' => strftime("%I:%M %p"),
+        'A' => strftime("%H:%M"),
+        'D' if chars.peek() == Some(&'{') => {
+            chars.next();
+            let format: String = chars.by_ref().take_while(|c| *c != '}').collect();
+            match format.as_str() {
+                "" => strftime("%X"),
+                f  => strftime(f),
+            }
+        },
+        'j' => core.job_table.len().to_string(),
+        '!' => core.history_number.to_string(),
+        '#' => core.command_number.to_string(),
+        's' => "sush".to_string(),
+        'v' | 'V' => env!("CARGO_PKG_VERSION").to_string(),
+        'n' => "\n".to_string(),
+        'a' => "\x07".to_string(),
+        'e' => "\x1b".to_string(),
+        '\\' => "\\".to_string(),
+        _   => return None,
+    };
+    Some(ans)
+}
+
+/* \[ and \] are left for the calculation of the width */
+fn decode_escapes(raw: &str, core: &mut ShellCore) -> (String, String) {
+    let mut plain = String::new();
+    let mut quoted = String::new();
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let s = match (c, chars.peek().copied()) {
+            ('\\', Some(e)) if e != '[' && e != ']' => {
+                chars.next();
+                match escape(e, &mut chars, core) {
+                    Some(s) => s,
+                    None    => format!("\\{}", e),
+                }
+            },
+            _ => {
+                plain.push(c);
+                match c {
+                    '"' => quoted.push_str("\\\""),
+                    _   => quoted.push(c),
+                }
+                continue;
+            },
+        };
+
+        plain += &s;
+        for c in s.chars() {
+            if "\\$`\"".contains(c) {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+    }
+    (plain, quoted)
+}
+
+/* parameters and command substitutions in the prompt are expanded like in double quotes */
+fn expand(quoted: &str, core: &mut ShellCore) -> Option<String> {
+    let mut feeder = Feeder::new(&format!("\"{}\"", quoted));
+    feeder.check_only = true;
+    let word = Word::parse(&mut feeder, core, false)?;
+    if feeder.len() != 0 {
+        return None;
+    }
+
+    let status = core.data.exit_status();
+    let ans = word.eval_as_value(core).ok();
+    core.data.set_exit_status(status.code());
+    ans
+}
+
+pub fn make_prompt_string(ps: &str, core: &mut ShellCore) -> String {
+    let raw = oct_to_hex_in_str(&core.data.get_param(ps));
+    let (plain, quoted) = decode_escapes(&raw, core);
+    expand(&quoted, core).unwrap_or(plain)
+}
+
+/* PROMPT_COMMAND is a command or an array of commands */
+pub fn run_prompt_command(core: &mut ShellCore) {
+    let commands = match core.data.get_value("PROMPT_COMMAND") {
+        Some(Value::EvaluatedArray(a)) => a.into_values().collect(),
+        Some(Value::EvaluatedSingle(s)) => vec![s],
+        _ => return,
+    };
+
+    let status = core.data.exit_status();
+    for com in commands.iter().filter(|c| ! c.is_empty()) {
+        core.exec_source(InputSource::text(com, "PROMPT_COMMAND"));
+    }
+    core.data.set_exit_status(status.code());
+}
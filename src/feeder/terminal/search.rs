@@ -0,0 +1,101 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::terminal::{next_key, Terminal};
+use std::io::Stdin;
+use termion::event;
+use termion::input::Events;
+
+fn search(history: &[String], query: &str, from: usize) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    (from..history.len()).find(|i| history[*i].contains(query))
+}
+
+impl Terminal {
+    fn set_line(&mut self, prompt: &str, width_map: Vec<usize>, line: &str, cursor: usize) {
+        self.prompt = prompt.to_string();
+        self.prompt_width_map = width_map;
+        self.chars = prompt.chars().chain(line.chars()).collect();
+        self.head = prompt.chars().count() + cursor;
+        self.check_scroll();
+        self.rewrite(true);
+    }
+
+    fn show_search(&mut self, query: &str, line: &str, failed: bool) {
+        let prompt = match failed {
+            true  => format!("(failed reverse-i-search)`{}': ", query),
+            false => format!("(reverse-i-search)`{}': ", query),
+        };
+        let cursor = match line.find(query) {
+            Some(i) if ! query.is_empty() => line[..i].chars().count(),
+            _ => line.chars().count(),
+        };
+        self.set_line(&prompt, Self::make_width_map(&prompt), line, cursor);
+    }
+
+    /* returns true when the line is accepted with the enter key */
+    pub fn reverse_search(&mut self, core: &mut ShellCore, events: &mut Events<Stdin>) -> bool {
+        let prompt = self.prompt.clone();
+        let width_map = self.prompt_width_map.clone();
+        let original = self.get_string(prompt.chars().count());
+        let history: Vec<String> = core.history.iter().map(|h| h.replace("↵ \0", "\n")).collect();
+
+        let mut query = String::new();
+        let mut found = None;
+        let mut failed = false;
+        let mut end_key = None;
+        self.show_search(&query, &original, failed);
+
+        while let Some(key) = next_key(events) {
+            let from = match key {
+                event::Key::Ctrl('r') => found.map_or(1, |i| i + 1),
+                event::Key::Backspace => {
+                    query.pop();
+                    1
+                },
+                event::Key::Char(ch) if ch != '\n' => {
+                    query.push(ch);
+                    found.unwrap_or(1)
+                },
+                event::Key::Ctrl('g') | event::Key::Ctrl('c') => {
+                    self.set_line(&prompt, width_map, &original, original.chars().count());
+                    return false;
+                },
+                _ => {
+                    end_key = Some(key);
+                    break;
+                },
+            };
+
+            match search(&history, &query, from) {
+                Some(i) => {
+                    found = Some(i);
+                    failed = false;
+                },
+                None => failed = ! query.is_empty(),
+            }
+            let line = found.map_or(&original, |i| &history[i]);
+            self.show_search(&query, line, failed);
+        }
+
+        let cursor = self.head - self.prompt.chars().count();
+        let line = self.get_string(self.prompt.chars().count());
+        self.set_line(&prompt, width_map, &line, cursor);
+        if let Some(i) = found {
+            self.hist_ptr = i;
+        }
+
+        match end_key {
+            Some(event::Key::Char('\n')) => return true,
+            Some(event::Key::Ctrl('a')) | Some(event::Key::Home) => self.goto_origin(),
+            Some(event::Key::Ctrl('e')) | Some(event::Key::End) => self.goto_end(),
+            Some(event::Key::Ctrl('b')) | Some(event::Key::Left) => self.shift_cursor(-1),
+            Some(event::Key::Ctrl('f')) | Some(event::Key::Right) => self.shift_cursor(1),
+            _ => {},
+        }
+        false
+    }
+}
@@ -0,0 +1,342 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::terminal::{next_key, Terminal};
+use std::{fs, process};
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Stdin, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use termion::cursor::DetectCursorPos;
+use termion::event::Key;
+use termion::input::Events;
+
+/* the file is newly created with mode 0600 so that no one else can
+   prepare or read it, even in a shared directory such as /tmp */
+fn create_edit_file(core: &mut ShellCore, text: &str) -> Option<String> {
+    let dir = match core.data.get_param("TMPDIR") {
+        d if d.is_empty() => "/tmp".to_string(),
+        d => d,
+    };
+
+    for n in 0..100 {
+        let path = format!("{}/sush_edit_{}_{}", dir.trim_end_matches('/'), process::id(), n);
+        let file = OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path);
+        match file {
+            Ok(mut f) => {
+                if f.write_all(text.as_bytes()).is_ok() {
+                    return Some(path);
+                }
+                let _ = fs::remove_file(&path);
+                return None;
+            },
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+pub enum ViResult {
+    Pass,
+    Done,
+    Accept,
+}
+
+fn next_char(events: &mut Events<Stdin>) -> Option<char> {
+    match next_key(events) {
+        Some(Key::Char(c)) => Some(c),
+        _ => None,
+    }
+}
+
+/* 0: blank, 1: word, 2: others. bigword ignores the difference between 1 and 2 */
+fn class(c: char, bigword: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    }else if bigword || c.is_alphanumeric() || c == '_' {
+        1
+    }else{
+        2
+    }
+}
+
+impl Terminal {
+    fn word_forward(&self, bigword: bool) -> usize {
+        let mut pos = self.head;
+        let len = self.chars.len();
+        if pos < len {
+            let cls = class(self.chars[pos], bigword);
+            while pos < len && cls != 0 && class(self.chars[pos], bigword) == cls {
+                pos += 1;
+            }
+        }
+        while pos < len && class(self.chars[pos], bigword) == 0 {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn word_backward(&self, bigword: bool) -> usize {
+        let mut pos = self.head;
+        let origin = self.origin();
+        while pos > origin && class(self.chars[pos-1], bigword) == 0 {
+            pos -= 1;
+        }
+        if pos > origin {
+            let cls = class(self.chars[pos-1], bigword);
+            while pos > origin && class(self.chars[pos-1], bigword) == cls {
+                pos -= 1;
+            }
+        }
+        pos
+    }
+
+    fn word_end(&self, bigword: bool) -> usize {
+        let mut pos = self.head + 1;
+        let len = self.chars.len();
+        while pos < len && class(self.chars[pos], bigword) == 0 {
+            pos += 1;
+        }
+        if pos < len {
+            let cls = class(self.chars[pos], bigword);
+            while pos + 1 < len && class(self.chars[pos+1], bigword) == cls {
+                pos += 1;
+            }
+        }
+        pos.min(len.saturating_sub(1)).max(self.origin())
+    }
+
+    fn find_char(&self, cmd: char, target: char) -> Option<usize> {
+        let origin = self.origin();
+        match cmd {
+            'f' | 't' => {
+                let found = (self.head + 1..self.chars.len()).find(|i| self.chars[*i] == target)?;
+                Some(if cmd == 't' { found - 1 } else { found })
+            },
+            _ => {
+                let found = (origin..self.head).rev().find(|i| self.chars[*i] == target)?;
+                Some(if cmd == 'T' { found + 1 } else { found })
+            },
+        }
+    }
+
+    /* returns the destination and whether the character on it is included by operators */
+    fn vi_motion(&mut self, cmd: char, events: &mut Events<Stdin>) -> Option<(usize, bool)> {
+        let origin = self.origin();
+        let ans = match cmd {
+            'h' => (self.head.saturating_sub(1).max(origin), false),
+            'l' | ' ' => ((self.head + 1).min(self.chars.len()), false),
+            'w' | 'W' => (self.word_forward(cmd == 'W'), false),
+            'b' | 'B' => (self.word_backward(cmd == 'B'), false),
+            'e' | 'E' => (self.word_end(cmd == 'E'), true),
+            '0' => (origin, false),
+            '^' => {
+                let mut pos = origin;
+                while pos < self.chars.len() && self.chars[pos].is_whitespace() {
+                    pos += 1;
+                }
+                (pos, false)
+            },
+            '$' => (self.chars.len(), false),
+            'f' | 'F' | 't' | 'T' => {
+                let target = next_char(events)?;
+                self.vi_last_find = Some((cmd, target));
+                (self.find_char(cmd, target)?, cmd == 'f' || cmd == 't')
+            },
+            ';' | ',' => {
+                let (mut c, target) = self.vi_last_find?;
+                if cmd == ',' {
+                    c = match c { 'f' => 'F', 'F' => 'f', 't' => 'T', _ => 't' };
+                }
+                (self.find_char(c, target)?, c == 'f' || c == 't')
+            },
+            _ => return None,
+        };
+        Some(ans)
+    }
+
+    fn vi_move(&mut self, pos: usize) {
+        let max = match self.chars.len() > self.origin() {
+            true  => self.chars.len() - 1,
+            false => self.origin(),
+        };
+        self.head = pos.min(max);
+        self.goto(self.head);
+        self.flush();
+    }
+
+    /* d, c and y with a motion, or doubled for the whole line */
+    fn vi_operator(&mut self, op: char, core: &mut ShellCore, events: &mut Events<Stdin>) {
+        let (from, to) = match next_char(events) {
+            Some(c) if c == op => (self.origin(), self.chars.len()),
+            Some(c) => match self.vi_motion(c, events) {
+                Some((pos, _)) if pos < self.head => (pos, self.head),
+                Some((pos, inclusive)) => {
+                    let end = if inclusive { pos + 1 } else { pos };
+                    (self.head, end.min(self.chars.len()))
+                },
+                None => return self.cloop(),
+            },
+            None => return self.cloop(),
+        };
+
+        if op == 'y' {
+            core.kill_ring = self.chars[from..to].iter().collect();
+            return;
+        }
+        core.kill_ring = self.chars.drain(from..to).collect();
+        self.head = from;
+        self.rewrite(true);
+        match op {
+            'c' => self.vi_normal = false,
+            _   => self.vi_move(from),
+        }
+    }
+
+    fn vi_insert(&mut self, pos: usize) {
+        self.vi_normal = false;
+        self.head = pos;
+        self.goto(self.head);
+        self.flush();
+    }
+
+    fn vi_history(&mut self, inc: i32, core: &mut ShellCore) {
+        if self.move_line(inc > 0) {
+            return;
+        }
+        self.call_history(inc, core);
+        self.vi_move(self.origin());
+    }
+
+    /* the line is edited with $VISUAL or $EDITOR and executed */
+    fn edit_with_editor(&mut self, core: &mut ShellCore) -> ViResult {
+        let editor = ["VISUAL", "EDITOR"].iter()
+                     .map(|v| core.data.get_param(v))
+                     .find(|e| ! e.is_empty())
+                     .unwrap_or_else(|| "vi".to_string());
+        let line = self.get_string(self.origin());
+        let path = match create_edit_file(core, &(line + "\n")) {
+            Some(p) => p,
+            None => {
+                self.cloop();
+                return ViResult::Done;
+            },
+        };
+
+        let mut words = editor.split_whitespace();
+        let _ = self.stdout.suspend_raw_mode();
+        self.write("\r\n");
+        let _ = process::Command::new(words.next().unwrap_or("vi"))
+                .args(words).arg(&path).status();
+        let _ = self.stdout.activate_raw_mode();
+
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let _ = fs::remove_file(&path);
+        self.chars = self.prompt.chars().chain(text.trim_end_matches('\n').chars()).collect();
+        self.head = self.chars.len();
+        self.prompt_row = self.stdout.cursor_pos().unwrap_or((1,1)).1 as usize;
+        self.check_scroll();
+        self.rewrite(true);
+        ViResult::Accept
+    }
+
+    fn vi_command(&mut self, cmd: char, core: &mut ShellCore, events: &mut Events<Stdin>) -> ViResult {
+        let origin = self.origin();
+        match cmd {
+            'i' => self.vi_insert(self.head),
+            'a' => self.vi_insert((self.head + 1).min(self.chars.len())),
+            'I' => self.vi_insert(origin),
+            'A' => self.vi_insert(self.chars.len()),
+            'x' if self.head < self.chars.len() => {
+                core.kill_ring = self.chars.remove(self.head).to_string();
+                self.rewrite(true);
+                self.vi_move(self.head);
+            },
+            'X' if self.head > origin => {
+                self.head -= 1;
+                core.kill_ring = self.chars.remove(self.head).to_string();
+                self.rewrite(true);
+            },
+            'r' => match next_char(events) {
+                Some(c) if self.head < self.chars.len() => {
+                    self.chars[self.head] = c;
+                    self.rewrite(true);
+                },
+                _ => self.cloop(),
+            },
+            'D' | 'C' => {
+                core.kill_ring = self.chars.drain(self.head..).collect();
+                self.rewrite(true);
+                match cmd {
+                    'C' => self.vi_normal = false,
+                    _   => self.vi_move(self.head),
+                }
+            },
+            'S' => {
+                self.chars.truncate(origin);
+                self.head = origin;
+                self.rewrite(true);
+                self.vi_normal = false;
+            },
+            'd' | 'c' | 'y' => self.vi_operator(cmd, core, events),
+            'p' | 'P' if ! core.kill_ring.is_empty() => {
+                if cmd == 'p' && self.head < self.chars.len() {
+                    self.head += 1;
+                }
+                let text: Vec<char> = core.kill_ring.chars().collect();
+                let len = text.len();
+                self.chars.splice(self.head..self.head, text);
+                self.rewrite(true);
+                self.vi_move(self.head + len - 1);
+            },
+            'k' | '-' => self.vi_history(1, core),
+            'j' | '+' => self.vi_history(-1, core),
+            'v' => return self.edit_with_editor(core),
+            c => match self.vi_motion(c, events) {
+                Some((pos, _)) => self.vi_move(pos),
+                None => self.cloop(),
+            },
+        }
+        ViResult::Done
+    }
+
+    pub fn vi_key(&mut self, key: Key, core: &mut ShellCore, events: &mut Events<Stdin>) -> ViResult {
+        if ! self.vi_normal {
+            let cmd = match key {
+                Key::Esc    => None,
+                Key::Alt(c) => Some(c),
+                _ => return ViResult::Pass,
+            };
+            self.vi_normal = true;
+            self.vi_move(self.head.saturating_sub(1).max(self.origin()));
+            return match cmd {
+                Some('\r') | Some('\n') => ViResult::Accept,
+                Some(c) => self.vi_command(c, core, events),
+                None    => ViResult::Done,
+            };
+        }
+
+        match key {
+            Key::Char('\n') | Key::Char('\t') => ViResult::Pass,
+            Key::Char(c) => self.vi_command(c, core, events),
+            Key::Backspace => {
+                self.vi_move(self.head.saturating_sub(1).max(self.origin()));
+                ViResult::Done
+            },
+            Key::Up => {
+                self.vi_history(1, core);
+                ViResult::Done
+            },
+            Key::Down => {
+                self.vi_history(-1, core);
+                ViResult::Done
+            },
+            Key::Esc => {
+                self.cloop();
+                ViResult::Done
+            },
+            _ => ViResult::Pass,
+        }
+    }
+}
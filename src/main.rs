@@ -11,13 +11,16 @@ mod utils;
 use builtins::option_commands;
 use std::{env, process};
 use std::fs::File;
-use std::os::fd::IntoRawFd;
+use std::io::ErrorKind;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::ffi::OsStrExt;
 use std::sync::atomic::Ordering::Relaxed;
 use crate::core::{builtins, ShellCore};
-use crate::elements::io;
 use crate::elements::script::Script;
-use crate::feeder::{Feeder, InputError};
-use utils::file_check;
+use crate::feeder::{Feeder, InputError, ParseError};
+use crate::feeder::input::InputSource;
+use nix::fcntl;
+use utils::{bytes, file_check};
 
 fn show_version() {
     const V: &'static str = env!("CARGO_PKG_VERSION");
@@ -31,106 +34,232 @@ There is no warranty, to the extent permitted by law.", V);
     process::exit(0);
 }
 
-fn read_rc_file(core: &mut ShellCore) {
-    if ! core.data.flags.contains("i") {
+/* options for the startup files */
+#[derive(Default)]
+struct Startup {
+    login: bool,
+    norc: bool,
+    noprofile: bool,
+    rcfile: Option<String>,
+    restricted: bool,
+}
+
+fn source_file(core: &mut ShellCore, file: &str) {
+    core.run_builtin(&mut vec![".".to_string(), file.to_string()], &mut vec![]);
+}
+
+/* a login shell reads the profiles, and an interactive shell reads the rc files otherwise */
+fn read_startup_files(core: &mut ShellCore, startup: &Startup) {
+    let home = core.data.get_param("HOME");
+    if startup.login {
+        core.shopts.set("login_shell", true);
+        if startup.noprofile {
+            return;
+        }
+        if file_check::is_regular_file("/etc/profile") {
+            source_file(core, "/etc/profile");
+        }
+        let profiles = ["/.sush_profile", "/.sush_login", "/.profile"];
+        if let Some(f) = profiles.iter().map(|f| home.clone() + f).find(|f| file_check::is_regular_file(f)) {
+            source_file(core, &f);
+        }
+        return;
+    }
+
+    if ! core.data.flags.contains('i') || startup.norc {
+        return;
+    }
+    if let Some(f) = &startup.rcfile {
+        source_file(core, f);
         return;
     }
 
     let dir = match core.data.get_param("CARGO_MANIFEST_DIR").as_str() {
-        "" => core.data.get_param("HOME"),
+        "" => home,
         s  => s.to_string(),
     };
+    for rc_file in ["/etc/sushrc".to_string(), dir + "/.sushrc"] {
+        if file_check::is_regular_file(&rc_file) {
+            source_file(core, &rc_file);
+        }
+    }
+}
 
-    let rc_file = dir + "/.sushrc";
+/* what the command line gives to the shell */
+#[derive(Default)]
+struct Invocation {
+    command: Option<String>,
+    script: Option<String>,
+    interactive: bool,
+    options: Vec<String>,
+    parameters: Vec<String>,
+    startup: Startup,
+}
 
-    if file_check::is_regular_file(&rc_file) {
-        core.run_builtin(&mut vec![".".to_string(), rc_file], &mut vec![]);
-    }
+fn invocation_error(msg: &str) -> ! {
+    error_message::print_without_core(msg);
+    process::exit(2);
 }
 
-fn configure(args: &Vec<String>, options: &mut Vec<String>, parameters: &mut Vec<String>,
-             script: &mut String, c_flag: &mut bool) {
-    for i in 1..args.len() {
-        if args[i] == "-c" {
-            *c_flag = true;
-            io::close(0, &format!("sush(fatal): cannot close stdin"));
-            if i == args.len()-1 {
-                eprintln!("bash: -c: option requires an argument");
-                process::exit(2);
+fn parse_long_option(args: &[String], i: &mut usize, inv: &mut Invocation) {
+    let arg = &args[*i];
+    match arg.as_str() {
+        "--version" => show_version(),
+        "--login" => inv.startup.login = true,
+        "--norc" => inv.startup.norc = true,
+        "--noprofile" => inv.startup.noprofile = true,
+        "--restricted" => inv.startup.restricted = true,
+        "--posix" => inv.options.extend(["-o".to_string(), "posix".to_string()]),
+        "--rcfile" | "--init-file" => {
+            *i += 1;
+            match args.get(*i) {
+                Some(f) => inv.startup.rcfile = Some(f.clone()),
+                None => invocation_error(&format!("{}: option requires an argument", arg)),
             }
-            *script = args[i+1].to_string();
+        },
+        _ => invocation_error(&format!("{}: invalid option", arg)),
+    }
+}
+
+/* options end at the first argument that is not an option, which is the
+   command string with -c or the script file name without it. with -s, the
+   commands are read from stdin and all the rest become positional parameters.
+   the options of the set builtin are left to it */
+fn parse_args(args: &[String]) -> Invocation {
+    let mut inv = Invocation::default();
+    inv.startup.login = args[0].starts_with('-');
+    inv.startup.restricted = args[0].trim_start_matches('-').rsplit('/').next() == Some("rsush");
+
+    let (mut c_flag, mut s_flag) = (false, false);
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" || arg == "-" {
+            i += 1;
             break;
         }
-
-        if args[i].starts_with("-") {
-            parameters.remove(i);
-            options.push(args[i].clone());
-        }else{
-            *script = args[i].clone();
-            *parameters = args[i..].to_vec();
+        if arg.starts_with("--") {
+            parse_long_option(args, &mut i, &mut inv);
+            i += 1;
+            continue;
+        }
+        if arg.len() < 2 || ! (arg.starts_with('-') || arg.starts_with('+')) {
             break;
         }
+
+        let pm = &arg[0..1];
+        for ch in arg[1..].chars() {
+            match (pm, ch) {
+                ("-", 'c') => c_flag = true,
+                ("-", 'i') => inv.interactive = true,
+                ("-", 'l') => inv.startup.login = true,
+                ("-", 'r') => inv.startup.restricted = true,
+                ("-", 's') => s_flag = true,
+                (_, 'o') => {
+                    inv.options.push(pm.to_string() + "o");
+                    i += 1;
+                    match args.get(i) {
+                        Some(name) => inv.options.push(name.clone()),
+                        None => invocation_error(&format!("{}o: option requires an argument", pm)),
+                    }
+                },
+                (_, ch) if option_commands::FLAG_OPTIONS.iter().any(|(_, c)| *c == ch) => {
+                    inv.options.push(format!("{}{}", pm, ch));
+                },
+                _ => invocation_error(&format!("{}{}: invalid option", pm, ch)),
+            }
+        }
+        i += 1;
     }
+
+    let rest = &args[i.min(args.len())..];
+    inv.parameters = args[0..1].to_vec();
+    if c_flag {
+        match rest.first() {
+            Some(s) => inv.command = Some(s.clone()),
+            None => invocation_error("-c: option requires an argument"),
+        }
+        if rest.len() > 1 {
+            inv.parameters = rest[1..].to_vec();
+        }
+    }else if s_flag || rest.is_empty() {
+        inv.parameters.extend(rest.iter().cloned());
+    }else{
+        inv.script = Some(rest[0].clone());
+        inv.parameters = rest.to_vec();
+    }
+    inv
 }
 
-fn set_script_file(script: &str) {
+/* the script is read from the file so that the commands in it can use stdin.
+   the fd is moved to 255 or above not to be touched by redirections */
+fn open_script_file(script: &str) -> File {
+    if file_check::is_dir(script) {
+        error_message::print_without_core(&format!("{}: Is a directory", script));
+        process::exit(126);
+    }
+
     match File::open(script) {
         Ok(file) => {
-            let fd = file.into_raw_fd();
-            let result = io::replace(fd, 0);
-            if ! result {
-                io::close(fd, &format!("sush(fatal): file does not close"));
-            }
+            let fd = fcntl::fcntl(file.as_raw_fd(), fcntl::F_DUPFD_CLOEXEC(255))
+                     .expect("sush(fatal): Can't allocate fd for the script");
+            unsafe{ File::from_raw_fd(fd) }
         },
-        Err(why)  => {
-            eprintln!("sush: {}: {}", script, why);
-            process::exit(1);
+        Err(e) => {
+            error_message::print_without_core(&format!("{}: {}", script, error_message::io_error(&e)));
+            process::exit(if e.kind() == ErrorKind::NotFound { 127 } else { 126 });
         },
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 && args[1] == "--version" {
-        show_version();
-    }
-
-    let mut options = args[0..1].to_vec();
-    let mut parameters = args.to_vec();
-    let mut script = "-".to_string();
-    let mut c_flag = false;
+    let args: Vec<String> = env::args_os().map(|a| bytes::decode(a.as_bytes())).collect();
+    let inv = parse_args(&args);
+    let read_stdin = inv.command.is_none() && inv.script.is_none();
 
-    configure(&args, &mut options, &mut parameters, &mut script, &mut c_flag);
-
-    if script != "-" && ! c_flag {
-        set_script_file(&script);
+    let mut core = ShellCore::new(read_stdin, inv.interactive);
+    core.script_name = inv.parameters[0].clone();
+    if let Some(script) = &inv.script {
+        let file = open_script_file(script);
+        core.input_sources = vec![InputSource::file(file, script)];
+        core.data.set_main_source(script);
     }
-
-    let mut core = ShellCore::new();
-    core.script_name = script.clone();
-    option_commands::set(&mut core, &mut options);
-    option_commands::set_parameters(&mut core, &mut parameters);
+    option_commands::set_options(&mut core, &inv.options);
+    if inv.command.is_some() {
+        core.data.flags += "c";
+    }else if read_stdin {
+        core.data.flags += "s";
+    }
+    option_commands::set_parameters(&mut core, &inv.parameters);
     signal::run_signal_check(&mut core);
+    read_startup_files(&mut core, &inv.startup);
+    if inv.startup.restricted {
+        core.set_restricted();
+    }
 
-    if c_flag {
-        main_c_option(&mut core, &script);
+    if let Some(command) = &inv.command {
+        main_c_option(&mut core, command);
         core.exit();
     }
 
-    read_rc_file(&mut core);
+    core.load_history();
     main_loop(&mut core);
 }
 
 fn set_history(core: &mut ShellCore, s: &str) {
-    if core.read_stdin || core.history.len() == 0 {
-        return;
+    if core.reading_terminal() {
+        if core.history.is_empty() {
+            return;
+        }
+        core.history.remove(0); // the entry used during line editing
     }
 
-    core.history[0] = s.trim_end().replace("\n", "↵ \0").to_string();
-    if core.history[0].len() == 0
-    || (core.history.len() > 1 && core.history[0] == core.history[1]) {
-        core.history.remove(0);
+    if ! core.options.query("history") {
+        return;
     }
+
+    let line = s.trim_end().replace("\n", "↵ \0").to_string();
+    core.add_history(&line);
 }
 
 fn main_loop(core: &mut ShellCore) {
@@ -145,17 +274,31 @@ fn main_loop(core: &mut ShellCore) {
                 signal::input_interrupt_check(&mut feeder, core);
                 continue;
             },
-            _ => break,
+            _ => match core.jobtable_check_before_exit() {
+                true  => break,
+                false => continue,
+            },
         }
 
         core.word_eval_error = false;
         core.sigint.store(false, Relaxed);
         match Script::parse(&mut feeder, core, false){
-            Some(mut s) => {
-                s.exec(core);
+            Ok(mut s) => {
                 set_history(core, &s.get_text());
+                s.exec(core);
+                core.command_number += 1;
+            },
+            Err(ParseError::Interrupt) => {
+                signal::input_interrupt_check(&mut feeder, core);
+            },
+            Err(e) => {
+                e.print(core);
+                core.data.set_exit_status(2);
+                if ! core.data.flags.contains('i') {
+                    core.exit();
+                }
+                feeder.reset();
             },
-            None => {},
         }
         core.sigint.store(false, Relaxed);
     }
@@ -164,9 +307,6 @@ fn main_loop(core: &mut ShellCore) {
 }
 
 fn main_c_option(core: &mut ShellCore, script: &String) {
-    let mut feeder = Feeder::new(script);
-    if let Some(mut s) = Script::parse(&mut feeder, core, false){
-        s.exec(core);
-    }
+    core.exec_source(InputSource::text(script, "-c"));
     core.exit();
 }
@@ -1,39 +1,80 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use std::{thread, time};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 use crate::core::ShellCore;
+use crate::core::data::ExitStatus;
 use crate::feeder::Feeder;
+use crate::feeder::input::InputSource;
+use nix::libc;
+use nix::sys::signal;
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
 use signal_hook::consts;
-use signal_hook::iterator::Signals;
 
-pub fn run_signal_check(core: &mut ShellCore) {
-    for fd in 3..10 { //use FD 3~9 to prevent signal-hool from using these FDs
-        nix::unistd::dup2(2, fd).expect("sush(fatal): init error");
+/* set from the signal handler, consumed by run_traps between commands */
+static CAUGHT: [AtomicBool; 65] = [const { AtomicBool::new(false) }; 65];
+/* SIGINT stays with the handler of signal-hook; a trap on it replaces the interruption */
+static SIGINT_TRAPPED: AtomicBool = AtomicBool::new(false);
+/* set on SIGCHLD so that the job table is polled only after a child changes */
+static CHILD_CHANGED: AtomicBool = AtomicBool::new(true);
+
+pub const PSEUDO_SIGNALS: [&str; 4] = ["EXIT", "DEBUG", "ERR", "RETURN"];
+
+extern "C" fn catch(sig: libc::c_int) {
+    if let Some(flag) = CAUGHT.get(sig as usize) {
+        flag.store(true, Relaxed);
     }
+    if sig == libc::SIGCHLD {
+        CHILD_CHANGED.store(true, Relaxed);
+    }
+}
 
-    let sigint = Arc::clone(&core.sigint); //追加
- 
-    thread::spawn(move || {
-        let mut signals = Signals::new(vec![consts::SIGINT])
-                          .expect("sush(fatal): cannot prepare signal data");
+extern "C" fn catch_sigchld(_: libc::c_int) {
+    CHILD_CHANGED.store(true, Relaxed);
+}
 
-        for fd in 3..10 { // release FD 3~9
-            nix::unistd::close(fd).expect("sush(fatal): init error");
-        }
+/* true once after children have exited, stopped or continued */
+pub fn child_changed() -> bool {
+    CHILD_CHANGED.swap(false, Relaxed)
+}
 
-        loop {
-            thread::sleep(time::Duration::from_millis(100)); //0.1秒周期に変更
-            for signal in signals.pending() {
-                if signal == consts::SIGINT {
-                    sigint.store(true, Relaxed);
-                }
-            }
+pub fn run_signal_check(core: &mut ShellCore) {
+    let sigint = Arc::clone(&core.sigint);
+
+    /* the flags are set in the handler so that an interrupted wait sees them at once */
+    let action = move || match SIGINT_TRAPPED.load(Relaxed) {
+        true  => CAUGHT[consts::SIGINT as usize].store(true, Relaxed),
+        false => sigint.store(true, Relaxed),
+    };
+    unsafe { signal_hook::low_level::register(consts::SIGINT, action) }
+        .expect("sush(fatal): cannot prepare signal data");
+
+    let action = SigAction::new(SigHandler::Handler(catch_sigchld), SaFlags::SA_RESTART, SigSet::empty());
+    let _ = unsafe { signal::sigaction(Signal::SIGCHLD, &action) };
+}
+
+fn set_sigint_restart(restart: bool) {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(libc::SIGINT, std::ptr::null(), &mut action);
+        match restart {
+            true  => action.sa_flags |= libc::SA_RESTART,
+            false => action.sa_flags &= ! libc::SA_RESTART,
         }
-    });
-} //thanks: https://dev.to/talzvon/handling-unix-kill-signals-in-rust-55g6
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+/* runs a blocking system call that SIGINT breaks with EINTR */
+pub fn interruptible<T>(call: impl FnOnce() -> T) -> T {
+    set_sigint_restart(false);
+    let ans = call();
+    set_sigint_restart(true);
+    ans
+}
 
 pub fn input_interrupt_check(feeder: &mut Feeder, core: &mut ShellCore) -> bool {
     if ! core.sigint.load(Relaxed) { //core.input_interrupt {
@@ -41,7 +82,152 @@ pub fn input_interrupt_check(feeder: &mut Feeder, core: &mut ShellCore) -> bool
     }
 
     core.sigint.store(false, Relaxed); //core.input_interrupt = false;
-    core.data.set_param("?", "130");
-    feeder.consume(feeder.len());
+    core.data.set_exit_status(130);
+    feeder.reset();
     true
 }
+
+/* "int", "SIGINT" and "2" all give "SIGINT"; pseudo signals keep their names */
+pub fn trap_name(spec: &str) -> Option<String> {
+    let upper = spec.to_uppercase();
+    if upper == "0" || upper == "SIGEXIT" {
+        return Some("EXIT".to_string());
+    }
+    if PSEUDO_SIGNALS.contains(&upper.as_str()) {
+        return Some(upper);
+    }
+
+    let sig = match spec.parse::<i32>() {
+        Ok(n)  => Signal::try_from(n).ok(),
+        Err(_) => match upper.starts_with("SIG") {
+            true  => Signal::from_str(&upper).ok(),
+            false => Signal::from_str(&("SIG".to_owned() + &upper)).ok(),
+        },
+    };
+    sig.map(|s| s.as_str().to_string())
+}
+
+/* the order in which trap -p lists the table */
+pub fn trap_names() -> Vec<String> {
+    let mut ans = vec!["EXIT".to_string()];
+    ans.extend(Signal::iterator().map(|s| s.as_str().to_string()));
+    ans.extend(PSEUDO_SIGNALS[1..].iter().map(|s| s.to_string()));
+    ans
+}
+
+fn set_action(core: &mut ShellCore, sig: Signal, handler: SigHandler) {
+    let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+    if let Ok(old) = unsafe { signal::sigaction(sig, &action) } {
+        core.saved_sigactions.entry(sig as i32).or_insert(old);
+    }
+}
+
+fn restore_action(core: &mut ShellCore, sig: Signal) {
+    if let Some(old) = core.saved_sigactions.remove(&(sig as i32)) {
+        let _ = unsafe { signal::sigaction(sig, &old) };
+    }
+}
+
+pub fn set_trap(core: &mut ShellCore, name: &str, command: &str) {
+    if let Ok(sig) = Signal::from_str(name) {
+        CAUGHT[sig as usize].store(false, Relaxed);
+        if sig == Signal::SIGINT {
+            SIGINT_TRAPPED.store(! command.is_empty(), Relaxed);
+        }
+        match (command.is_empty(), sig) {
+            (true, _) => set_action(core, sig, SigHandler::SigIgn),
+            (false, Signal::SIGINT) => restore_action(core, sig), //the handler flags it
+            (false, Signal::SIGKILL) | (false, Signal::SIGSTOP) => {},
+            (false, _) => set_action(core, sig, SigHandler::Handler(catch)),
+        }
+    }
+    core.traps.insert(name.to_string(), command.to_string());
+}
+
+pub fn reset_trap(core: &mut ShellCore, name: &str) {
+    if let Ok(sig) = Signal::from_str(name) {
+        if sig == Signal::SIGINT {
+            SIGINT_TRAPPED.store(false, Relaxed);
+        }
+        restore_action(core, sig);
+    }
+    core.traps.remove(name);
+}
+
+/* set -T and set -E let functions and subshells inherit these traps. extdebug does both */
+pub fn inherited_traps(core: &mut ShellCore) -> Vec<&'static str> {
+    let extdebug = core.shopts.query("extdebug");
+    let mut ans = vec![];
+    if extdebug || core.data.flags.contains('T') {
+        ans.extend(["DEBUG", "RETURN"]);
+    }
+    if extdebug || core.data.flags.contains('E') {
+        ans.push("ERR");
+    }
+    ans
+}
+
+/* a subshell keeps ignored signals but not the handlers */
+pub fn reset_traps_for_subshell(core: &mut ShellCore) {
+    let inherited = inherited_traps(core);
+    let traps: Vec<(String, String)> = core.traps.iter()
+        .filter(|(name, _)| ! inherited.contains(&name.as_str()))
+        .map(|(name, com)| (name.clone(), com.clone())).collect();
+
+    for (name, com) in traps {
+        match com.is_empty() {
+            true  => set_trap(core, &name, ""),
+            false => reset_trap(core, &name),
+        }
+    }
+}
+
+/* returns the exit status of the trap */
+pub fn run_trap_command(core: &mut ShellCore, command: &str) -> ExitStatus {
+    if command.is_empty() {
+        return ExitStatus::default();
+    }
+
+    let exit_status = core.data.exit_status();
+    core.exec_source(InputSource::text(command, "trap"));
+    let ans = core.data.exit_status();
+    core.data.set_exit_status(exit_status.code());
+    ans
+}
+
+pub fn run_return_trap(core: &mut ShellCore) {
+    if let Some(com) = core.traps.get("RETURN").cloned() {
+        run_trap_command(core, &com);
+    }
+}
+
+/* the trap is put aside while it runs so that failures in it don't call it again */
+pub fn run_err_trap(core: &mut ShellCore) {
+    if let Some(com) = core.traps.remove("ERR") {
+        run_trap_command(core, &com);
+        core.traps.entry("ERR".to_string()).or_insert(com);
+    }
+}
+
+/* returns false if extdebug skips the command after a failure of the trap */
+pub fn run_debug_trap(core: &mut ShellCore) -> bool {
+    let com = match core.traps.remove("DEBUG") {
+        Some(com) => com,
+        None => return true,
+    };
+    let exit_status = run_trap_command(core, &com);
+    core.traps.entry("DEBUG".to_string()).or_insert(com);
+    exit_status.is_success() || ! core.shopts.query("extdebug")
+}
+
+pub fn run_traps(core: &mut ShellCore) {
+    for sig in Signal::iterator() {
+        if ! CAUGHT[sig as usize].swap(false, Relaxed) {
+            continue;
+        }
+
+        if let Some(com) = core.traps.get(sig.as_str()).cloned() {
+            run_trap_command(core, &com);
+        }
+    }
+}
@@ -1,50 +1,98 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
+pub mod bytes;
 pub mod file_check;
 pub mod glob;
 pub mod directory;
 
+use crate::feeder::quoting::Quoting;
+
 pub fn reserved(w: &str) -> bool {
     match w {
-        "[[" | "]]" | "{" | "}" | "while" | "until" | "for" | "do" | "done" | "if" | "then" | "elif" | "else" | "fi" | "case" => true,
+        "[[" | "]]" | "{" | "}" | "while" | "until" | "for" | "do" | "done" | "if" | "then" | "elif"
+        | "else" | "fi" | "case" | "time" | "coproc" | "!" => true,
         _ => false,
     }
 }
 
-pub fn split_words(s: &str) -> Vec<String> {
-    let mut ans = vec![];
+/* a position inside a multibyte char is moved to the end of the char */
+pub fn char_boundary(s: &str, pos: usize) -> usize {
+    let mut pos = pos.min(s.len());
+    while ! s.is_char_boundary(pos) {
+        pos += 1;
+    }
+    pos
+}
 
-    let mut in_quote = false;
-    let mut escaped = false;
-    let mut quote = ' ';
+/* single quotes only the words that the shell would read differently */
+pub fn quote_for_trace(s: &str) -> String {
+    let special = |c: char| " \t\n'\"\\|&;()<>!{}*[?]^$`".contains(c);
+    if ! s.is_empty() && ! s.contains(special)
+    && ! s.starts_with("~") && ! s.starts_with("#") {
+        return s.to_string();
+    }
 
-    let mut tmp = String::new();
-    for c in s.chars() {
-        if escaped || c == '\\' {
-            escaped = ! escaped;
-            tmp.push(c);
-            continue;
-        }
+    format!("'{}'", s.replace("'", "'\\''"))
+}
 
-        if c == '\'' || c == '"' {
-            if c == quote {
-                in_quote = ! in_quote;
-                quote = ' ';
-            }else if quote == ' ' {
-                in_quote = ! in_quote;
-                quote = c;
-            }
-            tmp.push(c);
+/* seconds with the given number of decimal places, as 1m2.345s if long */
+pub fn time_string(usec: i64, precision: usize, long: bool) -> String {
+    let (sec, frac) = (usec / 1_000_000, usec % 1_000_000);
+    let decimal = match precision {
+        0 => String::new(),
+        p => format!(".{:0w$}", frac / 10_i64.pow(6 - p as u32), w = p),
+    };
+
+    match long {
+        true  => format!("{}m{}{}s", sec / 60, sec % 60, decimal),
+        false => format!("{}{}", sec, decimal),
+    }
+}
+
+/* %[p][l]R, %[p][l]U, %[p][l]S and %P in TIMEFORMAT */
+pub fn format_times(format: &str, real: i64, user: i64, sys: i64) -> Result<String, char> {
+    let mut ans = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' || chars.peek().is_none() {
+            ans.push(c);
             continue;
         }
 
-        if in_quote {
-            tmp.push(c);
-            continue;
+        let mut precision = 3;
+        if let Some(d) = chars.next_if(|c| c.is_ascii_digit()) {
+            precision = (d as usize - '0' as usize).min(3);
         }
+        let long = chars.next_if_eq(&'l').is_some();
+
+        let usec = match chars.next() {
+            Some('%') if precision == 3 && ! long => { ans.push('%'); continue; },
+            Some('P') if precision == 3 && ! long => {
+                let per = match real {
+                    0 => 0,
+                    _ => (user + sys) * 10000 / real,
+                };
+                ans += &format!("{}.{:02}", per / 100, per % 100);
+                continue;
+            },
+            Some('R') => real,
+            Some('U') => user,
+            Some('S') => sys,
+            Some(c)   => return Err(c),
+            None      => return Err(' '),
+        };
+        ans += &time_string(usec, precision, long);
+    }
+    Ok(ans)
+}
+
+pub fn split_words(s: &str) -> Vec<String> {
+    let mut ans = vec![];
 
-        if ! in_quote && ( c == ' ' || c == '\t') {
+    let mut tmp = String::new();
+    for (_, c, bare) in Quoting::new(s) {
+        if bare && ( c == ' ' || c == '\t') {
             if tmp.len() != 0 {
                 ans.push(tmp.clone());
                 tmp.clear();
@@ -69,3 +117,17 @@ pub fn is_wsl() -> bool {
 
     false
 }
+
+pub fn default_path() -> String {
+    let len = unsafe { nix::libc::confstr(nix::libc::_CS_PATH, std::ptr::null_mut(), 0) };
+    if len > 0 {
+        let mut buf = vec![0u8; len];
+        unsafe { nix::libc::confstr(nix::libc::_CS_PATH, buf.as_mut_ptr() as *mut nix::libc::c_char, len) };
+        buf.pop();
+        if let Ok(path) = String::from_utf8(buf) {
+            return path;
+        }
+    }
+
+    "/bin:/usr/bin".to_string()
+}
@@ -0,0 +1,71 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::ffi::CString;
+
+/* Bytes that are not valid UTF-8 are kept in strings as the characters
+ * U+F780..U+F7FF so that they can be written back unchanged. A literal
+ * character in this range is also kept as the raw bytes of its UTF-8
+ * sequence. Otherwise encode would turn it into a single byte. */
+const RAW_BYTE_BASE: u32 = 0xF700;
+
+fn push_raw_bytes(ans: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        ans.push(char::from_u32(RAW_BYTE_BASE + *b as u32).unwrap());
+    }
+}
+
+fn push_valid(ans: &mut String, s: &str) {
+    let mut buf = [0; 4];
+    for c in s.chars() {
+        match raw_byte(c) {
+            Some(_) => push_raw_bytes(ans, c.encode_utf8(&mut buf).as_bytes()),
+            None    => ans.push(c),
+        }
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> String {
+    let mut ans = String::new();
+    let mut rest = bytes;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(s) => {
+                push_valid(&mut ans, s);
+                return ans;
+            },
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                push_valid(&mut ans, std::str::from_utf8(valid).unwrap_or(""));
+                let len = e.error_len().unwrap_or(invalid.len());
+                push_raw_bytes(&mut ans, &invalid[..len]);
+                rest = &invalid[len..];
+            },
+        }
+    }
+}
+
+pub fn raw_byte(c: char) -> Option<u8> {
+    match c {
+        '\u{F780}'..='\u{F7FF}' => Some((c as u32 - RAW_BYTE_BASE) as u8),
+        _ => None,
+    }
+}
+
+pub fn encode(s: &str) -> Vec<u8> {
+    let mut ans = vec![];
+    let mut buf = [0; 4];
+    for c in s.chars() {
+        match raw_byte(c) {
+            Some(b) => ans.push(b),
+            None    => ans.extend_from_slice(c.encode_utf8(&mut buf).as_bytes()),
+        }
+    }
+    ans
+}
+
+pub fn to_cstring(s: &str) -> CString {
+    let mut bytes = encode(s);
+    bytes.retain(|b| *b != 0);
+    CString::new(bytes).unwrap()
+}
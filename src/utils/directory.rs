@@ -4,6 +4,7 @@
 use std::fs::DirEntry;
 use std::path::Path;
 use super::glob;
+use crate::core::options::Options;
 
 pub fn files(dir: &str) -> Vec<String> {
     let readdir = match dir {
@@ -19,7 +20,7 @@ pub fn files(dir: &str) -> Vec<String> {
     }
 }
 
-pub fn glob(dir: &str, glob: &str, extglob: bool) -> Vec<String> {
+pub fn glob(dir: &str, glob: &str, opts: &Options) -> Vec<String> {
     let make_path = |file| dir.to_owned() + file + "/";
 
     if glob == "" || glob == "." || glob == ".." {
@@ -29,8 +30,29 @@ pub fn glob(dir: &str, glob: &str, extglob: bool) -> Vec<String> {
     let mut fs = files(dir);
     fs.append( &mut vec![".".to_string(), "..".to_string()] );
 
-    let compare = |file: &String| ( ! file.starts_with(".") || glob.starts_with(".") )
-                            && glob::compare(file, glob, extglob);
+    let dotglob = opts.query("dotglob");
+    let matcher = glob::compile(glob, opts.query("extglob"))
+                  .ignore_case(opts.query("nocaseglob"));
+    let visible = |file: &String| ! file.starts_with(".") || glob.starts_with(".")
+                            || (dotglob && file != "." && file != "..");
+    let compare = |file: &String| visible(file) && matcher.matches(file);
 
     fs.iter().filter(|f| compare(f) ).map(|f| make_path(f) ).collect()
 }
+
+/* dir itself and all files under it for ** of globstar; symbolic links are not followed */
+pub fn glob_star(dir: &str, dotglob: bool) -> Vec<String> {
+    let mut ans = vec![dir.to_string()];
+    for f in files(dir) {
+        if f.starts_with(".") && ! dotglob {
+            continue;
+        }
+
+        let path = dir.to_owned() + &f;
+        match Path::new(&path).symlink_metadata() {
+            Ok(m) if m.is_dir() => ans.extend(glob_star(&(path + "/"), dotglob)),
+            _ => ans.push(path + "/"),
+        }
+    }
+    ans
+}
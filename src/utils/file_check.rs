@@ -5,6 +5,7 @@ use faccess;
 use faccess::PathExt;
 use nix::unistd;
 use std::fs;
+use std::io::Read;
 use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 
 use std::os::unix::fs::MetadataExt as UnixMetadataExt;
@@ -83,6 +84,15 @@ pub fn metadata_check(name: &str, tp: &str) -> bool {
     }
 }
 
+/* the same check as bash: a null byte before the first newline in the first 80 bytes */
+pub fn is_binary(name: &str) -> bool {
+    let mut buf = [0; 80];
+    match fs::File::open(name).and_then(|mut f| f.read(&mut buf)) {
+        Ok(len) => buf[..len].iter().take_while(|b| **b != b'\n').any(|b| *b == 0),
+        Err(_)  => false,
+    }
+}
+
 pub fn is_symlink(name: &str) -> bool {
     Path::new(name).is_symlink()
 }
@@ -106,3 +116,18 @@ pub fn is_tty(name: &str) -> bool {
     };
     unistd::isatty(fd) == Ok(true)
 }
+
+pub fn search_path(name: &str, path: &str) -> Option<String> {
+    for dir in path.split(':') {
+        let dir = match dir {
+            "" => ".",
+            d  => d,
+        };
+
+        let full = format!("{}/{}", dir, name);
+        if is_regular_file(&full) && is_executable(&full) {
+            return Some(full);
+        }
+    }
+    None
+}
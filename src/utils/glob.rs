@@ -3,183 +3,211 @@
 
 use crate::error_message;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Wildcard {
     Normal(String),
     Asterisk,
     Question,
-    OneOf(Vec<char>),
-    NotOneOf(Vec<char>),
-    ExtGlob(char, Vec<String>),
+    OneOf(Vec<BracketItem>),
+    NotOneOf(Vec<BracketItem>),
+    ExtGlob(char, Vec<Vec<Wildcard>>),
 }
 
-pub fn compare(word: &String, pattern: &str, extglob: bool) -> bool {
-    let mut candidates = vec![word.to_string()];
+#[derive(Debug, Clone)]
+enum BracketItem {
+    Char(char),
+    Range(char, char),
+    Class(String),
+}
 
-    for w in parse(pattern, extglob) {
-        compare_internal(&mut candidates, &w);
+impl BracketItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            BracketItem::Char(x) => *x == c,
+            BracketItem::Range(from, to) => *from <= c && c <= *to,
+            BracketItem::Class(name) => match name.as_str() {
+                "alpha"  => c.is_alphabetic(),
+                "digit"  => c.is_ascii_digit(),
+                "alnum"  => c.is_alphanumeric(),
+                "upper"  => c.is_uppercase(),
+                "lower"  => c.is_lowercase(),
+                "space"  => c.is_whitespace(),
+                "blank"  => c == ' ' || c == '\t',
+                "punct"  => c.is_ascii_punctuation(),
+                "print"  => ! c.is_control(),
+                "graph"  => ! c.is_control() && ! c.is_whitespace(),
+                "cntrl"  => c.is_control(),
+                "xdigit" => c.is_ascii_hexdigit(),
+                "word"   => c.is_alphanumeric() || c == '_',
+                _        => false,
+            },
+        }
     }
 
-    candidates.iter().any(|c| c == "")
+    fn to_ascii_lowercase(&self) -> BracketItem {
+        match self {
+            BracketItem::Char(c) => BracketItem::Char(c.to_ascii_lowercase()),
+            BracketItem::Range(from, to) => BracketItem::Range(from.to_ascii_lowercase(),
+                                                               to.to_ascii_lowercase()),
+            item => item.clone(),
+        }
+    }
 }
 
-fn compare_internal(candidates: &mut Vec<String>, w: &Wildcard) {
-    match w {
-        Wildcard::Normal(s) => compare_normal(candidates, &s),
-        Wildcard::Asterisk  => asterisk(candidates),
-        Wildcard::Question  => question(candidates),
-        Wildcard::OneOf(cs) => one_of(candidates, &cs, false),
-        Wildcard::NotOneOf(cs) => one_of(candidates, &cs, true),
-        Wildcard::ExtGlob(prefix, ps) => ext_paren(candidates, *prefix, &ps),
-    }
+/* a pattern parsed once and reusable for any number of comparisons.
+ * A comparison moves a set of positions in the word wildcard by wildcard,
+ * so that it never takes more than polynomial time on any pattern. */
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    wildcards: Vec<Wildcard>,
+    nocase: bool,
 }
 
-fn compare_normal(cands: &mut Vec<String>, s: &String) {
-    let mut ans = vec![];
+impl Matcher {
+    pub fn matches(&self, word: &str) -> bool {
+        let word = self.fold(word);
+        run(&word, vec![0], &self.wildcards).contains(&word.len())
+    }
 
-    for c in cands.into_iter() {
-        if ! c.starts_with(s) {
-            continue;
+    /* ASCII letters are folded so that byte lengths of prefixes do not change */
+    pub fn ignore_case(mut self, nocase: bool) -> Matcher {
+        if nocase {
+            self.wildcards = self.wildcards.iter().map(lowercase).collect();
         }
-        
-        ans.push(c[s.len()..].to_string());
+        self.nocase = nocase;
+        self
     }
 
-    *cands = ans;
-}
-
-fn asterisk(cands: &mut Vec<String>) {
-    let mut ans = vec![];
-    for cand in cands.into_iter() {
-        let mut s = String::new();
-        ans.push(s.clone());
-        for c in cand.chars().rev() {
-            s = c.to_string() + &s.clone();
-            ans.push(s.clone());
-        }
+    pub fn has_wildcard(&self) -> bool {
+        self.wildcards.iter().any(|w| ! matches!(w, Wildcard::Normal(_)))
     }
 
-    *cands = ans;
-}
+    /* byte lengths of the prefixes of word matching the pattern, shortest first */
+    pub fn prefix_matches(&self, word: &str) -> Vec<usize> {
+        let word = self.fold(word);
+        run(&word, vec![0], &self.wildcards)
+    }
 
-fn question(cands: &mut Vec<String>) {
-    let mut ans = vec![];
-    for cand in cands.into_iter() {
-        match cand.chars().nth(0) {
-            Some(c) => {
-                let len = c.len_utf8();
-                ans.push(cand[len..].to_string());
-            },
-            _ => {},
+    fn fold(&self, word: &str) -> String {
+        match self.nocase {
+            true  => word.to_ascii_lowercase(),
+            false => word.to_string(),
         }
     }
-    *cands = ans;
 }
 
-fn ext_paren(cands: &mut Vec<String>, prefix: char, patterns: &Vec<String>) {
-    match prefix {
-        '?' => ext_question(cands, patterns),
-        '*' => ext_zero_or_more(cands, patterns),
-        '+' => ext_more_than_zero(cands, patterns),
-        '@' => ext_once(cands, patterns),
-        '!' => ext_not(cands, patterns),
-        _   => error_message::internal("unknown extglob prefix"),
-    }
+pub fn compile(pattern: &str, extglob: bool) -> Matcher {
+    Matcher { wildcards: parse(pattern, extglob), nocase: false }
 }
 
-fn ext_question(cands: &mut Vec<String>, patterns: &Vec<String>) {
-    let mut ans = cands.clone();
-    for p in patterns {
-        let mut tmp = cands.clone();
-        parse(p, true).iter().for_each(|w| compare_internal(&mut tmp, &w));
-        ans.append(&mut tmp);
-    }
-    *cands = ans;
+/* a pattern matching only the string itself */
+pub fn escape(s: &str) -> String {
+    s.chars().map(|c| match "*?[]\\@+!()|".contains(c) {
+        true  => format!("\\{}", c),
+        false => c.to_string(),
+    }).collect()
 }
 
-fn ext_zero_or_more(cands: &mut Vec<String>, patterns: &Vec<String>) {//TODO: buggy
-    let mut ans = vec![];
-    let mut tmp = cands.clone();
-    let mut len = tmp.len();
-
-    while len > 0 {
-        ans.extend(tmp.clone());
-        ext_once(&mut tmp, patterns);
-        for a in &ans {
-            tmp.retain(|t| a.as_str() != t.as_str());
-        }
-
-        len = tmp.len();
+fn lowercase(w: &Wildcard) -> Wildcard {
+    let lower = |cs: &Vec<BracketItem>| cs.iter().map(|c| c.to_ascii_lowercase()).collect();
+    match w {
+        Wildcard::Normal(s) => Wildcard::Normal(s.to_ascii_lowercase()),
+        Wildcard::OneOf(cs) => Wildcard::OneOf(lower(cs)),
+        Wildcard::NotOneOf(cs) => Wildcard::NotOneOf(lower(cs)),
+        Wildcard::ExtGlob(prefix, ps) => Wildcard::ExtGlob(*prefix,
+                      ps.iter().map(|p| p.iter().map(lowercase).collect()).collect()),
+        w => w.clone(),
     }
-    *cands = ans;
 }
 
-fn ext_more_than_zero(cands: &mut Vec<String>, patterns: &Vec<String>) {//TODO: buggy
-    let mut ans: Vec<String> = vec![];
-    let mut tmp: Vec<String> = cands.clone();
-    let mut len = tmp.len();
-
-    while len > 0  {
-        ext_once(&mut tmp, patterns);
-
-        for a in &ans {
-            tmp.retain(|t| a.as_str() != t.as_str());
+/* the positions after the wildcards from any of the given positions, sorted */
+fn run(word: &str, mut positions: Vec<usize>, wildcards: &[Wildcard]) -> Vec<usize> {
+    for w in wildcards {
+        if positions.is_empty() {
+            break;
         }
-        ans.extend(tmp.clone());
-        len = tmp.len();
+        positions = step(word, &positions, w);
+        positions.sort();
+        positions.dedup();
     }
-    *cands = ans;
+    positions
 }
 
-fn ext_once(cands: &mut Vec<String>, patterns: &Vec<String>) {
-    let mut ans = vec![];
-    for p in patterns {
-        let mut tmp = cands.clone();
-        parse(p, true).iter().for_each(|w| compare_internal(&mut tmp, &w));
-        ans.append(&mut tmp);
+fn step(word: &str, positions: &[usize], w: &Wildcard) -> Vec<usize> {
+    match w {
+        Wildcard::Normal(s) => positions.iter()
+                               .filter(|p| word[**p..].starts_with(s.as_str()))
+                               .map(|p| p + s.len()).collect(),
+        Wildcard::Asterisk  => asterisk(word, positions),
+        Wildcard::Question  => one_char(word, positions, |_| true),
+        Wildcard::OneOf(cs) => one_char(word, positions, |c| cs.iter().any(|i| i.matches(c))),
+        Wildcard::NotOneOf(cs) => one_char(word, positions, |c| ! cs.iter().any(|i| i.matches(c))),
+        Wildcard::ExtGlob(prefix, ps) => ext_paren(word, positions, *prefix, ps),
     }
-    *cands = ans;
 }
 
-fn ext_once_exact_match(cand: &String, patterns: &Vec<String>) -> bool {
-    let mut tmp = vec![cand.clone()];
-    ext_once(&mut tmp, patterns);
-    tmp.iter().any(|t| t == "")
+/* all the positions from the first one to the end since they are sorted */
+fn asterisk(word: &str, positions: &[usize]) -> Vec<usize> {
+    let first = match positions.first() {
+        Some(p) => *p,
+        None    => return vec![],
+    };
+
+    let mut ans: Vec<usize> = word[first..].char_indices().map(|(i, _)| first + i).collect();
+    ans.push(word.len());
+    ans
 }
 
-fn make_prefix_strings(s: &String) -> Vec<String> {
-    let mut ans = vec![];
-    let mut prefix = s.clone();
+fn one_char(word: &str, positions: &[usize], judge: impl Fn(char) -> bool) -> Vec<usize> {
+    positions.iter().filter_map(|p| match word[*p..].chars().next() {
+        Some(c) if judge(c) => Some(p + c.len_utf8()),
+        _ => None,
+    }).collect()
+}
 
-    ans.push(prefix.clone());
-    while prefix.len() > 0 {
-        prefix.pop();
-        ans.push(prefix.clone());
+fn ext_paren(word: &str, positions: &[usize], prefix: char, patterns: &[Vec<Wildcard>]) -> Vec<usize> {
+    match prefix {
+        '?' => {
+            let mut ans = positions.to_vec();
+            ans.extend(ext_once(word, positions, patterns));
+            ans
+        },
+        '*' => ext_repeat(word, positions, patterns, positions.to_vec()),
+        '+' => ext_repeat(word, positions, patterns, vec![]),
+        '@' => ext_once(word, positions, patterns),
+        '!' => ext_not(word, positions, patterns),
+        _   => error_message::internal("unknown extglob prefix"),
     }
-    ans
 }
 
-fn ext_not(cands: &mut Vec<String>, patterns: &Vec<String>) {
-    let mut ans = vec![];
-    for cand in cands.iter_mut() {
-        for prefix in make_prefix_strings(cand)  {
-            if ! ext_once_exact_match(&prefix, patterns) {
-                ans.push(cand[prefix.len()..].to_string());
-            }
-        }
+fn ext_once(word: &str, positions: &[usize], patterns: &[Vec<Wildcard>]) -> Vec<usize> {
+    patterns.iter().flat_map(|p| run(word, positions.to_vec(), p)).collect()
+}
+
+/* adds the positions after each number of repetitions until no new one appears */
+fn ext_repeat(word: &str, positions: &[usize], patterns: &[Vec<Wildcard>],
+              mut ans: Vec<usize>) -> Vec<usize> {
+    let mut tmp = positions.to_vec();
+    while ! tmp.is_empty() {
+        tmp = ext_once(word, &tmp, patterns);
+        tmp.sort();
+        tmp.dedup();
+        tmp.retain(|t| ! ans.contains(t));
+        ans.extend(tmp.iter());
     }
-    *cands = ans;
+    ans
 }
 
-fn one_of(cands: &mut Vec<String>, cs: &Vec<char>, inverse: bool) {
+/* the ends of the parts from each position that no pattern matches */
+fn ext_not(word: &str, positions: &[usize], patterns: &[Vec<Wildcard>]) -> Vec<usize> {
     let mut ans = vec![];
-    for cand in cands.into_iter() {
-        if cs.iter().any(|c| cand.starts_with(*c)) ^ inverse {
-            let h = cand.chars().nth(0).unwrap();
-            ans.push(cand[h.len_utf8()..].to_string());
-        }
+    for p in positions {
+        let matched = ext_once(word, &[*p], patterns);
+        let ends = word[*p..].char_indices().map(|(i, _)| p + i)
+                   .chain(std::iter::once(word.len()));
+        ans.extend(ends.filter(|e| ! matched.contains(e)));
     }
-    *cands = ans;
+    ans
 }
 
 fn parse(pattern: &str, extglob: bool) -> Vec<Wildcard > {
@@ -262,14 +290,41 @@ fn scanner_chars(remaining: &str) -> usize {
     ans
 }
 
+/* a char in brackets: an escaped char, [.c.], [=c=] or a plain char */
+fn scanner_bracket_char(remaining: &str) -> Option<(usize, char)> {
+    for (open, close) in [("[.", ".]"), ("[=", "=]")] {
+        if let Some(rest) = remaining.strip_prefix(open) {
+            let end = rest.find(close)?;
+            let mut cs = rest[..end].chars();
+            return match (cs.next(), cs.next()) {
+                (Some(c), None) => Some((end + 4, c)),
+                _ => None,
+            };
+        }
+    }
+
+    match remaining.chars().next()? {
+        '\\' => {
+            let c = remaining[1..].chars().next()?;
+            Some((1 + c.len_utf8(), c))
+        },
+        c => Some((c.len_utf8(), c)),
+    }
+}
+
+fn scanner_bracket_class(remaining: &str) -> Option<(usize, BracketItem)> {
+    let rest = remaining.strip_prefix("[:")?;
+    let end = rest.find(":]")?;
+    Some((end + 4, BracketItem::Class(rest[..end].to_string())))
+}
+
 fn scanner_bracket(remaining: &str) -> (usize, Wildcard) {
     if ! remaining.starts_with("[") {
         return (0, Wildcard::OneOf(vec![]) );
     }
     
-    let mut chars = vec![];
+    let mut items = vec![];
     let mut len = 1;
-    let mut escaped = false;
     let mut not = false;
 
     if remaining.starts_with("[^") || remaining.starts_with("[!") {
@@ -277,27 +332,37 @@ fn scanner_bracket(remaining: &str) -> (usize, Wildcard) {
         len = 2;
     }
 
-    for c in remaining[len..].chars() {
-        len += c.len_utf8();
-
-        if escaped {
-            chars.push(c); 
-            escaped = false;
-            continue;
+    let head = len;
+    while len < remaining.len() {
+        if remaining[len..].starts_with("]") && len != head {
+            len += 1;
+            match not {
+                false => return (len, Wildcard::OneOf(items) ),
+                true  => return (len, Wildcard::NotOneOf(items) ),
+            }
         }
-        if c == '\\' {
-            escaped = true;
+
+        if let Some((n, class)) = scanner_bracket_class(&remaining[len..]) {
+            len += n;
+            items.push(class);
             continue;
         }
 
-        if c == ']' {
-            match not {
-                false => return (len, Wildcard::OneOf(chars) ),
-                true  => return (len, Wildcard::NotOneOf(chars) ),
+        let (n, from) = match scanner_bracket_char(&remaining[len..]) {
+            Some(c) => c,
+            None    => break,
+        };
+        len += n;
+
+        let rest = &remaining[len..];
+        if rest.starts_with("-") && ! rest.starts_with("-]") {
+            if let Some((n, to)) = scanner_bracket_char(&rest[1..]) {
+                len += 1 + n;
+                items.push(BracketItem::Range(from, to));
+                continue;
             }
         }
-
-        chars.push(c);
+        items.push(BracketItem::Char(from));
     }
 
     (0, Wildcard::OneOf(vec![]) )
@@ -330,6 +395,7 @@ fn scanner_ext_paren(remaining: &str) -> (usize, Option<Wildcard>) {
             continue;
         }
         if c == '\\' {
+            chars.push(c); //kept for the parse of each pattern
             escaped = true;
             continue;
         }
@@ -350,6 +416,7 @@ fn scanner_ext_paren(remaining: &str) -> (usize, Option<Wildcard>) {
             match nest {
                 0 => return {
                     patterns.push(chars.iter().collect());
+                    let patterns = patterns.iter().map(|p: &String| parse(p, true)).collect();
                     (len, Some(Wildcard::ExtGlob(prefix, patterns)) )
                 },
                 _ => nest -= 1,
@@ -363,6 +430,7 @@ fn scanner_ext_paren(remaining: &str) -> (usize, Option<Wildcard>) {
 }
 
 fn consume(remaining: &mut String, cutpos: usize) -> String {
+    let cutpos = super::char_boundary(remaining, cutpos);
     let cut = remaining[0..cutpos].to_string();
     *remaining = remaining[cutpos..].to_string();
 
@@ -21,6 +21,7 @@ com=../target/release/sush
 ./test_job.bash nobuild &
 ./test_brace.bash nobuild &
 ./test_builtins.bash nobuild &
+./test_quoting.bash nobuild &
 
 wait 
 
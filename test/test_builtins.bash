@@ -22,9 +22,30 @@ res=$($com <<< 'rm -f /tmp/link; cd /tmp; mkdir -p hoge; ln -s hoge link; cd lin
 [ "$res" = "/tmp/link
 /private/tmp/hoge" ] || err $LINENO
 
+res=$($com <<< 'cd /tmp; mkdir -p hoge; cd -P link; pwd; echo $PWD')
+[ "$res" = "/tmp/hoge
+/tmp/hoge" ] ||
+[ "$res" = "/private/tmp/hoge
+/private/tmp/hoge" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; cd /; cd -; echo $OLDPWD')
+[ "$res" = "/tmp
+/" ] || err $LINENO
+
+res=$($com <<< 'mkdir -p /tmp/hoge/cdpath_test; cd /; CDPATH=/tmp/hoge; cd cdpath_test; pwd')
+[ "$res" = "/tmp/hoge/cdpath_test
+/tmp/hoge/cdpath_test" ] || err $LINENO
+
+res=$($com <<< 'cd /; cd /nonexistent; echo $?; pwd')
+[ "$res" = "1
+/" ] || err $LINENO
+
+res=$($com <<< 'HOME=/tmp; cd; pwd')
+[ "$res" = "/tmp" ] || err $LINENO
+
 res=$($com <<< 'pwd -a 2>/tmp/rusty_bash; cat /tmp/rusty_bash')
-[ "$res" = "sush: pwd: -a: invalid option
-pwd: usage: pwd [-LP]" ] || err $LINENO
+[ "$res" = "$com: line 1: pwd: -a: invalid option
+$com: line 1: pwd: usage: pwd [-LP]" ] || err $LINENO
 
 echo aaaaaaaaaaaaaaaa > /tmp/hoge.txt
 res=$($com <<< 'source /tmp/hoge.txt')
@@ -54,12 +75,43 @@ b=$(cd ; compgen -f . | wc -l )
 res=$($com <<< 'cd ; compgen -f . | wc -l')
 [ "$res" = "$b" ] || err $LINENO
 
+res=$($com <<< 'compgen -A builtin -P "<" -S ">" ech; compgen -W "x y" -- z; echo $?')
+[ "$res" = "<echo>
+1" ] || err $LINENO
+
+res=$($com <<< 'f(){ COMPREPLY=("$1" "$2" "$3"); }; compgen -F f abc')
+[ "$res" = "compgen
+abc" ] || err $LINENO
+
+res=$($com <<< 'complete -W "a b" -F _f foo; complete -d -A function foo bar; complete -p bar; complete -r bar; complete')
+[ "$res" = "complete -d -A function bar
+complete -d -A function foo" ] || err $LINENO
+
+res=$($com <<< 'complete -p nosuch; echo $?; compgen -A nosuch; echo $?')
+[ "$res" = "1
+1" ] || err $LINENO
+
 res=$($com <<< 'eval "echo a" b')
 [ "$res" = "a b" ] || err $LINENO
 
 res=$($com <<< 'eval "(" echo abc ")" "|" rev')
 [ "$res" = "cba" ] || err $LINENO
 
+res=$($com <<< 'eval "echo a
+echo b"')
+[ "$res" = "a
+b" ] || err $LINENO
+
+res=$($com <<< 'eval "for i in 1 2"
+echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'eval "echo \$LINENO
+
+echo \$LINENO"')
+[ "$res" = "1
+3" ] || err $LINENO
+
 res=$($com <<< 'A=aaa ; unset A ; echo $A')
 [ "$res" = "" ] || err $LINENO
 
@@ -78,11 +130,70 @@ res=$($com <<< 'A () { echo aaa ; } ; unset -f A ; A')
 res=$($com <<< 'A () { echo aaa ; } ; unset A ; A')
 [ "$res" = "" ] || err $LINENO
 
+res=$($com <<< 'A=1 B=2; unset A B; echo [$A$B]')
+[ "$res" = "[]" ] || err $LINENO
+
+res=$($com <<< 'A () { echo aaa ; } ; A=1; unset A ; A; echo [$A]')
+[ "$res" = "aaa
+[]" ] || err $LINENO
+
+res=$($com <<< 'A=(1 2 3); unset "A[1]"; echo ${A[@]}')
+[ "$res" = "1 3" ] || err $LINENO
+
+res=$($com <<< 'A=(0 1 2); unset "A[0]"; echo ${A[1]} [${A[0]}] [$A]; declare -p A')
+[ "$res" = "1 [] []
+declare -a A=([1]=\"1\" [2]=\"2\")" ] || err $LINENO
+
+res=$($com <<< 'A=g; f(){ local A=l; g; echo $A; }; g(){ unset A; }; f')
+[ "$res" = "g" ] || err $LINENO
+
+res=$($com <<< 'readonly A=1; unset A; echo $? $A')
+[ "$res" = "1 1" ] || err $LINENO
+
+res=$($com <<< 'readonly A=1; A=2; echo $? $A')
+[ "$res" = "1 1" ] || err $LINENO
+
+res=$($com <<< 'A=1; declare -n R=A; unset -n R; echo [$R] $A')
+[ "$res" = "[] 1" ] || err $LINENO
+
+res=$($com <<< 'unset 1A')
+[ "$?" = "1" ] || err $LINENO
+
 # source command
 
+printf 'echo a\necho $LINENO\n' > /tmp/rusty_bash_source
+res=$($com <<< 'source /tmp/rusty_bash_source
+echo b')
+[ "$res" = "a
+2
+b" ] || err $LINENO
+
+printf 'read x\necho $x\n' > /tmp/rusty_bash_source
+res=$($com <<< 'source /tmp/rusty_bash_source
+hoge')
+[ "$res" = "hoge" ] || err $LINENO
+
+printf 'echo $# $1\nreturn 3\necho NG\n' > /tmp/rusty_bash_source
+res=$($com <<< 'set -- a b; source /tmp/rusty_bash_source x; echo $? $# $1')
+[ "$res" = "1 x
+3 2 a" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; PATH=/nonexistent; . rusty_bash_source; PATH=/tmp; cd /; . rusty_bash_source')
+[ "$res" = "0
+0" ] || err $LINENO
+
+res=$($com <<< 'source /tmp/rusty_bash_source_none; echo $?; source; echo $?')
+[ "$res" = "1
+2" ] || err $LINENO
+
 res=$($com <<< 'echo $PS1')
 [ "$res" = "" ] || err $LINENO
 
+res=$(HISTFILE=/dev/null $com -i <<< 'PS1="[\u|\W|\!|\#]> "; PS2="\[..\]> "; cd /
+echo a |
+cat' 2>&1 > /dev/null)
+[ "$res" = "🍣 [$(id -un)|/|2|2]> ..> [$(id -un)|/|3|3]> " ] || err $LINENO
+
 res=$($com <<< 'case aaa in aaa) return && echo NG ;; esac')
 [ "$?" = "2" ] || err $LINENO
 [ "$res" = "" ] || err $LINENO
@@ -121,11 +232,530 @@ res=$($com <<< 'echo あ い う | while read a b ; do echo $a ; echo $b ; done'
 [ "$res" == "あ
 い う" ] || err $LINENO
 
+res=$($com <<< 'echo "  a  b  " | (read; echo "[$REPLY]")')
+[ "$res" == "[  a  b  ]" ] || err $LINENO
+
+res=$($com <<< 'echo "  a  b  c  " | (read x y; echo "[$x][$y]")')
+[ "$res" == "[a][b  c]" ] || err $LINENO
+
+res=$($com <<< 'printf "a\\\\ b\\\\\nc d\n" | (read x y; echo "[$x][$y]")')
+[ "$res" == "[a bc][d]" ] || err $LINENO
+
+res=$($com <<< 'printf "a\\\\ b\n" | (read -r x y; echo "[$x][$y]")')
+[ "$res" == "[a\\][b]" ] || err $LINENO
+
+res=$($com <<< 'echo a b c | (read -a arr; echo ${arr[2]}${arr[0]})')
+[ "$res" == "ca" ] || err $LINENO
+
+res=$($com <<< 'echo abcdef | (read -n 3 x; echo $x)')
+[ "$res" == "abc" ] || err $LINENO
+
+res=$($com <<< 'echo あいう | (read -n 2 x; echo $x)')
+[ "$res" == "あい" ] || err $LINENO
+
+res=$($com <<< 'echo a:b | (read -d : x; echo $x)')
+[ "$res" == "a" ] || err $LINENO
+
+res=$($com <<< 'echo "a,b, c" | (IFS=, read x y z; echo "[$x][$y][$z]")')
+[ "$res" == "[a][b][ c]" ] || err $LINENO
+
+res=$($com <<< 'sleep 1 | (read -t 0.1 x; echo $?)')
+[ "$res" == "142" ] || err $LINENO
+
+res=$($com <<< 'printf x | (read y; echo $? $y)')
+[ "$res" == "1 x" ] || err $LINENO
+
+res=$($com <<< 'read -z')
+[ "$?" == "2" ] || err $LINENO
+
+# declare command
+
+res=$($com <<< 'x=1; declare -n r=x; echo $r; r=5; echo $x')
+[ "$res" = "1
+5" ] || err $LINENO
+
+res=$($com <<< 'f(){ local -n v=$1; echo $v; v=changed; }; y=orig; f y; echo $y')
+[ "$res" = "orig
+changed" ] || err $LINENO
+
+res=$($com <<< 'f(){ local -n v=$1; v=(a b c); }; f arr; echo ${arr[1]}')
+[ "$res" = "b" ] || err $LINENO
+
+res=$($com <<< 'declare -n a=b; declare -n b=a; echo [$a]' 2>&1)
+[ "$res" = "$com: line 1: warning: a: circular name reference
+[]" ] || err $LINENO
+
+res=$($com <<< 'declare -n s=s')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'x="a b"; w='"'"'$q"'"'"'; z=$'"'"'a\nb'"'"'; a=(1 "2 3"); declare -n N=x; declare -p x w z a N')
+[ "$res" = 'declare -- x="a b"
+declare -- w="\$q\""
+declare -- z=$'"'"'a\nb'"'"'
+declare -a a=([0]="1" [1]="2 3")
+declare -n N="x"' ] || err $LINENO
+
+res=$($com <<< 'x=1; readonly x; export x; declare -p | grep " x="; declare -p nonexist')
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = 'declare -rx x="1"' ] || err $LINENO
+
+res=$($com <<< 'x="a b"; y="it'"'"'s"; z=$'"'"'a\tb'"'"'; e=; a=(1 "2 3"); set | grep -E "^(x|y|z|e|a)="')
+[ "$res" = "a=([0]=\"1\" [1]=\"2 3\")
+e=
+x='a b'
+y='it'\\''s'
+z=\$'a\\tb'" ] || err $LINENO
+
+res=$($com <<< 'f(){ local x=loc; declare -p x; declare | grep ^x=; }; x=glob; f')
+[ "$res" = 'declare -- x="loc"
+x=loc' ] || err $LINENO
+
+# export command
+
+res=$($com <<< 'export A=1; bash -c "echo \$A"')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'A=1; bash -c "echo [\$A]"; export A; bash -c "echo \$A"')
+[ "$res" = "[]
+1" ] || err $LINENO
+
+res=$($com <<< 'export A=1; export -n A; bash -c "echo [\$A]"; echo $A')
+[ "$res" = "[]
+1" ] || err $LINENO
+
+res=$($com <<< "export A='a\"b'; export -p | grep '^declare -x A='")
+[ "$res" = 'declare -x A="a\"b"' ] || err $LINENO
+
+res=$($com <<< 'export 1A=1')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'unset HOME; bash -c "echo [\$HOME]"')
+[ "$res" = "[]" ] || err $LINENO
+
+res=$(env 'A-B=1' $com <<< 'echo [$A]; env | grep "^A-B="')
+[ "$res" = "[]
+A-B=1" ] || err $LINENO
+
+res=$(env "NL=$(printf 'a\nb')" $com <<< 'echo "$NL"; env | grep -c "^b$"')
+[ "$res" = "a
+b
+1" ] || err $LINENO
+
+res=$(env "BIN=$(printf 'a\377')" $com <<< 'printf %s "$BIN" | od -An -tx1; env | grep -a "^BIN=" | od -An -tx1')
+[ "$res" = " 61 ff
+ 42 49 4e 3d 61 ff 0a" ] || err $LINENO
+
+res=$($com <<< 'export X=1; f(){ local X=2; env | grep ^X=; }; f; B=5 env | grep ^B=; env | grep ^B=')
+[ "$res" = "X=2
+B=5" ] || err $LINENO
+
+res=$($com <<< 'X=1; declare -n R=X; export R; a=(1 2); export a; env | grep -E "^(R|X|a)="')
+[ "$res" = "X=1" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo f:$A; env | grep ^A=; }; A=0; A=1 f; echo $A; env | grep ^A= || echo none')
+[ "$res" = "f:1
+A=1
+0
+none" ] || err $LINENO
+
+res=$($com <<< 'g(){ A=changed; env | grep ^A=; }; A=tmp g; echo "[$A]"; C=3 eval "env | grep ^C="; echo "[$C]"')
+[ "$res" = "A=changed
+[]
+C=3
+[]" ] || err $LINENO
+
+res=$($com <<< 'f(){ export B=2; }; B=1 f; echo "[$B]"; env | grep ^B=; g(){ export C; }; C=1 g; env | grep ^C=')
+[ "$res" = "[2]
+B=2
+C=1" ] || err $LINENO
+
+res=$($com <<< 'export X=1; declare -n R=X; R=5 env | grep ^X=; env | grep ^X=')
+[ "$res" = "X=5
+X=1" ] || err $LINENO
+
+# export -f
+
+res=$($com <<< 'f() { echo "in f $1"; }; export -f f; bash -c "f a"; '$com' -c "f b"; export -nf f; bash -c "f c" 2>/dev/null; echo $?')
+[ "$res" = "in f a
+in f b
+127" ] || err $LINENO
+
+res=$($com <<< 'export -f nofunc; echo $?' 2>&1)
+[ "$res" = "$com: line 1: export: nofunc: not a function
+1" ] || err $LINENO
+
+res=$(bash -c 'g() { echo "g $1"; }; export -f g; '$com' -c "g x; unset -f g; env | grep -c ^BASH_FUNC_g"')
+[ "$res" = "g x
+0" ] || err $LINENO
+
+res=$(env 'BASH_FUNC_h%%=() { echo h; }; echo injected' $com -c 'h' 2>&1)
+[ "$res" = "$com: line 1: h: command not found" ] || err $LINENO
+
+res=$($com <<< 'P=$PATH; PATH=/nonexistent; ls 2> /dev/null; echo $?; PATH=$P; PATH=/nonexistent ls 2> /dev/null; echo $?')
+[ "$res" = "127
+127" ] || err $LINENO
+
 # set command
 
 res=$($com <<< 'set -- a b c ; echo $2')
 [ "$res" == "b" ] || err $LINENO
 
+res=$($com <<< 'set a b c ; echo $1 $#')
+[ "$res" == "a 3" ] || err $LINENO
+
+res=$($com <<< 'set a b ; set -- ; echo $#')
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'set -x -- a b ; echo $2' 2> /dev/null)
+[ "$res" == "b" ] || err $LINENO
+
+# caller command
+
+tmp=/tmp/rusty_bash_caller_$$
+cat << 'EOF' > $tmp
+f () { caller; caller 0; caller 1; caller 2; echo $?; }
+g () { f; }
+g
+caller; echo $?
+caller 0; echo $?
+EOF
+
+res=$($com $tmp)
+[ "$res" == "2 $tmp
+2 g $tmp
+3 main $tmp
+1
+0 NULL
+0
+1" ] || err $LINENO
+rm -f $tmp
+
+res=$($com -c 'f () { caller x; echo $?; }; f' 2>&1)
+[ "$res" == "$com: line 1: caller: x: invalid number
+$com: line 1: caller: usage: caller [expr]
+2" ] || err $LINENO
+
+# shift command
+
+res=$($com <<< 'set a b c ; shift ; echo $@ ; shift 2 ; echo $#')
+[ "$res" == "b c
+0" ] || err $LINENO
+
+res=$($com <<< 'set a b ; shift 3 ; echo $? $#')
+[ "$res" == "1 2" ] || err $LINENO
+
+res=$($com <<< 'shift x')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'f () { shift ; echo $@ ; } ; f 1 2 3 ; echo $#')
+[ "$res" == "2 3
+0" ] || err $LINENO
+
+# binary-safe read and command substitution
+
+res=$($com <<< 'printf "a\0b\n" | (read x; echo $x)')
+[ "$res" == "ab" ] || err $LINENO
+
+res=$($com <<< 'x=$(printf "\377"); echo -n "$x" | od -An -tx1')
+[ "$res" == " ff" ] || err $LINENO
+
+res=$($com <<< 'x=$(printf "a\0b"); echo $x' 2> /dev/null)
+[ "$res" == "ab" ] || err $LINENO
+
+res=$($com <<< 'x=$(printf "\376"); printf "%b%s" "\375" "$x" | od -An -tx1')
+[ "$res" == " fd fe" ] || err $LINENO
+
+res=$($com <<< 'x=$(printf "\357\236\200\377"); printf "%s" "$x" | od -An -tx1')
+[ "$res" == " ef 9e 80 ff" ] || err $LINENO
+
+res=$($com <<< 'printf "%s\n" $'"'"'\uF780'"'"' | od -An -tx1')
+[ "$res" == " ef 9e 80 0a" ] || err $LINENO
+
+# echo
+
+res=$($com <<< 'echo -n a; echo b c')
+[ "$res" == "ab c" ] || err $LINENO
+
+res=$($com <<< 'echo -e "a\tb\x41\0101"; echo -E "a\tb"; echo "a\tb"')
+[ "$res" == "a	bAA
+a\tb
+a\tb" ] || err $LINENO
+
+res=$($com <<< 'echo -e "x\cy" z; echo -x - a')
+[ "$res" == "x-x - a" ] || err $LINENO
+
+res=$($com <<< 'PATH=/nonexistent; echo ok')
+[ "$res" == "ok" ] || err $LINENO
+
+# printf
+
+res=$($com <<< 'printf "%s-%d|" a 1 b 2 c')
+[ "$res" == "a-1|b-2|c-0|" ] || err $LINENO
+
+res=$($com <<< 'printf "%5.2s|%-4d|%04x|%+.3e|%c\n" abc 7 255 1234.5 hello')
+[ "$res" == "   ab|7   |00ff|+1.234e+03|h" ] || err $LINENO
+
+res=$($com <<< 'printf "%q " "a b" "" "~x"')
+[ "$res" == "a\ b '' \~x " ] || err $LINENO
+
+res=$($com <<< 'printf "%b|" "a\0101" "x\cy" z')
+[ "$res" == "aA|x" ] || err $LINENO
+
+res=$($com <<< 'printf -v x "%s:%s" a b; echo $x')
+[ "$res" == "a:b" ] || err $LINENO
+
+res=$($com <<< 'printf "%d\n" "'"'"'A" 0x10 010')
+[ "$res" == "65
+16
+8" ] || err $LINENO
+
+res=$($com <<< 'printf "%d" abc')
+[ "$?" == "1" ] || err $LINENO
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'printf "\377\x41" | od -An -tx1')
+[ "$res" == " ff 41" ] || err $LINENO
+
+res=$($com <<< 'printf "%y"')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'printf')
+[ "$?" == "2" ] || err $LINENO
+
+# type and command
+
+res=$($com <<< 'type -P sh')
+[ "$res" != "" ] || err $LINENO
+
+res=$($com <<< 'type -P nosuchcommand')
+[ "$?" == "1" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< 'type cd')
+[ "$res" == "cd is a shell builtin" ] || err $LINENO
+
+res=$($com <<< 'ls () { echo func; }; command ls -d /')
+[ "$res" == "/" ] || err $LINENO
+
+res=$($com <<< 'PATH=/nonexistent; command -p ls -d /')
+[ "$res" == "/" ] || err $LINENO
+
+res=$($com <<< 'f () { echo f; }; command f')
+[ "$?" == "127" ] || err $LINENO
+
+res=$($com <<< 'cd () { echo fake; }; builtin cd /; pwd; echo () { printf "f:%s\n" "$*"; }; echo a; builtin echo b; command builtin echo c')
+[ "$res" == "/
+f:a
+b
+c" ] || err $LINENO
+
+res=$($com <<< 'builtin ls; echo $?; builtin; echo $?')
+[ "$res" == "1
+0" ] || err $LINENO
+
+res=$($com <<< 'f () { :; }; type -t f cd if ls; type -t nosuch; echo $?')
+[ "$res" == "function
+builtin
+keyword
+file
+1" ] || err $LINENO
+
+mkdir -p /tmp/hoge/bin1 /tmp/hoge/bin2
+for d in /tmp/hoge/bin1 /tmp/hoge/bin2 ; do
+	printf '#!/bin/sh\n' > $d/tcmd
+	chmod +x $d/tcmd
+done
+
+res=$($com <<< 'PATH=/tmp/hoge/bin1:/tmp/hoge/bin2; type -a tcmd; tcmd; type tcmd')
+[ "$res" == "tcmd is /tmp/hoge/bin1/tcmd
+tcmd is /tmp/hoge/bin2/tcmd
+tcmd is hashed (/tmp/hoge/bin1/tcmd)" ] || err $LINENO
+
+res=$($com <<< 'PATH=/tmp/hoge/bin1:/tmp/hoge/bin2; EXECIGNORE="*/bin1/*"; type -a tcmd; tcmd; type tcmd')
+[ "$res" == "tcmd is /tmp/hoge/bin2/tcmd
+tcmd is hashed (/tmp/hoge/bin2/tcmd)" ] || err $LINENO
+
+res=$($com <<< 'alias ll="ls -l"; f () { :; }; PATH=/bin; command -v ll f cd ls nosuch; echo $?; command -v nosuch; echo $?')
+[ "$res" == "alias ll='ls -l'
+f
+cd
+/bin/ls
+0
+1" ] || err $LINENO
+
+res=$($com <<< 'command -V cd nosuch')
+[ "$res" == "cd is a shell builtin" ] || err $LINENO
+
+# hash
+
+res=$($com <<< 'hash; PATH=/bin; ls > /dev/null; ls > /dev/null; hash; hash -t ls; hash -d ls; hash -d ls; echo $?')
+[ "$res" == "hash: hash table empty
+hits	command
+   2	/bin/ls
+/bin/ls
+1" ] || err $LINENO
+
+res=$($com <<< 'hash -p /bin/echo foo; foo hi; hash -l; hash -r; hash; hash nosuch; echo $?')
+[ "$res" == "hi
+builtin hash -p /bin/echo foo
+hash: hash table empty
+1" ] || err $LINENO
+
+res=$($com <<< 'PATH=/bin; hash ls; PATH=/usr/bin; hash')
+[ "$res" == "hash: hash table empty" ] || err $LINENO
+
+res=$($com <<< 'hash -p /nonexist/ls ls; ls /dev/null; echo $?; shopt -s checkhash; ls /dev/null' 2>&1)
+[ "$res" == "$com: line 1: /nonexist/ls: No such file or directory
+127
+/dev/null" ] || err $LINENO
+
+# history option
+
+res=$($com <<< 'echo a
+set -o history
+echo b
+echo b
+set +o history
+echo c
+history')
+[ "$res" == "a
+b
+b
+c
+    1  echo b
+    2  echo b
+    3  set +o history" ] || err $LINENO
+
+res=$($com <<< 'set -o history
+HISTCONTROL=ignoreboth
+echo a
+echo a
+ echo b
+history 2')
+[ "$res" == "a
+a
+b
+    2  echo a
+    3  history 2" ] || err $LINENO
+
+res=$($com <<< 'set -o history
+HISTIGNORE="echo b*:&"
+echo a
+echo bb
+echo a
+echo c
+history 3')
+[ "$res" == "a
+bb
+a
+c
+    2  echo a
+    3  echo c
+    4  history 3" ] || err $LINENO
+
+res=$(HISTFILE=/dev/null $com -i <<< 'PS1=
+echo a b
+echo !!:s/a/x/ !$
+^x^y
+!2' 2>&1)
+[ "$res" == "🍣 a b
+echo echo x b b
+echo x b b
+echo echo y b b
+echo y b b
+echo a b
+a b" ] || err $LINENO
+
+res=$(HISTFILE=/dev/null $com -i <<< 'set +H
+echo a
+echo !!' 2> /dev/null)
+[ "$res" == "a
+!!" ] || err $LINENO
+
+res=$($com <<< 'set -o history
+echo a
+echo b
+history -d 2
+history -d 9; echo $?
+history -c
+echo c
+history')
+[ "$res" == "a
+b
+1
+c
+    1  echo c
+    2  history" ] || err $LINENO
+
+res=$($com <<< "history -d ''; echo \$?; history -d é; echo \$?" 2>&1)
+[ "$res" == "$com: line 1: history: : history position out of range
+1
+$com: line 1: history: é: history position out of range
+1" ] || err $LINENO
+
+res=$($com <<< 'set -o history
+history -s echo x y
+history')
+[ "$res" == "    1  echo x y
+    2  history" ] || err $LINENO
+
+rm -f /tmp/rusty_bash_hist_$$
+res=$($com <<< "set -o history
+echo a
+history -w /tmp/rusty_bash_hist_$$
+echo b
+history -a /tmp/rusty_bash_hist_$$
+history -c
+history -r /tmp/rusty_bash_hist_$$
+history")
+[ "$res" == "a
+b
+    1  history -r /tmp/rusty_bash_hist_$$
+    2  echo a
+    3  history -w /tmp/rusty_bash_hist_$$
+    4  echo b
+    5  history -a /tmp/rusty_bash_hist_$$
+    6  history" ] || err $LINENO
+rm -f /tmp/rusty_bash_hist_$$
+
+res=$($com <<< 'set -o | grep history')
+[ "$res" == "history         off" ] || err $LINENO
+
+res=$($com <<< 'set -H; set -o | grep histexpand; set +H; set -o | grep histexpand')
+[ "$res" == "histexpand      on
+histexpand      off" ] || err $LINENO
+
+res=$($com <<< 'set -o vi; set -o | grep -E "^(emacs|vi) "; set -o emacs; set -o | grep -E "^(emacs|vi) "')
+[ "$res" == "emacs           off
+vi              on
+emacs           on
+vi              off" ] || err $LINENO
+
+# alias
+
+res=$($com <<< 'alias ll="echo LL"
+ll a')
+[ "$?" == "127" ] || err $LINENO
+
+res=$($com <<< 'shopt -s expand_aliases; alias ll="echo LL" s="echo " w=world
+ll a; echo ll; s w')
+[ "$res" == "LL a
+ll
+world" ] || err $LINENO
+
+res=$($com <<< 'shopt -s expand_aliases; alias t="if true; then echo T; fi" x="echo X; x" a=b b=a
+t; x; a')
+[ "$res" == "T
+X" ] || err $LINENO
+
+res=$($com <<< 'alias z=1 y="a'"'"'b"; alias; alias z q; echo $?; unalias z q; echo $?; alias; unalias -a; alias')
+[ "$res" == "alias y='a'\''b'
+alias z='1'
+alias z='1'
+1
+1
+alias y='a'\''b'" ] || err $LINENO
+
 # shopt command
 
 res=$($com <<< 'shopt -u extglob ; echo @(a)')
@@ -136,5 +766,499 @@ echo @(a)')
 [ "$?" == "2" ] || err $LINENO
 [ "$res" == "" ] || err $LINENO
 
+res=$($com <<< 'shopt -s cmdtimes; /bin/true; [[ $SUSH_LAST_RSS -gt 0 ]] && echo $SUSH_LAST_CPU | grep -E "^[0-9]+\.[0-9]{6}$"')
+[ "$?" == "0" ] || err $LINENO
+
+res=$($com <<< '/bin/true; echo "[$SUSH_LAST_RSS]"')
+[ "$res" == "[]" ] || err $LINENO
+
+res=$($com <<< 'shopt checkjobs')
+[ "$res" == "checkjobs       off" ] || err $LINENO
+
+res=$($com <<< 'shopt -s checkjobs; sleep 1 & exit 3; echo NG')
+[ "$?" == "3" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+res=$(HISTFILE=/dev/null $com -i <<< 'shopt -s checkjobs; sleep 1 &
+exit 3
+exit 4
+echo NG' 2> /dev/null)
+[ "$?" == "4" ] || err $LINENO
+[ "$(grep -c "Running.*sleep 1" <<< "$res")" == "1" ] || err $LINENO
+[ "$(grep -c NG <<< "$res")" == "0" ] || err $LINENO
+
+res=$($com <<< 'shopt -s lastpipe; echo hi | read x; echo "[$x]"; seq 3 | while read a; do n=$a; done; echo $n')
+[ "$res" == "[hi]
+3" ] || err $LINENO
+
+res=$($com <<< 'seq 2 | read x; echo "[$x]"; shopt -s lastpipe; printf "a\nb\n" | read x; echo "[$x]"; read y; echo "[$y]"
+line')
+[ "$res" == "[]
+[a]
+[line]" ] || err $LINENO
+
+res=$($com <<< 'read x < /etc/passwd; echo "$x" | grep -q "^root:" && echo OK')
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com <<< 'shopt -p nullglob dotglob; shopt -s nullglob dotglob; shopt -p nullglob dotglob')
+[ "$res" == "shopt -u nullglob
+shopt -u dotglob
+shopt -s nullglob
+shopt -s dotglob" ] || err $LINENO
+
+res=$($com <<< 'shopt -q extglob; echo $?; shopt -q extglob nullglob; echo $?; shopt nosuch; echo $?')
+[ "$res" == "0
+1
+1" ] || err $LINENO
+
+res=$($com <<< 'shopt -s -u extglob; echo $?; shopt -x; echo $?')
+[ "$res" == "1
+2" ] || err $LINENO
+
+res=$($com <<< 'shopt -so nounset; [[ $- == *u* ]] && echo u; shopt -po nounset noglob')
+[ "$res" == "u
+set -o nounset
+set +o noglob" ] || err $LINENO
+
+res=$($com <<< 'cd /etc; shopt -s nullglob; echo /etc/nosuch* "*" x')
+[ "$res" == "* x" ] || err $LINENO
+
+res=$($com <<< 'cd /; shopt -s dotglob; echo .nosuch* /etc/.?*[^.] | grep -F "/etc/.."')
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< '[[ "" == [^a] ]] || echo ok; case x in ?[^a]) ;; *) echo ok ;; esac')
+[ "$res" == "ok
+ok" ] || err $LINENO
+
+res=$($com <<< 'shopt -s nocaseglob; echo /ETC/PASSW?')
+[ "$res" == "/etc/passwd" ] || err $LINENO
+
+res=$($com <<< 'shopt -s nocasematch; case ABC in a*) echo yes;; esac; [[ aBc == ab? ]] && echo yes')
+[ "$res" == "yes
+yes" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; rm -rf sush_gi_$$; mkdir sush_gi_$$; cd sush_gi_$$; touch a.c b.c .d.c b.h; GLOBIGNORE="b*:*.h"; echo *; echo .*; for i in 1 2; do case $i.c in *[2].c) echo $i;; esac; done; cd ..; rm -rf sush_gi_$$')
+[ "$res" == ".d.c a.c
+.d.c
+2" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; rm -rf sush_gs; mkdir -p sush_gs/a/b; touch sush_gs/a/b/f sush_gs/g; cd sush_gs; shopt -s globstar; echo **; echo **/f; cd ..; rm -rf sush_gs')
+[ "$res" == "a a/b a/b/f g
+a/b/f" ] || err $LINENO
+
+res=$($com <<< 'cd /; pushd /tmp; pushd /usr; dirs -v; popd; pwd')
+[ "$res" == "/tmp /
+/usr /tmp /
+ 0  /usr
+ 1  /tmp
+ 2  /
+/tmp /
+/tmp" ] || err $LINENO
+
+res=$($com <<< 'cd /; pushd /tmp; pushd /usr; pushd; pwd; pushd +2; dirs -p')
+[ "$res" == "/tmp /
+/usr /tmp /
+/tmp /usr /
+/tmp
+/ /tmp /usr
+/
+/tmp
+/usr" ] || err $LINENO
+
+res=$($com <<< 'cd /; pushd -n /tmp; popd +1; popd; echo $?; dirs +3')
+[ "$res" == "/ /tmp
+/
+1" ] || err $LINENO
+
+res=$($com <<< 'HOME=/tmp; cd /tmp; pushd /usr; dirs -l; dirs -c; dirs')
+[ "$res" == "/usr ~
+/usr /tmp
+/usr" ] || err $LINENO
+
+res=$($com <<< 'exec echo hi; echo NG')
+[ "$res" == "hi" ] || err $LINENO
+
+res=$($com <<< 'exec nosuchcommand; echo NG')
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< 'shopt -s execfail; exec nosuchcommand; echo $?')
+[ "$res" == "127" ] || err $LINENO
+
+res=$($com <<< 'export A=1; exec -c env')
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< 'exec -a newname bash -c "echo \$0"')
+[ "$res" == "newname" ] || err $LINENO
+
+res=$($com <<< 'exec 3> /tmp/rusty_bash_exec; echo abc >&3; cat /tmp/rusty_bash_exec')
+[ "$res" == "abc" ] || err $LINENO
+
+res=$($com <<< 'exec > /dev/null; echo NG')
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< 'echo a >&2; echo b' 2> /dev/null)
+[ "$res" == "b" ] || err $LINENO
+
+res=$($com <<< 'trap "echo got" USR1; kill -USR1 $$; echo after')
+[ "$res" == "got
+after" ] || err $LINENO
+
+res=$($com <<< 'trap "echo a" usr1; trap "" 15; trap -p')
+[ "$res" == "trap -- 'echo a' SIGUSR1
+trap -- '' SIGTERM" ] || err $LINENO
+
+res=$($com <<< 'trap "echo a" USR1; trap - USR1; trap TERM; trap -p')
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< "trap \"echo it's\" EXIT; trap -p 0")
+[ "$res" == "trap -- 'echo it'\\''s' EXIT" ] || err $LINENO
+
+res=$($com <<< 'trap x NOSUCHSIG; echo $?')
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'trap "false" USR1; true; kill -USR1 $$; echo $?')
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'trap "echo int" INT; kill -INT $$; sleep 0.2; echo x')
+[ "$res" == "int
+x" ] || err $LINENO
+
+res=$($com <<< 'trap "" USR2; trap "echo a" USR1; (trap -p; kill -USR2 $BASHPID; echo ok)')
+[ "$res" == "trap -- '' SIGUSR2
+ok" ] || err $LINENO
+
+res=$($com <<< 'trap -l' | head -n 1)
+[ "$res" == " 1) SIGHUP	 2) SIGINT	 3) SIGQUIT	 4) SIGILL	 5) SIGTRAP" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye \$?" EXIT; echo x; false')
+[ "$res" == "x
+bye 1" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye" EXIT; exit 3' 2> /dev/null)
+[ "$?" == "3" ] || err $LINENO
+[ "$res" == "bye" ] || err $LINENO
+
+res=$($com <<< 'trap "exit 4" EXIT; exit 3' 2> /dev/null)
+[ "$?" == "4" ] || err $LINENO
+
+$com <<< 'exit -1'
+[ "$?" == "255" ] || err $LINENO
+
+$com <<< 'exit 300'
+[ "$?" == "44" ] || err $LINENO
+
+$com <<< 'false; exit'
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'exit abc; echo NG' 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "$com: line 1: exit: abc: numeric argument required" ] || err $LINENO
+
+res=$($com <<< 'trap "echo once \$?" EXIT; f () { exit 1 2; }; f; echo NG' 2> /dev/null)
+[ "$?" == "1" ] || err $LINENO
+[ "$res" == "once 1" ] || err $LINENO
+
+res=$($com <<< 'trap "echo p" EXIT; (echo a); (trap "echo b" EXIT; echo c)')
+[ "$res" == "a
+c
+b
+p" ] || err $LINENO
+
+res=$($com <<< 'trap "echo err \$?" ERR; false; false || true; ! true; if false; then :; fi; { false; }; echo $(false)')
+[ "$res" == "err 1
+err 1" ] || err $LINENO
+
+res=$($com <<< 'trap "echo err" ERR; f () { false; echo in; }; f; (false); set -E; f; (false)')
+[ "$res" == "in
+err
+err
+in
+err
+err" ] || err $LINENO
+
+res=$($com <<< 'set -e; trap "echo err \$?" ERR; { false && true; }; echo a; false; echo NG')
+[ "$res" == "a
+err 1" ] || err $LINENO
+
+res=$($com <<< 'set -o errtrace; echo $-; set -o | grep errtrace')
+[ "$res" == "sE
+errtrace        on" ] || err $LINENO
+
+res=$($com <<< 'trap "echo \"dbg: \$BASH_COMMAND\"" DEBUG; x=1; echo $x > /dev/null; trap - DEBUG; echo end')
+[ "$res" == "dbg: x=1
+dbg: echo \$x > /dev/null
+dbg: trap - DEBUG
+end" ] || err $LINENO
+
+res=$($com <<< 'trap "echo dbg" DEBUG; f () { echo in; }; f; set -T; f')
+[ "$res" == "dbg
+in
+dbg
+dbg
+dbg
+dbg
+in" ] || err $LINENO
+
+res=$($com <<< 'trap "echo dbg \$BASH_COMMAND" DEBUG; f () { echo in; }; set -T; f')
+[ "$res" == "dbg set -T
+dbg f
+dbg f
+dbg echo in
+in" ] || err $LINENO
+
+res=$($com <<< 'trap "echo ret" RETURN; f () { :; }; f; set -T; f')
+[ "$res" == "ret" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extdebug; trap "[[ \$BASH_COMMAND != *skip* ]]" DEBUG; echo skip; echo run')
+[ "$res" == "run" ] || err $LINENO
+
+res=$($com <<< 'trap "echo \$BASH_COMMAND" ERR; false')
+[ "$res" == "false" ] || err $LINENO
+
+### ULIMIT ###
+
+res=$($com <<< 'ulimit -n 100; ulimit -n; ulimit -Hn; ulimit -Sn 50; ulimit -n; ulimit -Hn')
+[ "$res" == "100
+100
+50
+100" ] || err $LINENO
+
+res=$($com <<< 'ulimit -Sc 0; ulimit -c; ulimit -Sn 60; ulimit -nc')
+[ "$res" == "0
+open files                          (-n) 60
+core file size              (blocks, -c) 0" ] || err $LINENO
+
+res=$($com <<< 'ulimit -n abc; echo $?; ulimit -z; echo $?' 2>&1)
+[ "$res" == "$com: line 1: ulimit: abc: invalid number
+1
+$com: line 1: ulimit: -z: invalid option
+$com: line 1: ulimit: usage: ulimit [-SHacfnstuv] [limit]
+2" ] || err $LINENO
+
+### UMASK ###
+
+res=$($com <<< 'umask 022; umask; umask -S; umask -p; umask u=rwx,g=rx,o=; umask; umask g+w,a-x; umask')
+[ "$res" == "0022
+u=rwx,g=rx,o=rx
+umask 0022
+0027
+0117" ] || err $LINENO
+
+res=$($com <<< 'umask -S 077; umask 077; touch /tmp/umask_$$; ls -l /tmp/umask_$$ | cut -c1-10; rm /tmp/umask_$$')
+[ "$res" == "u=rwx,g=,o=
+-rw-------" ] || err $LINENO
+
+res=$($com <<< 'umask 8; echo $?; umask u=q; echo $?; umask -z; echo $?' 2>&1)
+[ "$res" == "$com: line 1: umask: 8: octal number out of range
+1
+$com: line 1: umask: \`q': invalid symbolic mode character
+1
+$com: line 1: umask: -z: invalid option
+$com: line 1: umask: usage: umask [-p] [-S] [mode]
+2" ] || err $LINENO
+
+### GETOPTS ###
+
+res=$($com <<< 'set -- -ac -b val -bx -dfoo x y; while getopts ab:cd: o; do echo $o [$OPTARG] $OPTIND; done; shift $((OPTIND-1)); echo "$@"')
+[ "$res" == "a [] 1
+c [] 2
+b [val] 4
+b [x] 5
+d [foo] 6
+x y" ] || err $LINENO
+
+res=$($com <<< 'getopts :ab: o -x; echo $o $OPTARG; OPTIND=1; getopts :ab: o -b; echo $o $OPTARG')
+[ "$res" == "? x
+: b" ] || err $LINENO
+
+res=$($com <<< 'getopts a o -x; echo $? $o [$OPTARG]' 2>&1)
+[ "$res" == "$com: illegal option -- x
+0 ? []" ] || err $LINENO
+
+res=$($com <<< 'getopts ab o -ab; echo $o $OPTIND; getopts ab o -x; echo $? $o $OPTIND' 2>&1)
+[ "$res" == "a 1
+$com: illegal option -- x
+0 ? 2" ] || err $LINENO
+
+res=$($com <<< 'OPTERR=0; getopts a: o -a; echo $o [$OPTARG]' 2>&1)
+[ "$res" == "? []" ] || err $LINENO
+
+res=$($com <<< 'getopts a o -- -a; echo $? $o $OPTIND; OPTIND=1; getopts a o x; echo $? $OPTIND')
+[ "$res" == "1 ? 2
+1 1" ] || err $LINENO
+
+res=$($com <<< 'getopts a 1o; echo $?; getopts a')
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'set -eu -o pipefail; echo $-; set +o | grep -E "nounset|pipefail"')
+[ "$res" == "seu
+set -o nounset
+set -o pipefail" ] || err $LINENO
+
+res=$($com <<< 'set -o noglob; echo /*; set +f; echo /bi[n]')
+[ "$res" == "/*
+/bin" ] || err $LINENO
+
+res=$($com <<< 'set -o nosuchopt; echo $?; set -q; echo $?')
+[ "$res" == "2
+2" ] || err $LINENO
+
+res=$($com <<< 'set -x; set - a b; echo $- $2')
+[ "$res" == "s b" ] || err $LINENO
+
+res=$($com <<< 'set -o | grep errexit')
+[ "$res" == "errexit         off" ] || err $LINENO
+
+res=$($com --posix <<< 'shopt extglob; set(){ echo func; }; set -- a; echo $1; A=1 :; echo $A; B=1 true; echo "[$B]"')
+[ "$res" == "extglob         off
+a
+1
+[]" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob; set -o posix; shopt extglob; set -o posix; set +o posix; shopt extglob')
+[ "$res" == "extglob         off
+extglob         on" ] || err $LINENO
+
+res=$($com <<< 'set -o posix
+[[ a ]]; echo $?
+function f () { :; }' 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$(echo "$res" | sed -n 2p)" == "127" ] || err $LINENO
+
+res=$($com -r <<< 'cd /; echo $?; PATH=/bin; /bin/echo a; echo b > /tmp/rsush_out; exec true; hash -p /bin/sh foo; foo -c "echo escaped"; echo $-' 2>&1)
+[ "$res" == "$com: line 1: cd: restricted
+1
+$com: line 1: PATH: readonly variable
+$com: line 1: /bin/echo: restricted: cannot specify \`/' in command names
+$com: line 1: /tmp/rsush_out: restricted: cannot redirect output
+$com: line 1: exec: restricted
+$com: line 1: hash: /bin/sh: restricted
+$com: line 1: foo: command not found
+sr" ] || err $LINENO
+
+res=$($com <<< 'set -x; A="x y" echo "a b" "" "it'"'"'s" "*" ~x' 2>&1 >/dev/null)
+[ "$res" == "+ A='x y'
++ echo 'a b' '' 'it'\''s' '*' '~x'" ] || err $LINENO
+
+res=$($com <<< 'set -x; x=$(echo $(echo a)); eval "echo b"' 2>&1 >/dev/null)
+[ "$res" == "+++ echo a
+++ echo a
++ x=a
++ eval 'echo b'
+++ echo b" ] || err $LINENO
+
+res=$($com << 'EOF' 2>&1 >/dev/null
+set -v
+if true; then
+  echo a; set -x
+fi
+eval "echo b"
+set +vx
+echo c
+EOF
+)
+[ "$res" == "if true; then
+  echo a; set -x
+fi
+eval \"echo b\"
++ eval 'echo b'
+echo b
+++ echo b
+set +vx
++ set +vx" ] || err $LINENO
+
+res=$($com -v -c 'echo a' 2>&1 >/dev/null)
+[ "$res" == "echo a" ] || err $LINENO
+
+res=$($com -es a "b c" <<< 'echo $# "$2" $-')
+[ "$res" == "2 b c es" ] || err $LINENO
+
+res=$($com -s -- -x <<< 'echo $# $1')
+[ "$res" == "1 -x" ] || err $LINENO
+
+res=$($com -eu -o pipefail -c 'echo $- $0 $1; set -o | grep pipefail' zero one)
+[ "$res" == "euc zero one
+pipefail        on" ] || err $LINENO
+
+res=$(HISTFILE=/dev/null $com -is <<< 'echo $-')
+[ "$res" == "iHs" ] || err $LINENO
+
+$com -q -c 'echo NG' 2> /dev/null
+[ "$?" == "2" ] || err $LINENO
+
+$com --nosuchopt 2> /dev/null
+[ "$?" == "2" ] || err $LINENO
+
+res=$($com <<< 'PS4="[\$LINENO] "; set -x; f () { echo in; }; f' 2>&1 >/dev/null)
+[ "$res" == "[1] f
+[1] echo in" ] || err $LINENO
+
+res=$($com <<< 'set -x; for i in a "b c"; do :; done' 2>&1 >/dev/null)
+[ "$res" == "+ for i in a 'b c'
++ :
++ for i in a 'b c'
++ :" ] || err $LINENO
+
+res=$($com <<< 'set -u; echo $x; echo NG
+echo $?; echo ${x:-def} $# "$@"
+echo "a${y}b"
+a=(1 2); echo ${a[1]}; echo ${a[5]}
+set -- q; echo $1 ${1}
+echo $2' 2>&1)
+[ "$res" == "$com: line 1: x: unbound variable
+1
+def 0
+$com: line 3: y: unbound variable
+2
+$com: line 4: a[5]: unbound variable
+q q
+$com: line 6: \$2: unbound variable" ] || err $LINENO
+
+res=$($com <<< 'set -u; for i in $x; do echo NG; done; echo NG
+echo $?; a=$y; echo NG
+echo $?' 2>&1)
+[ "$res" == "$com: line 1: x: unbound variable
+1
+$com: line 2: y: unbound variable
+1" ] || err $LINENO
+
+res=$($com <<< 'set -u; [[ $x == a ]]; echo NG
+echo $?; echo $(( y + 1 )); echo NG
+echo $?; (( $z )); echo NG
+echo $?' 2>&1)
+[ "$res" == "$com: line 1: x: unbound variable
+1
+$com: line 2: y: unbound variable
+1
+$com: line 3: z: unbound variable
+1" ] || err $LINENO
+
+res=$($com <<< 'echo ${x:?empty}; echo NG
+echo $?; shopt -s failglob; echo /NONEXIST*; echo NG
+echo $?' 2>&1)
+[ "$res" == "$com: line 1: x: empty
+1
+$com: line 2: no match: /NONEXIST*
+1" ] || err $LINENO
+
+res=$($com <<< 'set -o pipefail; (exit 3) | (exit 2) | true; echo $? ${PIPESTATUS[@]}')
+[ "$res" == "2 3 2 0" ] || err $LINENO
+
+res=$($com <<< 'set -o pipefail; x=1 | false; echo $? ${PIPESTATUS[@]}; true | f () { :; }; echo $?')
+[ "$res" == "1 0 1
+0" ] || err $LINENO
+
+res=$($com <<< 'set -o pipefail; ! false | true; echo $?; false; echo ${PIPESTATUS[@]}')
+[ "$res" == "0
+1" ] || err $LINENO
+
+res=$($com <<< 'trap "false" EXIT; (exit 300); echo $?; ! (exit 3); echo $?; eval "(exit 4)"; echo $?; exit 5')
+[ "$?" == "5" ] || err $LINENO
+[ "$res" == "44
+0
+4" ] || err $LINENO
+
 echo $0 >> ./ok
 
@@ -139,6 +139,27 @@ res=$($com <<< 'function f () { local A=BBB ; echo $A ; } ; echo $A')
 res=$($com <<< 'function f () { local A=( a b c ) ; echo ${A[1]}; } ; f')
 [ "$res" = b ] || err $LINENO
 
+res=$($com <<< 'f(){ local x=1; x=2; echo f:$x; g; echo f:$x; }; g(){ echo g:$x; x=3; y=new; }; x=0; f; echo $x $y')
+[ "$res" = "f:2
+g:2
+f:3
+0 new" ] || err $LINENO
+
+res=$($com <<< 'f(){ local x; echo "[$x]"; x=1; unset x; echo "[$x]"; }; x=g; f; echo $x')
+[ "$res" = "[]
+[]
+g" ] || err $LINENO
+
+res=$($com <<< 'f(){ A=changed; echo $A; }; A=orig; A=tmp f; echo $A; f(){ eval "local B=1"; echo $B; }; f; echo "[$B]"')
+[ "$res" = "changed
+orig
+1
+[]" ] || err $LINENO
+
+res=$($com <<< '{ local x; }; echo $?; x=1 eval "local y"; echo $?' 2>/dev/null)
+[ "$res" = "1
+1" ] || err $LINENO
+
 res=$($com <<< 'function f () { return; echo NG; } ; f')
 [ "$res" = "" ] || err $LINENO
 
@@ -149,6 +170,40 @@ res=$($com <<< 'function f () { echo ok && return 3; } ; f')
 res=$($com <<< 'f () { g () { return; echo NG; } ; g ; echo OK; } ; f')
 [ "$res" = "OK" ] || err $LINENO
 
+res=$($com <<< 'f ()
+{ echo fn; }
+f')
+[ "$res" = "fn" ] || err $LINENO
+
+res=$($com <<< 'function g
+{
+  echo g
+}
+g')
+[ "$res" = "g" ] || err $LINENO
+
+res=$($com <<< 'function h { echo h; } ; h')
+[ "$res" = "h" ] || err $LINENO
+
+res=$($com <<< 'f () { false; return; } ; f; echo $?; f () { return -1; } ; f; echo $?; f () { return 300; } ; f; echo $?')
+[ "$res" = "1
+255
+44" ] || err $LINENO
+
+res=$($com <<< 'f () { return abc; echo NG; } ; f; echo $?; return; echo $?')
+[ "$res" = "2
+2" ] || err $LINENO
+
+res=$($com <<< 'trap "echo R" RETURN; f () { :; } ; f; g () { trap "echo G" RETURN; return 3; } ; g; echo $?; f')
+[ "$res" = "G
+3" ] || err $LINENO
+
+printf 'echo in; return 4\n' > /tmp/rusty_bash_return
+res=$($com <<< 'trap "echo R" RETURN; . /tmp/rusty_bash_return; echo $?')
+[ "$res" = "in
+R
+4" ] || err $LINENO
+
 ### WHILE TEST ###
 
 res=$($com <<< 'touch /tmp/rusty_bash ; while [ -f /tmp/rusty_bash ] ; do echo wait ; rm /tmp/rusty_bash ; done')
@@ -165,6 +220,26 @@ res=$($com <<< 'touch /tmp/rusty_bash_x ; while [ -f /tmp/rusty_bash_x ] ; do ec
 [ "$res" == "wait
 wait" ] || err $LINENO
 
+res=$($com <<< 'seq 3 | while read x ; do echo x$x ; done | tail -1')
+[ "$res" == "x3" ] || err $LINENO
+
+res=$($com <<< 'echo a | while read x ; do false ; done ; echo $?')
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'until true ; do echo NG ; done | cat ; echo $?')
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'i=0 ; until [ $i -ge 2 ] ; do echo $i ; i=$((i+1)) ; done | rev')
+[ "$res" == "0
+1" ] || err $LINENO
+
+res=$($com <<< '{ echo out ; echo err >&2 ; } 2>&1 | sort')
+[ "$res" == "err
+out" ] || err $LINENO
+
+res=$($com <<< 'set -o pipefail ; true | if false ; then : ; else false ; fi | cat ; echo $?')
+[ "$res" == "1" ] || err $LINENO
+
 ### FOR TEST ###
 
 res=$($com <<< 'set a b c ; for x ; do echo $x ; done')
@@ -201,6 +276,29 @@ res=$($com <<< 'for ((i=0 ; i<2 ; i++ )) ; do echo a ; done')
 [ "$res" == "a
 a" ] || err $LINENO
 
+res=$($com <<< 'false ; for i in ; do : ; done ; echo $?')
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'echo a | for i in 1 2 ; do read x ; echo $i$x ; done | cat')
+[ "$res" == "1a
+2" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 # list
+# comment
+
+do # body
+  echo $i
+done')
+[ "$res" == "1
+2" ] || err $LINENO
+
+res=$($com <<< 'true && # and
+
+# comment
+echo a | # pipe
+rev')
+[ "$res" == "a" ] || err $LINENO
+
 ### IF TEST ###
 
 res=$($com <<< 'if true ; then ; fi')
@@ -433,6 +531,33 @@ res=$($com <<< 'case yes in y[^abcde]s) echo NG ;; *) echo OK ;; esac')
 res=$($com <<< 'case yes in y[\^abcde]s) echo OK ;; *) echo NG ;; esac')
 [ "$res" = "OK" ] || err $LINENO
 
+res=$($com <<< 'case c in [a-e]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case x in [!a-c]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case - in [a-]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case ] in []a]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case b5 in [[:alpha:]][[:digit:]]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case A in [[:lower:]]) echo NG ;; [[:upper:]]) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case " " in [[:space:]]) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case a in [[.a.]][[=a=]]) echo NG ;; [[.a.]]) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case a in [[:nosuch:]]) echo NG ;; *) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
 res=$($com <<< 'case $- in *i*) echo NG ;; *) echo OK ;; esac')
 [ "$res" = "OK" ] || err $LINENO
 
@@ -493,6 +618,33 @@ res=$($com <<< 'case 山田山田aa in +(山|山本|山田)aa) echo OK ;; *) ech
 res=$($com <<< 'case 山aa in 山*(本|田)aa) echo OK ;; *) echo NG ;; esac')
 [ "$res" = "OK" ] || err $LINENO
 
+res=$($com <<< 'case ababab in *(+(a)b)) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case ab in !(a)b) echo NG ;; *) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case abb in a!(b)) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case a in !(!(a))) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'x=$(printf "%0500d" 0); case $x in *0*0*0*0*0*1) echo NG ;; *(0|00)) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< "case ab in @('a*'|c)) echo NG ;; 'a*') echo NG ;; @(a\\*|ab)) echo OK ;; esac")
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'v=b; case "a b" in @("a $v"|c)) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< '[[ abc == !(a*) ]] || echo OK')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< "echo '!(x)' \"@(y)\"")
+[ "$res" = "!(x) @(y)" ] || err $LINENO
+
 res=$($com <<< 'case 山aa in 山+(本||田)aa) echo OK ;; *) echo NG ;; esac')
 [ "$res" = "OK" ] || err $LINENO
 
@@ -520,6 +672,41 @@ res=$($com <<< 'shopt -u extglob; case baa in @(a|b)aa) echo OK ;; *) echo NG ;;
 res=$($com <<< 'shopt -u extglob; case 山小小小田 in !(山)田) echo マッチ ;; *) echo マッチせず ;; esac')
 [ "$res" = "マッチせず" ] || err $LINENO
 
+res=$($com <<< 'pat="a*"; case abc in $pat) echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'pat="a*"; case abc in "$pat") echo NG ;; *) echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'x="a  *"; case $x in "a  *") echo OK ;; *) echo NG ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case a in a|a) echo x ;;& *) echo y ;; esac')
+[ "$res" = "x
+y" ] || err $LINENO
+
+res=$($com <<< 'case a in a) echo x ;& $((n=1))) echo y ;; esac; echo [$n]')
+[ "$res" = "x
+y
+[]" ] || err $LINENO
+
+res=$($com <<< 'false; case a in b) ;; esac; echo $?')
+[ "$res" = "0" ] || err $LINENO
+
+res=$($com <<< 'case $u in "") echo OK ;; esac')
+[ "$res" = "OK" ] || err $LINENO
+
+res=$($com <<< 'case a # word
+in # items
+
+# first
+a) # pattern
+  echo OK ;; # end
+# second
+b) echo NG ;;
+esac')
+[ "$res" = "OK" ] || err $LINENO
+
 ### (( )) TEST ###
 
 res=$($com <<< '(( 0 ))')
@@ -557,6 +744,13 @@ res=$($com -c '[[ -a ]]')
 res=$($com -c '[[ -a /etc/passwd x ]]')
 [ "$?" = "2" ] || err $LINENO
 
+res=$($com <<< '[[ a &&
+   b ]] && echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com -c '[[ a &&')
+[ "$?" = "2" ] || err $LINENO
+
 res=$($com -c '[[ ! -a /etc/passwd ]]')
 [ "$?" = "1" ] || err $LINENO
 
@@ -882,4 +1076,53 @@ res=$($com -c '[[ -a /etc/passwd && -a /etc/passwdaaa ]]')
 res=$($com -c '[[ -a /etc/passwdaaaa || -a /etc/passwd ]]')
 [ "$?" = "0" ] || err $LINENO
 
+res=$($com <<< 'x=abc; [[ $x == a* ]] && echo 1; [[ $x == "a*" ]] || echo 2; [[ $x != ?b? ]] || echo 3')
+[ "$res" == "1
+2
+3" ] || err $LINENO
+
+res=$($com <<< 'p="a*"; [[ abc == $p ]] && echo 1; [[ abc == "$p" ]] || echo 2; [[ y == @(x|y) ]] && echo 3')
+[ "$res" == "1
+2
+3" ] || err $LINENO
+
+res=$($com <<< '[[ abc123 =~ ([a-z]+)([0-9]+) ]]; echo $? ${BASH_REMATCH[0]} ${BASH_REMATCH[1]} ${BASH_REMATCH[2]}')
+[ "$res" == "0 abc123 abc 123" ] || err $LINENO
+
+res=$($com <<< '[[ a.c =~ ^a"."c$ ]] && echo 1; [[ abc =~ ^a"."c$ ]] || echo 2; [[ abc =~ ^a\.c$ ]] || echo 3')
+[ "$res" == "1
+2
+3" ] || err $LINENO
+
+res=$($com <<< 'r="^(x|a)b"; [[ abc =~ $r && 1 -eq 1 ]] && echo ${BASH_REMATCH[1]}; [[ ab =~ a(x)?b ]]; echo "[${BASH_REMATCH[1]}]"')
+[ "$res" == "a
+[]" ] || err $LINENO
+
+res=$($com <<< '[[ abc =~ (b) ]]; [[ abc =~ x ]]; echo $? "${BASH_REMATCH[@]}"; shopt -s nocasematch; [[ ABC =~ ^abc$ ]] && echo ok')
+[ "$res" == "1
+ok" ] || err $LINENO
+
+res=$($com <<< 'a=(); b=(x "y z"); set -- "${a[@]}"; echo $#; set -- "${b[@]}" "<${b[@]}>"; echo $#; printf "[%s]" "$@" "${b[@]}|${b[@]}"')
+[ "$res" == "0
+4
+[x][y z][<x][y z>][x][y z|x][y z]" ] || err $LINENO
+
+### COPROC TEST ###
+
+res=$($com <<< 'coproc head -n 1; echo $?; [ "$!" == "$COPROC_PID" ] && echo ok
+echo hello >&${COPROC[1]}; read line <&${COPROC[0]}; echo "$line"')
+[ "$res" == "0
+ok
+hello" ] || err $LINENO
+
+res=$($com <<< 'coproc UP { while read l; do echo "up $l"; done; }
+echo abc >&${UP[1]}; read x <&${UP[0]}; echo $x
+eval "exec ${UP[1]}>&-"; wait $UP_PID; echo $?')
+[ "$res" == "up abc
+0" ] || err $LINENO
+
+res=$($com <<< 'coproc NOTNAME echo hi; read y <&${COPROC[0]}; echo "[$y]"' 2>&1)
+[ "$res" == "$com: line 1: NOTNAME: command not found
+[]" ] || err $LINENO
+
 echo $0 >> ./ok
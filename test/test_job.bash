@@ -23,4 +23,35 @@ echo "$res" | grep -F '[3]+ ' || err $LINENO
 res=$($com <<< 'sleep 5 | rev | cat & sleep 1 ; killall -SIGSTOP cat ; jobs')
 echo "$res" | grep Stopped || err $LINENO
 
+res=$($com <<< '(sleep 0.2; exit 3) & wait $!; echo $?')
+[ "$res" == "3" ] || err $LINENO
+
+res=$($com <<< '(exit 4) &
+sleep 0.2
+wait $!; echo $?' | tail -n 1)
+[ "$res" == "4" ] || err $LINENO
+
+res=$($com <<< '(sleep 0.3; exit 5) & (sleep 0.1; exit 6) & wait -n; echo $?; wait -n; echo $?; wait -n; echo $?')
+[ "$res" == "6
+5
+127" ] || err $LINENO
+
+res=$($com <<< '(exit 2) & (sleep 0.2; exit 7) & wait %2; echo $?; wait %1; echo $?; wait %1; echo $?')
+[ "$res" == "7
+2
+127" ] || err $LINENO
+
+res=$($com <<< 'wait 1; echo $?; wait abc; echo $?')
+[ "$res" == "127
+2" ] || err $LINENO
+
+res=$($com <<< 'sleep 0.3 & sleep 0.2 & wait; echo $?; jobs')
+[ "$res" == "0" ] || err $LINENO
+
+$com <<< '(sleep 0.2; kill -INT $$) & while true; do :; done; echo NG' | grep NG
+[ "$?" == "1" ] || err $LINENO
+
+$com <<< '(sleep 0.2; kill -INT $$) & sleep 5 & wait $!; echo NG' > /dev/null
+[ "$?" == "130" ] || err $LINENO
+
 echo $0 >> ./ok
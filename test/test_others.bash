@@ -63,7 +63,7 @@ res=$($com <<< 'echo ${A:?error}' )
 [ "$res" = "" ] || err $LINENO
 
 res=$($com <<< '(echo ${A:?error}) |& cat' )
-[ "$res" = "sush: A: error" ] || err $LINENO
+[ "$res" = "$com: line 1: A: error" ] || err $LINENO
 
 res=$($com <<< 'A= ; echo ${A:+set}' )
 [ "$res" = "" ] || err $LINENO
@@ -97,6 +97,11 @@ def"}' )
 [ "$res" = "あ
 def" ] || err $LINENO
 
+res=$($com <<< 'echo ${あ}; echo NG
+echo ${x:-🍣}' 2>&1)
+[ "$res" = "$com: line 1: \${あ}: bad substitution
+🍣" ] || err $LINENO
+
 ### IRREGULAR INPUT TEST ###
 
 res=$($com <<< 'eeeeeecho hoge')
@@ -133,6 +138,24 @@ res=$($com <<< 'seq 10 |  #コメントだよ
 res=$($com <<< 'seq 10 |   | head -n 1')
 [ "$?" = "2" ] || err $LINENO
 
+res=$($com <<< 'time sleep 0.1 | cat' 2>&1)
+echo "$res" | grep -qE "^real	0m0\.1[0-9]{2}s$" || err $LINENO
+echo "$res" | grep -qE "^sys	0m0\.[0-9]{3}s$" || err $LINENO
+
+res=$($com <<< 'time -p false; echo $?' 2>&1)
+echo "$res" | grep -qE "^user 0\.[0-9]{2}$" || err $LINENO
+[ "$(echo "$res" | tail -n 1)" = "1" ] || err $LINENO
+
+res=$($com <<< 'TIMEFORMAT="%1R %0lR %%"; time sleep 0.2; TIMEFORMAT=; time true' 2>&1)
+[ "$res" = "0.2 0m0s %" ] || err $LINENO
+
+res=$($com <<< 'TIMEFORMAT="%x"; time true' 2>&1)
+[ "$res" = "$com: line 1: TIMEFORMAT: \`x': invalid format character" ] || err $LINENO
+
+res=$($com <<< 'time=1; echo $time time; timeout 1 true && times | wc -l')
+[ "$res" = "1 time
+2" ] || err $LINENO
+
 ### COMMENT ###
 
 res=$($com <<< 'echo a #aaaaa')
@@ -286,11 +309,102 @@ res=$($com <<< 'pwd 200>&100')
 res=$($com <<< 'ls 200>&100')
 [ "$?" == "1" ] || err $LINENO
 
+# builtins
+
+res=$($com -c 'echo a >&5; echo $?' 2>&1)
+[ "$res" == "$com: line 1: 5: Bad file descriptor
+1" ] || err $LINENO
+
+res=$($com -c 'echo hi > /nonexistent/f; echo $?' 2>&1)
+[ "$res" == "$com: line 1: /nonexistent/f: No such file or directory
+1" ] || err $LINENO
+
+res=$($com -c 'cd /nonexistent 2>/dev/null; echo $?' 2>&1)
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com -c 'echo hi > /tmp/rusty_bash_$$; printf "%s\n" x >> /tmp/rusty_bash_$$; echo ok; cat /tmp/rusty_bash_$$; rm /tmp/rusty_bash_$$')
+[ "$res" == "ok
+hi
+x" ] || err $LINENO
+
+res=$($com -c 'echo a 3>/tmp/rusty_bash_$$; echo b >&3; rm /tmp/rusty_bash_$$' 2>&1)
+[ "$res" == "a
+$com: line 1: 3: Bad file descriptor" ] || err $LINENO
+
+res=$($com -c 'f () { return 1; }; f 4>/tmp/rusty_bash_$$ > /nonexistent/f; echo b >&4; rm /tmp/rusty_bash_$$; echo c' 2>&1)
+[ "$res" == "$com: line 1: /nonexistent/f: No such file or directory
+$com: line 1: 4: Bad file descriptor
+c" ] || err $LINENO
+
+res=$($com -c 'echo a > /dev/full; echo $?' 2>&1)
+[ "$res" == "$com: line 1: echo: write error: No space left on device
+1" ] || err $LINENO
+
+res=$($com -c 'pwd > /dev/full; echo $?' 2>&1)
+[ "$res" == "$com: line 1: pwd: write error: No space left on device
+1" ] || err $LINENO
+
+res=$($com -c 'alias a=b; alias > /dev/full; echo $?' 2>&1)
+[ "$res" == "$com: line 1: alias: write error: No space left on device
+1" ] || err $LINENO
+
+res=$($com -c 'sleep 0.1 & sleep 0.3; echo end' 2> /dev/null)
+[ "$res" == "end" ] || err $LINENO
+
+$com <<< 'sleep 0.1; nosuchcmd' 2>&1 | true
+[ "${PIPESTATUS[0]}" != "101" ] || err $LINENO
+
+res=$($com -c 'cd /nonexistent_dir 2> /dev/null; echo $?')
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com -c 'echo $(( 1 / 0 ))' 2> /dev/null)
+[ "$res" == "" ] || err $LINENO
+
+# noclobber
+
+nc=/tmp/rusty_bash_noclobber_$$
+res=$(f=$nc $com -c 'set -C; echo a > $f; echo b > $f; echo $?; cat $f' 2>&1)
+[ "$res" == "$com: line 1: $nc: cannot overwrite existing file
+1
+a" ] || err $LINENO
+
+res=$(f=$nc $com -c 'set -o noclobber; echo b >| $f; cat $f; echo c &> $f; echo d > /dev/null; echo $?' 2>&1)
+[ "$res" == "b
+$com: line 1: $nc: cannot overwrite existing file
+0" ] || err $LINENO
+
+res=$(f=$nc $com -c 'set +C; echo e > $f; cat $f; rm $f' 2>&1)
+[ "$res" == "e" ] || err $LINENO
+
+# <>, <&, &-
+
+res=$($com -c 'echo a > /tmp/rusty_bash_rw; cat <> /tmp/rusty_bash_rw; exec 4< /tmp/rusty_bash_rw; cat <&4; exec 4<&-; cat <&4; rm /tmp/rusty_bash_rw' 2>&1)
+[ "$res" == "a
+a
+$com: line 1: 4: Bad file descriptor" ] || err $LINENO
+
+# /dev/tcp, /dev/udp
+
+res=$($com -c 'exec 3<>/dev/tcp/127.0.0.1; echo $?' 2>&1)
+[ "$res" == "$com: line 1: /dev/tcp/127.0.0.1: No such file or directory
+1" ] || err $LINENO
+
+res=$($com -c 'exec 3<>/dev/tcp/127.0.0.1/abc; echo $?' 2>&1)
+[ "$res" == "$com: line 1: /dev/tcp/127.0.0.1/abc: Invalid argument
+1" ] || err $LINENO
+
+res=$($com -c 'exec 3<>/dev/tcp/127.0.0.1/1; echo $?' 2>&1)
+[ "$res" == "$com: line 1: /dev/tcp/127.0.0.1/1: Connection refused
+1" ] || err $LINENO
+
+res=$($com -c 'echo a > /dev/udp/127.0.0.1/9; echo $?' 2>&1)
+[ "$res" == "0" ] || err $LINENO
+
 # with expansion
 
 res=$($com <<< 'echo a > {a,b}' 2>&1)
 [ "$?" == "1" ] || err $LINENO
-[ "$res" == "sush: {a,b}: ambiguous redirect" ] || err $LINENO
+[ "$res" == "$com: line 1: {a,b}: ambiguous redirect" ] || err $LINENO
 
 ### JOB PARSE TEST ###
 
@@ -406,6 +520,24 @@ res=$($com <<< 'set あ い; echo a"$@"c')
 res=$($com <<< 'echo a"$@"c')
 [ "$res" == "ac" ] || err $LINENO
 
+res=$($com <<< 'set a b; for x in "x$@y"; do echo "<$x>"; done')
+[ "$res" == "<xa>
+<by>" ] || err $LINENO
+
+res=$($com <<< 'f () { echo $#; }; set --; f "$@"; f "${@}"; f "$*"')
+[ "$res" == "0
+0
+1" ] || err $LINENO
+
+res=$($com <<< 'IFS=,; set a b c; echo "$*" "${*}" $*')
+[ "$res" == "a,b,c a,b,c a b c" ] || err $LINENO
+
+res=$($com <<< 'IFS=; set a b c; echo "$*"')
+[ "$res" == "abc" ] || err $LINENO
+
+res=$($com <<< 'set a b; IFS=-; x="$*"; echo "$x"')
+[ "$res" == "a-b" ] || err $LINENO
+
 # single quoted
 
 res=$($com <<< "echo '' a")
@@ -1092,6 +1224,19 @@ res=$($com <<< 'echo $(( "1 +" 1 ))')
 res=$($com <<< 'echo $(( 1 "+" 1 ))')
 [ "$res" == "2" ] || err $LINENO
 
+# legacy arithmetic expansion
+
+res=$($com <<< 'a=3; echo $[1+2] $[a*2]x $[ (1 + 2) * 3 ]')
+[ "$res" == "3 6x 9" ] || err $LINENO
+
+res=$($com <<< 'echo $[1 +
+2]')
+[ "$res" == "3" ] || err $LINENO
+
+res=$($com <<< 'shopt -s legacy_arith_warn; echo $[1]' 2>&1)
+[ "$res" == "$com: line 1: \$[1]: deprecated arithmetic expansion, use \$((...))
+1" ] || err $LINENO
+
 # escaping
 
 res=$($com <<< "echo a\ \ \ a")
@@ -1134,10 +1279,52 @@ res=$($com <<< 'echo $BASH{PID,_SUBSHELL} | sed -E "s@[0-9]+@num@"')
 res=$($com <<< 'echo ${BASHPID} ${BASH_SUBSHELL} | sed -E "s@[0-9]+@num@"')
 [ "$res" == "num 0" ] || err $LINENO
 
+res=$($com <<< 'echo $$ | grep -E "^[0-9]+$"')
+[ "$res" != "" ] || err $LINENO
+
+res=$($com <<< '(echo $$); echo $$' | uniq | wc -l)
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'true | sleep 0 & A=$! ; wait ; [ -n "$A" ] && [ "$A" != "$$" ] && echo OK' 2> /dev/null | head -n 1)
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com -c 'echo $0 $1' a b)
+[ "$res" == "a b" ] || err $LINENO
+
+res=$($com <<< 'echo a b ; echo $_')
+[ "$res" == "a b
+b" ] || err $LINENO
+
+res=$($com <<< 'echo $- ; set -x ; echo $-' 2> /dev/null)
+[ "$res" == "s
+sx" ] || err $LINENO
+
+res=$($com -c 'echo $-')
+[ "$res" == "c" ] || err $LINENO
+
 res=$($com <<< 'echo ${ ')
 [ "$?" == "2" ] || err $LINENO
 [ "$res" == "" ] || err $LINENO
 
+res=$($com <<< 'echo a; if true; then echo x; done; echo b' 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "$com: line 1: syntax error near unexpected token \`done'" ] || err $LINENO
+
+res=$($com <<< 'echo a
+echo b |; echo c
+echo d' 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "a
+$com: line 2: syntax error near unexpected token \`;'" ] || err $LINENO
+
+res=$($com -c 'if true; then' 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "$com: line 2: syntax error: unexpected end of file" ] || err $LINENO
+
+res=$($com <<< 'eval "echo a; )"; echo $?; eval "if"; echo $?' 2> /dev/null)
+[ "$res" == "2
+2" ] || err $LINENO
+
 res=$($com <<< 'echo ${ A}')
 [ "$?" == "1" ] || err $LINENO
 [ "$res" == "" ] || err $LINENO
@@ -1314,4 +1501,173 @@ res=$($com <<< 'set -o pipefail; ls aaaa | false | true')
 res=$($com <<< 'set -o pipefail; set -e; false | true ; echo NG')
 [ "$res" == "" ] || err $LINENO
 
+### CALL STACK ###
+
+tmp=/tmp/rusty_bash_stack_$$
+cat << 'EOF' > $tmp
+f () { echo "${FUNCNAME[@]}|${BASH_SOURCE[@]}|${BASH_LINENO[@]}|$FUNCNAME"; }
+g () { f; }
+g
+echo "${FUNCNAME[@]}|${BASH_SOURCE[@]}|${BASH_LINENO[@]}"
+. $0.sourced
+h
+EOF
+cat << 'EOF' > $tmp.sourced
+echo "${FUNCNAME[@]}|${BASH_SOURCE[@]}|${BASH_LINENO[@]}"
+h () {
+  f
+}
+EOF
+
+res=$($com $tmp)
+[ "$res" == "f g main|$tmp $tmp $tmp|2 3 0|f
+|$tmp|0
+|$tmp.sourced $tmp|5 0
+f h main|$tmp $tmp.sourced $tmp|3 6 0|f" ] || err $LINENO
+
+res=$($com -c 'f () { FUNCNAME=x; echo $FUNCNAME; }; f; FUNCNAME=y; echo "[$FUNCNAME]"')
+[ "$res" == "f
+[]" ] || err $LINENO
+
+rm -f $tmp $tmp.sourced
+
+### DYNAMIC VARIABLES ###
+
+res=$($com -c 'RANDOM=5; a=$RANDOM; RANDOM=5; [ $a == $RANDOM ] && echo ok; echo $(( RANDOM >= 0 )) $(( RANDOM < 32768 ))')
+[ "$res" == "ok
+1 1" ] || err $LINENO
+
+res=$($com -c 'SECONDS=100; echo $SECONDS; unset SECONDS; echo "[$SECONDS]"')
+[ "$res" == "100
+[]" ] || err $LINENO
+
+res=$($com -c '[ $EPOCHSECONDS -gt 1700000000 ] && echo ok
+echo $EPOCHREALTIME | grep -qE "^[0-9]+\.[0-9]{6}$" && echo ok')
+[ "$res" == "ok
+ok" ] || err $LINENO
+
+res=$($com -c 'echo $LINENO
+f () {
+  echo $LINENO
+}
+f')
+[ "$res" == "1
+3" ] || err $LINENO
+
+res=$($com -c 'a=$(echo $RANDOM $RANDOM); b="$RANDOM $RANDOM"; [ "$a" != "$b" ] && echo ok')
+[ "$res" == "ok" ] || err $LINENO
+
+### -c OPTION ###
+
+res=$(echo input | $com -c 'read x; echo $0 $x $1' name a)
+[ "$res" == "name input a" ] || err $LINENO
+
+res=$($com -c -x 'echo a' 2>&1)
+[ "$res" == "+ echo a
+a" ] || err $LINENO
+
+res=$($com -ec 'false; echo NG')
+[ "$?" == "1" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+res=$($com -o pipefail -c 'false | true')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com -c 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "sush: -c: option requires an argument" ] || err $LINENO
+
+### STARTUP FILES ###
+
+tmp=/tmp/rusty_bash_home_$$
+mkdir -p $tmp
+echo 'echo profile; f () { echo func; }' > $tmp/.profile
+
+res=$(HOME=$tmp $com -l -c 'f; shopt login_shell')
+[ "$res" == "profile
+func
+login_shell     on" ] || err $LINENO
+
+res=$(HOME=$tmp $com --login --noprofile -c 'echo a')
+[ "$res" == "a" ] || err $LINENO
+
+res=$(HOME=$tmp $com -c 'echo a; shopt login_shell')
+[ "$res" == "a
+login_shell     off" ] || err $LINENO
+
+res=$($com --nosuch 2>&1)
+[ "$?" == "2" ] || err $LINENO
+[ "$res" == "sush: --nosuch: invalid option" ] || err $LINENO
+
+rm -rf $tmp
+
+### SCRIPT FILE ###
+
+tmp=/tmp/rusty_bash_script_$$
+cat << 'EOF' > $tmp
+echo $0 $# $1 $2
+read x
+echo "read $x"
+f () {
+  nocmd_in_function
+}
+f
+EOF
+
+res=$(echo input | $com $tmp a b 2>&1)
+[ "$res" == "$tmp 2 a b
+read input
+$tmp: line 5: nocmd_in_function: command not found" ] || err $LINENO
+
+res=$($com $tmp.none 2>&1)
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "sush: $tmp.none: No such file or directory" ] || err $LINENO
+
+### EXEC ERRORS ###
+
+echo 'echo $0 $1 $x $y' > $tmp
+chmod +x $tmp
+res=$($com -c "x=1; export y=2; $tmp a")
+[ "$res" == "$tmp a 2" ] || err $LINENO
+
+echo 'echo $0 $1; f' > $tmp
+res=$($com -c "f () { :; }; PATH=/tmp; ${tmp##*/} a" 2>&1)
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "$tmp a
+$tmp: line 1: f: command not found" ] || err $LINENO
+
+res=$($com -c "exec $tmp b; echo NG" 2>&1)
+[ "$res" == "$tmp b
+$tmp: line 1: f: command not found" ] || err $LINENO
+
+printf 'a\0b' > $tmp
+res=$($com -c "$tmp" 2>&1)
+[ "$?" == "126" ] || err $LINENO
+[ "$res" == "$com: line 1: $tmp: cannot execute binary file: Exec format error" ] || err $LINENO
+
+chmod -x $tmp
+res=$($com -c "$tmp" 2>&1)
+[ "$?" == "126" ] || err $LINENO
+[ "$res" == "$com: line 1: $tmp: Permission denied" ] || err $LINENO
+
+res=$($com -c "/tmp; $tmp.none" 2>&1)
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "$com: line 1: /tmp: Is a directory
+$com: line 1: $tmp.none: No such file or directory" ] || err $LINENO
+
+res=$(LC_ALL=ja_JP.UTF-8 $com -c "$tmp.none; cd $tmp.none; local x" 2>&1)
+[ "$res" == "$com: line 1: $tmp.none: そのようなファイルやディレクトリはありません
+$com: line 1: cd: $tmp.none: そのようなファイルやディレクトリはありません
+$com: line 1: local: 関数の中でのみ使用できます" ] || err $LINENO
+
+res=$(env -u LC_ALL -u LC_MESSAGES LANG=C $com -c "LANG=ja_JP.UTF-8; cd $tmp.none; LANG=C; cd $tmp.none" 2>&1)
+[ "$res" == "$com: line 1: cd: $tmp.none: そのようなファイルやディレクトリはありません
+$com: line 1: cd: $tmp.none: No such file or directory" ] || err $LINENO
+
+res=$($com -c '/bin/echo $(seq 1 300000)' 2>&1)
+[ "$?" == "126" ] || err $LINENO
+[ "$res" == "$com: line 1: /bin/echo: Argument list too long" ] || err $LINENO
+
+rm -f $tmp
+
 echo $0 >> ./ok
@@ -0,0 +1,164 @@
+#!/bin/bash
+# SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+# SPDX-License-Identifier: BSD-3-Clause
+
+# Feeds each fixture to bash and sush and compares the outputs
+
+err () {
+	echo $0 >> ./error
+	echo "ERROR!" FILE: $0, FIXTURE: "$1"
+	echo "bash: $2"
+	echo "sush: $3"
+	exit 1
+}
+
+[ "$1" == "nobuild" ] || cargo build --release || err $LINENO
+
+cd $(dirname $0)
+com=../target/release/sush
+
+compare () {
+	local b s
+	b=$(bash <<< "$1" 2> /dev/null)
+	s=$($com <<< "$1" 2> /dev/null)
+	[ "$b" == "$s" ] || err "$1" "$b" "$s"
+}
+
+### FIXTURES ###
+
+while IFS= read -r f ; do
+	compare "$f"
+done << 'FIXTURES'
+echo "a\"b"
+echo "a\`b"
+echo "a\\b"
+echo "a\$b"
+echo "a\xb"
+echo "a\nb"
+echo "a\'b"
+echo 'a\"b'
+echo 'a\\b'
+echo a\"b
+echo a\\b
+echo a\'b
+echo \'
+echo "'"
+echo '"'
+echo \"\'\"
+echo ""''""
+echo x"" y
+echo x'' y
+echo "" | wc -c
+printf '[%s]\n' "" '' x
+echo 'a'"b"c
+echo "\ "
+echo \ a
+printf '[%s]\n' a\ b
+printf '[%s]\n' "a\ b"
+printf '[%s]\n' 'a\ b'
+echo $'a\tb' | od -c
+echo $'it\'s'
+echo $'\x41\101あ'
+echo $'\e\E\a\b\f\v\r' | od -c
+echo $'a\0b'
+echo $'\1011'
+echo $'\x4g\xg'
+echo $'\cA\ca' | od -c
+echo $'\"\?\\'
+echo $'\q\z'
+echo $'\xe3\x81\x82'
+echo "$'x'"
+echo '$x'
+echo $"hello"
+echo $"a b" | wc -w
+a='"x"'; echo $a
+a="'x'"; echo $a
+a='\x'; echo $a
+a='a\ b'; printf '[%s]\n' $a
+a='$b'; b=1; echo $a
+a='*'; echo "$a"
+a='$(echo hi)'; echo $a
+a='`echo hi`'; echo $a
+a="a  b"; echo "$a" $a
+a=x; echo "$a"'$a'
+a=x; echo "${a}\${a}"
+a=x; echo '${a}'
+a=x; echo \$a
+a=x; echo "\$a" "$a"
+unset a; echo ${a:-'x y'}
+unset a; echo ${a:-"x y"}
+unset a; echo ${a:-x\ y}
+unset a; echo ${a:-$'x\ty'} | od -c
+unset a; echo "${a:-'x'}"
+unset a; echo "${a:-"y z"}"
+unset a; echo "${a:-\'}"
+unset a; echo "${a:-\"}"
+unset a; echo "${a:-\$}"
+unset a; echo "${a:-$'x'}"
+unset a; shopt -u extquote; echo "${a:-$'x\ty'}"
+unset a; shopt -s extquote; echo "${a:-$'x\ty'}"
+unset a; printf '[%s]\n' ${a:-'a b'} "${a:-a b}"
+a=v; echo "${a:+'x'}" ${a:+'x'}
+echo "$(echo "a b")"
+echo "$(echo 'a  b')"
+echo $(echo "a  b")
+echo "$(echo "\"")"
+set -- "a b" c; printf '[%s]\n' "$@"
+set -- "a b" c; printf '[%s]\n' "$*"
+set -- "a b" c; printf '[%s]\n' $@
+set -- "a b" c; printf '[%s]\n' "x$@y"
+set -- ; printf '[%s]\n' "$@" end
+IFS=:; set -- a b; echo "$*"
+case "a*" in 'a*') echo ok ;; esac
+case ab in "a"*) echo ok ;; esac
+case 'a*' in a\*) echo ok ;; esac
+[[ "a*" == 'a*' ]] && echo ok
+[[ ab == "a*" ]] || echo ng
+[[ ab == a"*" ]] || echo ng
+echo "#" '#' \# x#y
+echo ';' ";" \;
+echo '|' "|" \| '&' "&" \&
+echo '<' ">" \< \>
+echo '(' ")" \( \)
+echo '{a,b}' "{a,b}" \{a,b}
+echo '~' "~" \~
+echo '*' "*" \*
+echo `echo a; echo b`
+echo "a`echo b`c"
+echo `echo '|'` "`echo '<'`"
+echo "`echo \"q\"`"
+echo `echo \`echo nest\``
+echo `echo \$HOME` | grep -c /
+echo ${x:-`echo "}"`}
+echo $(echo `echo bq`)
+echo `echo a\\\\b`
+echo `echo \`echo \\\`echo deep\\\`\``
+echo "$(echo ")")" $(echo '(' )
+echo ${x:-$(echo "}")} ${x:-'}'} ${x:-\}}
+a=$(echo "a|b;c"); echo $a
+FIXTURES
+
+### MULTI-LINE FIXTURES ###
+
+compare 'echo "a
+b"'
+compare "echo 'a
+b'"
+compare "echo \$'a
+b'"
+compare 'echo "a\
+b"'
+compare 'echo a\
+b'
+
+### GENERATED FIXTURES ###
+
+atoms=( 'a' '"b"' "'c'" '\d' '$e' '"$e"' "'\$e'" '"\$e"' "\$'f\\tg'" '"h i"' "'j k'" '\ ' '""' "''" '"\\"' '"\""' "'\\'" '${e}' '"${e}"' '$"m"' )
+
+for x in "${atoms[@]}" ; do
+	for y in "${atoms[@]}" ; do
+		compare "e=E; printf '[%s]\n' $x$y"
+	done
+done
+
+echo $0 >> ./ok